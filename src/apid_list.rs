@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+
+
+/// The largest APID, as an APID is 11 bits
+pub const MAX_APID: u16 = 0x7FF;
+
+/// Whether an APID list file is CSV, whose APIDs are the first field of each row, rather than
+/// text listing APIDs separated by commas, spaces, or lines
+fn is_csv(file_name: &str) -> bool {
+    Path::new(file_name).extension()
+                        .map_or(false, |extension| extension.to_string_lossy().eq_ignore_ascii_case("csv"))
+}
+
+/// Parse a single APID, in decimal or as hex with a '0x' prefix
+pub fn parse_apid(apid_str: &str) -> Result<u16, String> {
+    let apid_str = apid_str.trim();
+
+    let apid = if apid_str.starts_with("0x") || apid_str.starts_with("0X") {
+        u16::from_str_radix(&apid_str[2..], 16)
+    } else {
+        apid_str.parse::<u16>()
+    };
+
+    match apid {
+        Ok(apid) if apid <= MAX_APID => Ok(apid),
+
+        _ => Err(format!("'{}' is not an APID from 0 to {}", apid_str, MAX_APID)),
+    }
+}
+
+/// Parse the text of an APID list. Anything after a '#' on a line is a comment. In CSV, the
+/// APID is the first field of each row, and a first row that does not start with an APID is
+/// taken as a header. Otherwise every field is an APID. An APID given twice is kept once.
+pub fn parse_apid_list_text(text: &str, csv: bool) -> Result<Vec<u16>, String> {
+    let mut apids = Vec::new();
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+
+        let fields = line.split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                         .map(|field| field.trim().trim_matches('"'))
+                         .filter(|field| !field.is_empty())
+                         .take(if csv { 1 } else { usize::max_value() });
+
+        for field in fields {
+            match parse_apid(field) {
+                Ok(apid) => {
+                    if !apids.contains(&apid) {
+                        apids.push(apid);
+                    }
+                },
+
+                Err(_) if csv && line_index == 0 => {},
+
+                Err(err_string) => return Err(format!("Line {}: {}", line_index + 1, err_string)),
+            }
+        }
+    }
+
+    Ok(apids)
+}
+
+/// Load an APID list from a text or CSV file
+pub fn load_apid_list(file_name: &str) -> Result<Vec<u16>, String> {
+    let mut text = String::new();
+    File::open(file_name).and_then(|mut file| file.read_to_string(&mut text))
+                         .map_err(|err| format!("Could not read APID list {}: {}", file_name, err))?;
+
+    parse_apid_list_text(&text, is_csv(file_name)).map_err(|err_string| format!("APID list {}: {}", file_name, err_string))
+}
+
+/// Save an APID list to a file, as CSV with an 'apid' header if the file name ends in '.csv',
+/// and otherwise as text with an APID on each line
+pub fn save_apid_list(file_name: &str, apids: &[u16]) -> Result<(), String> {
+    let mut text = String::new();
+
+    if is_csv(file_name) {
+        text.push_str("apid\n");
+    } else {
+        text.push_str("# APIDs, one on each line\n");
+    }
+
+    for apid in apids.iter() {
+        text.push_str(&format!("{}\n", apid));
+    }
+
+    File::create(file_name).and_then(|mut file| file.write_all(text.as_bytes()))
+                           .map_err(|err| format!("Could not write APID list {}: {}", file_name, err))
+}
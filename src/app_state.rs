@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+use imgui::*;
+
+use ccsds_router_core::types::*;
+
+
+/// The maximum number of error notifications kept for display in the GUI. Older
+/// notifications are dropped as new ones arrive.
+pub const MAX_ERROR_NOTIFICATIONS: usize = 100;
+
+/// An error reported to the user through the GUI
+#[derive(Debug, PartialEq, Clone)]
+pub struct ErrorNotification {
+    /// The error message
+    pub message: String,
+
+    /// The time at which the error was last received
+    pub time: SystemTime,
+
+    /// The number of times the error was received in a row
+    pub count: u64,
+}
+
+/// The state of the app, with information stored
+/// or shared across GUI components
+#[derive(Debug, PartialEq, Clone)]
+pub struct AppState {
+    pub imgui_str: ImString,
+    pub config_file_name: String,
+    pub config_settings_shown: bool,
+    pub input_settings_shown: bool,
+    pub output_settings_shown: bool,
+    pub ccsds_settings_shown: bool,
+    pub timestamp_selection: i32,
+    pub log_shown: bool,
+    pub log_level_selection: i32,
+    pub log_auto_scroll: bool,
+    pub error_notifications: VecDeque<ErrorNotification>,
+    pub pause_condition_editor: ConditionEditor,
+    pub trigger_condition_editor: ConditionEditor,
+    pub rate_alarm_editor: RateAlarmEditor,
+    /// The heartbeat packet data entered as hex, which replaces the data when set
+    pub heartbeat_data_hex: String,
+    /// The APIDs entered to be given high or low priority, which replace the APIDs of that priority when set
+    pub priority_apids_text: String,
+    pub packet_builder_shown: bool,
+    pub packet_template: PacketTemplate,
+    pub builder_payload_hex: String,
+    pub builder_payload_file: String,
+    pub builder_use_file: bool,
+    pub theme_editor_shown: bool,
+    pub timeline_shown: bool,
+    pub vc_stats_shown: bool,
+    pub resync_shown: bool,
+    pub bridge_shown: bool,
+    /// The length of time shown in the timeline, in seconds
+    pub timeline_seconds: f32,
+    /// The index of the routing session shown in the GUI
+    pub active_session: usize,
+    /// The configuration as it was last loaded or saved, used to detect unsaved changes
+    pub saved_config: Option<AppConfig>,
+    /// The profiles of the configuration file, as of when it was last loaded
+    pub profiles: Vec<String>,
+    /// The profile used when loading the configuration file
+    pub profile: Option<String>,
+    /// Set when the configuration file should be reloaded and applied to the running sessions
+    pub reload_requested: bool,
+    pub exit_requested: bool,
+    /// The problems found with the configuration when processing was last started
+    pub start_problems: Vec<String>,
+    /// The output files that would be overwritten when processing is started
+    pub existing_outputs: Vec<String>,
+    /// The APID entered to add to an APID filter, when it has not been seen while processing
+    pub added_apid: i32,
+}
+
+impl AppState {
+    pub fn new() -> AppState {
+        AppState {
+            imgui_str: ImString::with_capacity(256),
+            config_file_name: "".to_string(),
+            config_settings_shown: true,
+            input_settings_shown: true,
+            output_settings_shown: true,
+            ccsds_settings_shown: true,
+            timestamp_selection: 1,
+            log_shown: false,
+            log_level_selection: 2,
+            log_auto_scroll: true,
+            error_notifications: VecDeque::new(),
+            pause_condition_editor: Default::default(),
+            trigger_condition_editor: Default::default(),
+            rate_alarm_editor: Default::default(),
+            heartbeat_data_hex: "00".to_string(),
+            priority_apids_text: String::new(),
+            packet_builder_shown: false,
+            packet_template: Default::default(),
+            builder_payload_hex: "00".to_string(),
+            builder_payload_file: "".to_string(),
+            builder_use_file: false,
+            theme_editor_shown: false,
+            timeline_shown: false,
+            vc_stats_shown: false,
+            resync_shown: false,
+            bridge_shown: false,
+            timeline_seconds: 30.0,
+            active_session: 0,
+            saved_config: None,
+            profiles: Vec::new(),
+            profile: None,
+            reload_requested: false,
+            exit_requested: false,
+            start_problems: Vec::new(),
+            existing_outputs: Vec::new(),
+            added_apid: 0,
+        }
+    }
+
+    /// Restore the sections shown from a saved layout
+    pub fn apply_layout(&mut self, layout: &GuiLayout) {
+        self.config_settings_shown = layout.config_settings_shown;
+        self.input_settings_shown  = layout.input_settings_shown;
+        self.output_settings_shown = layout.output_settings_shown;
+        self.ccsds_settings_shown  = layout.ccsds_settings_shown;
+        self.log_shown             = layout.log_shown;
+        self.packet_builder_shown  = layout.packet_builder_shown;
+        self.theme_editor_shown    = layout.theme_editor_shown;
+        self.timeline_shown        = layout.timeline_shown;
+        self.vc_stats_shown        = layout.vc_stats_shown;
+        self.resync_shown          = layout.resync_shown;
+        self.bridge_shown          = layout.bridge_shown;
+        self.timeline_seconds      = layout.timeline_seconds;
+    }
+
+    /// Record the sections shown into a layout to be saved
+    pub fn record_layout(&self, layout: &mut GuiLayout) {
+        layout.config_settings_shown = self.config_settings_shown;
+        layout.input_settings_shown  = self.input_settings_shown;
+        layout.output_settings_shown = self.output_settings_shown;
+        layout.ccsds_settings_shown  = self.ccsds_settings_shown;
+        layout.log_shown             = self.log_shown;
+        layout.packet_builder_shown  = self.packet_builder_shown;
+        layout.theme_editor_shown    = self.theme_editor_shown;
+        layout.timeline_shown        = self.timeline_shown;
+        layout.vc_stats_shown        = self.vc_stats_shown;
+        layout.resync_shown          = self.resync_shown;
+        layout.bridge_shown          = self.bridge_shown;
+        layout.timeline_seconds      = self.timeline_seconds;
+    }
+
+    /// Check whether a configuration differs from the configuration last loaded or saved
+    pub fn config_modified(&self, config: &AppConfig) -> bool {
+        self.saved_config.as_ref() != Some(config)
+    }
+
+    /// Record an error to be shown to the user. An error repeating the last one is counted
+    /// against it, rather than kept again.
+    pub fn notify_error(&mut self, message: String) {
+        if let Some(last) = self.error_notifications.back_mut() {
+            if last.message == message {
+                last.time = SystemTime::now();
+                last.count += 1;
+                return;
+            }
+        }
+
+        if self.error_notifications.len() >= MAX_ERROR_NOTIFICATIONS {
+            self.error_notifications.pop_front();
+        }
+
+        self.error_notifications.push_back(ErrorNotification { message: message,
+                                                               time: SystemTime::now(),
+                                                               count: 1,
+        });
+    }
+
+    pub fn all_shown(&self) -> bool {
+        self.input_settings_shown  &&
+        self.output_settings_shown &&
+        self.ccsds_settings_shown  &&
+        self.config_settings_shown
+    }
+
+    pub fn show_all(&mut self) {
+        self.input_settings_shown  = true;
+        self.output_settings_shown = true;
+        self.ccsds_settings_shown  = true;
+        self.config_settings_shown = true;
+    }
+
+    pub fn hide_all(&mut self) {
+        self.input_settings_shown  = false;
+        self.output_settings_shown = false;
+        self.ccsds_settings_shown  = false;
+        self.config_settings_shown = false;
+    }
+}
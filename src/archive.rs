@@ -0,0 +1,114 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::Connection;
+use rusqlite::types::ToSql;
+
+use floating_duration::TimeAsFloat;
+
+use types::*;
+use stream::*;
+use processing::crc_valid;
+
+
+/// The number of packets inserted into the archive between commits. Committing each
+/// packet separately is too slow to keep up with high packet rates.
+const ARCHIVE_COMMIT_PACKETS: usize = 1000;
+
+const CREATE_PACKETS_TABLE: &str =
+    "CREATE TABLE IF NOT EXISTS packets (
+        id            INTEGER PRIMARY KEY,
+        apid          INTEGER NOT NULL,
+        seq_count     INTEGER NOT NULL,
+        length        INTEGER NOT NULL,
+        recv_time     REAL NOT NULL,
+        embedded_time REAL NOT NULL,
+        output_mask   INTEGER NOT NULL,
+        crc_valid     INTEGER
+    );
+    CREATE INDEX IF NOT EXISTS packets_apid ON packets (apid);";
+
+const INSERT_PACKET: &str =
+    "INSERT INTO packets (apid, seq_count, length, recv_time, embedded_time, output_mask, crc_valid)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)";
+
+/// A PacketArchive records the header information of each forwarded packet into an
+/// SQLite database, so a test can be queried afterwards without parsing the binary
+/// output files. Each row has the packet's APID, sequence count, length, receive time
+/// and embedded timestamp in seconds, the outputs it was written to as a bit mask,
+/// and whether its CRC was valid if CRCs are checked.
+pub struct PacketArchive {
+    connection: Connection,
+    timestamp_def: TimestampDef,
+    check_crc: bool,
+    uncommitted: usize,
+}
+
+impl PacketArchive {
+    /// Open the archive database, creating the packets table if it does not exist
+    pub fn open(archive_settings: &ArchiveSettings, timestamp_def: &TimestampDef) -> Result<PacketArchive, String> {
+        let connection = Connection::open(&archive_settings.file_name)
+                                    .map_err(|err| format!("Could not open archive {}: {}", archive_settings.file_name, err))?;
+
+        connection.execute_batch(CREATE_PACKETS_TABLE)
+                  .map_err(|err| format!("Could not create archive table in {}: {}", archive_settings.file_name, err))?;
+
+        connection.execute_batch("BEGIN")
+                  .map_err(|err| format!("Archive error {}", err))?;
+
+        Ok(PacketArchive {
+            connection: connection,
+            timestamp_def: timestamp_def.clone(),
+            check_crc: archive_settings.check_crc,
+            uncommitted: 0,
+        })
+    }
+
+    /// Insert a row for a forwarded packet. The output mask has bit N set if the packet
+    /// was written to output N.
+    pub fn insert(&mut self, packet: &Packet, recv_time: SystemTime, output_mask: u32) -> Result<(), String> {
+        let apid = packet.header.control.apid() as i64;
+        let seq_count = packet.header.sequence.sequence_count() as i64;
+        let length = packet.bytes.len() as i64;
+        let recv_secs = recv_time.duration_since(UNIX_EPOCH).map(|duration| duration.as_fractional_secs()).unwrap_or(0.0);
+        let embedded_secs = self.timestamp_def.decode(&packet.bytes).as_fractional_secs();
+        let output_mask = output_mask as i64;
+        let crc_status: Option<bool> = if self.check_crc {
+            Some(crc_valid(&packet.bytes))
+        } else {
+            None
+        };
+
+        self.connection.prepare_cached(INSERT_PACKET)
+                       .and_then(|mut statement| {
+                           statement.execute(&[&apid as &ToSql,
+                                               &seq_count,
+                                               &length,
+                                               &recv_secs,
+                                               &embedded_secs,
+                                               &output_mask,
+                                               &crc_status])
+                       })
+                       .map_err(|err| format!("Archive error {}", err))?;
+
+        self.uncommitted += 1;
+        if self.uncommitted >= ARCHIVE_COMMIT_PACKETS {
+            self.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Commit the packets inserted so far, so they are visible to other readers of the database
+    pub fn commit(&mut self) -> Result<(), String> {
+        self.uncommitted = 0;
+        self.connection.execute_batch("COMMIT; BEGIN")
+                       .map_err(|err| format!("Archive error {}", err))
+    }
+}
+
+impl Drop for PacketArchive {
+    fn drop(&mut self) {
+        // the result is ignored here as there is no one left to report it to
+        let _ = self.connection.execute_batch("COMMIT");
+    }
+}
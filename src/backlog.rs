@@ -0,0 +1,216 @@
+use std::cmp::min;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use byteorder::{LittleEndian, ByteOrder};
+
+use priority::*;
+
+
+/// The bytes of the length written before each packet in a backlog file
+pub const BACKLOG_LENGTH_BYTES: usize = 4;
+
+/// The most numbered names tried for a backlog file when its name is already in use
+const MAX_BACKLOG_NAME_TRIES: usize = 1000;
+
+/// The bytes moved at a time when a backlog file is compacted
+const COMPACT_CHUNK_BYTES: usize = 65536;
+
+/// A Backlog keeps the packets for an output that is down in a file, in the order they were
+/// forwarded, until they can be written. The file is emptied whenever the whole backlog has
+/// been written, and removed when the backlog is dropped.
+pub struct Backlog {
+    file_name: String,
+
+    file: File,
+
+    /// The offset in the file of the next packet to write to the output, and of the end of
+    /// the file, where the next packet stored is written
+    read_offset: u64,
+    write_offset: u64,
+
+    max_bytes: u64,
+
+    /// The packets stored and not yet written to the output
+    packet_count: u64,
+
+    /// The next packet to write to the output, once it has been read from the file
+    front: Option<Vec<u8>>,
+}
+
+impl Backlog {
+    /// Create the backlog file of a session's output in the given directory, creating the
+    /// directory if it does not exist. An output with APID priorities has a backlog for each
+    /// priority. The file holds at most the given number of bytes.
+    ///
+    /// The file is named for the session and output. A file of that name is never reused, as it
+    /// may belong to another session or router sharing the directory, so a number is added to
+    /// the name until it is unique.
+    pub fn create(directory: &str, session_name: &str, output_index: usize, priority: Option<Priority>, max_bytes: u64) -> Result<Backlog, String> {
+        fs::create_dir_all(directory)
+           .map_err(|err| format!("Could not create backlog directory {}: {}", directory, err))?;
+
+        let stem = match priority {
+            Some(priority) => format!("{}_output_{}_backlog_{}", session_name, output_index + 1, priority.name()),
+
+            None => format!("{}_output_{}_backlog", session_name, output_index + 1),
+        };
+
+        let mut name_number = 1;
+        let (file_name, file) = loop {
+            let base_name = if name_number == 1 {
+                format!("{}.bin", stem)
+            } else {
+                format!("{}_{}.bin", stem, name_number)
+            };
+            let file_name = Path::new(directory).join(base_name)
+                                                .to_string_lossy()
+                                                .to_string();
+
+            match OpenOptions::new().read(true).write(true).create_new(true).open(&file_name) {
+                Ok(file) => break (file_name, file),
+
+                Err(ref err) if err.kind() == ErrorKind::AlreadyExists && name_number < MAX_BACKLOG_NAME_TRIES => {
+                    name_number += 1;
+                },
+
+                Err(err) => return Err(format!("Could not create backlog file {}: {}", file_name, err)),
+            }
+        };
+
+        Ok(Backlog {
+            file_name: file_name,
+            file: file,
+            read_offset: 0,
+            write_offset: 0,
+            max_bytes: max_bytes,
+            packet_count: 0,
+            front: None,
+        })
+    }
+
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// The packets stored and not yet written to the output
+    pub fn len(&self) -> u64 {
+        self.packet_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packet_count == 0
+    }
+
+    /// The bytes of the packets stored and not yet written to the output, not counting the
+    /// packets already written from the start of the file
+    pub fn bytes(&self) -> u64 {
+        self.write_offset - self.read_offset
+    }
+
+    /// Store a packet at the end of the backlog, returning false if it does not fit. When the
+    /// packet only fits once the packets already written are removed from the start of the
+    /// file, the file is compacted first.
+    pub fn push(&mut self, packet_bytes: &[u8]) -> Result<bool, String> {
+        let record_bytes = (BACKLOG_LENGTH_BYTES + packet_bytes.len()) as u64;
+        if self.bytes() + record_bytes > self.max_bytes {
+            return Ok(false);
+        }
+
+        if self.write_offset + record_bytes > self.max_bytes {
+            self.compact()?;
+        }
+
+        let mut length = [0; BACKLOG_LENGTH_BYTES];
+        LittleEndian::write_u32(&mut length, packet_bytes.len() as u32);
+
+        self.file.seek(SeekFrom::Start(self.write_offset))
+                 .and_then(|_| self.file.write_all(&length))
+                 .and_then(|_| self.file.write_all(packet_bytes))
+                 .map_err(|err| format!("Could not write to backlog file {}: {}", self.file_name, err))?;
+
+        self.write_offset += record_bytes;
+        self.packet_count += 1;
+
+        Ok(true)
+    }
+
+    /// The next packet to write to the output, if any, which stays at the front of the backlog
+    /// until it is popped
+    pub fn front(&mut self) -> Result<Option<&[u8]>, String> {
+        if self.front.is_none() && self.packet_count > 0 {
+            let mut length = [0; BACKLOG_LENGTH_BYTES];
+            self.file.seek(SeekFrom::Start(self.read_offset))
+                     .and_then(|_| self.file.read_exact(&mut length))
+                     .map_err(|err| format!("Could not read from backlog file {}: {}", self.file_name, err))?;
+
+            let mut packet_bytes = vec![0; LittleEndian::read_u32(&length) as usize];
+            self.file.read_exact(&mut packet_bytes)
+                     .map_err(|err| format!("Could not read from backlog file {}: {}", self.file_name, err))?;
+
+            self.front = Some(packet_bytes);
+        }
+
+        Ok(self.front.as_ref().map(|packet_bytes| packet_bytes.as_slice()))
+    }
+
+    /// Remove the packet at the front of the backlog once it has been written to the output.
+    /// Once the backlog is empty, its file is emptied.
+    pub fn pop(&mut self) -> Result<(), String> {
+        let packet_bytes = match self.front.take() {
+            Some(packet_bytes) => packet_bytes,
+
+            None => return Ok(()),
+        };
+
+        self.read_offset += (BACKLOG_LENGTH_BYTES + packet_bytes.len()) as u64;
+        self.packet_count -= 1;
+
+        if self.packet_count == 0 {
+            self.read_offset = 0;
+            self.write_offset = 0;
+            self.file.set_len(0)
+                     .map_err(|err| format!("Could not empty backlog file {}: {}", self.file_name, err))?;
+        }
+
+        Ok(())
+    }
+
+    /// Move the packets not yet written to the start of the file, dropping the packets already
+    /// written ahead of them
+    fn compact(&mut self) -> Result<(), String> {
+        let mut chunk = vec![0; COMPACT_CHUNK_BYTES];
+        let mut from = self.read_offset;
+        let mut to = 0;
+
+        while from < self.write_offset {
+            let chunk_bytes = min(COMPACT_CHUNK_BYTES as u64, self.write_offset - from) as usize;
+
+            self.file.seek(SeekFrom::Start(from))
+                     .and_then(|_| self.file.read_exact(&mut chunk[..chunk_bytes]))
+                     .and_then(|_| self.file.seek(SeekFrom::Start(to)))
+                     .and_then(|_| self.file.write_all(&chunk[..chunk_bytes]))
+                     .map_err(|err| format!("Could not compact backlog file {}: {}", self.file_name, err))?;
+
+            from += chunk_bytes as u64;
+            to += chunk_bytes as u64;
+        }
+
+        self.file.set_len(to)
+                 .map_err(|err| format!("Could not compact backlog file {}: {}", self.file_name, err))?;
+
+        self.read_offset = 0;
+        self.write_offset = to;
+
+        Ok(())
+    }
+}
+
+impl Drop for Backlog {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.file_name) {
+            warn!("Could not remove backlog file {}: {}", self.file_name, err);
+        }
+    }
+}
@@ -0,0 +1,252 @@
+use std::time::Instant;
+
+use bytes::BytesMut;
+
+use floating_duration::TimeAsFloat;
+
+use ccsds_primary_header::primary_header::*;
+
+#[cfg(unix)]
+use libc;
+
+use types::*;
+use stream::*;
+use router::*;
+
+
+/// The name of the source plugin that generates packets for benchmarking
+pub const GENERATOR_PLUGIN: &str = "generator";
+
+/// The number of packets generated if the generator's 'packets' parameter is not given
+pub const DEFAULT_GENERATED_PACKETS: u64 = 1_000_000;
+
+/// The number of data bytes in each generated packet if the 'length' parameter is not given
+pub const DEFAULT_GENERATED_LENGTH: usize = 100;
+
+/// The number of APIDs packets are generated for if the 'apids' parameter is not given
+pub const DEFAULT_GENERATED_APIDS: u16 = 8;
+
+/// The result of a benchmark run
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    /// A description of the input the packets were read from
+    pub input: String,
+
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub elapsed_secs: f64,
+
+    /// The CPU time used by the process while processing, where it can be measured
+    pub cpu_secs: Option<f64>,
+}
+
+impl BenchReport {
+    pub fn packets_per_sec(&self) -> f64 {
+        if self.elapsed_secs > 0.0 {
+            self.packet_count as f64 / self.elapsed_secs
+        } else {
+            0.0
+        }
+    }
+
+    pub fn bytes_per_sec(&self) -> f64 {
+        if self.elapsed_secs > 0.0 {
+            self.byte_count as f64 / self.elapsed_secs
+        } else {
+            0.0
+        }
+    }
+
+    /// The CPU time as a percentage of one core. This exceeds 100% when the
+    /// input and processing threads run at the same time.
+    pub fn cpu_percent(&self) -> Option<f64> {
+        match self.cpu_secs {
+            Some(cpu_secs) if self.elapsed_secs > 0.0 => Some(100.0 * cpu_secs / self.elapsed_secs),
+
+            _ => None,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str(&format!("Input:        {}\n", self.input));
+        text.push_str(&format!("Packets:      {}\n", self.packet_count));
+        text.push_str(&format!("Bytes:        {}\n", self.byte_count));
+        text.push_str(&format!("Elapsed:      {:.3} s\n", self.elapsed_secs));
+        text.push_str(&format!("Packets/sec:  {:.0}\n", self.packets_per_sec()));
+        text.push_str(&format!("Bytes/sec:    {:.0} ({:.2} MB/s)\n", self.bytes_per_sec(), self.bytes_per_sec() / 1_000_000.0));
+        match self.cpu_percent() {
+            Some(cpu_percent) => text.push_str(&format!("CPU:          {:.1}% ({:.3} s)\n", cpu_percent, self.cpu_secs.unwrap())),
+
+            None => text.push_str("CPU:          not available\n"),
+        }
+
+        text
+    }
+}
+
+/// Run the packets of a configuration's input through the full processing pipeline as fast
+/// as possible, as a dry run so that nothing is written to the outputs, and report the rate
+/// they were processed at. File and plugin inputs are benchmarked as configured. Any other
+/// input is replaced with a stream of generated packets, as waiting on a network input would
+/// measure the network rather than the router.
+pub fn run_bench(config: &AppConfig) -> Result<BenchReport, String> {
+    register_source(GENERATOR_PLUGIN, open_generator_source);
+
+    let mut config = config.clone();
+
+    match config.input_selection {
+        StreamOption::File | StreamOption::Plugin => {},
+
+        _ => {
+            config.input_selection = StreamOption::Plugin;
+            config.input_settings.plugin = PluginSettings { name: GENERATOR_PLUGIN.to_string(), parameters: Default::default() };
+        },
+    }
+
+    config.timestamp_setting = TimestampSetting::Asap;
+    config.dry_run = true;
+    config.index_files = false;
+
+    // an output is needed for packets to be forwarded, although nothing is written to it
+    if config.output_selection.len() == 0 {
+        config.output_selection.push(StreamOption::File);
+        config.output_settings.push(Default::default());
+        config.allowed_output_apids.push(None);
+    }
+
+    let input = stream_spec(config.input_selection, &config.input_settings);
+
+    let mut router = Router::new(config);
+
+    let cpu_start = cpu_time_secs();
+    let start = Instant::now();
+
+    router.start()?;
+
+    let mut packet_count: u64 = 0;
+    let mut byte_count: u64 = 0;
+
+    let result = loop {
+        match router.events().recv() {
+            Ok(GuiMessage::PacketUpdate(packet_update)) => {
+                packet_count += packet_update.packet_count;
+                byte_count += packet_update.byte_count;
+            },
+
+            Ok(GuiMessage::Failed(_, error_msg)) => break Err(error_msg),
+
+            Ok(GuiMessage::Finished) => break Ok(()),
+
+            // the benchmark runs to the end of the input, so pause conditions do not stop it
+            Ok(GuiMessage::Paused) => {
+                if let Err(err_string) = router.resume() {
+                    break Err(err_string);
+                }
+            },
+
+            Ok(_) => {},
+
+            Err(_) => break Err("The router's processing thread exited during the benchmark".to_string()),
+        }
+    };
+
+    let elapsed_secs = start.elapsed().as_fractional_secs();
+    let cpu_secs = match (cpu_start, cpu_time_secs()) {
+        (Some(cpu_start), Some(cpu_end)) => Some(cpu_end - cpu_start),
+
+        _ => None,
+    };
+
+    router.shutdown();
+
+    result.map(|_| BenchReport {
+        input: input,
+        packet_count: packet_count,
+        byte_count: byte_count,
+        elapsed_secs: elapsed_secs,
+        cpu_secs: cpu_secs,
+    })
+}
+
+/// The user and system CPU time used by the process, in seconds
+#[cfg(unix)]
+fn cpu_time_secs() -> Option<f64> {
+    let mut usage: libc::rusage = unsafe { ::std::mem::zeroed() };
+
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+
+    let secs = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+
+    Some(secs(usage.ru_utime) + secs(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+fn cpu_time_secs() -> Option<f64> {
+    None
+}
+
+/// Generates CCSDS packets in memory, cycling through a number of APIDs with a sequence
+/// count for each. The parameters are 'packets', the number of packets to generate, 'length',
+/// the number of data bytes in each packet, and 'apids', the number of APIDs starting from 0.
+struct GeneratorSource {
+    packets_left: u64,
+    templates: Vec<PacketTemplate>,
+    next_template: usize,
+}
+
+pub fn open_generator_source(plugin_settings: &PluginSettings) -> Result<Box<PacketSource>, String> {
+    fn parameter<T: ::std::str::FromStr>(plugin_settings: &PluginSettings, name: &str, default: T) -> Result<T, String> {
+        match plugin_settings.parameters.get(name) {
+            Some(value) => value.parse().map_err(|_| format!("The generator's '{}' parameter '{}' is not valid", name, value)),
+
+            None => Ok(default),
+        }
+    }
+
+    let packets = parameter(plugin_settings, "packets", DEFAULT_GENERATED_PACKETS)?;
+    let length = parameter(plugin_settings, "length", DEFAULT_GENERATED_LENGTH)?;
+    let apids = parameter(plugin_settings, "apids", DEFAULT_GENERATED_APIDS)?;
+
+    if length == 0 || length > 0x10000 {
+        return Err("The generator's packet length must be between 1 and 65536 bytes".to_string());
+    }
+
+    if apids == 0 || apids > 0x800 {
+        return Err("The generator's number of APIDs must be between 1 and 2048".to_string());
+    }
+
+    let templates = (0..apids).map(|apid| PacketTemplate { apid: apid,
+                                                            data: vec!(0; length),
+                                                            ..Default::default()
+                                                          })
+                              .collect();
+
+    Ok(Box::new(GeneratorSource {
+        packets_left: packets,
+        templates: templates,
+        next_template: 0,
+    }))
+}
+
+impl PacketSource for GeneratorSource {
+    fn read(&mut self, bytes: &mut BytesMut, num_bytes: usize) -> Result<usize, String> {
+        let mut num_bytes_read = 0;
+        while num_bytes_read < num_bytes && self.packets_left > 0 {
+            let template = &mut self.templates[self.next_template];
+            let packet = template.to_bytes(false)?;
+            template.sequence_count = next_seq(template.sequence_count);
+
+            bytes.extend_from_slice(&packet);
+            num_bytes_read += packet.len();
+
+            self.packets_left -= 1;
+            self.next_template = (self.next_template + 1) % self.templates.len();
+        }
+
+        Ok(num_bytes_read)
+    }
+}
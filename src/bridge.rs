@@ -0,0 +1,398 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use ccsds_primary_header::parser::{CcsdsParser, CcsdsParserConfig};
+
+use types::*;
+use stream::*;
+use processing::{parser_config, primary_header};
+use apid_list::*;
+
+
+/// The most bytes requested in each read of a side of a bridge
+const BRIDGE_READ_BYTES: usize = 8192;
+
+/// A bridge joins the two streams of a test link, such as a TCP connection from ground software
+/// and a plugin reaching a flight computer over serial, and forwards the packets read from each
+/// side to the other at the same time. One router then carries both the commands sent up the
+/// link and the telemetry sent down it. Packets are forwarded as they are parsed, with their
+/// frame headers and footers, so the sessions' timing and transforms do not apply.
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeSettings {
+    /// Start the bridge when the router starts
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub side_a: BridgeSide,
+
+    #[serde(default)]
+    pub side_b: BridgeSide,
+}
+
+/// One side of a bridge, which is both read and written
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeSide {
+    /// The name of the side, such as 'Ground' or 'Spacecraft', used in the statistics and errors
+    #[serde(default)]
+    pub name: String,
+
+    /// The type of stream, which must be a TCP client, TCP server, or plugin
+    #[serde(default)]
+    pub selection: StreamOption,
+
+    #[serde(default)]
+    pub settings: StreamSettings,
+
+    /// The APIDs forwarded from this side to the other, or None to forward all APIDs
+    #[serde(default)]
+    pub allowed_apids: Option<Vec<u16>>,
+}
+
+impl BridgeSide {
+    /// The name of the side, or the given name if it has none
+    pub fn label(&self, unnamed: &str) -> String {
+        if self.name.trim().is_empty() {
+            unnamed.to_string()
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+impl BridgeSettings {
+    pub fn side_a_label(&self) -> String {
+        self.side_a.label("Side A")
+    }
+
+    pub fn side_b_label(&self) -> String {
+        self.side_b.label("Side B")
+    }
+
+    /// A description of each problem with the bridge, other than with the settings of each
+    /// side's stream
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        let sides = [(&self.side_a, self.side_a_label(), self.side_b_label()),
+                     (&self.side_b, self.side_b_label(), self.side_a_label())];
+        for (side, label, other_label) in sides.iter() {
+            if !side.selection.duplex() {
+                problems.push(format!("{} of the bridge is a {:?} stream, but each side must be a TCP client, TCP server, or plugin, which can be both read and written",
+                                      label,
+                                      side.selection));
+            }
+
+            if let Some(ref apids) = side.allowed_apids {
+                if apids.is_empty() {
+                    problems.push(format!("{} of the bridge does not allow any APIDs, so no packets would be sent to {}", label, other_label));
+                }
+
+                for apid in apids.iter().filter(|apid| **apid > MAX_APID) {
+                    problems.push(format!("{} of the bridge allows APID {}, but APIDs are at most {}", label, apid, MAX_APID));
+                }
+            }
+        }
+
+        if self.side_a.selection == StreamOption::TcpServer &&
+           self.side_b.selection == StreamOption::TcpServer &&
+           self.side_a.settings.tcp_server.port == self.side_b.settings.tcp_server.port {
+            problems.push(format!("Both sides of the bridge listen on port {}", self.side_a.settings.tcp_server.port));
+        }
+
+        problems
+    }
+}
+
+/// The direction packets are forwarded across a bridge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeDirection {
+    AToB,
+    BToA,
+}
+
+/// The packets forwarded in one direction across a bridge
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct DirectionStats {
+    /// The packets and bytes written to the other side
+    pub packet_count: u64,
+    pub byte_count: u64,
+
+    /// The packets not forwarded as their APID is not allowed
+    pub filtered_count: u64,
+
+    /// The bytes skipped by the parser while searching for a valid header
+    pub bytes_skipped: u64,
+
+    /// The packets read of each APID, whether or not they were forwarded
+    pub apid_counts: BTreeMap<u16, u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BridgeState {
+    /// Waiting for the streams of each side to connect
+    Connecting,
+    Running,
+    Stopped,
+}
+
+/// The state of a bridge and the packets it has forwarded in each direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeStatus {
+    pub state: BridgeState,
+
+    pub a_to_b: DirectionStats,
+    pub b_to_a: DirectionStats,
+
+    /// Why the bridge stopped, if it was not stopped on request
+    pub error: Option<String>,
+}
+
+impl BridgeStatus {
+    pub fn direction(&self, direction: BridgeDirection) -> &DirectionStats {
+        match direction {
+            BridgeDirection::AToB => &self.a_to_b,
+            BridgeDirection::BToA => &self.b_to_a,
+        }
+    }
+
+    fn direction_mut(&mut self, direction: BridgeDirection) -> &mut DirectionStats {
+        match direction {
+            BridgeDirection::AToB => &mut self.a_to_b,
+            BridgeDirection::BToA => &mut self.b_to_a,
+        }
+    }
+}
+
+/// A running bridge. The sides are opened in turn on the bridge's own thread, and once both are
+/// open, each direction is forwarded on a thread of its own. If either side closes or fails, the
+/// whole bridge stops.
+pub struct Bridge {
+    stop: Arc<AtomicBool>,
+    status: Arc<Mutex<BridgeStatus>>,
+}
+
+impl Bridge {
+    /// Start a bridge with the bridge settings of a configuration, which parses packets with the
+    /// configuration's packet settings
+    pub fn start(app_config: &AppConfig) -> Bridge {
+        let stop = Arc::new(AtomicBool::new(false));
+        let status = Arc::new(Mutex::new(BridgeStatus {
+            state: BridgeState::Connecting,
+            a_to_b: Default::default(),
+            b_to_a: Default::default(),
+            error: None,
+        }));
+
+        let timeout = if app_config.connect_timeout_secs > 0.0 {
+            let secs = app_config.connect_timeout_secs;
+            Some(Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32))
+        } else {
+            None
+        };
+
+        let settings = app_config.bridge.clone();
+        let ccsds_parser_config = parser_config(app_config);
+        let open_wait = OpenWait::new(timeout, stop.clone());
+        let thread_stop = stop.clone();
+        let thread_status = status.clone();
+        thread::spawn(move || {
+            run_bridge(settings, ccsds_parser_config, open_wait, thread_stop, thread_status);
+        });
+
+        Bridge {
+            stop: stop,
+            status: status,
+        }
+    }
+
+    /// Stop the bridge. Its threads stop once their current read times out.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    pub fn status(&self) -> BridgeStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.lock().unwrap().state != BridgeState::Stopped
+    }
+}
+
+impl Drop for Bridge {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run_bridge(settings: BridgeSettings,
+              ccsds_parser_config: CcsdsParserConfig,
+              open_wait: OpenWait,
+              stop: Arc<AtomicBool>,
+              status: Arc<Mutex<BridgeStatus>>) {
+    let a_label = settings.side_a_label();
+    let b_label = settings.side_b_label();
+
+    info!("Bridge opening {} and {}", a_label, b_label);
+    let opened = settings.side_a.selection.open_duplex_with(&settings.side_a.settings, &open_wait)
+                         .map_err(|err| format!("Bridge could not open {}: {}", a_label, err))
+                         .and_then(|side_a| {
+                             settings.side_b.selection.open_duplex_with(&settings.side_b.settings, &open_wait)
+                                     .map_err(|err| format!("Bridge could not open {}: {}", b_label, err))
+                                     .map(|side_b| (side_a, side_b))
+                         });
+
+    let ((mut a_reader, mut a_writer), (mut b_reader, mut b_writer)) = match opened {
+        Ok(sides) => sides,
+
+        Err(err_string) => {
+            error!("{}", err_string);
+            let mut status = status.lock().unwrap();
+            status.state = BridgeState::Stopped;
+            status.error = Some(err_string);
+            return;
+        },
+    };
+
+    info!("Bridge running between {} and {}", a_label, b_label);
+    status.lock().unwrap().state = BridgeState::Running;
+
+    let b_to_a_thread = {
+        let settings = settings.clone();
+        let ccsds_parser_config = ccsds_parser_config.clone();
+        let stop = stop.clone();
+        let status = status.clone();
+        thread::spawn(move || {
+            forward_direction(BridgeDirection::BToA, &settings, &mut b_reader, &mut a_writer, &ccsds_parser_config, &stop, &status)
+        })
+    };
+
+    let a_to_b_result = forward_direction(BridgeDirection::AToB, &settings, &mut a_reader, &mut b_writer, &ccsds_parser_config, &stop, &status);
+    let b_to_a_result = b_to_a_thread.join().unwrap_or_else(|_| Err("Bridge thread panicked".to_string()));
+
+    let mut status = status.lock().unwrap();
+    status.state = BridgeState::Stopped;
+    match a_to_b_result.and(b_to_a_result) {
+        Ok(()) => {
+            info!("Bridge between {} and {} stopped", a_label, b_label);
+        },
+
+        Err(err_string) => {
+            error!("Bridge stopped: {}", err_string);
+            status.error = Some(err_string);
+        },
+    }
+}
+
+/// Forward the packets read from one side of the bridge to the other, until the bridge is
+/// stopped. Closing or failing either side stops the whole bridge.
+fn forward_direction(direction: BridgeDirection,
+                     settings: &BridgeSettings,
+                     reader: &mut ReadStream,
+                     writer: &mut WriteStream,
+                     ccsds_parser_config: &CcsdsParserConfig,
+                     stop: &AtomicBool,
+                     status: &Mutex<BridgeStatus>) -> Result<(), String> {
+    let (from, from_label, to_label) = match direction {
+        BridgeDirection::AToB => (&settings.side_a, settings.side_a_label(), settings.side_b_label()),
+
+        BridgeDirection::BToA => (&settings.side_b, settings.side_b_label(), settings.side_a_label()),
+    };
+
+    let result = forward_packets(direction, from, reader, writer, ccsds_parser_config, stop, status, &from_label, &to_label);
+
+    // the other direction stops as well, as the link is no longer whole
+    stop.store(true, Ordering::SeqCst);
+
+    result
+}
+
+fn forward_packets(direction: BridgeDirection,
+                   from: &BridgeSide,
+                   reader: &mut ReadStream,
+                   writer: &mut WriteStream,
+                   ccsds_parser_config: &CcsdsParserConfig,
+                   stop: &AtomicBool,
+                   status: &Mutex<BridgeStatus>,
+                   from_label: &str,
+                   to_label: &str) -> Result<(), String> {
+    // frame headers and footers are forwarded along with each packet
+    let mut parser_config = ccsds_parser_config.clone();
+    parser_config.keep_header = true;
+    parser_config.keep_sync = true;
+    parser_config.keep_footer = true;
+    // the primary header follows any sync marker and frame header kept with the packet
+    let header_start = parser_config.sync_bytes.len() + parser_config.num_header_bytes as usize;
+    let little_endian_header = parser_config.little_endian_header;
+
+    let mut parser = CcsdsParser::with_config(parser_config.clone());
+
+    while !stop.load(Ordering::SeqCst) {
+        match reader.stream_read_timed(&mut parser.bytes, BRIDGE_READ_BYTES)
+                    .map_err(|err| format!("Could not read from {}: {}", from_label, err))? {
+            StreamRead::TimedOut => continue,
+
+            StreamRead::Bytes(0, _) if !reader.is_datagram() => {
+                return Err(format!("{} closed its stream", from_label));
+            },
+
+            StreamRead::Bytes(_, _) => {},
+        }
+
+        let parser_bytes = parser.bytes.len();
+        let mut packet_bytes_total = 0;
+        let mut any_packets = false;
+        while let Some(packet_bytes) = parser.pull_packet() {
+            packet_bytes_total += packet_bytes.len();
+            any_packets = true;
+
+            let apid = packet_bytes.get(header_start..)
+                                   .and_then(|bytes| primary_header(bytes, little_endian_header))
+                                   .map(|header| header.control.apid());
+
+            let allowed = match (&from.allowed_apids, apid) {
+                (None, _) => true,
+
+                (Some(apids), Some(apid)) => apids.contains(&apid),
+
+                (Some(_), None) => false,
+            };
+
+            if allowed {
+                writer.stream_send(&packet_bytes)
+                      .map_err(|err| format!("Could not write to {}: {}", to_label, err))?;
+            }
+
+            let mut status = status.lock().unwrap();
+            let stats = status.direction_mut(direction);
+            if allowed {
+                stats.packet_count += 1;
+                stats.byte_count += packet_bytes.len() as u64;
+            } else {
+                stats.filtered_count += 1;
+            }
+            if let Some(apid) = apid {
+                *stats.apid_counts.entry(apid).or_insert(0) += 1;
+            }
+        }
+
+        let skipped = parser_bytes.saturating_sub(parser.bytes.len()).saturating_sub(packet_bytes_total);
+        if skipped > 0 {
+            status.lock().unwrap().direction_mut(direction).bytes_skipped += skipped as u64;
+        }
+
+        // the bytes after the last packet are moved to the start of a new parser
+        if any_packets {
+            let remaining_bytes = parser.bytes.take().freeze();
+            parser = CcsdsParser::with_config(parser_config.clone());
+            parser.bytes.reserve(remaining_bytes.len() + BRIDGE_READ_BYTES);
+            parser.bytes.extend(remaining_bytes);
+        }
+    }
+
+    writer.stream_flush().map_err(|err| format!("Could not write to {}: {}", to_label, err))
+}
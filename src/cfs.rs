@@ -0,0 +1,52 @@
+/// The offset of a cFS command's function code, the first byte of its secondary header
+pub const CFS_FUNCTION_CODE_OFFSET: usize = 6;
+
+/// The offset of a cFS command's checksum, the second byte of its secondary header
+pub const CFS_CHECKSUM_OFFSET: usize = 7;
+
+/// The function code is 7 bits, with the top bit of its byte reserved
+pub const CFS_FUNCTION_CODE_MASK: u8 = 0x7F;
+
+/// Whether a packet is a command with a secondary header, which for cFS is the command
+/// secondary header holding the function code and checksum
+pub fn is_cfs_command(packet_bytes: &[u8], little_endian_header: bool) -> bool {
+    if packet_bytes.len() <= CFS_CHECKSUM_OFFSET {
+        return false;
+    }
+
+    // the packet type and secondary header flag are in the high byte of the first header word
+    let high_byte = if little_endian_header { packet_bytes[1] } else { packet_bytes[0] };
+
+    (high_byte & 0x10) != 0 && (high_byte & 0x08) != 0
+}
+
+/// The function code of a cFS command, or None if the packet is not a command
+pub fn cfs_function_code(packet_bytes: &[u8], little_endian_header: bool) -> Option<u8> {
+    if is_cfs_command(packet_bytes, little_endian_header) {
+        Some(packet_bytes[CFS_FUNCTION_CODE_OFFSET] & CFS_FUNCTION_CODE_MASK)
+    } else {
+        None
+    }
+}
+
+/// The checksum of a cFS command, computed as cFE does, so that the exclusive or of every
+/// byte of the command, including the checksum, is 0xFF
+pub fn cfs_checksum(packet_bytes: &[u8]) -> u8 {
+    packet_bytes.iter()
+                .enumerate()
+                .filter(|(index, _)| *index != CFS_CHECKSUM_OFFSET)
+                .fold(0xFF, |checksum, (_, byte)| checksum ^ *byte)
+}
+
+/// Whether the checksum of a cFS command is valid
+pub fn cfs_checksum_valid(packet_bytes: &[u8]) -> bool {
+    packet_bytes.len() > CFS_CHECKSUM_OFFSET &&
+    packet_bytes[CFS_CHECKSUM_OFFSET] == cfs_checksum(packet_bytes)
+}
+
+/// Set the checksum of a cFS command, such as after changing the command's bytes
+pub fn set_cfs_checksum(packet_bytes: &mut [u8]) {
+    if packet_bytes.len() > CFS_CHECKSUM_OFFSET {
+        packet_bytes[CFS_CHECKSUM_OFFSET] = cfs_checksum(packet_bytes);
+    }
+}
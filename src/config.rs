@@ -0,0 +1,624 @@
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::net::Ipv4Addr;
+
+use serde_json;
+use serde_json::Value;
+
+use types::*;
+use stream::*;
+use container::*;
+use transfer_frame::*;
+use bridge::*;
+
+
+/// The field naming another configuration file that the settings of a file are merged over
+const INCLUDE_FIELD: &str = "include";
+
+/// The most files that can be included within each other, which stops a file that
+/// includes itself from being loaded forever
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// The field holding the named profiles of a configuration file. Each profile is a set of
+/// settings that are merged over the file's common settings when the profile is selected.
+const PROFILES_FIELD: &str = "profiles";
+
+/// A description of each setting in the configuration file, used to comment a generated configuration
+const CONFIG_FIELD_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("input_settings",        "Settings for each type of input stream. Only the settings of the selected type are used."),
+    ("input_selection",       "The type of input stream: File, Udp, TcpClient, or TcpServer"),
+    ("output_settings",       "Settings for each output stream, one entry per output"),
+    ("output_selection",      "The type of each output stream: File, Udp, TcpClient, or TcpServer"),
+    ("allowed_output_apids",  "For each output, the APIDs it forwards, or null to forward all APIDs"),
+    ("output_transforms",     "For each output, the transforms applied to each packet before it is written, in order"),
+    ("output_enabled",        "For each output, whether it is enabled. A disabled output keeps its settings but is not opened"),
+    ("output_names",          "For each output, the name identifying it in the GUI, statistics, and errors"),
+    ("allowed_input_apids",   "The APIDs accepted from the input, or null to accept all APIDs"),
+    ("gap_fill",              "A backup input receiving the same downlink, whose packets fill the gaps in the input's sequence counts, holding packets for 'hold_secs'"),
+    ("reorder",               "Hold the input's packets for up to 'max_latency_secs' and 'max_packets', releasing them in 'Timestamp' or 'Sequence' order"),
+    ("replay_query",          "Replay only the packets of an input file with the given APIDs, from the start time to before the end time, using its index"),
+    ("theme",                 "The GUI theme: Dark, Light, or Custom"),
+    ("packet_size",           "Variable to use the CCSDS header's length, or {\"Fixed\": <bytes>} for fixed size packets"),
+    ("little_endian_ccsds",   "Decode the CCSDS primary header as little endian, which violates the standard"),
+    ("frame_settings",        "The number of frame header (prefix) and footer (postfix) bytes around each packet, whether to keep them, and a 'sync_marker' to strip"),
+    ("container_format",      "The recorder format wrapping the input's packets: Raw, {\"LengthPrefixed\": {...}}, or {\"Sfdu\": {...}}, or HexText"),
+    ("max_length_bytes",      "The maximum packet size in bytes that is forwarded"),
+    ("drop_crc_failures",     "Drop packets whose CRC-16, in their last two bytes, is not valid"),
+    ("timestamp_setting",     "When to forward packets: Asap, Replay, {\"Delay\": <duration>}, or {\"Throttle\": <duration>}"),
+    ("timestamp_def",         "The location and format of the timestamp in each packet, used for Replay timing"),
+    ("auto_start",            "Start processing when the application starts"),
+    ("pause_conditions",      "Conditions that pause processing when a packet matches them"),
+    ("rate_alarms",           "Minimum and maximum packet rates of APIDs, or of all packets, that raise an alarm when crossed"),
+    ("stale_timeout",         "Seconds without a packet before an APID is shown as stale, or 0 to disable"),
+    ("ui_scale",              "The scale of the GUI's text and widgets"),
+    ("custom_themes",         "Themes created in the theme editor"),
+    ("custom_theme",          "The name of the custom theme used when the theme is Custom"),
+    ("trigger_settings",      "Trigger based capture, which only writes packets once a trigger condition is met"),
+    ("packet_history_length", "The number of recent packets kept for each APID for the packet inspector"),
+    ("index_files",           "Create an index alongside file inputs and outputs, used to seek to a packet or time"),
+    ("archive_settings",      "Record the header information of each forwarded packet into an SQLite database"),
+    ("parquet_settings",      "Write the header fields and payload of each forwarded packet into a Parquet table per APID"),
+    ("control_port",          "The localhost port of the control socket, or null to disable it"),
+    ("control_address",       "The address the control socket listens on, 127.0.0.1 unless a peer router connects from another machine"),
+    ("control_token",         "The token a control connection must give with 'auth <token>' before any other command, or empty for no token"),
+    ("control_allowed_hosts", "The IP addresses or host names allowed to connect to the control socket, including this machine's if it connects, or empty for any host"),
+    ("peer_settings",         "The control socket address of a peer router that starts, pauses, continues, and cancels with this one"),
+    ("schedule",              "When processing starts and stops, each as a UTC time or a countdown such as '+90' or '+00:01:30'"),
+    ("apid_priorities",       "The APIDs written first ('high_apids') and last ('low_apids') by outputs with a rate limit or a store and forward backlog"),
+    ("bridge",                "Two TCP or plugin streams ('side_a' and 'side_b') whose packets are forwarded to each other, each with its own 'allowed_apids'"),
+    ("outage_settings",       "Simulated link outages that drop everything written to the outputs, repeating every 'period_secs' for 'duration_secs', or at given times"),
+    ("csv_log_settings",      "Log a line per packet to a CSV file"),
+    ("decom_settings",        "Write the values of the given parameters, by APID, byte offset, and type, to a CSV file"),
+    ("raw_capture_settings",  "Write the bytes read from the input, exactly as read and before parsing, to a capture file"),
+    ("log_settings",          "The log level, with levels for particular modules, the log format, syslog, and log file rotation"),
+    ("gui_updates_per_sec",   "The most packet updates sent to the GUI for each APID per second, or 0 for every packet"),
+    ("read_settings",         "The bytes requested in each read of the input, and whether the read size grows while reads fill it"),
+    ("io_engine",             "How network inputs are read: Threaded, with a thread per input, or Polled, with one thread polling every input"),
+    ("output_retry_secs",     "Seconds between attempts to reconnect a failed network or plugin output, or 0 to leave it disconnected"),
+    ("output_open_policy",    "Whether processing starts with the outputs that opened (Continue) or only if every output opens (AllOrNothing)"),
+    ("pause_on_disconnect",   "Stop reading the input while a TCP output is disconnected, continuing once it reconnects"),
+    ("connect_timeout_secs",  "Seconds to wait for a TCP stream to connect when processing starts, or 0 to wait until cancelled"),
+    ("heartbeat_settings",    "Heartbeat packets written to outputs that have not been sent a packet for a time"),
+    ("time_packet_settings",  "Time packets holding the ground time, written to every output at a fixed interval"),
+    ("exit_on_finish",        "Exit once every session has finished processing, rather than waiting to process again"),
+    ("exit_on_alarm",         "Exit with an error when a rate alarm is raised while running without the GUI"),
+    ("dry_run",               "Process packets without writing to the outputs, reporting what would be forwarded to each"),
+    ("stats_interval",        "Seconds between statistics reports when running without the GUI, or 0 to disable them"),
+    ("session_name",          "The name of the main session, shown on its tab in the GUI"),
+    ("sessions",              "Additional routing sessions, each a configuration of its own"),
+];
+
+/// Remove comments from a configuration file. A line starting with '//' is a comment.
+/// Comment lines are replaced with empty lines so that errors are reported at the
+/// line they occur in the file.
+pub fn strip_comments(config_str: &str) -> String {
+    config_str.lines()
+              .map(|line| if line.trim_start().starts_with("//") { "" } else { line })
+              .collect::<Vec<&str>>()
+              .join("\n")
+}
+
+/// Replace each '${NAME}' in a configuration file with the value of the environment variable
+/// NAME. The value is substituted as text, so a string setting is written as "${NAME}" and a
+/// number as ${NAME}. A variable that is not set is an error, reported with its line number.
+pub fn expand_env_vars(config_str: &str) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = config_str;
+
+    while let Some(start) = rest.find("${") {
+        let line_num = config_str[..config_str.len() - rest.len() + start].matches('\n').count() + 1;
+        expanded.push_str(&rest[..start]);
+
+        let after = &rest[start + 2..];
+        let end = after.find('}')
+                       .ok_or_else(|| format!("{}: '${{' without a closing '}}'", line_num))?;
+        let var_name = &after[..end];
+
+        let value = env::var(var_name)
+                        .map_err(|_| format!("{}: environment variable '{}' is not set", line_num, var_name))?;
+        expanded.push_str(&value);
+
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+// Read the text of a configuration file into a value, after removing comments and
+// expanding environment variables. The expanded text is also returned.
+fn config_value(file_name: &str, config_str: &str) -> Result<(String, Value), Vec<String>> {
+    let expanded = expand_env_vars(&strip_comments(config_str))
+                                  .map_err(|err| vec!(format!("{}:{}", file_name, err)))?;
+
+    let value = serde_json::from_str(&expanded).map_err(|err| {
+        vec!(format!("{}:{}:{}: {}", file_name, err.line(), err.column(), err))
+    })?;
+
+    Ok((expanded, value))
+}
+
+// If a configuration value has an include field, load the file it names and merge the
+// value over it. The file name is relative to the directory of the including file.
+// Returns whether a file was included.
+fn merge_includes(file_name: &str, value: &mut Value, depth: usize) -> Result<bool, Vec<String>> {
+    let include = match value.as_object_mut().and_then(|fields| fields.remove(INCLUDE_FIELD)) {
+        Some(Value::String(include)) => include,
+
+        Some(_) => return Err(vec!(format!("{}: '{}' must be a file name", file_name, INCLUDE_FIELD))),
+
+        None => return Ok(false),
+    };
+
+    if depth >= MAX_INCLUDE_DEPTH {
+        return Err(vec!(format!("{}: too many nested includes, including '{}'", file_name, include)));
+    }
+
+    let include_path = Path::new(file_name).parent().unwrap_or(Path::new("")).join(&include);
+    let include_name = include_path.to_string_lossy().to_string();
+
+    let mut include_str = String::new();
+    File::open(&include_path).and_then(|mut file| file.read_to_string(&mut include_str))
+                             .map_err(|err| vec!(format!("{}: could not include {}: {}", file_name, include_name, err)))?;
+
+    let (_, mut base) = config_value(&include_name, &include_str)?;
+    merge_includes(&include_name, &mut base, depth + 1)?;
+
+    merge_values(&mut base, value.clone());
+    *value = base;
+
+    Ok(true)
+}
+
+// Merge a configuration value over a base value. Objects are merged field by field, and
+// any other value, including an array, replaces the base value.
+fn merge_values(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base_fields), Value::Object(fields)) => {
+            for (name, field_value) in fields {
+                match base_fields.get_mut(&name) {
+                    Some(base_field_value) => merge_values(base_field_value, field_value),
+
+                    None => { base_fields.insert(name, field_value); },
+                }
+            }
+        },
+
+        (base, value) => *base = value,
+    }
+}
+
+/// Create the text of a default configuration file, with a comment describing each setting
+pub fn generate_config() -> String {
+    let config: AppConfig = Default::default();
+    let config_str = serde_json::to_string_pretty(&config).unwrap();
+
+    let mut generated = String::new();
+    generated.push_str("// CCSDS Router configuration. Lines starting with '//' are comments.\n");
+
+    for line in config_str.lines() {
+        // top level settings are indented by two spaces
+        if line.starts_with("  \"") && !line.starts_with("   ") {
+            let field_name = line.trim_start().trim_start_matches('"').split('"').next().unwrap_or("");
+            if let Some(&(_, description)) = CONFIG_FIELD_DESCRIPTIONS.iter().find(|&&(name, _)| name == field_name) {
+                generated.push_str(&format!("  // {}\n", description));
+            }
+        }
+
+        generated.push_str(line);
+        generated.push('\n');
+    }
+
+    generated
+}
+
+/// Check a configuration file, returning the configuration if it is valid, or a
+/// description of each problem found along with its location in the file. Each of
+/// the file's profiles is checked as well.
+pub fn validate_config(file_name: &str) -> Result<AppConfig, Vec<String>> {
+    let mut config_str = String::new();
+    File::open(file_name).and_then(|mut file| file.read_to_string(&mut config_str))
+                         .map_err(|err| vec!(format!("{}: {}", file_name, err)))?;
+
+    let config = check_config(file_name, &config_str, None)?;
+
+    let mut problems = Vec::new();
+    for profile in profile_names(file_name) {
+        if let Err(profile_problems) = check_config(file_name, &config_str, Some(&profile)) {
+            problems.extend(profile_problems.iter().map(|problem| format!("{} (profile '{}')", problem, profile)));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(config)
+    } else {
+        Err(problems)
+    }
+}
+
+/// Check the settings of a configuration for problems that would stop processing, so they
+/// can be reported before processing starts rather than when a stream fails to open.
+/// Returns a description of each problem found.
+pub fn config_problems(config: &AppConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let (source_names, sink_names) = plugin_names();
+
+    problems.extend(stream_problems("Input", config.input_selection, &config.input_settings, &source_names));
+    if config.input_selection == StreamOption::File && !Path::new(&config.input_settings.file.file_name).is_file() {
+        problems.push(format!("Input file '{}' does not exist", config.input_settings.file.file_name));
+    }
+
+    if config.output_selection.is_empty() {
+        problems.push("There are no outputs".to_string());
+    } else if !(0..config.output_selection.len()).any(|index| config.is_output_enabled(index)) {
+        problems.push("Every output is disabled, so no packets would be forwarded".to_string());
+    }
+
+    for (index, (selection, settings)) in config.output_selection.iter().zip(config.output_settings.iter()).enumerate() {
+        // a disabled output is not opened, so its settings are not checked until it is enabled
+        if !config.is_output_enabled(index) {
+            continue;
+        }
+
+        problems.extend(stream_problems(&config.output_label(index), *selection, settings, &sink_names));
+
+        if let Some(Some(ref apids)) = config.allowed_output_apids.get(index) {
+            if apids.is_empty() {
+                problems.push(format!("{} does not allow any APIDs, so no packets would be sent to it", config.output_label(index)));
+            }
+        }
+
+        let store_forward = &settings.store_forward;
+        if store_forward.enabled {
+            if *selection == StreamOption::File {
+                problems.push(format!("{} stores and forwards its packets, but is a file, which is not reopened once it fails", config.output_label(index)));
+            }
+
+            if store_forward.directory.trim().is_empty() {
+                problems.push(format!("{} stores and forwards its packets, but has no backlog directory", config.output_label(index)));
+            }
+
+            if store_forward.max_backlog_bytes == 0 {
+                problems.push(format!("{} stores and forwards its packets, but its backlog can not hold any", config.output_label(index)));
+            }
+        }
+
+        if settings.sync_marker.enabled {
+            for problem in settings.sync_marker.problems() {
+                problems.push(format!("{}: {}", config.output_label(index), problem));
+            }
+
+            if *selection == StreamOption::File && settings.file.format == FileFormat::Text {
+                problems.push(format!("{} writes a sync marker before each packet, but is a text output", config.output_label(index)));
+            }
+        }
+
+        if settings.frame_output.enabled {
+            for problem in settings.frame_output.problems() {
+                problems.push(format!("{}: {}", config.output_label(index), problem));
+            }
+
+            if *selection == StreamOption::File && settings.file.format == FileFormat::Text {
+                problems.push(format!("{} writes frames, but is a text output", config.output_label(index)));
+            }
+
+            if *selection == StreamOption::File && settings.file.metadata_sidecar {
+                problems.push(format!("{} writes frames, so its sidecar could not give the offset of each packet", config.output_label(index)));
+            }
+        }
+    }
+
+    if config.frame_settings.prefix_bytes < 0 {
+        problems.push(format!("The frame prefix must not be negative, but is {} bytes", config.frame_settings.prefix_bytes));
+    }
+
+    if config.frame_settings.postfix_bytes < 0 {
+        problems.push(format!("The frame postfix must not be negative, but is {} bytes", config.frame_settings.postfix_bytes));
+    }
+
+    for problem in config.frame_settings.sync_marker.problems() {
+        problems.push(format!("Input: {}", problem));
+    }
+
+    if let Some(offset) = config.frame_settings.transfer_frame_offset {
+        if (offset + TM_FRAME_HEADER_BYTES) as i32 > config.frame_settings.prefix_bytes {
+            problems.push(format!("The transfer frame header ends at byte {}, past the end of the {} byte frame header",
+                                  offset + TM_FRAME_HEADER_BYTES,
+                                  config.frame_settings.prefix_bytes));
+        }
+    }
+
+    if let Some(offset) = config.frame_settings.fec_corrections_offset {
+        if offset as i32 >= config.frame_settings.prefix_bytes {
+            problems.push(format!("The FEC corrections byte at offset {} is past the end of the {} byte frame header",
+                                  offset,
+                                  config.frame_settings.prefix_bytes));
+        }
+    }
+
+    problems.extend(config.decom_settings.problems());
+
+    problems.extend(config.schedule.problems());
+
+    problems.extend(config.outage_settings.problems());
+
+    problems.extend(config.apid_priorities.problems());
+
+    if config.bridge.enabled {
+        problems.extend(bridge_problems(&config.bridge, &source_names, &sink_names));
+    }
+
+    problems.extend(config.gap_fill.problems());
+
+    problems.extend(config.reorder.problems());
+
+    if config.gap_fill.enabled {
+        problems.extend(stream_problems("Backup input", config.gap_fill.selection, &config.gap_fill.settings, &source_names));
+        if config.gap_fill.selection == StreamOption::File && !Path::new(&config.gap_fill.settings.file.file_name).is_file() {
+            problems.push(format!("Backup input file '{}' does not exist", config.gap_fill.settings.file.file_name));
+        }
+
+        // the backup's packets would fill the gaps left by the packets the query leaves out
+        if config.replay_query.enabled && config.input_selection == StreamOption::File {
+            problems.push("Gap filling can not be used with a replay query".to_string());
+        }
+    }
+
+    if config.replay_query.enabled && config.input_selection == StreamOption::File {
+        for (name, time_str) in [("start", &config.replay_query.start), ("end", &config.replay_query.end)].iter() {
+            if !time_str.trim().is_empty() {
+                if let Err(problem) = config.timestamp_def.epoch.parse_time(time_str) {
+                    problems.push(format!("The replay query {} time is not valid: {}", name, problem));
+                }
+            }
+        }
+    }
+
+    if config.parquet_settings.enabled && config.parquet_settings.directory.is_empty() {
+        problems.push("The Parquet archive is enabled, but has no directory".to_string());
+    }
+
+    if let ContainerFormat::LengthPrefixed(ref length_prefixed) = config.container_format {
+        if let Some(problem) = length_prefixed.problem() {
+            problems.push(problem);
+        }
+    }
+
+    if config.max_length_bytes <= 0 {
+        problems.push(format!("The maximum packet length must be greater than 0, but is {} bytes", config.max_length_bytes));
+    }
+
+    if config.heartbeat_settings.enabled {
+        if config.heartbeat_settings.data.is_empty() {
+            problems.push("The heartbeat packet must have at least one byte of data".to_string());
+        }
+
+        if !(config.heartbeat_settings.interval_secs > 0.0) {
+            problems.push(format!("The heartbeat interval must be greater than 0, but is {} seconds", config.heartbeat_settings.interval_secs));
+        }
+    }
+
+    if config.time_packet_settings.enabled {
+        let time_def = &config.time_packet_settings.time_def;
+
+        if time_def.num_bytes_seconds == TimeSize::ZeroBytes && time_def.num_bytes_subseconds == TimeSize::ZeroBytes {
+            problems.push("The time packet must have a seconds or subseconds field".to_string());
+        }
+
+        if time_def.offset < 0 {
+            problems.push(format!("The time packet's time offset must not be negative, but is {} bytes", time_def.offset));
+        }
+
+        if time_def.num_bytes_subseconds != TimeSize::ZeroBytes && !(time_def.subsecond_resolution > 0.0) {
+            problems.push(format!("The time packet's subsecond resolution must be greater than 0, but is {}", time_def.subsecond_resolution));
+        }
+
+        if let Some(problem) = time_def.epoch.problem() {
+            problems.push(format!("The time packet's epoch is not valid: {}", problem));
+        }
+
+        if !(config.time_packet_settings.interval_secs > 0.0) {
+            problems.push(format!("The time packet interval must be greater than 0, but is {} seconds", config.time_packet_settings.interval_secs));
+        }
+    }
+
+    if !(config.timestamp_def.subsecond_resolution > 0.0) {
+        problems.push(format!("The subsecond resolution must be greater than 0, but is {}", config.timestamp_def.subsecond_resolution));
+    }
+
+    if let Some(problem) = config.timestamp_def.epoch.problem() {
+        problems.push(format!("The timestamp epoch is not valid: {}", problem));
+    }
+
+    problems
+}
+
+// Check the settings of a stream, using the names of the plugins registered for its direction
+fn stream_problems(name: &str, selection: StreamOption, settings: &StreamSettings, plugin_names: &Vec<String>) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    let (ip, port) = match selection {
+        StreamOption::File => {
+            if settings.file.file_name.trim().is_empty() {
+                problems.push(format!("{} does not name a file", name));
+            }
+            return problems;
+        },
+
+        StreamOption::Plugin => {
+            if !plugin_names.contains(&settings.plugin.name) {
+                problems.push(format!("{} uses the plugin '{}', which is not registered", name, settings.plugin.name));
+            }
+            return problems;
+        },
+
+        StreamOption::Udp => (&settings.udp.ip, settings.udp.port),
+
+        StreamOption::TcpClient => (&settings.tcp_client.ip, settings.tcp_client.port),
+
+        StreamOption::TcpServer => (&settings.tcp_server.ip, settings.tcp_server.port),
+    };
+
+    if ip.parse::<Ipv4Addr>().is_err() {
+        problems.push(format!("{} has the IP address '{}', which is not a valid IPv4 address", name, ip));
+    }
+
+    if port == 0 {
+        problems.push(format!("{} has port 0", name));
+    }
+
+    problems
+}
+
+/// A description of each problem with a bridge, including the settings of each side's stream.
+/// A plugin side is both read and written, so it needs a source and a sink of the same name.
+pub fn bridge_problems(bridge: &BridgeSettings, source_names: &Vec<String>, sink_names: &Vec<String>) -> Vec<String> {
+    let mut problems = bridge.problems();
+
+    for (label, side) in [(bridge.side_a_label(), &bridge.side_a), (bridge.side_b_label(), &bridge.side_b)].iter() {
+        let name = format!("{} of the bridge", label);
+        match side.selection {
+            StreamOption::Plugin => {
+                if !source_names.contains(&side.settings.plugin.name) || !sink_names.contains(&side.settings.plugin.name) {
+                    problems.push(format!("{} uses the plugin '{}', which is not registered as both an input and an output plugin",
+                                          name,
+                                          side.settings.plugin.name));
+                }
+            },
+
+            StreamOption::TcpClient | StreamOption::TcpServer => {
+                problems.extend(stream_problems(&name, side.selection, &side.settings, source_names));
+            },
+
+            _ => {},
+        }
+    }
+
+    problems
+}
+
+/// The names of the profiles in a configuration file, in the order they appear. A file
+/// that can not be loaded has no profiles.
+pub fn profile_names(file_name: &str) -> Vec<String> {
+    let mut config_str = String::new();
+    if File::open(file_name).and_then(|mut file| file.read_to_string(&mut config_str)).is_err() {
+        return Vec::new();
+    }
+
+    let mut value = match config_value(file_name, &config_str) {
+        Ok((_, value)) => value,
+
+        Err(_) => return Vec::new(),
+    };
+
+    if merge_includes(file_name, &mut value, 0).is_err() {
+        return Vec::new();
+    }
+
+    match value.get(PROFILES_FIELD) {
+        Some(Value::Object(profiles)) => profiles.keys().cloned().collect(),
+
+        _ => Vec::new(),
+    }
+}
+
+/// Parse and check the contents of a configuration file, using the settings of a profile
+/// if one is given. Fields that are not known are reported as problems, rather than ignored,
+/// so that a misspelled setting is not silently replaced by its default.
+pub fn check_config(file_name: &str, config_str: &str, profile: Option<&str>) -> Result<AppConfig, Vec<String>> {
+    let (expanded, mut value) = config_value(file_name, config_str)?;
+
+    let mut merged = merge_includes(file_name, &mut value, 0)?;
+
+    let profiles = value.as_object_mut().and_then(|fields| fields.remove(PROFILES_FIELD));
+    if let Some(profile) = profile {
+        let profile_value = match profiles {
+            Some(Value::Object(mut profiles)) => {
+                let profile_names = profiles.keys().cloned().collect::<Vec<String>>().join(", ");
+                profiles.remove(profile)
+                        .ok_or_else(|| vec!(format!("{}: no profile named '{}', the profiles are: {}", file_name, profile, profile_names)))?
+            },
+
+            Some(_) => return Err(vec!(format!("{}: '{}' must map profile names to settings", file_name, PROFILES_FIELD))),
+
+            None => return Err(vec!(format!("{}: no profile named '{}', as there are no profiles", file_name, profile))),
+        };
+
+        merge_values(&mut value, profile_value);
+        merged = true;
+    }
+
+    let config: AppConfig;
+    if merged {
+        // the location of an error is not known once files are merged
+        config = serde_json::from_value(value.clone())
+                            .map_err(|err| vec!(format!("{}: {}", file_name, err)))?;
+    } else {
+        config = serde_json::from_str(&expanded).map_err(|err| {
+            vec!(format!("{}:{}:{}: {}", file_name, err.line(), err.column(), err))
+        })?;
+    }
+
+    // fields that are not known are ignored when loading, so they are found by comparing
+    // against the fields of a default configuration.
+    let default_value = serde_json::to_value(&AppConfig::default()).unwrap_or(Value::Null);
+
+    let mut problems = Vec::new();
+    for path in unknown_fields(&value, &default_value, "") {
+        let field_name = path.rsplit('.').next().unwrap_or("");
+        let line_num = config_str.lines()
+                                 .position(|line| line.contains(&format!("\"{}\"", field_name)))
+                                 .map(|index| index + 1)
+                                 .unwrap_or(0);
+        problems.push(format!("{}:{}: unknown field '{}'", file_name, line_num, path));
+    }
+
+    if problems.is_empty() {
+        Ok(config)
+    } else {
+        Err(problems)
+    }
+}
+
+// Find the fields of a configuration value that are not in the default configuration,
+// returning the path to each field.
+fn unknown_fields(value: &Value, default_value: &Value, path: &str) -> Vec<String> {
+    let mut unknown = Vec::new();
+
+    if let (Value::Object(fields), Value::Object(default_fields)) = (value, default_value) {
+        for (name, field_value) in fields.iter() {
+            let field_path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+
+            match default_fields.get(name) {
+                Some(default_field_value) => {
+                    if name == "sessions" {
+                        // each session is a configuration of its own
+                        if let Value::Array(sessions) = field_value {
+                            for (index, session) in sessions.iter().enumerate() {
+                                unknown.extend(unknown_fields(session, default_value, &format!("{}[{}]", field_path, index)));
+                            }
+                        }
+                    } else if default_field_value.as_object().map_or(false, |default_fields| default_fields.is_empty()) {
+                        // a map, such as the module log levels, has names that are not known ahead of time
+                    } else if let (Value::Array(items), Value::Array(default_items)) = (field_value, default_field_value) {
+                        // arrays are checked against the first default item, if there is one
+                        if let Some(default_item) = default_items.get(0) {
+                            for (index, item) in items.iter().enumerate() {
+                                unknown.extend(unknown_fields(item, default_item, &format!("{}[{}]", field_path, index)));
+                            }
+                        }
+                    } else {
+                        unknown.extend(unknown_fields(field_value, default_field_value, &field_path));
+                    }
+                },
+
+                None => unknown.push(field_path),
+            }
+        }
+    }
+
+    unknown
+}
@@ -0,0 +1,313 @@
+use bytes::{Bytes, BytesMut};
+
+use types::parse_hex_bytes;
+
+
+/// The largest record a container may hold. A larger record length is taken to mean that the
+/// input is not in the configured format, rather than waiting for the record to be read.
+pub const MAX_RECORD_BYTES: usize = 16 * 1024 * 1024;
+
+/// The length of an SFDU label
+pub const SFDU_LABEL_BYTES: usize = 20;
+
+/// The type of the CHDO holding the data of a DSN SFDU
+pub const DATA_CHDO_TYPE: u16 = 10;
+
+/// The length of a CHDO's type and length fields
+const CHDO_HEADER_BYTES: usize = 4;
+
+/// The container format of an input records the input stream as a series of records, each
+/// wrapping part of the stream of CCSDS packets, such as the files written by a ground station's
+/// recorder. The records are unwrapped before the packets are parsed, so the packet settings
+/// apply to the unwrapped bytes.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum ContainerFormat {
+    /// The input stream is not in a container, and is parsed directly
+    Raw,
+
+    /// Each record starts with a header giving its length
+    LengthPrefixed(LengthPrefixedSettings),
+
+    /// Each record is an SFDU with a CCSDS SFDU label, as written by ITOS and the DSN
+    Sfdu(SfduSettings),
+
+    /// The input is hex text, such as a packet dump pasted from an email, and each line is
+    /// converted to bytes. Blank lines and lines starting with '#' are skipped.
+    HexText,
+}
+
+impl Default for ContainerFormat {
+    fn default() -> Self {
+        ContainerFormat::Raw
+    }
+}
+
+/// The layout of a length prefixed record, which is a header, containing a length field, then
+/// the record's data, then an optional footer.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct LengthPrefixedSettings {
+    /// The bytes of the record header before the length field
+    #[serde(default)]
+    pub length_offset: usize,
+
+    /// The size of the length field, which is 1, 2, or 4 bytes
+    #[serde(default = "default_length_bytes")]
+    pub length_bytes: usize,
+
+    /// The length field is little endian
+    #[serde(default)]
+    pub little_endian: bool,
+
+    /// The bytes of the record header, including the length field
+    #[serde(default = "default_length_bytes")]
+    pub header_bytes: usize,
+
+    /// The bytes of the record footer, which are dropped with the header
+    #[serde(default)]
+    pub footer_bytes: usize,
+
+    /// The bytes added to the length field to give the length of the whole record. With the
+    /// default, the length field gives the length of the data following a 4 byte header.
+    #[serde(default = "default_length_adjust")]
+    pub length_adjust: i64,
+}
+
+fn default_length_bytes() -> usize {
+    4
+}
+
+fn default_length_adjust() -> i64 {
+    4
+}
+
+impl Default for LengthPrefixedSettings {
+    fn default() -> Self {
+        LengthPrefixedSettings {
+            length_offset: 0,
+            length_bytes: default_length_bytes(),
+            little_endian: false,
+            header_bytes: default_length_bytes(),
+            footer_bytes: 0,
+            length_adjust: default_length_adjust(),
+        }
+    }
+}
+
+impl LengthPrefixedSettings {
+    /// A description of the problem with the layout, if it is not valid
+    pub fn problem(&self) -> Option<String> {
+        if self.length_bytes != 1 && self.length_bytes != 2 && self.length_bytes != 4 {
+            return Some(format!("The record length field must be 1, 2, or 4 bytes, but is {} bytes", self.length_bytes));
+        }
+
+        if self.length_offset + self.length_bytes > self.header_bytes {
+            return Some(format!("The record length field ends at byte {}, past the end of the {} byte record header",
+                                self.length_offset + self.length_bytes,
+                                self.header_bytes));
+        }
+
+        None
+    }
+}
+
+/// The settings of SFDU records
+#[derive(PartialEq, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SfduSettings {
+    /// The SFDUs are DSN SFDUs, whose value field is a series of CHDOs (Compressed Header Data
+    /// Objects), and only the data CHDO is parsed. Otherwise the whole value field is parsed.
+    #[serde(default)]
+    pub data_chdo: bool,
+}
+
+/// A ContainerReader holds the bytes read from an input stream in a container format, and
+/// unwraps the records within them as they are completed.
+pub struct ContainerReader {
+    format: ContainerFormat,
+    bytes: BytesMut,
+}
+
+impl ContainerReader {
+    /// A reader for the given format, or None if the input stream is not in a container
+    pub fn new(format: &ContainerFormat) -> Option<ContainerReader> {
+        if *format == ContainerFormat::Raw {
+            return None;
+        }
+
+        Some(ContainerReader {
+            format: format.clone(),
+            bytes: BytesMut::new(),
+        })
+    }
+
+    /// The bytes read from the input stream that are not yet part of a complete record
+    pub fn bytes(&mut self) -> &mut BytesMut {
+        &mut self.bytes
+    }
+
+    /// Discard any partial record
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+
+    /// Take the next complete record from the bytes read, returning the data it wraps, or None
+    /// if no record is complete. An error means the bytes are not in the container format.
+    pub fn pull_record(&mut self) -> Result<Option<Bytes>, String> {
+        let record = match self.format {
+            ContainerFormat::Raw => return Ok(None),
+
+            ContainerFormat::HexText => {
+                return match self.bytes.iter().position(|byte| *byte == b'\n') {
+                    Some(line_end) => {
+                        let line = self.bytes.split_to(line_end + 1);
+                        hex_line(&line).map(Some)
+                    },
+
+                    None if self.bytes.len() > MAX_RECORD_BYTES => {
+                        Err(format!("Hex text line is longer than {} bytes", MAX_RECORD_BYTES))
+                    },
+
+                    None => Ok(None),
+                };
+            },
+
+            ContainerFormat::LengthPrefixed(ref settings) => length_prefixed_record(settings, &self.bytes)?,
+
+            ContainerFormat::Sfdu(ref settings) => sfdu_record(settings, &self.bytes)?,
+        };
+
+        match record {
+            Some((record_length, data_start, data_end)) => {
+                let record_bytes = self.bytes.split_to(record_length).freeze();
+                Ok(Some(record_bytes.slice(data_start, data_end)))
+            },
+
+            None => Ok(None),
+        }
+    }
+
+    /// Take the data of a record ending at the end of the input stream, which is the last line
+    /// of hex text when it does not end in a newline. An error means that a record was cut short.
+    pub fn pull_final(&mut self) -> Result<Option<Bytes>, String> {
+        if self.bytes.is_empty() {
+            return Ok(None);
+        }
+
+        match self.format {
+            ContainerFormat::HexText => {
+                let line = self.bytes.take();
+                hex_line(&line).map(Some)
+            },
+
+            _ => Err(format!("The input ended partway through a record, with {} bytes of it read", self.bytes.len())),
+        }
+    }
+}
+
+/// The bytes of a line of hex text
+fn hex_line(line: &[u8]) -> Result<Bytes, String> {
+    let text = String::from_utf8_lossy(line);
+    let text = text.trim();
+
+    if text.is_empty() || text.starts_with('#') {
+        return Ok(Bytes::new());
+    }
+
+    parse_hex_bytes(text).map(Bytes::from)
+}
+
+/// The length of the record at the start of the bytes, and the range of its data, if the
+/// whole record has been read
+type RecordLayout = Option<(usize, usize, usize)>;
+
+fn length_prefixed_record(settings: &LengthPrefixedSettings, bytes: &[u8]) -> Result<RecordLayout, String> {
+    if bytes.len() < settings.header_bytes {
+        return Ok(None);
+    }
+
+    let length_field = &bytes[settings.length_offset..settings.length_offset + settings.length_bytes];
+    let mut length: u64 = 0;
+    for index in 0..length_field.len() {
+        let byte = if settings.little_endian {
+            length_field[length_field.len() - 1 - index]
+        } else {
+            length_field[index]
+        };
+        length = (length << 8) | byte as u64;
+    }
+
+    let record_length = length as i64 + settings.length_adjust;
+    if record_length < (settings.header_bytes + settings.footer_bytes) as i64 || record_length as usize > MAX_RECORD_BYTES {
+        return Err(format!("Record length of {} bytes is not valid, so the input is not in the configured record format", record_length));
+    }
+    let record_length = record_length as usize;
+
+    if bytes.len() < record_length {
+        return Ok(None);
+    }
+
+    Ok(Some((record_length, settings.header_bytes, record_length - settings.footer_bytes)))
+}
+
+fn sfdu_record(settings: &SfduSettings, bytes: &[u8]) -> Result<RecordLayout, String> {
+    if bytes.len() < SFDU_LABEL_BYTES {
+        return Ok(None);
+    }
+
+    // the label is the control authority (4 bytes), version (1), class (1), spare (2),
+    // data description (4), and the length of the value field (8).
+    let length_field = &bytes[12..SFDU_LABEL_BYTES];
+    let value_length = match bytes[4] {
+        // version 1 gives the length as ASCII decimal digits
+        b'1' => {
+            let digits = String::from_utf8_lossy(length_field);
+            digits.trim().parse::<u64>()
+                  .map_err(|_| format!("SFDU label length '{}' is not a number, so the input is not in SFDU records", digits))?
+        },
+
+        // version 2 gives the length as a big endian binary number
+        b'2' => {
+            length_field.iter().fold(0, |length, byte| (length << 8) | *byte as u64)
+        },
+
+        b'3' => {
+            return Err("SFDUs delimited by a marker (version 3) are not supported".to_string());
+        },
+
+        version => {
+            return Err(format!("SFDU label version {:#04X} is not valid, so the input is not in SFDU records", version));
+        },
+    };
+
+    if value_length as usize > MAX_RECORD_BYTES {
+        return Err(format!("SFDU length of {} bytes is not valid, so the input is not in SFDU records", value_length));
+    }
+    let record_length = SFDU_LABEL_BYTES + value_length as usize;
+
+    if bytes.len() < record_length {
+        return Ok(None);
+    }
+
+    if !settings.data_chdo {
+        return Ok(Some((record_length, SFDU_LABEL_BYTES, record_length)));
+    }
+
+    // the data CHDO follows the header CHDOs, which are within an aggregation CHDO
+    let mut chdo_start = SFDU_LABEL_BYTES;
+    while chdo_start + CHDO_HEADER_BYTES <= record_length {
+        let chdo_type = ((bytes[chdo_start] as u16) << 8) | bytes[chdo_start + 1] as u16;
+        let chdo_length = ((bytes[chdo_start + 2] as usize) << 8) | bytes[chdo_start + 3] as usize;
+        let chdo_data_start = chdo_start + CHDO_HEADER_BYTES;
+
+        if chdo_data_start + chdo_length > record_length {
+            break;
+        }
+
+        if chdo_type == DATA_CHDO_TYPE {
+            return Ok(Some((record_length, chdo_data_start, chdo_data_start + chdo_length)));
+        }
+
+        chdo_start = chdo_data_start + chdo_length;
+    }
+
+    Err("SFDU has no data CHDO, so the input is not in DSN SFDU records".to_string())
+}
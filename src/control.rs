@@ -0,0 +1,250 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+
+/// How long a connection waits for the application to act on a command
+const CONTROL_REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The help text sent in reply to the 'help' command
+pub const CONTROL_HELP: &str =
+"start          start processing each session that is not already running
+start_at <t>   start each session that is not already running at a UNIX time, in seconds
+pause          pause each session that is processing
+continue       continue each paused session
+cancel         cancel processing in each session
+stats          print the statistics of each session
+load <file>    load a configuration file, replacing the current configuration
+auth <token>   authenticate the connection, when the control socket requires a token
+help           print this message";
+
+/// A ControlCommand is a command received on the control socket
+#[derive(Debug, PartialEq, Clone)]
+pub enum ControlCommand {
+    Start,
+    /// Start at the given time, so routers can start together
+    StartAt(SystemTime),
+    Pause,
+    Continue,
+    Cancel,
+    Stats,
+    Load(String),
+    Help,
+}
+
+impl ControlCommand {
+    /// Parse a line of the control protocol into a command
+    pub fn parse(line: &str) -> Result<ControlCommand, String> {
+        let line = line.trim();
+        let (command, argument) = match line.find(char::is_whitespace) {
+            Some(index) => (&line[..index], line[index..].trim()),
+
+            None => (line, ""),
+        };
+
+        match command.to_lowercase().as_str() {
+            "start"    => Ok(ControlCommand::Start),
+            "pause"    => Ok(ControlCommand::Pause),
+            "continue" => Ok(ControlCommand::Continue),
+            "cancel"   => Ok(ControlCommand::Cancel),
+            "stats"    => Ok(ControlCommand::Stats),
+            "help"     => Ok(ControlCommand::Help),
+
+            "start_at" => {
+                let start_secs: f64 = argument.parse()
+                                              .map_err(|_| format!("start_at requires a UNIX time in seconds, not '{}'", argument))?;
+                if !(start_secs >= 0.0) {
+                    return Err(format!("start_at requires a UNIX time in seconds, not '{}'", argument));
+                }
+
+                Ok(ControlCommand::StartAt(UNIX_EPOCH + Duration::new(start_secs as u64, (start_secs.fract() * 1_000_000_000.0) as u32)))
+            },
+
+            "load" => {
+                if argument.is_empty() {
+                    Err("load requires a configuration file name".to_string())
+                } else {
+                    Ok(ControlCommand::Load(argument.to_string()))
+                }
+            },
+
+            _ => Err(format!("unknown command '{}', try 'help'", command)),
+        }
+    }
+}
+
+/// A ControlRequest is a command sent to the application, along with a channel for
+/// the reply that is sent back to the client.
+#[derive(Debug)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub reply: Sender<String>,
+}
+
+/// Resolve the hosts allowed to connect to the control socket, given as IP addresses or host
+/// names, into their addresses
+pub fn resolve_allowed_hosts(allowed_hosts: &[String]) -> Result<Vec<IpAddr>, String> {
+    let mut addresses = Vec::new();
+
+    for host in allowed_hosts.iter().map(|host| host.trim()).filter(|host| !host.is_empty()) {
+        match host.parse::<IpAddr>() {
+            Ok(address) => addresses.push(address),
+
+            Err(_) => {
+                let resolved = (host, 0).to_socket_addrs()
+                                        .map_err(|err| format!("Could not resolve allowed control host '{}': {}", host, err))?;
+                addresses.extend(resolved.map(|socket_addr| socket_addr.ip()));
+            },
+        }
+    }
+
+    Ok(addresses)
+}
+
+/// Whether a connection from the given address is accepted. Without allowed hosts every
+/// connection is accepted, and otherwise only connections from an allowed host, so connections
+/// from the same machine are only accepted if it is listed, such as by 127.0.0.1 or localhost.
+fn host_allowed(address: &IpAddr, allowed_hosts: &[IpAddr]) -> bool {
+    allowed_hosts.is_empty() || allowed_hosts.contains(address)
+}
+
+/// Compare a token given by a client with the control socket's token, taking the same time
+/// wherever they differ so the token can not be guessed a byte at a time
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len() &&
+    given.bytes().zip(token.bytes()).fold(0, |difference, (given_byte, token_byte)| difference | (given_byte ^ token_byte)) == 0
+}
+
+/// Start the control socket, listening for connections on the given address, which is
+/// localhost unless another router needs to connect. Each connection sends commands as
+/// lines of text, and receives a reply to each command. Commands are received from the
+/// returned channel, and must be replied to through their reply channel.
+///
+/// If a token is given, each connection must send 'auth <token>' before any other command.
+/// If allowed hosts are given, connections from any other host are refused, including this machine
+/// unless it is listed.
+pub fn start_control_server(address: &str,
+                            port: u16,
+                            token: &str,
+                            allowed_hosts: &[String]) -> Result<Receiver<ControlRequest>, String> {
+    let allowed_hosts = resolve_allowed_hosts(allowed_hosts)?;
+
+    let listener = TcpListener::bind((address, port))
+                               .map_err(|err| format!("Could not open control socket on {} port {}: {}", address, port, err))?;
+
+    let (request_sender, request_receiver) = channel();
+    let token = token.trim().to_string();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    // a connection whose address can not be read can not be checked, so it is refused
+                    match stream.peer_addr() {
+                        Ok(peer_addr) => {
+                            if !host_allowed(&peer_addr.ip(), &allowed_hosts) {
+                                warn!("Control connection from {} refused, as it is not an allowed host", peer_addr);
+                                continue;
+                            }
+                        },
+
+                        Err(err) => {
+                            warn!("Control connection refused, as its address could not be read: {}", err);
+                            continue;
+                        },
+                    }
+
+                    let request_sender = request_sender.clone();
+                    let token = token.clone();
+                    thread::spawn(move || {
+                        control_connection(stream, request_sender, &token);
+                    });
+                },
+
+                Err(err) => {
+                    warn!("Control socket connection error: {}", err);
+                },
+            }
+        }
+    });
+
+    Ok(request_receiver)
+}
+
+// Handle the commands from a single control connection until it is closed. With a token, only
+// 'auth' and 'help' are accepted until the connection gives the token, and a wrong token closes
+// the connection.
+fn control_connection(stream: TcpStream, request_sender: Sender<ControlRequest>, token: &str) {
+    let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or("unknown".to_string());
+    info!("Control connection from {}", peer);
+
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+
+        Err(err) => {
+            warn!("Control connection error: {}", err);
+            return;
+        },
+    };
+
+    let mut authenticated = token.is_empty();
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+
+            Err(_) => break,
+        };
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if line == "auth" || line.starts_with("auth ") {
+            let given = line["auth".len()..].trim();
+
+            if !token.is_empty() {
+                if !token_matches(given, token) {
+                    warn!("Control connection from {} gave the wrong token", peer);
+                    let _ = writeln!(writer, "error: wrong token");
+                    break;
+                }
+
+                info!("Control connection from {} authenticated", peer);
+            }
+            authenticated = true;
+
+            if writeln!(writer, "ok").is_err() {
+                break;
+            }
+            continue;
+        }
+
+        let reply = match ControlCommand::parse(line) {
+            Ok(ref command) if !authenticated && *command != ControlCommand::Help => {
+                "error: not authenticated, send 'auth <token>' first".to_string()
+            },
+
+            Ok(command) => {
+                let (reply_sender, reply_receiver) = channel();
+                if request_sender.send(ControlRequest { command: command, reply: reply_sender }).is_err() {
+                    break;
+                }
+
+                reply_receiver.recv_timeout(CONTROL_REPLY_TIMEOUT)
+                              .unwrap_or("error: no reply from the application".to_string())
+            },
+
+            Err(err) => format!("error: {}", err),
+        };
+
+        if writeln!(writer, "{}", reply).is_err() {
+            break;
+        }
+    }
+
+    info!("Control connection from {} closed", peer);
+}
@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::SystemTime;
+
+use chrono::prelude::*;
+
+use floating_duration::TimeAsFloat;
+
+use types::*;
+use stream::*;
+
+
+/// The type of a parameter's value within a packet
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum ParameterType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl ParameterType {
+    pub fn num_bytes(&self) -> usize {
+        match self {
+            ParameterType::U8  | ParameterType::I8 => 1,
+            ParameterType::U16 | ParameterType::I16 => 2,
+            ParameterType::U32 | ParameterType::I32 | ParameterType::F32 => 4,
+            ParameterType::U64 | ParameterType::I64 | ParameterType::F64 => 8,
+        }
+    }
+}
+
+/// A ParameterDef gives the location and type of a parameter within the packets of an APID,
+/// and the conversion of its raw value to engineering units.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ParameterDef {
+    /// The name of the parameter, used as its column heading
+    pub name: String,
+
+    pub apid: u16,
+
+    /// The byte offset of the parameter from the start of the packet, including the primary header
+    pub offset: usize,
+
+    pub data_type: ParameterType,
+
+    #[serde(default)]
+    pub little_endian: bool,
+
+    /// The raw value is multiplied by the scale, and then the bias is added
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
+    #[serde(default)]
+    pub bias: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl ParameterDef {
+    /// The parameter's value in a packet, in engineering units, or None if the packet is too
+    /// short to hold it
+    pub fn decode(&self, packet_bytes: &[u8]) -> Option<f64> {
+        let num_bytes = self.data_type.num_bytes();
+        if self.offset + num_bytes > packet_bytes.len() {
+            return None;
+        }
+
+        let mut raw_bytes = [0u8; 8];
+        raw_bytes[..num_bytes].copy_from_slice(&packet_bytes[self.offset..self.offset + num_bytes]);
+        if self.little_endian {
+            raw_bytes[..num_bytes].reverse();
+        }
+
+        // the bytes are now big endian, and are read into the low bytes of a u64
+        let raw = raw_bytes[..num_bytes].iter().fold(0u64, |raw, byte| (raw << 8) | *byte as u64);
+
+        let value = match self.data_type {
+            ParameterType::U8 | ParameterType::U16 | ParameterType::U32 | ParameterType::U64 => raw as f64,
+            ParameterType::I8 => raw as u8 as i8 as f64,
+            ParameterType::I16 => raw as u16 as i16 as f64,
+            ParameterType::I32 => raw as u32 as i32 as f64,
+            ParameterType::I64 => raw as i64 as f64,
+            ParameterType::F32 => f32::from_bits(raw as u32) as f64,
+            ParameterType::F64 => f64::from_bits(raw),
+        };
+
+        Some(value * self.scale + self.bias)
+    }
+}
+
+/// The decom CSV writes the values of selected parameters to a CSV file
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct DecomSettings {
+    pub enabled: bool,
+
+    /// The CSV file to write. If empty, a file named by the time processing
+    /// started is written to the logs directory.
+    #[serde(default)]
+    pub file_name: String,
+
+    /// The parameters written, in the order of their columns
+    #[serde(default)]
+    pub parameters: Vec<ParameterDef>,
+}
+
+impl DecomSettings {
+    /// A description of each problem with the parameter definitions
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.enabled && self.parameters.is_empty() {
+            problems.push("The decom CSV is enabled, but no parameters are defined".to_string());
+        }
+
+        for (index, parameter) in self.parameters.iter().enumerate() {
+            if parameter.name.is_empty() {
+                problems.push(format!("Decom parameter {} has no name", index));
+            } else if self.parameters[..index].iter().any(|other| other.name == parameter.name) {
+                problems.push(format!("Decom parameter '{}' is defined more than once", parameter.name));
+            }
+        }
+
+        problems
+    }
+}
+
+/// A DecomCsv writes a line for each packet containing one of the selected parameters, giving
+/// the packet's receive time, embedded timestamp in seconds, and APID, followed by a column for
+/// each parameter. The columns of parameters that are not in the packet are left empty, so the
+/// file can be plotted directly in a spreadsheet.
+pub struct DecomCsv {
+    writer: BufWriter<File>,
+    file_name: String,
+    timestamp_def: TimestampDef,
+    parameters: Vec<ParameterDef>,
+}
+
+impl DecomCsv {
+    /// Create the CSV file, writing the heading of each column. If no file name is given, a file
+    /// named by the current time is created in the logs directory.
+    pub fn create(decom_settings: &DecomSettings, timestamp_def: &TimestampDef) -> Result<DecomCsv, String> {
+        let file_name;
+        if decom_settings.file_name.is_empty() {
+            file_name = format!("{}/{}", LOG_DIRECTORY, Local::now().format("ccsds_router_decom_%Y%m%d_%H_%M_%S.csv"));
+        } else {
+            file_name = decom_settings.file_name.clone();
+        }
+
+        let csv_file = File::create(&file_name)
+                            .map_err(|err| format!("Could not create decom CSV {}: {}", file_name, err))?;
+
+        let mut decom_csv = DecomCsv {
+            writer: BufWriter::new(csv_file),
+            file_name: file_name,
+            timestamp_def: timestamp_def.clone(),
+            parameters: decom_settings.parameters.clone(),
+        };
+
+        let mut heading = "recv_time,timestamp,apid".to_string();
+        for parameter in decom_csv.parameters.iter() {
+            heading.push(',');
+            heading.push_str(&parameter.name);
+        }
+
+        writeln!(decom_csv.writer, "{}", heading)
+            .map_err(|err| format!("Could not write decom CSV {}: {}", decom_csv.file_name, err))?;
+
+        Ok(decom_csv)
+    }
+
+    /// Write the line for a packet, if it contains any of the parameters
+    pub fn write_packet(&mut self, packet: &Packet, recv_time: SystemTime) -> Result<(), String> {
+        let apid = packet.header.control.apid();
+        if !self.parameters.iter().any(|parameter| parameter.apid == apid) {
+            return Ok(());
+        }
+
+        let recv_time: DateTime<Local> = recv_time.into();
+        let timestamp_secs = self.timestamp_def.decode(&packet.bytes).as_fractional_secs();
+
+        let mut line = format!("{},{:.6},{}", recv_time.format("%Y-%m-%d %H:%M:%S%.6f"), timestamp_secs, apid);
+        for parameter in self.parameters.iter() {
+            line.push(',');
+            if parameter.apid == apid {
+                if let Some(value) = parameter.decode(&packet.bytes) {
+                    line.push_str(&value.to_string());
+                }
+            }
+        }
+
+        writeln!(self.writer, "{}", line)
+            .map_err(|err| format!("Could not write decom CSV {}: {}", self.file_name, err))
+    }
+}
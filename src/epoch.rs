@@ -0,0 +1,184 @@
+use chrono::prelude::*;
+
+
+/// The UNIX time of the GPS epoch, 1980-01-06T00:00:00Z
+pub const GPS_EPOCH_UNIX_SECS: i64 = 315964800;
+
+/// The difference between TAI and UTC in seconds, and the UNIX time it took effect at, for
+/// each leap second since UTC adopted leap seconds in 1972. This must be extended when the
+/// IERS announces a new leap second.
+const LEAP_SECONDS: &[(i64, i64)] = &[
+    (63072000, 10),
+    (78796800, 11),
+    (94694400, 12),
+    (126230400, 13),
+    (157766400, 14),
+    (189302400, 15),
+    (220924800, 16),
+    (252460800, 17),
+    (283996800, 18),
+    (315532800, 19),
+    (362793600, 20),
+    (394329600, 21),
+    (425865600, 22),
+    (489024000, 23),
+    (567993600, 24),
+    (631152000, 25),
+    (662688000, 26),
+    (709948800, 27),
+    (741484800, 28),
+    (773020800, 29),
+    (820454400, 30),
+    (867715200, 31),
+    (915148800, 32),
+    (1136073600, 33),
+    (1230768000, 34),
+    (1341100800, 35),
+    (1435708800, 36),
+    (1483228800, 37),
+];
+
+/// The difference between TAI and UTC at a UNIX time. Times before 1972 are given the
+/// difference at the start of 1972.
+fn tai_minus_utc(unix_secs: f64) -> f64 {
+    LEAP_SECONDS.iter()
+                .take_while(|(start_secs, _)| *start_secs as f64 <= unix_secs)
+                .last()
+                .map_or(LEAP_SECONDS[0].1, |(_, offset)| *offset) as f64
+}
+
+/// The epoch of packet timestamps, which gives them a UTC calendar time
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum TimeEpoch {
+    /// The timestamps are seconds with no calendar time, such as the time since power on
+    Relative,
+
+    /// Seconds since 1970-01-01T00:00:00Z, not counting leap seconds
+    Unix,
+
+    /// Seconds since the GPS epoch, 1980-01-06T00:00:00Z, counting leap seconds
+    Gps,
+
+    /// Seconds since a mission epoch given as a UTC time, such as "2000-01-01T11:58:55.816Z",
+    /// counting leap seconds if the mission's clock does
+    Custom {
+        utc: String,
+
+        #[serde(default)]
+        count_leap_seconds: bool,
+    },
+}
+
+impl Default for TimeEpoch {
+    fn default() -> Self {
+        TimeEpoch::Relative
+    }
+}
+
+impl TimeEpoch {
+    /// The UNIX time of the epoch, or None for relative timestamps
+    fn unix_secs(&self) -> Option<f64> {
+        match self {
+            TimeEpoch::Relative => None,
+
+            TimeEpoch::Unix => Some(0.0),
+
+            TimeEpoch::Gps => Some(GPS_EPOCH_UNIX_SECS as f64),
+
+            TimeEpoch::Custom { utc, .. } => parse_utc(utc).ok().map(|utc| unix_secs(&utc)),
+        }
+    }
+
+    fn counts_leap_seconds(&self) -> bool {
+        match self {
+            TimeEpoch::Gps => true,
+
+            TimeEpoch::Custom { count_leap_seconds, .. } => *count_leap_seconds,
+
+            _ => false,
+        }
+    }
+
+    /// A description of the problem with the epoch, if it is not valid
+    pub fn problem(&self) -> Option<String> {
+        match self {
+            TimeEpoch::Custom { utc, .. } => parse_utc(utc).err(),
+
+            _ => None,
+        }
+    }
+
+    /// The UTC time of a timestamp, or None for relative timestamps
+    pub fn to_utc(&self, timestamp_secs: f64) -> Option<DateTime<Utc>> {
+        let epoch_secs = self.unix_secs()?;
+
+        let unix_secs = if self.counts_leap_seconds() {
+            // the leap seconds inserted since the epoch are part of the timestamp, and the
+            // number inserted depends on the result, so it is found in two steps
+            let tai_secs = epoch_secs + tai_minus_utc(epoch_secs) + timestamp_secs;
+            let estimate = tai_secs - tai_minus_utc(tai_secs);
+            tai_secs - tai_minus_utc(estimate)
+        } else {
+            epoch_secs + timestamp_secs
+        };
+
+        let whole_secs = unix_secs.floor();
+        let nanos = (((unix_secs - whole_secs) * 1_000_000_000.0) as u32).min(999_999_999);
+
+        Utc.timestamp_opt(whole_secs as i64, nanos).single()
+    }
+
+    /// The timestamp of a UTC time, or None for relative timestamps
+    pub fn from_utc(&self, utc: &DateTime<Utc>) -> Option<f64> {
+        let epoch_secs = self.unix_secs()?;
+        let utc_secs = unix_secs(utc);
+
+        if self.counts_leap_seconds() {
+            Some(utc_secs + tai_minus_utc(utc_secs) - epoch_secs - tai_minus_utc(epoch_secs))
+        } else {
+            Some(utc_secs - epoch_secs)
+        }
+    }
+
+    /// A timestamp as a UTC calendar time, or None for relative timestamps
+    pub fn format_utc(&self, timestamp_secs: f64) -> Option<String> {
+        self.to_utc(timestamp_secs).map(|utc| utc.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+    }
+
+    /// Parse a time given either as a timestamp in seconds, or as a UTC time such as
+    /// '2020-01-01T12:00:00Z', which is converted to a timestamp using the epoch
+    pub fn parse_time(&self, time_str: &str) -> Result<f64, String> {
+        let time_str = time_str.trim();
+
+        if let Ok(timestamp_secs) = time_str.parse::<f64>() {
+            return Ok(timestamp_secs);
+        }
+
+        let utc = parse_utc(time_str)?;
+
+        self.from_utc(&utc)
+            .ok_or_else(|| format!("The time '{}' can only be given in seconds, as the timestamps have no epoch", time_str))
+    }
+}
+
+fn unix_secs(utc: &DateTime<Utc>) -> f64 {
+    utc.timestamp() as f64 + utc.timestamp_subsec_nanos() as f64 / 1_000_000_000.0
+}
+
+/// Parse a UTC time, either in RFC 3339 form, such as '2020-01-01T12:00:00.5Z', or as a
+/// date and time without a time zone, such as '2020-01-01 12:00:00', which is taken as UTC.
+pub fn parse_utc(time_str: &str) -> Result<DateTime<Utc>, String> {
+    let time_str = time_str.trim();
+
+    if let Ok(time) = DateTime::parse_from_rfc3339(time_str) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"].iter() {
+        if let Ok(time) = NaiveDateTime::parse_from_str(time_str, format) {
+            return Ok(DateTime::<Utc>::from_utc(time, Utc));
+        }
+    }
+
+    Err(format!("'{}' is not a UTC time, such as 2020-01-01T12:00:00Z", time_str))
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use floating_duration::TimeAsFloat;
+
+use types::*;
+use stream::*;
+use packet_file::*;
+
+
+/// The packets kept when filtering a file. Each setting that is not given keeps every packet.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PacketFilter {
+    pub apids: Option<Vec<u16>>,
+
+    /// Keep packets with timestamps at or after this time, in seconds
+    pub start_secs: Option<f64>,
+
+    /// Keep packets with timestamps before this time, in seconds
+    pub end_secs: Option<f64>,
+}
+
+impl PacketFilter {
+    pub fn keeps(&self, packet: &Packet, timestamp_def: &TimestampDef) -> bool {
+        if let Some(ref apids) = self.apids {
+            if !apids.contains(&packet.header.control.apid()) {
+                return false;
+            }
+        }
+
+        let timestamp_secs = timestamp_def.decode(&packet.bytes).as_fractional_secs();
+        self.start_secs.map_or(true, |start_secs| timestamp_secs >= start_secs) &&
+        self.end_secs.map_or(true, |end_secs| timestamp_secs < end_secs)
+    }
+}
+
+/// Split a packet file by APID, writing the packets of each APID to a file named
+/// '<name>_apid<APID>.<extension>'. The files are written to the directory of the
+/// packet file, unless another directory is given. Returns a description of the files written.
+pub fn split_file(file_name: &str, output_dir: Option<&Path>, config: &AppConfig) -> Result<String, String> {
+    let mut reader = PacketFileReader::open(file_name, config)?;
+
+    let path = Path::new(file_name);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    let output_dir = match output_dir {
+        Some(output_dir) => output_dir.to_path_buf(),
+
+        None => path.parent().map(|parent| parent.to_path_buf()).unwrap_or(PathBuf::new()),
+    };
+
+    let mut writers: BTreeMap<u16, PacketFileWriter> = BTreeMap::new();
+    while let Some((packet, _)) = reader.next_packet()? {
+        let apid = packet.header.control.apid();
+
+        if !writers.contains_key(&apid) {
+            let apid_file_name = output_dir.join(format!("{}_apid{}{}", stem, apid, extension));
+            writers.insert(apid, PacketFileWriter::create(&apid_file_name.to_string_lossy())?);
+        }
+
+        if let Some(writer) = writers.get_mut(&apid) {
+            writer.write_packet(&packet)?;
+        }
+    }
+
+    let mut description = String::new();
+    for (apid, writer) in writers.iter_mut() {
+        writer.finish()?;
+        description.push_str(&format!("Wrote {} packets of APID {} to {}\n", writer.packet_count, apid, writer.file_name));
+    }
+
+    Ok(description)
+}
+
+/// Merge packet files into one file, ordered by packet timestamp. Packets with the same
+/// timestamp are written in the order their files are given. Returns a description of the file written.
+pub fn merge_files(file_names: &[String], output_name: &str, config: &AppConfig) -> Result<String, String> {
+    if file_names.iter().any(|file_name| Path::new(file_name) == Path::new(output_name)) {
+        return Err(format!("The merged file {} can not also be merged into itself", output_name));
+    }
+
+    let mut readers = Vec::new();
+    for file_name in file_names.iter() {
+        readers.push(PacketFileReader::open(file_name, config)?);
+    }
+
+    // the next packet of each file, along with its timestamp
+    let mut next_packets = Vec::new();
+    for reader in readers.iter_mut() {
+        next_packets.push(next_timed_packet(reader, &config.timestamp_def)?);
+    }
+
+    let mut writer = PacketFileWriter::create(output_name)?;
+    loop {
+        let mut earliest: Option<(usize, f64)> = None;
+        for (index, next_packet) in next_packets.iter().enumerate() {
+            if let Some((_, timestamp_secs)) = *next_packet {
+                if earliest.map_or(true, |(_, earliest_secs)| timestamp_secs < earliest_secs) {
+                    earliest = Some((index, timestamp_secs));
+                }
+            }
+        }
+
+        match earliest {
+            Some((index, _)) => {
+                if let Some((packet, _)) = next_packets[index].take() {
+                    writer.write_packet(&packet)?;
+                }
+                next_packets[index] = next_timed_packet(&mut readers[index], &config.timestamp_def)?;
+            },
+
+            None => break,
+        }
+    }
+    writer.finish()?;
+
+    Ok(format!("Wrote {} packets from {} files to {}\n", writer.packet_count, file_names.len(), output_name))
+}
+
+fn next_timed_packet(reader: &mut PacketFileReader, timestamp_def: &TimestampDef) -> Result<Option<(Packet, f64)>, String> {
+    Ok(reader.next_packet()?.map(|(packet, _)| {
+        let timestamp_secs = timestamp_def.decode(&packet.bytes).as_fractional_secs();
+        (packet, timestamp_secs)
+    }))
+}
+
+/// Write the packets of a file that are kept by a filter to a new file. Returns a
+/// description of the file written.
+pub fn filter_file(file_name: &str, output_name: &str, filter: &PacketFilter, config: &AppConfig) -> Result<String, String> {
+    if Path::new(file_name) == Path::new(output_name) {
+        return Err(format!("The filtered file {} can not replace the file it is filtered from", output_name));
+    }
+
+    let mut reader = PacketFileReader::open(file_name, config)?;
+    let mut writer = PacketFileWriter::create(output_name)?;
+
+    let mut packet_count: u64 = 0;
+    while let Some((packet, _)) = reader.next_packet()? {
+        packet_count += 1;
+
+        if filter.keeps(&packet, &config.timestamp_def) {
+            writer.write_packet(&packet)?;
+        }
+    }
+    writer.finish()?;
+
+    Ok(format!("Wrote {} of {} packets to {}\n", writer.packet_count, packet_count, output_name))
+}
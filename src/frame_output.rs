@@ -0,0 +1,385 @@
+use std::time::{Duration, Instant};
+
+use transfer_frame::*;
+use processing::crc16_ccitt;
+
+
+/// The largest TM or AOS transfer frame
+pub const MAX_FRAME_BYTES: usize = 2048;
+
+/// The length of an AOS transfer frame primary header, without an insert zone or header error control
+pub const AOS_FRAME_HEADER_BYTES: usize = 6;
+
+/// The length of the M_PDU header that gives the first header pointer of an AOS frame
+pub const MPDU_HEADER_BYTES: usize = 2;
+
+/// The length of the frame error control field, a CRC-16 of the rest of the frame
+pub const FECF_BYTES: usize = 2;
+
+/// The first header pointer of a frame in which no packet starts
+pub const FHP_NO_PACKET_START: u16 = 0x7FF;
+
+/// The first header pointer of a frame holding only idle data
+pub const FHP_IDLE_DATA: u16 = 0x7FE;
+
+/// The APID of an idle packet
+pub const IDLE_APID: u16 = 0x7FF;
+
+/// The virtual channel of AOS idle frames
+pub const AOS_IDLE_VCID: u8 = 63;
+
+/// The byte idle packets and idle frames are filled with
+pub const IDLE_FILL_BYTE: u8 = 0x55;
+
+/// The smallest packet, a primary header and a single byte of data
+const MIN_PACKET_BYTES: usize = 7;
+
+/// The kind of transfer frame an output packs its packets into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrameType {
+    /// TM transfer frames, with a 10 bit spacecraft ID and a 3 bit VCID
+    Tm,
+    /// AOS transfer frames, with an 8 bit spacecraft ID and a 6 bit VCID
+    Aos,
+}
+
+impl Default for FrameType {
+    fn default() -> Self {
+        FrameType::Tm
+    }
+}
+
+/// Frame output packs the packets written to an output into fixed length TM or AOS transfer
+/// frames on a single virtual channel, so the router can feed frame level receivers and
+/// modulators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FrameOutputSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub frame_type: FrameType,
+
+    #[serde(default)]
+    pub spacecraft_id: u16,
+
+    #[serde(default)]
+    pub vcid: u8,
+
+    /// The length of each frame, including its headers and frame error control field
+    #[serde(default = "default_frame_bytes")]
+    pub frame_bytes: usize,
+
+    /// End each frame with a CRC-16 frame error control field
+    #[serde(default)]
+    pub fecf: bool,
+
+    /// Split packets that do not fit in the rest of a frame across frames, using the first
+    /// header pointer to find the packets that start in each. Otherwise the frame is completed
+    /// with an idle packet, and the packet starts the next frame, so only packets longer than
+    /// a frame are split.
+    #[serde(default = "default_split_packets")]
+    pub split_packets: bool,
+
+    /// The milliseconds a partly filled frame waits for more packets before it is completed
+    /// with an idle packet and written, or 0 to wait until it is full
+    #[serde(default = "default_flush_ms")]
+    pub flush_ms: u32,
+
+    /// The milliseconds without a frame after which an idle frame is written, keeping the link
+    /// full, or 0 to not write idle frames
+    #[serde(default)]
+    pub idle_ms: u32,
+}
+
+fn default_frame_bytes() -> usize {
+    1115
+}
+
+fn default_split_packets() -> bool {
+    true
+}
+
+fn default_flush_ms() -> u32 {
+    1000
+}
+
+impl Default for FrameOutputSettings {
+    fn default() -> Self {
+        FrameOutputSettings {
+            enabled: false,
+            frame_type: Default::default(),
+            spacecraft_id: 0,
+            vcid: 0,
+            frame_bytes: default_frame_bytes(),
+            fecf: false,
+            split_packets: default_split_packets(),
+            flush_ms: default_flush_ms(),
+            idle_ms: 0,
+        }
+    }
+}
+
+impl FrameOutputSettings {
+    /// The bytes of each frame before its data field, including the M_PDU header of an AOS frame
+    pub fn header_bytes(&self) -> usize {
+        match self.frame_type {
+            FrameType::Tm => TM_FRAME_HEADER_BYTES,
+
+            FrameType::Aos => AOS_FRAME_HEADER_BYTES + MPDU_HEADER_BYTES,
+        }
+    }
+
+    /// The bytes of packets held in each frame
+    pub fn data_field_bytes(&self) -> usize {
+        let fecf_bytes = if self.fecf { FECF_BYTES } else { 0 };
+        self.frame_bytes.saturating_sub(self.header_bytes() + fecf_bytes)
+    }
+
+    /// A description of each problem with the frame output
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.enabled {
+            return problems;
+        }
+
+        let (max_spacecraft_id, max_vcid) = match self.frame_type {
+            FrameType::Tm => (0x3FF, 7),
+
+            // VCID 63 is kept for idle frames
+            FrameType::Aos => (0xFF, AOS_IDLE_VCID - 1),
+        };
+
+        if self.spacecraft_id > max_spacecraft_id {
+            problems.push(format!("The frame spacecraft ID is at most {} for {:?} frames, but is {}", max_spacecraft_id, self.frame_type, self.spacecraft_id));
+        }
+
+        if self.vcid > max_vcid {
+            problems.push(format!("The frame VCID is at most {} for {:?} frames, but is {}", max_vcid, self.frame_type, self.vcid));
+        }
+
+        if self.frame_bytes > MAX_FRAME_BYTES {
+            problems.push(format!("Frames are at most {} bytes, but are set to {} bytes", MAX_FRAME_BYTES, self.frame_bytes));
+        } else if self.data_field_bytes() < MIN_PACKET_BYTES {
+            problems.push(format!("{} byte frames leave {} bytes for packets, but need at least {}",
+                                  self.frame_bytes,
+                                  self.data_field_bytes(),
+                                  MIN_PACKET_BYTES));
+        }
+
+        problems
+    }
+}
+
+/// A FrameBuilder packs packets into the data fields of transfer frames, giving each frame once
+/// it is full. A partly filled frame is completed with an idle packet once it has waited for the
+/// flush time, and an idle frame is given once no frame has been given for the idle time.
+#[derive(Debug, Clone)]
+pub struct FrameBuilder {
+    settings: FrameOutputSettings,
+
+    /// The data field of the frame being filled
+    data: Vec<u8>,
+
+    /// The offset in the data field of the first packet that starts in it, if any
+    first_header: Option<usize>,
+
+    /// The frame count of every frame, written in TM frames
+    master_count: u8,
+
+    /// The frame count of the virtual channel, and of the AOS idle virtual channel
+    vc_count: u32,
+    idle_vc_count: u32,
+
+    /// The sequence count of the next idle packet
+    idle_seq_count: u16,
+
+    /// When a packet was last added to the frame being filled, while it holds any
+    last_packet: Option<Instant>,
+
+    /// When a frame was last given
+    last_frame: Instant,
+}
+
+impl FrameBuilder {
+    pub fn new(settings: &FrameOutputSettings) -> FrameBuilder {
+        FrameBuilder {
+            settings: settings.clone(),
+            data: Vec::with_capacity(settings.data_field_bytes()),
+            first_header: None,
+            master_count: 0,
+            vc_count: 0,
+            idle_vc_count: 0,
+            idle_seq_count: 0,
+            last_packet: None,
+            last_frame: Instant::now(),
+        }
+    }
+
+    /// Add a packet, giving the frames it completes
+    pub fn push(&mut self, packet: &[u8], now: Instant, frames: &mut Vec<Vec<u8>>) {
+        let data_field_bytes = self.settings.data_field_bytes();
+
+        let fits = self.data.len() + packet.len() <= data_field_bytes;
+        if !self.settings.split_packets && !fits && !self.data.is_empty() && packet.len() <= data_field_bytes {
+            self.fill(now, frames);
+        }
+
+        self.append(packet, now, frames);
+    }
+
+    /// Give the frames that are due, completing a partly filled frame that has waited for the
+    /// flush time, or giving an idle frame once none has been given for the idle time
+    pub fn due(&mut self, now: Instant, frames: &mut Vec<Vec<u8>>) {
+        let flush = Duration::from_millis(self.settings.flush_ms as u64);
+        if self.settings.flush_ms > 0 && self.last_packet.map_or(false, |last_packet| now >= last_packet + flush) {
+            self.fill(now, frames);
+        }
+
+        // an idle frame is not written while a packet is split across frames
+        let idle = Duration::from_millis(self.settings.idle_ms as u64);
+        if self.settings.idle_ms > 0 && self.data.is_empty() && now >= self.last_frame + idle {
+            let frame = self.idle_frame();
+            self.last_frame = now;
+            frames.push(frame);
+        }
+    }
+
+    /// Complete a partly filled frame, such as when processing stops
+    pub fn flush(&mut self, now: Instant, frames: &mut Vec<Vec<u8>>) {
+        self.fill(now, frames);
+    }
+
+    fn append(&mut self, packet: &[u8], now: Instant, frames: &mut Vec<Vec<u8>>) {
+        let data_field_bytes = self.settings.data_field_bytes();
+
+        if self.first_header.is_none() {
+            self.first_header = Some(self.data.len());
+        }
+
+        let mut remaining = packet;
+        while !remaining.is_empty() {
+            let take = remaining.len().min(data_field_bytes - self.data.len());
+            self.data.extend_from_slice(&remaining[..take]);
+            remaining = &remaining[take..];
+
+            if self.data.len() == data_field_bytes {
+                let frame = self.frame();
+                self.last_frame = now;
+                frames.push(frame);
+            }
+        }
+
+        self.last_packet = if self.data.is_empty() { None } else { Some(now) };
+    }
+
+    /// Complete the frame being filled with an idle packet. When the rest of the frame is too
+    /// short for a packet, the idle packet runs on into the next frame.
+    fn fill(&mut self, now: Instant, frames: &mut Vec<Vec<u8>>) {
+        if self.data.is_empty() {
+            return;
+        }
+
+        let mut idle_bytes = self.settings.data_field_bytes() - self.data.len();
+        if idle_bytes < MIN_PACKET_BYTES {
+            idle_bytes += self.settings.data_field_bytes();
+        }
+
+        let idle_packet = self.idle_packet(idle_bytes);
+        self.append(&idle_packet, now, frames);
+    }
+
+    fn idle_packet(&mut self, packet_bytes: usize) -> Vec<u8> {
+        let mut packet = vec![IDLE_FILL_BYTE; packet_bytes];
+
+        let sequence = 0xC000 | self.idle_seq_count;
+        let length = (packet_bytes - MIN_PACKET_BYTES) as u16;
+        packet[0..2].copy_from_slice(&[(IDLE_APID >> 8) as u8, IDLE_APID as u8]);
+        packet[2..4].copy_from_slice(&[(sequence >> 8) as u8, sequence as u8]);
+        packet[4..6].copy_from_slice(&[(length >> 8) as u8, length as u8]);
+
+        self.idle_seq_count = (self.idle_seq_count + 1) & 0x3FFF;
+
+        packet
+    }
+
+    /// The frame holding the data field, which is emptied for the next frame
+    fn frame(&mut self) -> Vec<u8> {
+        let first_header_pointer = self.first_header.map_or(FHP_NO_PACKET_START, |offset| offset as u16);
+        let vcid = self.settings.vcid;
+
+        let mut frame = self.frame_header(vcid, first_header_pointer);
+        frame.extend_from_slice(&self.data);
+        self.end_frame(&mut frame);
+
+        self.data.clear();
+        self.first_header = None;
+
+        frame
+    }
+
+    /// A frame holding only idle data. TM idle frames are on the output's virtual channel, and
+    /// AOS idle frames on VCID 63, without an M_PDU header.
+    fn idle_frame(&mut self) -> Vec<u8> {
+        let mut frame = match self.settings.frame_type {
+            FrameType::Tm => self.frame_header(self.settings.vcid, FHP_IDLE_DATA),
+
+            FrameType::Aos => self.frame_header(AOS_IDLE_VCID, FHP_IDLE_DATA)[..AOS_FRAME_HEADER_BYTES].to_vec(),
+        };
+
+        let fecf_bytes = if self.settings.fecf { FECF_BYTES } else { 0 };
+        frame.resize(self.settings.frame_bytes - fecf_bytes, IDLE_FILL_BYTE);
+        self.end_frame(&mut frame);
+
+        frame
+    }
+
+    /// The headers of the next frame of a virtual channel, counting the frame
+    fn frame_header(&mut self, vcid: u8, first_header_pointer: u16) -> Vec<u8> {
+        let mut header = Vec::with_capacity(self.settings.header_bytes());
+        let spacecraft_id = self.settings.spacecraft_id;
+
+        match self.settings.frame_type {
+            FrameType::Tm => {
+                // version 0, and no operational control field
+                let id = ((spacecraft_id & 0x3FF) << 4) | (((vcid & 0x07) as u16) << 1);
+                // no secondary header, synchronous packets in order, with segment length ID 3
+                let status = 0x1800 | (first_header_pointer & 0x7FF);
+
+                header.extend_from_slice(&[(id >> 8) as u8, id as u8]);
+                header.push(self.master_count);
+                header.push(self.vc_count as u8);
+                header.extend_from_slice(&[(status >> 8) as u8, status as u8]);
+
+                self.master_count = self.master_count.wrapping_add(1);
+                self.vc_count = (self.vc_count + 1) & 0xFF;
+            },
+
+            FrameType::Aos => {
+                let count = if vcid == AOS_IDLE_VCID { &mut self.idle_vc_count } else { &mut self.vc_count };
+
+                // version 1
+                let id = 0x4000 | ((spacecraft_id & 0xFF) << 6) | (vcid & 0x3F) as u16;
+
+                header.extend_from_slice(&[(id >> 8) as u8, id as u8]);
+                header.extend_from_slice(&[(*count >> 16) as u8, (*count >> 8) as u8, *count as u8]);
+                // the signaling field, with no replay flag or frame count cycle
+                header.push(0);
+                header.extend_from_slice(&[((first_header_pointer >> 8) & 0x07) as u8, first_header_pointer as u8]);
+
+                *count = (*count + 1) & 0xFF_FFFF;
+            },
+        }
+
+        header
+    }
+
+    /// Add the frame error control field, if frames have one
+    fn end_frame(&self, frame: &mut Vec<u8>) {
+        if self.settings.fecf {
+            let crc = crc16_ccitt(frame);
+            frame.extend_from_slice(&[(crc >> 8) as u8, crc as u8]);
+        }
+    }
+}
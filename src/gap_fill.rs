@@ -0,0 +1,247 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use types::*;
+use stream::*;
+
+
+/// The most packets from either input held for merging. Once more are held, the oldest are
+/// released before their hold time is up, so an input read faster than real time, such as a
+/// file, does not hold an unbounded number of packets.
+pub const MAX_HELD_PACKETS: usize = 10_000;
+
+/// Sequence counts less than half the sequence count range ahead of another are after it
+const SEQ_HALF_RANGE: u16 = 0x2000;
+
+/// Gap filling merges the packets of a backup input, receiving the same downlink as the input,
+/// into the input's packets. Sequence counts are used to fill the gaps in the input with the
+/// backup's packets, and to remove the packets received on both, giving a single stream.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct GapFillSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The type of the backup input stream
+    #[serde(default)]
+    pub selection: StreamOption,
+
+    /// The settings of the backup input stream
+    #[serde(default)]
+    pub settings: StreamSettings,
+
+    /// The seconds each packet of the input is held, waiting for the backup's packets that fill
+    /// any gap before it. A backup packet arriving later than this can not fill a gap.
+    #[serde(default = "default_hold_secs")]
+    pub hold_secs: f32,
+}
+
+fn default_hold_secs() -> f32 {
+    1.0
+}
+
+impl Default for GapFillSettings {
+    fn default() -> Self {
+        GapFillSettings {
+            enabled: false,
+            selection: Default::default(),
+            settings: Default::default(),
+            hold_secs: default_hold_secs(),
+        }
+    }
+}
+
+impl GapFillSettings {
+    /// A description of each problem with gap filling, other than with the backup input's stream
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.enabled {
+            return problems;
+        }
+
+        if !(self.hold_secs >= 0.0) {
+            problems.push(format!("The gap filling hold time must not be negative, but is {} seconds", self.hold_secs));
+        }
+
+        problems
+    }
+
+    pub fn hold(&self) -> Duration {
+        let secs = self.hold_secs.max(0.0);
+        Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32)
+    }
+}
+
+/// The packets of the backup input used to fill gaps, and the packets of either input removed
+/// as duplicates
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct GapFillStats {
+    pub filled: u64,
+    pub duplicates: u64,
+}
+
+/// A GapFiller merges the items read from an input with the packets read from a backup input.
+/// The input's items are released in the order they arrived once they have been held for the
+/// hold time, and its packets are released before the backup's copy of the same packet. Before a
+/// packet of the input following a gap in its APID's sequence counts, the backup's packets
+/// within the gap are released in sequence order. A backup packet that the input has not
+/// delivered within the hold time is released as well, so an outage of the input is filled.
+/// Any packet whose sequence count is not after the last released for its APID is a duplicate.
+pub struct GapFiller<T> {
+    hold: Duration,
+
+    /// The items of the input, in the order they arrived, with the APID and sequence count of
+    /// those that are packets
+    prime: VecDeque<(Instant, Option<(u16, u16)>, T)>,
+
+    /// The packets of the backup input, in the order they arrived, with their APID and sequence count
+    backup: VecDeque<(Instant, u16, u16, T)>,
+
+    /// The sequence count of the last packet released for each APID
+    last_seqs: HashMap<u16, u16>,
+
+    stats: GapFillStats,
+}
+
+impl<T> GapFiller<T> {
+    pub fn new(hold: Duration) -> GapFiller<T> {
+        GapFiller {
+            hold: hold,
+            prime: VecDeque::new(),
+            backup: VecDeque::new(),
+            last_seqs: HashMap::new(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn stats(&self) -> GapFillStats {
+        self.stats
+    }
+
+    /// Add an item read from the input, with the APID and sequence count of a packet
+    pub fn push_prime(&mut self, key: Option<(u16, u16)>, item: T, now: Instant) {
+        self.prime.push_back((now, key, item));
+    }
+
+    /// Add a packet read from the backup input. A packet already released, or already held from
+    /// the backup, is a duplicate.
+    pub fn push_backup(&mut self, apid: u16, seq_count: u16, item: T, now: Instant) {
+        if !self.is_new(apid, seq_count) || self.backup.iter().any(|held| held.1 == apid && held.2 == seq_count) {
+            self.stats.duplicates += 1;
+            return;
+        }
+
+        self.backup.push_back((now, apid, seq_count, item));
+    }
+
+    /// The time until the next item is due to be released, if any are held
+    pub fn next_release(&self, now: Instant) -> Option<Duration> {
+        let prime_arrived = self.prime.front().map(|held| held.0);
+        let backup_arrived = self.backup.front().map(|held| held.0);
+
+        let arrived = match (prime_arrived, backup_arrived) {
+            (Some(prime_arrived), Some(backup_arrived)) => prime_arrived.min(backup_arrived),
+
+            (arrived, None) | (None, arrived) => arrived?,
+        };
+
+        let deadline = arrived + self.hold;
+        if deadline > now {
+            Some(deadline.duration_since(now))
+        } else {
+            Some(Duration::from_secs(0))
+        }
+    }
+
+    /// Release the items that have been held for the hold time, or that are the oldest of too
+    /// many held
+    pub fn release(&mut self, now: Instant, released: &mut VecDeque<T>) {
+        self.release_held(Some(now), released);
+    }
+
+    /// Release every item held, such as at the end of the input
+    pub fn finish(&mut self, released: &mut VecDeque<T>) {
+        self.release_held(None, released);
+    }
+
+    /// Start again from a new position in the input, such as after a seek. The input's items
+    /// are released as they are, the backup's packets are discarded, and the sequence counts
+    /// of each APID are forgotten.
+    pub fn restart(&mut self, released: &mut VecDeque<T>) {
+        released.extend(self.prime.drain(..).map(|held| held.2));
+        self.backup.clear();
+        self.last_seqs.clear();
+    }
+
+    fn release_held(&mut self, now: Option<Instant>, released: &mut VecDeque<T>) {
+        let hold = self.hold;
+        let due = |arrived: Instant, held_count: usize| {
+            now.map_or(true, |now| now >= arrived + hold || held_count > MAX_HELD_PACKETS)
+        };
+
+        while self.prime.front().map_or(false, |held| due(held.0, self.prime.len())) {
+            let (_, key, item) = self.prime.pop_front().unwrap();
+            self.release_prime(key, item, released);
+        }
+
+        while self.backup.front().map_or(false, |held| due(held.0, self.backup.len())) {
+            let (_, apid, seq_count, item) = self.backup.pop_front().unwrap();
+
+            // the input's copy of the packet is released in its place
+            let held_by_prime = self.prime.iter().any(|held| held.1 == Some((apid, seq_count)));
+
+            if held_by_prime || !self.is_new(apid, seq_count) {
+                self.stats.duplicates += 1;
+            } else {
+                self.last_seqs.insert(apid, seq_count);
+                self.stats.filled += 1;
+                released.push_back(item);
+            }
+        }
+    }
+
+    fn release_prime(&mut self, key: Option<(u16, u16)>, item: T, released: &mut VecDeque<T>) {
+        let (apid, seq_count) = match key {
+            Some(key) => key,
+
+            None => {
+                released.push_back(item);
+                return;
+            },
+        };
+
+        if !self.is_new(apid, seq_count) {
+            self.stats.duplicates += 1;
+            return;
+        }
+
+        // the backup's packets of the APID after the last released and before this one fill the gap
+        let (mut fills, remaining): (Vec<_>, Vec<_>) = self.backup.drain(..).partition(|held| {
+            held.1 == apid && seq_delta(held.2, seq_count) < SEQ_HALF_RANGE && held.2 != seq_count
+        });
+        self.backup = remaining.into_iter().collect();
+
+        let fill_count = fills.len();
+        fills.retain(|held| self.is_new(held.1, held.2));
+        self.stats.duplicates += (fill_count - fills.len()) as u64;
+        fills.sort_by_key(|held| SEQ_HALF_RANGE - seq_delta(held.2, seq_count));
+
+        self.stats.filled += fills.len() as u64;
+        released.extend(fills.into_iter().map(|held| held.3));
+
+        self.last_seqs.insert(apid, seq_count);
+        released.push_back(item);
+    }
+
+    /// Whether a packet's sequence count is after the last released for its APID
+    fn is_new(&self, apid: u16, seq_count: u16) -> bool {
+        match self.last_seqs.get(&apid) {
+            Some(last_seq) => {
+                let delta = seq_delta(*last_seq, seq_count);
+                delta != 0 && delta < SEQ_HALF_RANGE
+            },
+
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use log::{Log, Level, LevelFilter, Metadata, Record};
+
+use simplelog::{Config, SharedLogger};
+
+use chrono::prelude::*;
+
+
+/// The maximum number of log entries kept for display in the GUI. Older entries
+/// are dropped as new ones arrive.
+pub const MAX_LOG_ENTRIES: usize = 1000;
+
+/// A LogEntry is a single log record kept for display in the GUI
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    pub level: Level,
+    pub time: DateTime<Local>,
+    pub message: String,
+}
+
+/// The log buffer is shared between the logger and the GUI thread
+pub type LogBuffer = Arc<Mutex<VecDeque<LogEntry>>>;
+
+/// The GuiLogger records log messages into a buffer so they can be
+/// displayed in the GUI's log panel.
+pub struct GuiLogger {
+    level: LevelFilter,
+    config: Config,
+    entries: LogBuffer,
+}
+
+impl GuiLogger {
+    /// Create a new GuiLogger, returning the logger along with the buffer
+    /// that it will record into.
+    pub fn new(level: LevelFilter, config: Config) -> (Box<GuiLogger>, LogBuffer) {
+        let entries = Arc::new(Mutex::new(VecDeque::with_capacity(MAX_LOG_ENTRIES)));
+
+        let logger = GuiLogger { level: level,
+                                 config: config,
+                                 entries: entries.clone(),
+        };
+
+        (Box::new(logger), entries)
+    }
+}
+
+impl Log for GuiLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            if let Ok(mut entries) = self.entries.lock() {
+                if entries.len() >= MAX_LOG_ENTRIES {
+                    entries.pop_front();
+                }
+
+                entries.push_back(LogEntry { level: record.level(),
+                                             time: Local::now(),
+                                             message: format!("{}", record.args()),
+                });
+            }
+        }
+    }
+
+    fn flush(&self) {
+    }
+}
+
+impl SharedLogger for GuiLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        Some(&self.config)
+    }
+
+    fn as_log(self: Box<Self>) -> Box<Log> {
+        Box::new(*self)
+    }
+}
+
+/// Convert the index of the log level selection in the GUI to a log level.
+/// The levels are ordered from most to least severe.
+pub fn level_from_index(index: i32) -> Level {
+    match index {
+        0 => Level::Error,
+        1 => Level::Warn,
+        2 => Level::Info,
+        3 => Level::Debug,
+        _ => Level::Trace,
+    }
+}
+
+/// The color used to display a log entry of a given level
+pub fn level_color(level: Level) -> (f32, f32, f32, f32) {
+    match level {
+        Level::Error => (1.0, 0.3, 0.3, 1.0),
+        Level::Warn  => (1.0, 0.8, 0.2, 1.0),
+        Level::Info  => (0.8, 0.8, 0.8, 1.0),
+        Level::Debug => (0.5, 0.7, 1.0, 1.0),
+        Level::Trace => (0.5, 0.5, 0.5, 1.0),
+    }
+}
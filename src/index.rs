@@ -0,0 +1,248 @@
+use std::fs::{File, rename, remove_file, metadata};
+use std::io::{self, BufReader, BufWriter, Read, Write, Seek, SeekFrom, ErrorKind};
+use std::collections::VecDeque;
+use std::cmp::min;
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+
+/// The extension added to a packet file's name to get the name of its index
+pub const INDEX_EXTENSION: &str = "idx";
+
+/// An IndexEntry locates a single packet within a packet file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IndexEntry {
+    /// The byte offset of the start of the packet, including any frame header
+    pub offset: u64,
+    pub apid: u16,
+    /// The packet's timestamp in seconds, decoded using the timestamp definition
+    pub timestamp_secs: f64,
+}
+
+/// A PacketIndex is read from the sidecar index file of a packet file. It allows
+/// seeking to a packet number or a time without scanning the packet file.
+///
+/// The index file is a sequence of little endian records, one per packet in the
+/// order that the packets appear in the file: the offset as a u64, the APID as
+/// a u16, and the timestamp as an f64.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PacketIndex {
+    pub entries: Vec<IndexEntry>,
+}
+
+impl PacketIndex {
+    /// The name of the index file for a packet file
+    pub fn index_file_name(file_name: &str) -> String {
+        format!("{}.{}", file_name, INDEX_EXTENSION)
+    }
+
+    /// Check whether a packet file has an index that was created after the file was last modified
+    pub fn is_current(file_name: &str) -> bool {
+        let file_modified = metadata(file_name).and_then(|file_metadata| file_metadata.modified());
+        let index_modified = metadata(PacketIndex::index_file_name(file_name)).and_then(|index_metadata| index_metadata.modified());
+
+        match (file_modified, index_modified) {
+            (Ok(file_modified), Ok(index_modified)) => index_modified >= file_modified,
+
+            _ => false,
+        }
+    }
+
+    /// Load the index of a packet file, if it has one
+    pub fn load(file_name: &str) -> Result<PacketIndex, String> {
+        let index_name = PacketIndex::index_file_name(file_name);
+        let index_file = File::open(&index_name)
+                              .map_err(|err| format!("Could not open index {}: {}", index_name, err))?;
+        let mut reader = BufReader::new(index_file);
+
+        let mut index = PacketIndex::default();
+        loop {
+            let offset = match reader.read_u64::<LittleEndian>() {
+                Ok(offset) => offset,
+
+                Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => break,
+
+                Err(err) => return Err(format!("Could not read index {}: {}", index_name, err)),
+            };
+
+            let apid = reader.read_u16::<LittleEndian>()
+                             .map_err(|err| format!("Could not read index {}: {}", index_name, err))?;
+            let timestamp_secs = reader.read_f64::<LittleEndian>()
+                                       .map_err(|err| format!("Could not read index {}: {}", index_name, err))?;
+
+            index.entries.push(IndexEntry { offset: offset, apid: apid, timestamp_secs: timestamp_secs });
+        }
+
+        Ok(index)
+    }
+
+    /// The offset of a packet, by its position in the file starting from 0
+    pub fn packet_offset(&self, packet_number: u64) -> Option<u64> {
+        self.entries.get(packet_number as usize).map(|entry| entry.offset)
+    }
+
+    /// The offset of the first packet with a timestamp at or after the given time
+    pub fn time_offset(&self, timestamp_secs: f64) -> Option<u64> {
+        self.entries.iter()
+                    .find(|entry| entry.timestamp_secs >= timestamp_secs)
+                    .map(|entry| entry.offset)
+    }
+
+    /// The byte ranges of the file holding the packets with the given APIDs, or any APID if none
+    /// are given, and with timestamps at or after the start and before the end. Each packet runs
+    /// to the start of the next, and the ranges of neighbouring packets are merged.
+    pub fn query_ranges(&self, file_len: u64, apids: &[u16], start_secs: Option<f64>, end_secs: Option<f64>) -> Vec<(u64, u64)> {
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+
+        for (packet_number, entry) in self.entries.iter().enumerate() {
+            let selected = (apids.is_empty() || apids.contains(&entry.apid)) &&
+                           start_secs.map_or(true, |start_secs| entry.timestamp_secs >= start_secs) &&
+                           end_secs.map_or(true, |end_secs| entry.timestamp_secs < end_secs);
+            if !selected {
+                continue;
+            }
+
+            let packet_end = self.entries.get(packet_number + 1).map_or(file_len, |next_entry| next_entry.offset);
+            if ranges.last().map_or(false, |range| range.1 == entry.offset) {
+                ranges.last_mut().unwrap().1 = packet_end;
+            } else {
+                ranges.push((entry.offset, packet_end));
+            }
+        }
+
+        ranges
+    }
+}
+
+/// A ReplayQuery replays only some of the packets of an input file, selected by APID and
+/// timestamp using the file's index, so that only the parts of the file holding them are read
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayQuery {
+    pub enabled: bool,
+
+    /// The APIDs replayed, or every APID if empty
+    #[serde(default)]
+    pub apids: Vec<u16>,
+
+    /// The first timestamp replayed, and the timestamp the replay ends before, each in seconds,
+    /// or as a UTC time when the timestamps have an epoch. An empty time is not a limit.
+    #[serde(default)]
+    pub start: String,
+
+    #[serde(default)]
+    pub end: String,
+}
+
+/// A QueryReader reads the byte ranges of a file selected by a replay query one after
+/// another, as though they were a file of their own
+#[derive(Debug)]
+pub struct QueryReader {
+    file: BufReader<File>,
+    ranges: VecDeque<(u64, u64)>,
+    position: u64,
+}
+
+impl QueryReader {
+    pub fn new(file: File, ranges: Vec<(u64, u64)>) -> QueryReader {
+        QueryReader {
+            file: BufReader::new(file),
+            ranges: ranges.into_iter().collect(),
+            position: 0,
+        }
+    }
+}
+
+impl Read for QueryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let (start, end) = match self.ranges.front() {
+                Some(&range) => range,
+
+                None => return Ok(0),
+            };
+
+            if self.position >= end {
+                self.ranges.pop_front();
+                continue;
+            }
+
+            if self.position < start {
+                self.file.seek(SeekFrom::Start(start))?;
+                self.position = start;
+            }
+
+            let max_bytes = min(buf.len() as u64, end - self.position) as usize;
+            let num_bytes_read = self.file.read(&mut buf[..max_bytes])?;
+
+            // the file ended before the end of the range, so there is nothing more to read
+            if num_bytes_read == 0 {
+                self.ranges.clear();
+            }
+            self.position += num_bytes_read as u64;
+
+            return Ok(num_bytes_read);
+        }
+    }
+}
+
+/// An IndexWriter creates the index of a packet file as its packets are read or written.
+/// The index is written to a temporary file, which is only given the index's name when the
+/// index is finished, so an incomplete index is never used for seeking.
+pub struct IndexWriter {
+    index_name: String,
+    partial_name: String,
+    writer: BufWriter<File>,
+
+    /// The offset in the packet file of the next packet written to it
+    pub position: u64,
+}
+
+impl IndexWriter {
+    pub fn create(file_name: &str) -> Result<IndexWriter, String> {
+        let index_name = PacketIndex::index_file_name(file_name);
+        let partial_name = format!("{}.partial", index_name);
+
+        let index_file = File::create(&partial_name)
+                              .map_err(|err| format!("Could not create index {}: {}", partial_name, err))?;
+
+        Ok(IndexWriter {
+            index_name: index_name,
+            partial_name: partial_name,
+            writer: BufWriter::new(index_file),
+            position: 0,
+        })
+    }
+
+    /// Add a packet to the index at a given offset
+    pub fn write_entry(&mut self, offset: u64, apid: u16, timestamp_secs: f64) -> Result<(), String> {
+        self.writer.write_u64::<LittleEndian>(offset)
+            .and_then(|_| self.writer.write_u16::<LittleEndian>(apid))
+            .and_then(|_| self.writer.write_f64::<LittleEndian>(timestamp_secs))
+            .map_err(|err| format!("Could not write index {}: {}", self.partial_name, err))
+    }
+
+    /// Add a packet of a given length at the current position, for indexing a file as it is written
+    pub fn append(&mut self, num_bytes: usize, apid: u16, timestamp_secs: f64) -> Result<(), String> {
+        let offset = self.position;
+        self.position += num_bytes as u64;
+        self.write_entry(offset, apid, timestamp_secs)
+    }
+
+    /// Complete the index, replacing any previous index of the packet file
+    pub fn finish(mut self) -> Result<String, String> {
+        self.writer.flush()
+            .map_err(|err| format!("Could not write index {}: {}", self.partial_name, err))?;
+
+        rename(&self.partial_name, &self.index_name)
+            .map_err(|err| format!("Could not rename index {} to {}: {}", self.partial_name, self.index_name, err))?;
+
+        Ok(self.index_name)
+    }
+
+    /// Discard an incomplete index
+    pub fn abandon(self) {
+        let partial_name = self.partial_name;
+        drop(self.writer);
+        let _ = remove_file(partial_name);
+    }
+}
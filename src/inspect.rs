@@ -0,0 +1,214 @@
+use std::collections::BTreeMap;
+
+use floating_duration::TimeAsFloat;
+
+use types::*;
+use report::MAX_REPORTED_GAPS;
+use packet_file::*;
+
+
+/// The packets of one APID found when inspecting a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApidInspection {
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub min_length: usize,
+    pub max_length: usize,
+    pub gap_count: u64,
+    pub last_seq: u16,
+}
+
+/// A gap in an APID's sequence counts, found at the offset of the packet after the gap
+#[derive(Debug, Clone, PartialEq)]
+pub struct InspectionGap {
+    pub apid: u16,
+    pub expected: u16,
+    pub received: u16,
+    pub offset: u64,
+}
+
+/// A region of a file that is not part of any packet, such as corrupted data that the
+/// parser skipped over, or a partial packet at the end of the file
+#[derive(Debug, Clone, PartialEq)]
+pub struct MalformedRegion {
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// An Inspection summarizes the packets of a file without forwarding them
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Inspection {
+    pub file_name: String,
+    pub file_bytes: u64,
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub min_length: usize,
+    pub max_length: usize,
+
+    /// The packets longer then the maximum packet length of the configuration
+    pub oversize_count: u64,
+
+    /// The first, last, earliest and latest packet timestamps, in seconds
+    pub first_timestamp_secs: f64,
+    pub last_timestamp_secs: f64,
+    pub min_timestamp_secs: f64,
+    pub max_timestamp_secs: f64,
+
+    pub apids: BTreeMap<u16, ApidInspection>,
+    pub gaps: Vec<InspectionGap>,
+    pub malformed_regions: Vec<MalformedRegion>,
+
+    /// The error that stopped the file from being parsed, if it could not be read to its end
+    pub parse_error: Option<String>,
+}
+
+/// Read each packet of a file, using the packet settings of a configuration, and summarize them
+pub fn inspect_file(file_name: &str, config: &AppConfig) -> Result<Inspection, String> {
+    let mut reader = PacketFileReader::open(file_name, config)?;
+
+    let mut inspection: Inspection = Default::default();
+    inspection.file_name = file_name.to_string();
+
+    // the offset that the next packet's frame starts at, if no bytes are skipped
+    let mut expected_offset: u64 = 0;
+
+    loop {
+        let (packet, offset) = match reader.next_packet() {
+            Ok(Some(packet_and_offset)) => packet_and_offset,
+
+            Ok(None) => break,
+
+            Err(err) => {
+                inspection.parse_error = Some(err);
+                break;
+            },
+        };
+
+        if offset > expected_offset {
+            inspection.malformed_regions.push(MalformedRegion { offset: expected_offset, length: offset - expected_offset });
+        }
+        expected_offset = reader.position() - reader.unparsed_bytes();
+
+        inspection.packet(&packet, offset, config);
+    }
+
+    // bytes left at the end of the file did not form a packet
+    inspection.file_bytes = reader.position();
+    if inspection.parse_error.is_none() && inspection.file_bytes > expected_offset {
+        inspection.malformed_regions.push(MalformedRegion { offset: expected_offset, length: inspection.file_bytes - expected_offset });
+    }
+
+    Ok(inspection)
+}
+
+impl Inspection {
+    fn packet(&mut self, packet: &Packet, offset: u64, config: &AppConfig) {
+        let apid = packet.header.control.apid();
+        let seq_count = packet.header.sequence.sequence_count();
+        let length = packet.bytes.len();
+        let timestamp_secs = config.timestamp_def.decode(&packet.bytes).as_fractional_secs();
+
+        if self.packet_count == 0 {
+            self.min_length = length;
+            self.first_timestamp_secs = timestamp_secs;
+            self.min_timestamp_secs = timestamp_secs;
+            self.max_timestamp_secs = timestamp_secs;
+        }
+
+        self.packet_count += 1;
+        self.byte_count += length as u64;
+        self.min_length = self.min_length.min(length);
+        self.max_length = self.max_length.max(length);
+        self.last_timestamp_secs = timestamp_secs;
+        self.min_timestamp_secs = self.min_timestamp_secs.min(timestamp_secs);
+        self.max_timestamp_secs = self.max_timestamp_secs.max(timestamp_secs);
+
+        if length > config.max_length_bytes as usize {
+            self.oversize_count += 1;
+        }
+
+        let mut gap = None;
+        {
+            let apid_inspection = self.apids.entry(apid).or_insert(ApidInspection {
+                packet_count: 0,
+                byte_count: 0,
+                min_length: length,
+                max_length: length,
+                gap_count: 0,
+                last_seq: seq_count,
+            });
+
+            if apid_inspection.packet_count > 0 && is_seq_gap(apid_inspection.last_seq, seq_count) {
+                apid_inspection.gap_count += 1;
+                gap = Some(InspectionGap { apid: apid, expected: next_seq(apid_inspection.last_seq), received: seq_count, offset: offset });
+            }
+
+            apid_inspection.packet_count += 1;
+            apid_inspection.byte_count += length as u64;
+            apid_inspection.min_length = apid_inspection.min_length.min(length);
+            apid_inspection.max_length = apid_inspection.max_length.max(length);
+            apid_inspection.last_seq = seq_count;
+        }
+
+        if let Some(gap) = gap {
+            if self.gaps.len() < MAX_REPORTED_GAPS {
+                self.gaps.push(gap);
+            }
+        }
+    }
+
+    /// The inspection as text, for printing
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str(&format!("File: {} ({} bytes)\n", self.file_name, self.file_bytes));
+        text.push_str(&format!("Packets: {} ({} bytes)\n", self.packet_count, self.byte_count));
+
+        if self.packet_count > 0 {
+            text.push_str(&format!("Packet length: min {}, mean {:.1}, max {}\n",
+                                   self.min_length,
+                                   self.byte_count as f64 / self.packet_count as f64,
+                                   self.max_length));
+            text.push_str(&format!("Timestamps: first {:.6}, last {:.6}, span {:.6} seconds\n",
+                                   self.first_timestamp_secs,
+                                   self.last_timestamp_secs,
+                                   self.max_timestamp_secs - self.min_timestamp_secs));
+        }
+
+        if self.oversize_count > 0 {
+            text.push_str(&format!("Packets over the maximum length: {}\n", self.oversize_count));
+        }
+
+        text.push_str("\nAPIDs:\n");
+        for (apid, apid_inspection) in self.apids.iter() {
+            text.push_str(&format!("  APID {}: {} packets, {} bytes, length {}-{}, {} gaps\n",
+                                   apid,
+                                   apid_inspection.packet_count,
+                                   apid_inspection.byte_count,
+                                   apid_inspection.min_length,
+                                   apid_inspection.max_length,
+                                   apid_inspection.gap_count));
+        }
+
+        let gap_count: u64 = self.apids.values().map(|apid_inspection| apid_inspection.gap_count).sum();
+        text.push_str(&format!("\nSequence gaps: {}\n", gap_count));
+        for gap in self.gaps.iter() {
+            text.push_str(&format!("  APID {}: expected {}, received {}, at offset {}\n",
+                                   gap.apid, gap.expected, gap.received, gap.offset));
+        }
+        if gap_count > self.gaps.len() as u64 {
+            text.push_str(&format!("  ({} more gaps not listed)\n", gap_count - self.gaps.len() as u64));
+        }
+
+        text.push_str(&format!("\nMalformed regions: {}\n", self.malformed_regions.len()));
+        for region in self.malformed_regions.iter() {
+            text.push_str(&format!("  offset {}: {} bytes\n", region.offset, region.length));
+        }
+
+        if let Some(ref parse_error) = self.parse_error {
+            text.push_str(&format!("\nThe file could not be read to its end: {}\n", parse_error));
+        }
+
+        text
+    }
+}
@@ -0,0 +1,99 @@
+//! The core of CCSDS Router: reading CCSDS packets from an input stream, parsing and
+//! filtering them, and forwarding them to output streams with the configured timing.
+//!
+//! The GUI and command line application is built on this library, and other tools can
+//! use the same forwarding engine through the Router, without the GUI's dependencies.
+//! Building with 'default-features = false' leaves out the GUI.
+//!
+//! The library also has the tools that work with packet files directly, such as
+//! inspecting, splitting, merging, and filtering them.
+//!
+extern crate ccsds_primary_header;
+
+extern crate bytes;
+extern crate mio;
+extern crate byteorder;
+
+extern crate num;
+#[macro_use] extern crate num_derive;
+
+extern crate serde;
+extern crate serde_json;
+#[macro_use] extern crate serde_derive;
+
+#[macro_use] extern crate log;
+
+extern crate chrono;
+
+extern crate floating_duration;
+
+extern crate rusqlite;
+
+#[cfg(feature = "parquet")]
+extern crate parquet;
+
+#[macro_use] extern crate lazy_static;
+
+#[cfg(unix)]
+extern crate libc;
+
+
+pub mod stream;
+
+pub mod types;
+
+pub mod processing;
+
+pub mod router;
+
+pub mod report;
+
+pub mod archive;
+
+pub mod index;
+
+pub mod packet_log;
+
+pub mod config;
+
+pub mod packet_file;
+
+pub mod inspect;
+
+pub mod file_tools;
+
+pub mod bench;
+
+pub mod transform;
+
+pub mod sidecar;
+
+pub mod container;
+
+pub mod decom;
+
+pub mod parquet_archive;
+
+pub mod cfs;
+
+pub mod epoch;
+
+pub mod transfer_frame;
+
+pub mod schedule;
+
+pub mod apid_list;
+
+pub mod outage;
+
+pub mod backlog;
+
+pub mod priority;
+
+pub mod bridge;
+
+pub mod gap_fill;
+
+pub mod reorder;
+
+pub mod frame_output;
@@ -0,0 +1,356 @@
+use std::cmp::max;
+use std::fs::{File, read_dir, remove_file};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use log::{Log, LevelFilter, Metadata, Record};
+#[cfg(unix)]
+use log::Level;
+
+use simplelog::{Config, SharedLogger, CombinedLogger};
+
+use chrono::prelude::*;
+
+use serde_json;
+
+#[cfg(unix)]
+use syslog;
+
+use ccsds_router_core::types::*;
+use gui_log::*;
+
+
+/// The start of the name of each application log file in the logs directory. Only these
+/// files are rotated and removed- reports and packet logs are kept.
+const LOG_FILE_PREFIX: &str = "ccsds_router_log_";
+
+/// A LogFilter decides which log messages are written, with a level for each module
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    level: LevelFilter,
+
+    /// Module paths and their levels, longest path first so the most specific module is found first
+    module_levels: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Create a filter from the log settings, along with a description of each level that
+    /// could not be parsed.
+    pub fn new(log_settings: &LogSettings) -> (LogFilter, Vec<String>) {
+        let mut problems = Vec::new();
+
+        let level = parse_level(&log_settings.level, &mut problems);
+
+        let mut module_levels: Vec<(String, LevelFilter)> =
+            log_settings.module_levels.iter()
+                                      .map(|(module, module_level)| (module.clone(), parse_level(module_level, &mut problems)))
+                                      .collect();
+        module_levels.sort_by(|first, second| second.0.len().cmp(&first.0.len()));
+
+        (LogFilter { level: level, module_levels: module_levels }, problems)
+    }
+
+    /// The level of a log target. A module's level applies to the modules within it.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.module_levels.iter()
+                          .find(|entry| target == entry.0 || target.starts_with(&format!("{}::", entry.0)))
+                          .map(|entry| entry.1)
+                          .unwrap_or(self.level)
+    }
+
+    /// The most verbose level of any module
+    pub fn max_level(&self) -> LevelFilter {
+        self.module_levels.iter().map(|entry| entry.1).fold(self.level, max)
+    }
+}
+
+fn parse_level(level_str: &str, problems: &mut Vec<String>) -> LevelFilter {
+    LevelFilter::from_str(level_str).unwrap_or_else(|_| {
+        problems.push(format!("Unknown log level '{}', using info", level_str));
+        LevelFilter::Info
+    })
+}
+
+/// A FilteredLogger passes the messages allowed by a LogFilter on to another logger
+pub struct FilteredLogger {
+    filter: LogFilter,
+    logger: Box<Log>,
+}
+
+impl FilteredLogger {
+    pub fn new(filter: LogFilter, logger: Box<Log>) -> Box<FilteredLogger> {
+        Box::new(FilteredLogger { filter: filter, logger: logger })
+    }
+}
+
+impl Log for FilteredLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            self.logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
+
+impl SharedLogger for FilteredLogger {
+    fn level(&self) -> LevelFilter {
+        self.filter.max_level()
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<Log> {
+        Box::new(*self)
+    }
+}
+
+/// A log message written as a line of JSON
+#[derive(Serialize)]
+struct JsonLogLine<'a> {
+    time: String,
+    level: String,
+    target: &'a str,
+    message: String,
+}
+
+// Format a log message as a line of text, or as a line of JSON
+fn format_line(record: &Record, json: bool) -> String {
+    let time = Local::now();
+
+    if json {
+        let json_line = JsonLogLine {
+            time: time.to_rfc3339(),
+            level: record.level().to_string(),
+            target: record.target(),
+            message: format!("{}", record.args()),
+        };
+        serde_json::to_string(&json_line).unwrap_or_default()
+    } else {
+        format!("{} [{:5}] {}: {}", time.format("%Y-%m-%d %H:%M:%S%.3f"), record.level(), record.target(), record.args())
+    }
+}
+
+/// A TerminalLogger writes log messages to stderr, leaving stdout for output such as the run summary
+pub struct TerminalLogger {
+    json: bool,
+}
+
+impl Log for TerminalLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let _ = writeln!(io::stderr(), "{}", format_line(record, self.json));
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+struct LogFile {
+    file: File,
+    bytes_written: u64,
+}
+
+/// A RotatingFileLogger writes log messages to a file in the logs directory. Once the file
+/// reaches its maximum size a new file is started, and the oldest log files are removed so
+/// the logs directory does not grow without bound.
+pub struct RotatingFileLogger {
+    json: bool,
+    max_file_bytes: u64,
+    max_files: usize,
+    log_file: Mutex<LogFile>,
+}
+
+impl RotatingFileLogger {
+    pub fn new(log_settings: &LogSettings) -> Result<RotatingFileLogger, String> {
+        let file = create_log_file()?;
+        remove_old_log_files(log_settings.max_files);
+
+        Ok(RotatingFileLogger {
+            json: log_settings.json,
+            max_file_bytes: log_settings.max_file_bytes,
+            max_files: log_settings.max_files,
+            log_file: Mutex::new(LogFile { file: file, bytes_written: 0 }),
+        })
+    }
+}
+
+impl Log for RotatingFileLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let line = format_line(record, self.json);
+        let line_bytes = line.len() as u64 + 1;
+
+        if let Ok(mut log_file) = self.log_file.lock() {
+            if self.max_file_bytes > 0 &&
+               log_file.bytes_written > 0 &&
+               log_file.bytes_written + line_bytes > self.max_file_bytes {
+                // if a new file can not be created, the current file continues to be used
+                if let Ok(file) = create_log_file() {
+                    log_file.file = file;
+                    log_file.bytes_written = 0;
+                    remove_old_log_files(self.max_files);
+                }
+            }
+
+            if writeln!(log_file.file, "{}", line).is_ok() {
+                log_file.bytes_written += line_bytes;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut log_file) = self.log_file.lock() {
+            let _ = log_file.file.flush();
+        }
+    }
+}
+
+// Create a new log file named by the current time
+fn create_log_file() -> Result<File, String> {
+    let base_name = format!("{}/{}{}", LOG_DIRECTORY, LOG_FILE_PREFIX, Local::now().format("%Y%m%d_%H_%M_%S"));
+
+    // files rotated within the same second are numbered
+    let mut file_name = format!("{}.log", base_name);
+    let mut file_number = 1;
+    while Path::new(&file_name).exists() {
+        file_name = format!("{}_{}.log", base_name, file_number);
+        file_number += 1;
+    }
+
+    File::create(&file_name).map_err(|err| format!("Could not create log file {}: {}", file_name, err))
+}
+
+// Remove the oldest log files, keeping at most the given number. Log files are named
+// by the time they were created, so the oldest files sort first. A limit of 0 keeps all files.
+fn remove_old_log_files(max_files: usize) {
+    if max_files == 0 {
+        return;
+    }
+
+    let mut file_names: Vec<String> = match read_dir(LOG_DIRECTORY) {
+        Ok(entries) => {
+            entries.filter_map(|entry| entry.ok())
+                   .map(|entry| entry.file_name().to_string_lossy().to_string())
+                   .filter(|file_name| file_name.starts_with(LOG_FILE_PREFIX) && file_name.ends_with(".log"))
+                   .collect()
+        },
+
+        Err(_) => return,
+    };
+    file_names.sort();
+
+    if file_names.len() > max_files {
+        let remove_count = file_names.len() - max_files;
+        for file_name in file_names[..remove_count].iter() {
+            let _ = remove_file(Path::new(LOG_DIRECTORY).join(file_name));
+        }
+    }
+}
+
+/// A SyslogLogger sends log messages to the local syslog daemon, which journald also collects
+#[cfg(unix)]
+pub struct SyslogLogger {
+    writer: Mutex<syslog::Logger<syslog::LoggerBackend, String, syslog::Formatter3164>>,
+}
+
+#[cfg(unix)]
+impl SyslogLogger {
+    pub fn new() -> Result<SyslogLogger, String> {
+        let formatter = syslog::Formatter3164 {
+            facility: syslog::Facility::LOG_USER,
+            hostname: None,
+            process: "ccsds_router".to_string(),
+            pid: ::std::process::id() as i32,
+        };
+
+        syslog::unix(formatter).map(|writer| SyslogLogger { writer: Mutex::new(writer) })
+                               .map_err(|err| format!("Could not connect to syslog: {}", err))
+    }
+}
+
+#[cfg(unix)]
+impl Log for SyslogLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let message = format!("{}: {}", record.target(), record.args());
+
+        if let Ok(mut writer) = self.writer.lock() {
+            let _ = match record.level() {
+                Level::Error => writer.err(message),
+                Level::Warn  => writer.warning(message),
+                Level::Info  => writer.info(message),
+                Level::Debug | Level::Trace => writer.debug(message),
+            };
+        }
+    }
+
+    fn flush(&self) {
+    }
+}
+
+#[cfg(unix)]
+fn syslog_logger() -> Result<Box<Log>, String> {
+    SyslogLogger::new().map(|logger| Box::new(logger) as Box<Log>)
+}
+
+#[cfg(not(unix))]
+fn syslog_logger() -> Result<Box<Log>, String> {
+    Err("Logging to syslog is only supported on POSIX systems".to_string())
+}
+
+/// Set up the application log from the log settings, logging to the terminal if requested, to
+/// the log file, to syslog if enabled, and to the GUI's log panel. Returns the GUI's log buffer,
+/// along with a description of each problem found, to be logged once the log is set up.
+pub fn init_logging(log_settings: &LogSettings, terminal: bool) -> (LogBuffer, Vec<String>) {
+    let (filter, mut problems) = LogFilter::new(log_settings);
+
+    let mut loggers: Vec<Box<SharedLogger>> = Vec::new();
+
+    if terminal {
+        loggers.push(FilteredLogger::new(filter.clone(), Box::new(TerminalLogger { json: log_settings.json })));
+    }
+
+    match RotatingFileLogger::new(log_settings) {
+        Ok(file_logger) => loggers.push(FilteredLogger::new(filter.clone(), Box::new(file_logger))),
+
+        Err(err) => problems.push(err),
+    }
+
+    if log_settings.syslog {
+        match syslog_logger() {
+            Ok(syslog_logger) => loggers.push(FilteredLogger::new(filter.clone(), syslog_logger)),
+
+            Err(err) => problems.push(err),
+        }
+    }
+
+    let (gui_logger, log_buffer) = GuiLogger::new(LevelFilter::max(), Config::default());
+    loggers.push(FilteredLogger::new(filter, gui_logger.as_log()));
+
+    if let Err(err) = CombinedLogger::init(loggers) {
+        problems.push(format!("Could not set up logging: {}", err));
+    }
+
+    (log_buffer, problems)
+}
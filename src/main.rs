@@ -43,13 +43,11 @@
 //! protocol. These must be fixed size, and can be forwarded along with the CCSDS packets or
 //! omitted.
 //!
-extern crate ccsds_primary_header;
+extern crate ccsds_router_core;
 
 extern crate bytes;
-extern crate byteorder;
 
 extern crate num;
-#[macro_use] extern crate num_derive;
 
 extern crate serde;
 extern crate serde_json;
@@ -68,6 +66,15 @@ extern crate hexdump;
 
 extern crate ctrlc;
 
+#[cfg(unix)]
+extern crate signal_hook;
+#[cfg(unix)]
+extern crate daemonize;
+#[cfg(unix)]
+extern crate syslog;
+
+extern crate tinyfiledialogs;
+
 extern crate sdl2;
 extern crate imgui;
 extern crate imgui_sdl2;
@@ -76,17 +83,17 @@ extern crate imgui_opengl_renderer;
 
 
 use std::time::{Duration, SystemTime};
-use std::thread;
 use std::io::{Write, Read};
 use std::default::Default;
-use std::collections::{VecDeque};
-use std::sync::mpsc::{channel, Sender, Receiver};
+use std::sync::mpsc::{Sender, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::fs::File;
-use std::fs::create_dir;
-use std::path::PathBuf;
+use std::fs::{create_dir, remove_file};
+use std::path::{Path, PathBuf};
 use std::cmp::{min, max};
-
-use simplelog::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::net::IpAddr;
 
 use chrono::prelude::*;
 
@@ -98,36 +105,148 @@ use hexdump::*;
 
 use imgui::*;
 
-mod stream;
-use stream::*;
-
-mod types;
-use types::*;
-
-mod processing;
-use processing::*;
+use ccsds_router_core::stream::*;
+use ccsds_router_core::types::*;
+use ccsds_router_core::processing::*;
+use ccsds_router_core::router::*;
+use ccsds_router_core::report::*;
+use ccsds_router_core::config::*;
+use ccsds_router_core::inspect::*;
+use ccsds_router_core::file_tools::*;
+use ccsds_router_core::bench::*;
+use ccsds_router_core::container::*;
+use ccsds_router_core::decom::*;
+use ccsds_router_core::parquet_archive::*;
+use ccsds_router_core::cfs::*;
+use ccsds_router_core::epoch::*;
+use ccsds_router_core::transfer_frame::*;
+use ccsds_router_core::schedule::*;
+use ccsds_router_core::outage::*;
+use ccsds_router_core::priority::*;
+use ccsds_router_core::apid_list::*;
+use ccsds_router_core::bridge::*;
+use ccsds_router_core::gap_fill::*;
+use ccsds_router_core::reorder::*;
+use ccsds_router_core::frame_output::*;
+
+mod app_state;
+use app_state::*;
 
 mod style;
 use style::*;
 
+mod gui_log;
+use gui_log::*;
+
+mod session;
+use session::*;
+
+mod control;
+use control::*;
+
+mod logging;
+use logging::*;
+
+mod plugins;
+use plugins::*;
+
+mod peer;
+use peer::*;
+
+
+/// Width of popup windows
+const POPUP_WIDTH: f32 = 620.0;
+
+/* Section Sizes */
+// Section heights are given in lines of text so that they follow the font size, and
+// their widths fill the window.
+const CONFIG_SETTINGS_FRAME_LINES: f32 = 3.5;
+
+const INPUT_SETTINGS_FRAME_LINES: f32 = 15.0;
+
+const OUTPUT_SETTINGS_FRAME_LINES: f32 = 9.5;
+
+/// The number of APIDs on each line of the APID filter editor
+const APID_EDITOR_COLUMNS: usize = 8;
+
+const CCSDS_SETTINGS_FRAME_LINES: f32 = 12.0;
+
+const LOG_FRAME_LINES: f32 = 8.0;
+
+const PACKET_BUILDER_FRAME_LINES: f32 = 6.5;
+
+const THEME_EDITOR_FRAME_LINES: f32 = 9.5;
+
+const TIMELINE_FRAME_LINES: f32 = 8.0;
+
+const VC_STATS_FRAME_LINES: f32 = 6.0;
+
+const RESYNC_FRAME_LINES: f32 = 6.0;
+
+const BRIDGE_FRAME_LINES: f32 = 14.0;
+
+const ERROR_DETAILS_FRAME_LINES: f32 = 13.0;
+
+/// Lines below the statistics table used for the log header, error notifications,
+/// and control buttons. The statistics table takes the rest of the window.
+const STATS_RESERVED_LINES: f32 = 5.5;
+
+/// Lines used by the transport controls while processing
+const TRANSPORT_CONTROL_LINES: f32 = 4.5;
+
+/// The statistics table is never smaller then this number of lines
+const STATS_MIN_LINES: f32 = 4.0;
+
+/// Color used to indicate that the configuration has unsaved changes
+const MODIFIED_COLOR: (f32, f32, f32, f32) = (1.0, 0.8, 0.2, 1.0);
+
+/// Color used to highlight the packet that triggered a pause condition
+const BREAKPOINT_COLOR: (f32, f32, f32, f32) = (1.0, 0.8, 0.2, 1.0);
+
+/// Color used for APIDs with sequence count gaps in the statistics table
+const GAP_COLOR: (f32, f32, f32, f32) = (1.0, 0.3, 0.3, 1.0);
+
+/// Seconds without a read from the input while processing before the time since the last read is highlighted
+const INPUT_IDLE_WARNING_SECS: f64 = 5.0;
 
-/// Window width given to SDL
-const WINDOW_WIDTH:  f32 = 680.0;
+/// Color used to mark outputs that have failed while processing
+const FAILED_OUTPUT_COLOR: (f32, f32, f32, f32) = (1.0, 0.3, 0.3, 1.0);
 
-/// Window height given to SDL
-const WINDOW_HEIGHT: f32 = 740.0;
+/// Color used for APIDs that have not received a packet recently
+const STALE_COLOR: (f32, f32, f32, f32) = (0.5, 0.5, 0.5, 1.0);
 
-const STATS_FRAME_HEIGHT: f32 = 170.0;
+/// Color of the button for the session shown in the GUI
+const ACTIVE_SESSION_COLOR: (f32, f32, f32, f32) = (0.2, 0.4, 0.7, 1.0);
 
-const CONFIG_SETTINGS_FRAME_HEIGHT: f32 = 50.0;
+/// Color of packet arrivals in the timeline
+const TIMELINE_COLOR: (f32, f32, f32, f32) = (0.9, 0.7, 0.0, 1.0);
 
-const INPUT_SETTINGS_FRAME_HEIGHT: f32 = 100.0;
+/// Color behind each APID's row in the timeline
+const TIMELINE_BACKGROUND_COLOR: (f32, f32, f32, f32) = (0.5, 0.5, 0.5, 0.2);
 
-const OUTPUT_SETTINGS_FRAME_HEIGHT: f32 = 100.0;
+const MIN_TIMELINE_SECONDS: f32 = 1.0;
 
-const CCSDS_SETTINGS_FRAME_HEIGHT: f32 = 180.0;
+/// The most packets that can be kept in each APID's history
+const MAX_PACKET_HISTORY: usize = 1000;
 
-const LOG_DIRECTORY: &str = "logs";
+/// Number of seconds that the most recent error is shown next to the error count
+const ERROR_TOAST_SECONDS: u64 = 5;
+
+const MIN_UI_SCALE: f32 = 0.5;
+
+const MAX_UI_SCALE: f32 = 3.0;
+
+/// The GUI layout is saved to this file on exit, and restored on startup
+const GUI_LAYOUT_FILE: &str = "ccsds_router_gui.json";
+
+/// The exit codes of a run without the GUI, so scripts can tell how the run ended.
+/// An exit code of 1 is used for other errors, such as a failed configuration command.
+const EXIT_FINISHED: i32 = 0;
+const EXIT_CONFIG_ERROR: i32 = 2;
+const EXIT_OPEN_ERROR: i32 = 3;
+const EXIT_PARSE_ERROR: i32 = 4;
+const EXIT_TERMINATED: i32 = 5;
+const EXIT_ALARM: i32 = 6;
 
 
 #[derive(Debug, StructOpt)]
@@ -136,720 +255,4298 @@ struct Opt {
     #[structopt(short = "s", long = "supressgui")]
     supress_gui: bool,
 
+    /// Run in the background, detached from the terminal and without the GUI, logging only to
+    /// the log file. Only supported on POSIX systems.
+    #[structopt(long = "daemon")]
+    daemon: bool,
+
+    /// The file the process ID is written to when running as a daemon
+    #[structopt(long = "pid-file", default_value = "ccsds_router.pid")]
+    pid_file: String,
+
     #[structopt(parse(from_os_str))]
     config_file_name: Option<PathBuf>,
+
+    // The options below override the configuration file's settings for the main session
+
+    /// The input stream, as file:<name>, udp:<ip>:<port>, tcp_client:<ip>:<port>, or tcp_server:<ip>:<port>
+    #[structopt(short = "i", long = "input", parse(try_from_str = "parse_stream_spec"))]
+    input: Option<(StreamOption, StreamSettings)>,
+
+    /// An output stream, in the same form as the input. Give this option once for each output.
+    #[structopt(short = "o", long = "output", parse(try_from_str = "parse_stream_spec"))]
+    outputs: Vec<(StreamOption, StreamSettings)>,
+
+    /// Only accept these APIDs from the input, as a comma separated list, or as '@<file>' to load
+    /// them from a text or CSV APID list
+    #[structopt(short = "a", long = "apids", parse(try_from_str = "parse_apid_list"))]
+    apids: Option<Vec<u16>>,
+
+    /// The timing of forwarded packets, as asap, replay, delay:<seconds>, or throttle:<seconds>
+    #[structopt(short = "t", long = "timing", parse(try_from_str = "TimestampSetting::from_spec"))]
+    timing: Option<TimestampSetting>,
+
+    /// The number of frame header bytes before each CCSDS packet
+    #[structopt(long = "prefix-bytes")]
+    prefix_bytes: Option<i32>,
+
+    /// Keep the frame header bytes when forwarding packets
+    #[structopt(long = "keep-prefix")]
+    keep_prefix: bool,
+
+    /// The number of frame footer bytes after each CCSDS packet
+    #[structopt(long = "postfix-bytes")]
+    postfix_bytes: Option<i32>,
+
+    /// Keep the frame footer bytes when forwarding packets
+    #[structopt(long = "keep-postfix")]
+    keep_postfix: bool,
+
+    /// Packets have a fixed size in bytes, ignoring their CCSDS headers
+    #[structopt(long = "fixed-size")]
+    fixed_size: Option<u32>,
+
+    /// Decode the CCSDS primary header as little endian
+    #[structopt(long = "little-endian")]
+    little_endian: bool,
+
+    /// The maximum packet size in bytes that will be forwarded
+    #[structopt(long = "max-length")]
+    max_length: Option<i32>,
+
+    /// Start processing immediately
+    #[structopt(long = "auto-start")]
+    auto_start: bool,
+
+    /// Exit once every session has finished processing
+    #[structopt(long = "exit-on-finish")]
+    exit_on_finish: bool,
+
+    /// Exit with an error when a rate alarm is raised while running without the GUI
+    #[structopt(long = "exit-on-alarm")]
+    exit_on_alarm: bool,
+
+    /// Process packets without writing to the outputs, reporting what would be forwarded to each
+    #[structopt(long = "dry-run")]
+    dry_run: bool,
+
+    /// Start processing at this UTC time, or after a countdown such as '+90' or '+00:01:30'
+    #[structopt(long = "start-at")]
+    start_at: Option<String>,
+
+    /// Stop processing at this UTC time, or after it has run for a time such as '+600' or '+00:10:00'
+    #[structopt(long = "stop-at")]
+    stop_at: Option<String>,
+
+    /// Open the control socket on this port of localhost
+    #[structopt(long = "control-port")]
+    control_port: Option<u16>,
+
+    /// Use the settings of a named profile from the configuration file
+    #[structopt(short = "p", long = "profile")]
+    profile: Option<String>,
+
+    /// Print a summary of the run as a single line of JSON when running without the GUI
+    #[structopt(long = "summary")]
+    summary: bool,
+
+    /// Seconds between statistics reports when running without the GUI, or 0 to disable them
+    #[structopt(long = "stats-interval")]
+    stats_interval: Option<f32>,
+
+    /// Process the input file, or generated packets, as fast as possible without writing to the
+    /// outputs, and print the packet rate, byte rate, and CPU usage
+    #[structopt(long = "bench")]
+    bench: bool,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Create, check, or show configuration files
+    #[structopt(name = "config")]
+    Config {
+        #[structopt(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Summarize the packets of a file without forwarding them, using the packet settings of the configuration
+    #[structopt(name = "inspect")]
+    Inspect {
+        #[structopt(parse(from_os_str))]
+        file_name: PathBuf,
+    },
+
+    /// Split a packet file into one file for each APID
+    #[structopt(name = "split")]
+    Split {
+        #[structopt(parse(from_os_str))]
+        file_name: PathBuf,
+
+        /// The directory to write the files to, instead of the directory of the packet file
+        #[structopt(long = "output-dir", parse(from_os_str))]
+        output_dir: Option<PathBuf>,
+    },
+
+    /// Merge packet files into one file, ordered by packet timestamp
+    #[structopt(name = "merge")]
+    Merge {
+        /// The file to write the merged packets to
+        #[structopt(short = "o", long = "output", parse(from_os_str))]
+        output: PathBuf,
+
+        #[structopt(parse(from_os_str))]
+        file_names: Vec<PathBuf>,
+    },
+
+    /// Write the packets of a file with the given APIDs and timestamps to a new file
+    #[structopt(name = "filter")]
+    Filter {
+        #[structopt(parse(from_os_str))]
+        file_name: PathBuf,
+
+        /// The file to write the kept packets to
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+
+        /// Only keep these APIDs, as a comma separated list
+        #[structopt(long = "apids", parse(try_from_str = "parse_apid_list"))]
+        apids: Option<Vec<u16>>,
+
+        /// Only keep packets with timestamps at or after this time, in seconds or as a UTC time
+        #[structopt(long = "start")]
+        start: Option<String>,
+
+        /// Only keep packets with timestamps before this time, in seconds or as a UTC time
+        #[structopt(long = "end")]
+        end: Option<String>,
+    },
+}
+
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Write a default configuration, with a comment describing each setting
+    #[structopt(name = "generate")]
+    Generate {
+        /// The file to write. The configuration is printed if no file is given.
+        #[structopt(parse(from_os_str))]
+        file_name: Option<PathBuf>,
+    },
+
+    /// Check a configuration file, reporting the location of each problem
+    #[structopt(name = "validate")]
+    Validate {
+        #[structopt(parse(from_os_str))]
+        file_name: PathBuf,
+    },
+
+    /// Print the configuration that would be used, including command line overrides
+    #[structopt(name = "describe")]
+    Describe,
+}
+
+impl Opt {
+    /// Override the settings of a configuration with those given on the command line
+    fn apply(&self, config: &mut AppConfig) {
+        if let Some((input_selection, ref input_settings)) = self.input {
+            config.input_selection = input_selection;
+            config.input_settings = input_settings.clone();
+        }
+
+        if self.outputs.len() > 0 {
+            config.output_selection = self.outputs.iter().map(|&(selection, _)| selection).collect();
+            config.output_settings = self.outputs.iter().map(|&(_, ref settings)| settings.clone()).collect();
+            config.allowed_output_apids = vec!(None; self.outputs.len());
+            config.output_transforms = Vec::new();
+            config.output_enabled = Vec::new();
+            config.output_names = Vec::new();
+        }
+
+        if let Some(ref apids) = self.apids {
+            config.allowed_input_apids = Some(apids.clone());
+        }
+
+        if let Some(ref timing) = self.timing {
+            config.timestamp_setting = timing.clone();
+        }
+
+        if let Some(prefix_bytes) = self.prefix_bytes {
+            config.frame_settings.prefix_bytes = max(prefix_bytes, 0);
+        }
+        if self.keep_prefix {
+            config.frame_settings.keep_prefix = true;
+        }
+
+        if let Some(postfix_bytes) = self.postfix_bytes {
+            config.frame_settings.postfix_bytes = max(postfix_bytes, 0);
+        }
+        if self.keep_postfix {
+            config.frame_settings.keep_postfix = true;
+        }
+
+        if let Some(fixed_size) = self.fixed_size {
+            config.packet_size = PacketSize::Fixed(fixed_size);
+        }
+
+        if self.little_endian {
+            config.little_endian_ccsds = true;
+        }
+
+        if let Some(max_length) = self.max_length {
+            config.max_length_bytes = max_length;
+        }
+
+        if self.auto_start {
+            config.auto_start = true;
+        }
+
+        if self.exit_on_finish {
+            config.exit_on_finish = true;
+        }
+
+        if self.exit_on_alarm {
+            config.exit_on_alarm = true;
+        }
+
+        // a dry run applies to every session, so nothing is written by any of them
+        if self.dry_run {
+            config.dry_run = true;
+            for session_config in config.sessions.iter_mut() {
+                session_config.dry_run = true;
+            }
+        }
+
+        // a schedule applies to every session, so they start and stop together
+        if let Some(ref start_at) = self.start_at {
+            config.schedule.start = start_at.clone();
+            for session_config in config.sessions.iter_mut() {
+                session_config.schedule.start = start_at.clone();
+            }
+        }
+
+        if let Some(ref stop_at) = self.stop_at {
+            config.schedule.stop = stop_at.clone();
+            for session_config in config.sessions.iter_mut() {
+                session_config.schedule.stop = stop_at.clone();
+            }
+        }
+
+        if let Some(control_port) = self.control_port {
+            config.control_port = Some(control_port);
+        }
+
+        if let Some(stats_interval) = self.stats_interval {
+            config.stats_interval = stats_interval;
+        }
+    }
+}
+
+// Parse a comma separated list of APIDs given on the command line, or load the list from the
+// file given after an '@'
+fn parse_apid_list(apids_str: &str) -> Result<Vec<u16>, String> {
+    if apids_str.starts_with('@') {
+        return load_apid_list(&apids_str[1..]);
+    }
+
+    apids_str.split(',')
+             .map(|apid_str| apid_str.trim())
+             .filter(|apid_str| !apid_str.is_empty())
+             .map(|apid_str| apid_str.parse::<u16>().map_err(|_| format!("Invalid APID '{}'", apid_str)))
+             .collect()
 }
 
 fn main() {
-    let opt = Opt::from_args();
+    let mut opt = Opt::from_args();
+
+    // plugins are registered before any configuration that names them is used
+    register_plugins();
+
+    // a daemon has no one to show the GUI to
+    if opt.daemon {
+        opt.supress_gui = true;
+    }
 
     let mut config: AppConfig;
 
     let mut config_file_name: String;
 
+    // Read configuration file
+    match opt.config_file_name {
+        Some(ref path) => config_file_name = path.to_string_lossy().to_string(),
+        None => config_file_name = "ccsds_router.json".to_string(),
+    }
+
+    // configuration commands run without starting the router
+    if let Some(Command::Config { ref command }) = opt.command {
+        std::process::exit(run_config_command(command, &opt, &config_file_name));
+    }
+
+    if let Some(Command::Inspect { ref file_name }) = opt.command {
+        std::process::exit(run_inspect_command(file_name, &opt, &config_file_name));
+    }
+
+    match opt.command {
+        Some(Command::Split { .. }) | Some(Command::Merge { .. }) | Some(Command::Filter { .. }) => {
+            std::process::exit(run_file_command(&opt, &config_file_name));
+        },
+
+        _ => {},
+    }
+
+    if opt.bench {
+        std::process::exit(run_bench_command(&opt, &config_file_name));
+    }
+
+    // the process detaches before any threads are started, as only the forking thread continues
+    if opt.daemon {
+        if let Err(err) = run_as_daemon(&opt.pid_file) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
     // Set Up Logging
     // we ignore the result as it will fail if the directory already exists.
     let _ = create_dir(LOG_DIRECTORY);
 
-    let date = Local::now();
-    let log_name = format!("{}/{}", LOG_DIRECTORY, date.format("ccsds_router_log_%Y%m%d_%H_%M_%S.log"));
-    let _ = CombinedLogger::init(vec!(TermLogger::new(LevelFilter::max(),   Config::default()).unwrap(),
-                                      WriteLogger::new(LevelFilter::max(), Config::default(), File::create(log_name).unwrap())
-                                      )).unwrap();
+    // the configuration is loaded before logging is set up, as it has the log settings. The
+    // result of loading it is logged once logging is set up.
+    let config_result = load_config(&config_file_name, &opt.profile);
+    let log_settings = match config_result {
+        Ok(Some(ref config_read)) => config_read.log_settings.clone(),
 
+        _ => Default::default(),
+    };
 
-    // Read configuration file
-    match opt.config_file_name {
-        Some(path) => config_file_name = path.to_string_lossy().to_string(),
-        None => config_file_name = "ccsds_router.json".to_string(),
+    // a daemon has no terminal to log to
+    let (log_buffer, log_problems) = init_logging(&log_settings, !opt.daemon);
+    for problem in log_problems {
+        warn!("{}", problem);
     }
 
-    // Load the initial configuration
-    match load_config(&config_file_name) {
-      Some(config_read) => {
-          let config_used = format!("Configuration Used: {}", config_file_name);
+    // Load the initial configuration. A configuration file that exists but can not be
+    // loaded is reported, rather than silently replaced with the defaults.
+    let mut config_error = None;
+    match config_result {
+      Ok(Some(config_read)) => {
+          let config_used = match opt.profile {
+              Some(ref profile) => format!("Configuration Used: {} (profile {})", config_file_name, profile),
+
+              None => format!("Configuration Used: {}", config_file_name),
+          };
           info!("{}", config_used);
 
           config = config_read;
       },
 
-      None => {
+      Ok(None) => {
           // use defaults if no config was read
-          warn!("Configuration '{}' provided. Default Configuration Used", config_file_name);
-          config = Default::default();
+          warn!("Configuration '{}' not found. Default Configuration Used", config_file_name);
+          config = default_config();
+      },
+
+      Err(err) => {
+          error!("Could not load configuration '{}':\n{}", config_file_name, err);
+
+          // without a GUI there is no one to fix the configuration, so the router does not run
+          if opt.supress_gui {
+              if opt.summary {
+                  print_run_summary(&RunSummary {
+                      outcome: exit_outcome(EXIT_CONFIG_ERROR).to_string(),
+                      exit_code: EXIT_CONFIG_ERROR,
+                      sessions: Vec::new(),
+                  });
+              }
+              std::process::exit(EXIT_CONFIG_ERROR);
+          }
 
-          // the default max length is 0xFFFF in the length field, 
-          // plus the size of a CCSDS Primary header, plus 1.
-          config.max_length_bytes = 65535 + 6 + 1;
+          config = default_config();
+          config_error = Some(err);
       },
     }
 
-    // make sure there is at least one of the output settings
-    if config.output_settings.len() == 0 {
-        config.output_settings = vec!(Default::default());
-    }
-    if config.output_selection.len() == 0 {
-        config.output_selection = vec!(Default::default());
+    // settings given on the command line take priority over the configuration file
+    opt.apply(&mut config);
+
+    // the defaults used in place of a broken configuration are not started automatically
+    if config_error.is_some() && config.auto_start {
+        warn!("Not starting automatically, as the configuration could not be loaded");
+        config.auto_start = false;
     }
-    if config.allowed_output_apids.len() == 0 {
-        config.allowed_output_apids = vec!(None);
+
+    // an unreasonable scale would make the GUI unusable
+    if !(config.ui_scale >= MIN_UI_SCALE && config.ui_scale <= MAX_UI_SCALE) {
+        warn!("UI scale {} is out of range, using a scale of 1.0", config.ui_scale);
+        config.ui_scale = 1.0;
     }
 
-    // Spawn processing thread
-    let (gui_sender,  gui_receiver)  = channel::<GuiMessage>();
-    let (proc_sender, proc_receiver) = channel::<ProcessingMsg>();
+    fill_session_outputs(&mut config);
 
-    let ccsds_thread = thread::spawn(move || {
-        process_thread( gui_sender, proc_receiver );
-    });
+    // Spawn a processing thread for each session
+    let mut sessions: Vec<Session> = (0..config.session_count()).map(|_| Session::spawn()).collect();
 
     // Set up ctrl-c handling
-    let proc_sender_clone = proc_sender.clone();
+    let proc_senders: Vec<Sender<ProcessingMsg>> = sessions.iter().map(|session| session.router.sender()).collect();
     ctrlc::set_handler(move || {
-        proc_sender_clone.send(ProcessingMsg::Terminate).unwrap();
+        for proc_sender in proc_senders.iter() {
+            let _ = proc_sender.send(ProcessingMsg::Terminate);
+        }
         std::thread::sleep(Duration::from_millis(200));
     }).expect("Error setting up ctrl-c handling");
 
+    // a hangup signal reloads the configuration file, so a supervised router can change
+    // its routing without being restarted
+    let reload_requested = Arc::new(AtomicBool::new(false));
+    #[cfg(unix)]
+    {
+        if let Err(err) = signal_hook::flag::register(signal_hook::SIGHUP, reload_requested.clone()) {
+            error!("Could not set up SIGHUP handling: {}", err);
+        }
+    }
+
+    // Start the control socket, so scripts can drive the router
+    let control_receiver = match config.control_port {
+        Some(port) => {
+            match start_control_server(&config.control_address, port, &config.control_token, &config.control_allowed_hosts) {
+                Ok(receiver) => {
+                    info!("Control socket listening on {} port {}", config.control_address, port);
+
+                    let localhost_only = match config.control_address.parse::<IpAddr>() {
+                        Ok(address) => address.is_loopback(),
+
+                        Err(_) => config.control_address == "localhost",
+                    };
+                    if !localhost_only && config.control_token.trim().is_empty() && config.control_allowed_hosts.is_empty() {
+                        warn!("The control socket accepts commands from other machines without a token or allowed hosts, so anyone who can reach it can command the router");
+                    }
+                    Some(receiver)
+                },
+
+                Err(err) => {
+                    error!("{}", err);
+                    None
+                },
+            }
+        },
+
+        None => None,
+    };
+
+    // Start the bridge, which forwards packets between its two streams alongside the sessions
+    let mut bridge = if config.bridge.enabled {
+        let (source_names, sink_names) = plugin_names();
+        let problems = bridge_problems(&config.bridge, &source_names, &sink_names);
+        if problems.is_empty() {
+            Some(Bridge::start(&config))
+        } else {
+            error!("Not starting the bridge:\n{}", problems.join("\n"));
+            None
+        }
+    } else {
+        None
+    };
+
     // if we run without a GUI, make sure to autostart or nothing will happen.
     if opt.supress_gui {
         config.auto_start = true;
     }
 
-    // If auto start is selected, start the processing thread immediately
+    // If auto start is selected, start each session immediately
     if config.auto_start {
         info!("Auto Start Processing. Configuration file {}", config_file_name);
 
-        proc_sender.send(ProcessingMsg::Start(config.clone())).unwrap();
+        let start_time = match coordinated_start_time(&config) {
+            Ok(start_time) => start_time,
+
+            Err(err) => {
+                error!("{}. Starting without the peer router", err);
+                None
+            },
+        };
+
+        for (index, session) in sessions.iter_mut().enumerate() {
+            start_or_schedule(session, config.session_config(index), start_time);
+        }
     }
 
+    let mut run_summary = None;
     if opt.supress_gui {
         info!("Running without GUI");
-        // if no gui is run, just read messages until the processing thread is finished
-        while let Ok(msg_result) = gui_receiver.recv_timeout(Duration::from_millis(500)) {
+        run_summary = Some(run_headless(&mut config, &mut config_file_name, &mut sessions, &bridge, control_receiver.as_ref(), &opt, &reload_requested));
+    } else {
+        // Run GUI main loop
+        run_gui( &mut config, &mut config_file_name, &mut sessions, &mut bridge, log_buffer, control_receiver.as_ref(), config_error, opt.profile.clone() );
+    }
 
-            match msg_result {
-                    GuiMessage::Terminate => {
-                    break;
-                },
 
-                GuiMessage::PacketUpdate(packet_update) => {
+    // Clean up and Exit 
+    for session in sessions.iter_mut() {
+        session.terminate();
+    }
+
+
+    info!("Exiting");
+
+    if opt.daemon {
+        let _ = remove_file(&opt.pid_file);
+    }
+
+    if let Some(run_summary) = run_summary {
+        if opt.summary {
+            print_run_summary(&run_summary);
+        }
+        std::process::exit(run_summary.exit_code);
+    }
+}
+
+// Detach from the terminal and run in the background, writing the process ID to a pidfile.
+// The working directory is kept, so relative file names in the configuration still work.
+#[cfg(unix)]
+fn run_as_daemon(pid_file: &str) -> Result<(), String> {
+    let working_directory = std::env::current_dir()
+                                     .map_err(|err| format!("Could not get the working directory: {}", err))?;
+
+    daemonize::Daemonize::new()
+                         .pid_file(pid_file)
+                         .working_directory(working_directory)
+                         .start()
+                         .map_err(|err| format!("Could not start as a daemon: {}", err))
+}
+
+#[cfg(not(unix))]
+fn run_as_daemon(_pid_file: &str) -> Result<(), String> {
+    Err("Daemon mode is only supported on POSIX systems".to_string())
+}
+
+// The outcome of a run without the GUI, as given in its summary
+fn exit_outcome(exit_code: i32) -> &'static str {
+    match exit_code {
+        EXIT_FINISHED => "finished",
+        EXIT_CONFIG_ERROR => "config_error",
+        EXIT_OPEN_ERROR => "open_error",
+        EXIT_PARSE_ERROR => "parse_error",
+        EXIT_TERMINATED => "terminated",
+        EXIT_ALARM => "alarm",
+        _ => "error",
+    }
+}
+
+// Print the summary of a run to stdout. It is a single line, so a script can take the
+// last line of output even when log messages are printed as well.
+fn print_run_summary(run_summary: &RunSummary) {
+    match serde_json::to_string(run_summary) {
+        Ok(summary_str) => println!("{}", summary_str),
+
+        Err(err) => error!("Could not write the run summary: {}", err),
+    }
+}
+
+// Run a configuration command, returning the process's exit code
+fn run_config_command(command: &ConfigCommand, opt: &Opt, config_file_name: &String) -> i32 {
+    match command {
+        ConfigCommand::Generate { file_name } => {
+            let config_str = generate_config();
+
+            match file_name {
+                Some(file_name) => {
+                    match File::create(file_name).and_then(|mut file| file.write_all(config_str.as_bytes())) {
+                        Ok(()) => {
+                            println!("Wrote default configuration to {}", file_name.display());
+                            0
+                        },
+
+                        Err(err) => {
+                            eprintln!("Could not write {}: {}", file_name.display(), err);
+                            1
+                        },
+                    }
                 },
 
-                GuiMessage::PacketDropped(header) => {
+                None => {
+                    print!("{}", config_str);
+                    0
                 },
+            }
+        },
 
-                GuiMessage::Finished => {
-                    break;
+        ConfigCommand::Validate { file_name } => {
+            match validate_config(&file_name.to_string_lossy()) {
+                Ok(_) => {
+                    println!("{} is a valid configuration", file_name.display());
+                    0
                 },
 
-                GuiMessage::Error(error_msg) => {
-                    error!("{}", error_msg);
+                Err(problems) => {
+                    for problem in problems.iter() {
+                        eprintln!("{}", problem);
+                    }
+                    1
                 },
             }
-        }
-    } else {
-        // Run GUI main loop
-        run_gui( &mut config, &mut config_file_name, gui_receiver, proc_sender );
+        },
+
+        ConfigCommand::Describe => {
+            let config = match command_config(opt, config_file_name) {
+                Some(config) => config,
+
+                None => return 1,
+            };
+
+            println!("{}", serde_json::to_string_pretty(&config).unwrap());
+            0
+        },
     }
+}
 
+// Inspect a packet file, printing a summary of its packets. Returns the process's exit code.
+fn run_inspect_command(file_name: &PathBuf, opt: &Opt, config_file_name: &String) -> i32 {
+    let config = match command_config(opt, config_file_name) {
+        Some(config) => config,
 
-    // Clean up and Exit 
-    ccsds_thread.join().unwrap();
+        None => return 1,
+    };
 
+    match inspect_file(&file_name.to_string_lossy(), &config) {
+        Ok(inspection) => {
+            print!("{}", inspection.to_text());
 
-    info!("Exiting");
-}
+            if inspection.parse_error.is_some() {
+                1
+            } else {
+                0
+            }
+        },
 
-fn ui_config_settings(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState) {
-    ui.same_line(0.0);
-    ui.with_id("ToggleConfigSettings", || {
-        // align the word 'Toggle' with other settings
-        ui.text("  ");
-        ui.same_line(0.0);
-        // button to show or hide section
-        if ui.small_button(im_str!("Toggle")) {
-            app_state.config_settings_shown = !app_state.config_settings_shown;
-        }
-    });
-    if app_state.config_settings_shown {
-        configuration_ui(&ui, config, &mut app_state.config_file_name, &mut app_state.imgui_str);
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        },
     }
 }
 
-fn ui_input_settings(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState) {
-    ui.same_line(0.0);
-    ui.with_id("ToggleInputSettings", || {
-        // align the word 'Toggle' with other settings
-        ui.text(" ");
-        ui.same_line(0.0);
-        // button to show or hide section
-        if ui.small_button(im_str!("Toggle")) {
-            app_state.input_settings_shown = !app_state.input_settings_shown;
-        }
-    });
-    if app_state.input_settings_shown {
-        ui.child_frame(im_str!("SelectInputType"), ((WINDOW_WIDTH - 15.0), INPUT_SETTINGS_FRAME_HEIGHT))
-            .show_borders(true)
-            .collapsible(true)
-            .build(|| {
-                input_stream_ui(&ui,
-                                &mut config.input_selection,
-                                &mut config.input_settings,
-                                &mut config.allowed_input_apids,
-                                &mut app_state.imgui_str);
-            });
-    }
-}
+// Split, merge, or filter packet files, printing the files written. Returns the process's exit code.
+fn run_file_command(opt: &Opt, config_file_name: &String) -> i32 {
+    let config = match command_config(opt, config_file_name) {
+        Some(config) => config,
 
-fn ui_output_settings(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState, output_index: &mut usize) {
-    ui.same_line(0.0);
-    ui.with_id("ToggleOutputSettings", || {
-        // align the word 'Toggle' with other settings
-        ui.text("");
-        ui.same_line(0.0);
-        // button to show or hide section
-        if ui.small_button(im_str!("Toggle")) {
-            app_state.output_settings_shown = !app_state.output_settings_shown;
-        }
-    });
-    ui.same_line(0.0);
-    if ui.small_button(im_str!("New")) {
-        config.output_selection.push(Default::default());
-        config.output_settings.push(Default::default());
-        config.allowed_output_apids.push(None);
-        *output_index += 1;
-    }
-    ui.same_line(0.0);
-    if ui.small_button(im_str!("Prev")) {
-        if *output_index > 0 {
-            *output_index -= 1;
-        }
-    }
-    ui.same_line(0.0);
-    ui.text(format!("{}", *output_index));
-    ui.same_line(0.0);
-    if ui.small_button(im_str!("Next")) {
-        *output_index = min(*output_index + 1, config.output_selection.len() - 1);
-    }
-    ui.same_line(0.0);
-    if ui.small_button(im_str!("Delete")) {
-        // only allow deletion if this is not the last output
-        if config.output_selection.len() > 1 {
-            config.output_selection.remove(*output_index);
-            config.output_settings.remove(*output_index);
-            config.allowed_output_apids.remove(*output_index);
-            *output_index = min(*output_index, config.output_selection.len() - 1);
-        }
-    }
-    ui.same_line(0.0);
-    ui.text(format!("({})", config.output_selection.len()));
-    if app_state.output_settings_shown {
-        ui.child_frame(im_str!("SelectOutputType"), ((WINDOW_WIDTH - 15.0), OUTPUT_SETTINGS_FRAME_HEIGHT))
-            .movable(true)
-            .show_borders(true)
-            .collapsible(true)
-            .show_scrollbar(true)
-            .always_show_vertical_scroll_bar(true)
-            .build(|| {
-                output_stream_ui(&ui,
-                                 &mut config.output_selection[*output_index],
-                                 &mut config.output_settings[*output_index],
-                                 &mut config.allowed_output_apids[*output_index],
-                                 &mut app_state.imgui_str);
-            });
-    }
-}
-
-fn run_gui(config: &mut AppConfig, config_file_name: &mut String, receiver: Receiver<GuiMessage>, sender: Sender<ProcessingMsg>) {
-    let sdl_context = sdl2::init().unwrap();
-    let video = sdl_context.video().unwrap();
-
-    {
-        let gl_attr = video.gl_attr();
-        gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
-        gl_attr.set_context_version(3, 0);
-    }
-
-    let window = video.window("CCSDS Packet Router", WINDOW_WIDTH as u32, WINDOW_HEIGHT as u32)
-        .position_centered()
-        .resizable()
-        .opengl()
-        .allow_highdpi()
-        .build()
-        .unwrap();
+        None => return 1,
+    };
 
-    let _gl_context = window.gl_create_context().expect("Couldn't create GL context");
-    gl::load_with(|s| video.gl_get_proc_address(s) as _);
+    let result = match opt.command {
+        Some(Command::Split { ref file_name, ref output_dir }) => {
+            split_file(&file_name.to_string_lossy(), output_dir.as_ref().map(|dir| dir.as_path()), &config)
+        },
 
-    let mut imgui = imgui::ImGui::init();
-    imgui.set_ini_filename(None);
+        Some(Command::Merge { ref output, ref file_names }) => {
+            let file_names: Vec<String> = file_names.iter().map(|file_name| file_name.to_string_lossy().to_string()).collect();
+            merge_files(&file_names, &output.to_string_lossy(), &config)
+        },
 
-    let mut imgui_sdl2 = imgui_sdl2::ImguiSdl2::new(&mut imgui);
+        Some(Command::Filter { ref file_name, ref output, ref apids, ref start, ref end }) => {
+            // the times may be given as UTC times, which are converted using the timestamp epoch
+            let parse_time = |time: &Option<String>| -> Result<Option<f64>, String> {
+                match time {
+                    Some(time) => config.timestamp_def.epoch.parse_time(time).map(Some),
 
-    let renderer = imgui_opengl_renderer::Renderer::new(&mut imgui, |s| video.gl_get_proc_address(s) as _);
+                    None => Ok(None),
+                }
+            };
 
-    let mut event_pump = sdl_context.event_pump().unwrap();
+            match (parse_time(start), parse_time(end)) {
+                (Ok(start_secs), Ok(end_secs)) => {
+                    let filter = PacketFilter { apids: apids.clone(), start_secs: start_secs, end_secs: end_secs };
+                    filter_file(&file_name.to_string_lossy(), &output.to_string_lossy(), &filter, &config)
+                },
 
-    let mut app_state: AppState = AppState::new();
-    app_state.config_file_name = config_file_name.clone();
+                (Err(err), _) | (_, Err(err)) => Err(err),
+            }
+        },
 
+        _ => return 1,
+    };
 
-    /* Application State */
-    let mut processing_stats: ProcessingStats = Default::default();
+    match result {
+        Ok(description) => {
+            print!("{}", description);
+            0
+        },
 
-    // NOTE this could be a state machine instead of bools
-    let mut paused = false;
-    let mut processing = config.auto_start;
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        },
+    }
+}
 
-    let mut output_index = 0;
+// Benchmark processing of the main session's input, printing the rates achieved. Returns the process's exit code.
+fn run_bench_command(opt: &Opt, config_file_name: &String) -> i32 {
+    let config = match command_config(opt, config_file_name) {
+        Some(config) => config,
 
-    let mut packet_recv_diffs: VecDeque<SystemTime> = VecDeque::new();
-    let mut packet_recv_bytes: usize = 0;
+        None => return 1,
+    };
 
-    match config.theme {
-        GuiTheme::Dark => {
-            set_style_dark(imgui.style_mut());
+    match run_bench(&config) {
+        Ok(bench_report) => {
+            print!("{}", bench_report.to_text());
+            0
         },
 
-        GuiTheme::Light => {
-            set_style_light(imgui.style_mut());
+        Err(err) => {
+            eprintln!("{}", err);
+            1
         },
     }
+}
 
+// The configuration used by a command, including the settings given on the command line.
+// If the configuration can not be loaded, the problem is printed and None is returned.
+fn command_config(opt: &Opt, config_file_name: &String) -> Option<AppConfig> {
+    let mut config = match load_config(config_file_name, &opt.profile) {
+        Ok(Some(config)) => config,
 
-    // Main GUI event loop
-    'running: loop {
-        /* SDL Events */
-        use sdl2::event::Event;
-        use sdl2::keyboard::Keycode;
-
-        for event in event_pump.poll_iter() {
-            imgui_sdl2.handle_event(&mut imgui, &event);
-            if imgui_sdl2.ignore_event(&event) { continue; }
-
-            match event {
-                Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
-                    break 'running;
-                },
-                _ => {}
-            }
-        }
-
-        /* Read Updates from Packet Processing Thread */
-        while let Ok(msg_result) = receiver.recv_timeout(Duration::from_millis(0)) {
+        Ok(None) => {
+            eprintln!("Configuration '{}' not found. Using the default configuration", config_file_name);
+            default_config()
+        },
 
-            match msg_result {
-                    GuiMessage::Terminate => {
-                    break 'running;
-                },
+        Err(err) => {
+            eprintln!("Could not load configuration '{}':\n{}", config_file_name, err);
+            return None;
+        },
+    };
+    opt.apply(&mut config);
 
-                GuiMessage::PacketUpdate(packet_update) => {
-                    let apid = packet_update.apid;
-                    let packet_stats = processing_stats.packet_history.entry(apid).or_default();
-                    let packet_length = packet_update.packet_length as usize;
-                    packet_stats.update(packet_update);
-                    packet_recv_diffs.push_back(packet_stats.recv_time);
-                    packet_recv_bytes += packet_length;
-                },
+    Some(config)
+}
 
-                GuiMessage::PacketDropped(header) => {
-                    processing_stats.packets_dropped += 1;
+// if no gui is run, just read messages until each session's processing is finished.
+// With a control socket, processing can be started again after it finishes, so messages
+// are read until each session's processing thread terminates. A running bridge also keeps the
+// router waiting once processing finishes.
+fn run_headless(config: &mut AppConfig,
+                config_file_name: &mut String,
+                sessions: &mut Vec<Session>,
+                bridge: &Option<Bridge>,
+                control_receiver: Option<&Receiver<ControlRequest>>,
+                opt: &Opt,
+                reload_requested: &AtomicBool) -> RunSummary {
+    let mut terminated = vec!(false; sessions.len());
+    let mut errors: Vec<Vec<String>> = vec!(Vec::new(); sessions.len());
+    let mut exit_code = EXIT_FINISHED;
+    let mut last_stats_report = SystemTime::now();
+    let mut alarm_raised = false;
+
+    loop {
+        if reload_requested.swap(false, Ordering::SeqCst) {
+            info!("Reloading configuration {}", config_file_name);
+            match load_config(config_file_name, &opt.profile) {
+                Ok(Some(mut new_config)) => {
+                    opt.apply(&mut new_config);
+                    new_config.auto_start = true;
+                    apply_config(config, new_config, sessions);
+                    terminated.resize(sessions.len(), false);
+                    errors.resize(sessions.len(), Vec::new());
                 },
 
-                GuiMessage::Finished => {
-                    processing = false;
+                Ok(None) => {
+                    error!("Configuration '{}' not found. Keeping the current configuration", config_file_name);
                 },
 
-                GuiMessage::Error(error_msg) => {
-                    error!("{}", error_msg);
+                Err(err) => {
+                    error!("Could not reload configuration '{}', keeping the current configuration:\n{}", config_file_name, err);
                 },
             }
         }
 
-        if packet_recv_diffs.len() > 0 &&
-              SystemTime::now().duration_since(*packet_recv_diffs.get(0).unwrap()).unwrap() > Duration::from_secs(1) {
-            processing_stats.packets_per_second = packet_recv_diffs.len();
-            processing_stats.bytes_per_second = packet_recv_bytes;
-            packet_recv_diffs.clear();
-            packet_recv_bytes = 0;
-        }
+        if let Some(control_receiver) = control_receiver {
+            while let Ok(request) = control_receiver.try_recv() {
+                handle_control_request(request, config, config_file_name, sessions);
+            }
 
-        /* IMGUI UI */
-        let ui = imgui_sdl2.frame(&window, &mut imgui, &event_pump.mouse_state());
+            // loading a configuration can change the number of sessions
+            terminated.resize(sessions.len(), false);
+            errors.resize(sessions.len(), Vec::new());
+        }
 
-        ui.window(im_str!(""))
-            .position((0.0, 0.0), ImGuiCond::FirstUseEver)
-            .size((WINDOW_WIDTH, WINDOW_HEIGHT), ImGuiCond::FirstUseEver)
-            .title_bar(false)
-            .movable(false)
-            .scrollable(false)
-            .resizable(false)
-            .collapsible(false)
-            .build(|| {
-                ui.text("Configuration");
-                ui_config_settings(&ui, config, &mut app_state);
+        start_scheduled_sessions(config, sessions);
+        stop_scheduled_sessions(config, sessions);
 
-                /* Source Selection */
-                ui.text("Input Settings");
-                ui_input_settings(&ui, config, &mut app_state);
+        // with a control socket, the router waits for further commands once processing finishes,
+        // unless it is set to exit
+        let bridge_running = bridge.as_ref().map_or(false, |bridge| bridge.is_running());
+        let wait_when_idle = (control_receiver.is_some() && !config.exit_on_finish) || bridge_running;
+        let done = sessions.iter().zip(terminated.iter()).all(|(session, session_terminated)| {
+            *session_terminated || (!wait_when_idle && !session.processing && !session.paused && session.scheduled_start.is_none())
+        });
+        if done {
+            break;
+        }
 
-                /* Output Settings */
-                ui.text("Output Settings");
-                ui_output_settings(&ui, config, &mut app_state, &mut output_index);
+        for (index, session) in sessions.iter_mut().enumerate() {
+            if terminated[index] {
+                continue;
+            }
 
-                /* CCSDS Packet Settings */
-                ui.text("CCSDS Settings");
-                ui.same_line(0.0);
-                ui.with_id("ToggleCcsdsSettings", || {
-                    // align the word 'Toggle' with other settings
-                    ui.text(" ");
-                    ui.same_line(0.0);
-                    // button to show or hide section
-                    if ui.small_button(im_str!("Toggle")) {
-                        app_state.ccsds_settings_shown = !app_state.ccsds_settings_shown;
+            match session.router.events().recv_timeout(Duration::from_millis(50)) {
+                Ok(msg) => {
+                    let paused = msg == GuiMessage::Paused;
+
+                    // statistics are kept so they can be requested through the control socket
+                    match session.update(msg, config.session_config(index).packet_history_length) {
+                        Some(GuiMessage::Terminate) => {
+                            terminated[index] = true;
+                            if exit_code == EXIT_FINISHED {
+                                exit_code = EXIT_TERMINATED;
+                            }
+                        },
+
+                        Some(GuiMessage::Error(error_msg)) => {
+                            error!("{}", error_msg);
+                            errors[index].push(error_msg);
+                        },
+
+                        // the first error that stops processing gives the exit code
+                        Some(GuiMessage::Failed(failure_kind, error_msg)) => {
+                            error!("{}", error_msg);
+                            errors[index].push(error_msg);
+                            if exit_code == EXIT_FINISHED || exit_code == EXIT_TERMINATED {
+                                exit_code = match failure_kind {
+                                    FailureKind::Open => EXIT_OPEN_ERROR,
+                                    FailureKind::Parse => EXIT_PARSE_ERROR,
+                                };
+                            }
+                        },
+
+                        _ => {},
                     }
-                });
-                if app_state.ccsds_settings_shown {
-                    packet_settings_ui(&ui, config, &mut app_state.timestamp_selection);
-                }
-
-                /* Packet Statistics */
-                ui.text("Packet Statistics");
-                packet_statistics_ui(&ui, &processing_stats, &app_state, processing_stats.packets_dropped);
 
-                /* Control Buttons */
-                if ui.small_button(im_str!("Clear Stats")) {
-                    info!("Clearing Statistics");
-                    processing_stats = Default::default();
-                }
-
-                ui.same_line(0.0);
-
-                if app_state.all_shown() {
-                    if ui.small_button(im_str!("Collapse All")) {
-                      app_state.hide_all();
-                    }
-                } else {
-                    if ui.small_button(im_str!(" Expand All ")) {
-                      app_state.show_all();
+                    // there is no user to continue processing without a GUI, so once a pause
+                    // condition has paused processing it is logged and processing continues.
+                    // Pauses requested through the control socket are kept.
+                    if paused {
+                        if let Some((apid, description)) = session.breakpoint.take() {
+                            warn!("Pause condition '{}' triggered by APID {}. Continuing without GUI", description, apid);
+                            session.processing = true;
+                            session.paused = false;
+                            session.router.resume().unwrap();
+                        }
                     }
-                }
+                },
 
-                // if we are paused, ask to continue or cancel
-                if paused {
-                    if ui.small_button(im_str!("Continue ")) {
-                        info!("Continuing Processing");
-                        sender.send(ProcessingMsg::Continue).unwrap();
-                        processing = true;
-                        paused = false;
-                    }
+                Err(RecvTimeoutError::Timeout) => {
+                },
 
-                    ui.same_line(0.0);
+                Err(RecvTimeoutError::Disconnected) => {
+                    terminated[index] = true;
+                },
+            }
 
-                    if ui.small_button(im_str!("Cancel")) {
-                        info!("Cancelled Processing");
-                        processing = false;
-                        paused = false;
-                        sender.send(ProcessingMsg::Cancel).unwrap();
-                    }
+            for alarm_msg in session.update_rates(&config.session_config(index).rate_alarms) {
+                errors[index].push(alarm_msg);
+                if config.exit_on_alarm {
+                    alarm_raised = true;
                 }
-                // if we are processing packets, ask to pause
-                else if processing {
-                    if ui.small_button(im_str!("  Pause  ")) {
-                        info!("Paused Processing");
-                        processing = false;
-                        paused = true;
-                        sender.send(ProcessingMsg::Pause).unwrap();
-                    }
+            }
+        }
 
-                    ui.same_line(0.0);
+        if alarm_raised {
+            error!("Exiting on rate alarm");
+            exit_code = EXIT_ALARM;
+            break;
+        }
 
-                    if ui.small_button(im_str!("Cancel")) {
-                        info!("Cancelled Processing");
-                        processing = false;
-                        paused = false;
-                        sender.send(ProcessingMsg::Cancel).unwrap();
-                    }
+        // without the GUI, the statistics are reported in the log so unattended runs can be followed
+        if config.stats_interval > 0.0 {
+            let since_report = SystemTime::now().duration_since(last_stats_report).unwrap_or(Duration::from_secs(0));
+            if since_report.as_fractional_secs() >= config.stats_interval as f64 {
+                last_stats_report = SystemTime::now();
+                for line in session_stats_text(config, sessions).lines() {
+                    info!("{}", line);
                 }
-                // otherwise, ask if we want to start processing packets
-                else {
-                    if ui.small_button(im_str!("Start")) {
-                        processing = true;
-
-                        // the current configuration is always saved when processing.
-                        // This is to prevent running a configuration that is not saved anywhere.
-                        save_config(config, &app_state.config_file_name.clone());
-                        info!("Start Processing. Configuration file {}", app_state.config_file_name);
-
-                        sender.send(ProcessingMsg::Start(config.clone())).unwrap();
+                if let Some(ref bridge) = *bridge {
+                    for line in bridge_stats_text(&config.bridge, &bridge.status()).lines() {
+                        info!("{}", line);
                     }
                 }
+            }
+        }
+    }
 
-                // don't exit unless the user confirms their action
-                if ui.small_button(im_str!("Exit")) {
-                    ui.open_popup(im_str!("Exit?"));
-                }
-                ui.popup_modal(im_str!("Exit?")).build(|| {
-                    ui.text("Exit the application?");
-                    if ui.small_button(im_str!("Exit")) {
-                        sender.send(ProcessingMsg::Terminate).unwrap();
-                    }
-
-                    ui.same_line(0.0);
+    // the processing threads wait for another Start once their input ends
+    for (index, session) in sessions.iter_mut().enumerate() {
+        if !terminated[index] {
+            session.terminate();
+        }
+    }
 
-                    if ui.small_button(im_str!("Don't Exit")) {
-                        ui.close_current_popup();
-                    }
-                });
-            });
+    RunSummary {
+        outcome: exit_outcome(exit_code).to_string(),
+        exit_code: exit_code,
+        sessions: sessions.iter().zip(errors.into_iter()).enumerate().map(|(index, (session, session_errors))| {
+            SessionSummary::new(&config.session_config(index).session_name, &session.processing_stats, session_errors)
+        }).collect(),
+    }
+}
 
+fn ui_config_settings(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleConfigSettings", || {
+        // align the word 'Toggle' with other settings
+        ui.text("  ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.config_settings_shown = !app_state.config_settings_shown;
+        }
+    });
+    if app_state.config_settings_shown {
+        configuration_ui(&ui, config, app_state);
+    }
+}
 
-        unsafe {
-            gl::ClearColor(0.2, 0.2, 0.2, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+fn ui_input_settings(ui: &Ui,
+                     config: &mut AppConfig,
+                     app_state: &mut AppState,
+                     processing_stats: &ProcessingStats,
+                     observed_apids: &BTreeSet<u16>,
+                     processing: bool) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleInputSettings", || {
+        // align the word 'Toggle' with other settings
+        ui.text(" ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.input_settings_shown = !app_state.input_settings_shown;
         }
+    });
+    if app_state.input_settings_shown {
+        ui.child_frame(im_str!("SelectInputType"), (0.0, frame_height(ui, INPUT_SETTINGS_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .build(|| {
+                input_stream_ui(&ui,
+                                &mut config.input_selection,
+                                &mut config.input_settings,
+                                &mut config.allowed_input_apids,
+                                observed_apids,
+                                &mut app_state.added_apid,
+                                &mut app_state.imgui_str);
 
-        renderer.render(ui);
+                if config.input_selection == StreamOption::File {
+                    replay_query_ui(ui, &mut config.replay_query, &mut app_state.imgui_str);
+                }
 
-        window.gl_swap_window();
+                ui.separator();
+                gap_fill_ui(ui, &mut config.gap_fill, &mut app_state.imgui_str);
 
+                ui.separator();
+                reorder_ui(ui, &mut config.reorder);
 
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
+                ui.separator();
+                input_stream_stats_ui(ui, config, processing_stats, processing);
+            });
     }
+}
 
-    match sender.send(ProcessingMsg::Terminate) {
-        Ok(_) => {
-            // NOTE awkward
-            while let Ok(msg) = receiver.recv_timeout(Duration::from_millis(500)) {
-                match msg {
-                    GuiMessage::Error(error_msg) => {
-                        error!("{}", error_msg);
-                    },
-
-                    _ => {}, // ignore other errors
+// The replay query reads only the packets of an input file selected by APID and time, found
+// using the file's index
+fn replay_query_ui(ui: &Ui, replay_query: &mut ReplayQuery, imgui_str: &mut ImString) {
+    ui.checkbox(im_str!("Replay Query"), &mut replay_query.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Read only the packets of the input file with the given APIDs and timestamps, using the file's index"));
+    }
+
+    if replay_query.enabled {
+        let mut apid_list_str: String = "".to_string();
+        for apid in replay_query.apids.iter() {
+            apid_list_str.push_str(&apid.to_string());
+            apid_list_str.push(',');
+        }
+        input_string(&ui, im_str!("Query APIDs"), &mut apid_list_str, imgui_str);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("A comma separated list of APIDs to replay. If empty, every APID is replayed"));
+        }
+        replay_query.apids.clear();
+        for apid_str in apid_list_str.split(",") {
+            apid_str.trim().parse().map(|apid| replay_query.apids.push(apid));
+        }
+
+        input_string(&ui, im_str!("Query Start"), &mut replay_query.start, imgui_str);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The earliest packet timestamp replayed, in seconds or as a UTC time. If empty, replay from the start of the file"));
+        }
+
+        input_string(&ui, im_str!("Query End"), &mut replay_query.end, imgui_str);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The latest packet timestamp replayed, in seconds or as a UTC time. If empty, replay to the end of the file"));
+        }
+    }
+}
+
+// Gap filling merges a backup input receiving the same downlink into the input
+fn gap_fill_ui(ui: &Ui, gap_fill: &mut GapFillSettings, imgui_str: &mut ImString) {
+    ui.checkbox(im_str!("Backup Input"), &mut gap_fill.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Fill the gaps in the input's sequence counts with the packets of a backup input receiving the same downlink,\nremoving the packets received on both"));
+    }
+
+    if !gap_fill.enabled {
+        return;
+    }
+
+    ui.same_line(0.0);
+    ui.push_item_width(80.0);
+    ui.input_float(im_str!("Hold (s)"), &mut gap_fill.hold_secs).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds each packet is held waiting for the backup's packets that fill a gap before it.\nA backup packet arriving later than this can not fill a gap."));
+    }
+    ui.pop_item_width();
+    gap_fill.hold_secs = gap_fill.hold_secs.max(0.0);
+
+    ui.with_id("BackupInput", || {
+        input_stream_settings_ui(ui, &mut gap_fill.selection, &mut gap_fill.settings, imgui_str);
+    });
+    ui.columns(1, im_str!("default"), false);
+
+    for problem in gap_fill.problems() {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
+    }
+}
+
+// The reorder buffer releases the input's packets in order, for outputs that can not handle disorder
+fn reorder_ui(ui: &Ui, reorder: &mut ReorderSettings) {
+    ui.checkbox(im_str!("Reorder Buffer"), &mut reorder.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Hold the input's packets briefly, releasing them in timestamp or sequence order\nwhen the input delivers them slightly out of order"));
+    }
+
+    if !reorder.enabled {
+        return;
+    }
+
+    let mut order_selection: i32 = match reorder.order {
+        ReorderOrder::Timestamp => 0,
+        ReorderOrder::Sequence => 1,
+    };
+
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("Timestamp Order"), &mut order_selection, 0);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Release packets in the order of their timestamps, as given by the timestamp settings"));
+    }
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("Sequence Order"), &mut order_selection, 1);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Release the packets of each APID in the order of their sequence counts"));
+    }
+    reorder.order = if order_selection == 1 { ReorderOrder::Sequence } else { ReorderOrder::Timestamp };
+
+    ui.push_item_width(80.0);
+    let mut max_packets = reorder.max_packets as i32;
+    ui.input_int(im_str!("Max Packets"), &mut max_packets).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The most packets held. Once more are held, the first in order is released."));
+    }
+    reorder.max_packets = min(max(max_packets, 1) as usize, MAX_REORDER_PACKETS);
+    ui.same_line(0.0);
+    ui.input_float(im_str!("Max Latency (s)"), &mut reorder.max_latency_secs).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The most seconds a packet is held waiting for the packets before it"));
+    }
+    ui.pop_item_width();
+    ui.same_line(0.0);
+    ui.checkbox(im_str!("Drop Late Packets"), &mut reorder.drop_late);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Drop the packets arriving after a packet following them has been released,\nrather than releasing them out of order"));
+    }
+
+    for problem in reorder.problems() {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
+    }
+}
+
+// The reads from the input stream, apart from the packets parsed from them, so that no data
+// arriving can be told apart from data arriving that does not parse
+fn input_stream_stats_ui(ui: &Ui, config: &AppConfig, processing_stats: &ProcessingStats, processing: bool) {
+    ui.columns(3, im_str!("InputStreamStats"), false);
+
+    ui.text(format!("Bytes Read: {} ({} bytes/s)", processing_stats.input_bytes_read, processing_stats.input_bytes_per_second));
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The bytes read from the input, whether or not they parse into packets"));
+    }
+    ui.next_column();
+
+    let buffer_percent = if processing_stats.parser_max_bytes > 0 {
+        100.0 * processing_stats.parser_buffered_bytes as f32 / processing_stats.parser_max_bytes as f32
+    } else {
+        0.0
+    };
+    ui.text(format!("Parser Buffer: {} bytes ({:.1}%)", processing_stats.parser_buffered_bytes, buffer_percent));
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The bytes held by the parser waiting to complete a packet, as a percent of the largest frame.\nA buffer that stays full while bytes are read is data arriving that does not parse."));
+    }
+    ui.next_column();
+
+    match processing_stats.last_read_time.and_then(|read_time| read_time.elapsed().ok()) {
+        Some(since_read) => {
+            let since_read_secs = since_read.as_fractional_secs();
+            let color = if processing && since_read_secs > INPUT_IDLE_WARNING_SECS { Some(GAP_COLOR) } else { None };
+            stats_text(ui, color, format!("Last Read: {:.1} s ago", since_read_secs));
+        },
+
+        None => ui.text("Last Read: never"),
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The time since bytes were last read from the input"));
+    }
+
+    ui.columns(1, im_str!("default"), false);
+
+    let gap_fill_stats = &processing_stats.gap_fill;
+    if config.gap_fill.enabled || *gap_fill_stats != Default::default() {
+        ui.text(format!("Gaps Filled: {}  Duplicates: {}", gap_fill_stats.filled, gap_fill_stats.duplicates));
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The packets of the backup input that filled gaps in the input, and the packets received on both that were removed"));
+        }
+    }
+
+    let reorder_stats = &processing_stats.reorder;
+    if config.reorder.enabled || *reorder_stats != Default::default() {
+        ui.text(format!("Reordered: {}  Late: {}  Late Dropped: {}", reorder_stats.reordered, reorder_stats.late, reorder_stats.late_dropped));
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The packets the reorder buffer released ahead of a packet that arrived before them,\nand the packets arriving too late to release in order"));
+        }
+    }
+}
+
+fn ui_output_settings(ui: &Ui,
+                      config: &mut AppConfig,
+                      app_state: &mut AppState,
+                      output_index: &mut usize,
+                      degraded_outputs: &BTreeMap<usize, String>,
+                      observed_apids: &BTreeSet<u16>,
+                      router: &Router) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleOutputSettings", || {
+        // align the word 'Toggle' with other settings
+        ui.text("");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.output_settings_shown = !app_state.output_settings_shown;
+        }
+    });
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("New")) {
+        config.output_selection.push(Default::default());
+        config.output_settings.push(Default::default());
+        config.allowed_output_apids.push(None);
+        *output_index += 1;
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Prev")) {
+        if *output_index > 0 {
+            *output_index -= 1;
+        }
+    }
+    ui.same_line(0.0);
+    match config.output_name(*output_index) {
+        Some(name) => ui.text(format!("{} {}", *output_index, name)),
+
+        None => ui.text(format!("{}", *output_index)),
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Next")) {
+        *output_index = min(*output_index + 1, config.output_selection.len() - 1);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Delete")) {
+        // only allow deletion if this is not the last output
+        if config.output_selection.len() > 1 {
+            config.output_selection.remove(*output_index);
+            config.output_settings.remove(*output_index);
+            config.allowed_output_apids.remove(*output_index);
+            if *output_index < config.output_transforms.len() {
+                config.output_transforms.remove(*output_index);
+            }
+            if *output_index < config.output_enabled.len() {
+                config.output_enabled.remove(*output_index);
+            }
+            if *output_index < config.output_names.len() {
+                config.output_names.remove(*output_index);
+            }
+            *output_index = min(*output_index, config.output_selection.len() - 1);
+        }
+    }
+    ui.same_line(0.0);
+    ui.text(format!("({})", config.output_selection.len()));
+    ui.same_line(0.0);
+    let mut output_enabled = config.is_output_enabled(*output_index);
+    if ui.checkbox(im_str!("Enabled"), &mut output_enabled) {
+        config.set_output_enabled(*output_index, output_enabled);
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("A disabled output keeps its settings, but is not opened or sent packets"));
+    }
+    if let Some(error_msg) = degraded_outputs.get(output_index) {
+        ui.same_line(0.0);
+        ui.text_colored(FAILED_OUTPUT_COLOR, im_str!("Failed"));
+        if ui.is_item_hovered() {
+            ui.tooltip_text(&ImString::new(format!("{}. Packets are not sent to this output until it is reconnected.", error_msg)));
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Retry")) {
+            info!("Reconnecting {}", config.output_label(*output_index));
+            if let Err(err_string) = router.send(ProcessingMsg::RetryOutput(*output_index)) {
+                error!("{}", err_string);
+            }
+        }
+    } else if degraded_outputs.len() > 0 {
+        ui.same_line(0.0);
+        ui.text_colored(FAILED_OUTPUT_COLOR, &ImString::new(format!("{} failed", degraded_outputs.len())));
+        if ui.is_item_hovered() {
+            let failed = degraded_outputs.keys().map(|index| config.output_label(*index)).collect::<Vec<String>>();
+            ui.tooltip_text(&ImString::new(failed.join("\n")));
+        }
+    }
+    if app_state.output_settings_shown {
+        ui.child_frame(im_str!("SelectOutputType"), (0.0, frame_height(ui, OUTPUT_SETTINGS_FRAME_LINES)))
+            .movable(true)
+            .show_borders(true)
+            .collapsible(true)
+            .show_scrollbar(true)
+            .always_show_vertical_scroll_bar(true)
+            .build(|| {
+                let mut output_name = config.output_names.get(*output_index).cloned().unwrap_or_default();
+                input_string(&ui, im_str!("Name"), &mut output_name, &mut app_state.imgui_str);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(im_str!("The name identifying this output in the statistics, errors, and processing report"));
+                }
+                if config.output_names.get(*output_index).map_or(!output_name.is_empty(), |name| *name != output_name) {
+                    config.set_output_name(*output_index, output_name);
+                }
+
+                output_stream_ui(&ui,
+                                 &mut config.output_selection[*output_index],
+                                 &mut config.output_settings[*output_index],
+                                 &mut config.allowed_output_apids[*output_index],
+                                 observed_apids,
+                                 &mut app_state.added_apid,
+                                 &mut app_state.imgui_str);
+            });
+    }
+}
+
+fn ui_transport_controls(ui: &Ui, config: &AppConfig, session: &mut Session, imgui_str: &mut ImString) {
+    let router = &session.router;
+
+    if let Some(ref description) = session.waiting_for {
+        ui.text_colored(BREAKPOINT_COLOR, &ImString::new(format!("Waiting for {} to connect", description)));
+    }
+
+    if session.in_outage {
+        ui.text_colored(BREAKPOINT_COLOR, im_str!("Simulated link outage: nothing is written to the outputs"));
+    }
+
+    // stepping forwards a single packet, so it is only available while paused
+    if session.paused {
+        if ui.small_button(im_str!("Step")) {
+            info!("Stepping One Packet");
+            router.send(ProcessingMsg::Step).unwrap();
+        }
+        ui.same_line(0.0);
+    }
+
+    if ui.slider_float(im_str!("Speed"), &mut session.playback_speed, 0.1, 10.0).build() {
+        router.send(ProcessingMsg::SetSpeed(session.playback_speed)).unwrap();
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Speed multiplier for Replay, Delay, and Throttle timing"));
+    }
+
+    if config.trigger_settings.enabled {
+        match session.capture {
+            Some((apid, ref description)) => {
+                ui.text_colored(BREAKPOINT_COLOR, &ImString::new(format!("Capturing: triggered on '{}' (APID {})", description, apid)));
+            },
+
+            None => {
+                ui.text("Capture waiting for trigger");
+            },
+        }
+    }
+
+    // seeking is only possible within a file, and the position is given as a fraction
+    // of the file's size.
+    if config.input_selection == StreamOption::File {
+        if let Ok(metadata) = std::fs::metadata(&config.input_settings.file.file_name) {
+            let file_size = metadata.len();
+
+            // while the slider is being dragged, keep the user's position rather then
+            // the position reported by the processing thread.
+            if !session.seek_dragging && file_size > 0 {
+                session.seek_fraction = session.input_position as f32 / file_size as f32;
+            }
+
+            if ui.slider_float(im_str!("Position"), &mut session.seek_fraction, 0.0, 1.0).build() {
+                session.seek_dragging = true;
+            }
+
+            if session.seek_dragging && !ui.is_item_active() {
+                let offset = (session.seek_fraction as f64 * file_size as f64) as u64;
+                info!("Seeking to byte {}", offset);
+                router.send(ProcessingMsg::Seek(offset)).unwrap();
+                session.input_position = offset;
+                session.seek_dragging = false;
+            }
+
+            // seeking to a packet or time uses the input file's index
+            if config.index_files {
+                if ui.small_button(im_str!("Go to Packet")) {
+                    info!("Seeking to packet {}", session.seek_packet);
+                    router.send(ProcessingMsg::SeekPacket(session.seek_packet as u64)).unwrap();
+                }
+                ui.same_line(0.0);
+                ui.push_item_width(100.0);
+                ui.input_int(im_str!("Packet"), &mut session.seek_packet).build();
+                ui.pop_item_width();
+                session.seek_packet = max(session.seek_packet, 0);
+
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Go to Time")) {
+                    match config.timestamp_def.epoch.parse_time(&session.seek_time) {
+                        Ok(timestamp_secs) => {
+                            info!("Seeking to time {}", timestamp_secs);
+                            router.send(ProcessingMsg::SeekTime(timestamp_secs)).unwrap();
+                        },
+
+                        Err(err) => error!("Could not seek to time '{}': {}", session.seek_time, err),
+                    }
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(im_str!("Seek to the first packet with a timestamp at or after this time, in seconds or as a UTC time such as 2020-01-01T12:00:00Z"));
+                }
+                ui.same_line(0.0);
+                ui.push_item_width(120.0);
+                input_string(ui, im_str!("Time (s)"), &mut session.seek_time, imgui_str);
+                ui.pop_item_width();
+            }
+        }
+    }
+}
+
+fn ui_packet_builder(ui: &Ui, config: &AppConfig, app_state: &mut AppState, router: &Router) {
+    ui.same_line(0.0);
+    ui.with_id("TogglePacketBuilder", || {
+        // align the word 'Toggle' with other settings
+        ui.text("       ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.packet_builder_shown = !app_state.packet_builder_shown;
+        }
+    });
+    if app_state.packet_builder_shown {
+        ui.child_frame(im_str!("PacketBuilderFrame"), (0.0, frame_height(ui, PACKET_BUILDER_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .build(|| {
+                packet_builder_ui(ui, config, app_state, router);
+            });
+    }
+}
+
+fn ui_theme_editor(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState, current_colors: &ThemeColors) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleThemeEditor", || {
+        // align the word 'Toggle' with other settings
+        ui.text("         ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.theme_editor_shown = !app_state.theme_editor_shown;
+        }
+    });
+    if app_state.theme_editor_shown {
+        ui.child_frame(im_str!("ThemeEditorFrame"), (0.0, frame_height(ui, THEME_EDITOR_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .build(|| {
+                theme_editor_ui(ui, config, app_state, current_colors);
+            });
+    }
+}
+
+fn theme_editor_ui(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState, current_colors: &ThemeColors) {
+    let theme_index = config.custom_themes.iter().position(|theme| theme.name == config.custom_theme);
+
+    // a new theme starts from the colors currently in use
+    if ui.small_button(im_str!("New")) {
+        let base = config.selected_custom_theme().map_or(config.theme, |theme| theme.base);
+
+        let mut theme_number = config.custom_themes.len() + 1;
+        while config.custom_themes.iter().any(|theme| theme.name == format!("Theme {}", theme_number)) {
+            theme_number += 1;
+        }
+
+        let name = format!("Theme {}", theme_number);
+        config.custom_themes.push(CustomTheme { name: name.clone(),
+                                                base: base,
+                                                colors: *current_colors,
+        });
+        config.custom_theme = name;
+        config.theme = GuiTheme::Custom;
+        return;
+    }
+
+    let theme_index = match theme_index {
+        Some(theme_index) => theme_index,
+        None => {
+            ui.same_line(0.0);
+            ui.text("Create a theme from the current colors, or select a saved theme");
+
+            if config.custom_themes.len() > 0 {
+                let mut theme_selection = -1;
+                let theme_names: Vec<ImString> = config.custom_themes.iter().map(|theme| ImString::new(theme.name.clone())).collect();
+                let theme_name_strs: Vec<&ImStr> = theme_names.iter().map(|name| name.as_ref()).collect();
+                if ui.combo(im_str!("Theme"), &mut theme_selection, &theme_name_strs, 5) && theme_selection >= 0 {
+                    config.custom_theme = config.custom_themes[theme_selection as usize].name.clone();
+                    config.theme = GuiTheme::Custom;
+                }
+            }
+            return;
+        },
+    };
+
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Delete")) {
+        let theme = config.custom_themes.remove(theme_index);
+        config.custom_theme.clear();
+        if config.theme == GuiTheme::Custom {
+            config.theme = theme.base;
+        }
+        return;
+    }
+
+    ui.same_line(0.0);
+    ui.push_item_width(150.0);
+    let mut theme_selection = theme_index as i32;
+    let theme_names: Vec<ImString> = config.custom_themes.iter().map(|theme| ImString::new(theme.name.clone())).collect();
+    let theme_name_strs: Vec<&ImStr> = theme_names.iter().map(|name| name.as_ref()).collect();
+    if ui.combo(im_str!("Theme"), &mut theme_selection, &theme_name_strs, 5) {
+        config.custom_theme = config.custom_themes[theme_selection as usize].name.clone();
+        config.theme = GuiTheme::Custom;
+    }
+
+    // renaming a theme keeps it selected, as long as the new name is unique
+    ui.same_line(0.0);
+    let mut name = config.custom_theme.clone();
+    input_string(ui, im_str!("Name"), &mut name, &mut app_state.imgui_str);
+    ui.pop_item_width();
+    if name.len() > 0 && !config.custom_themes.iter().any(|theme| theme.name == name) {
+        config.custom_themes[theme_index].name = name.clone();
+        config.custom_theme = name;
+    }
+
+    if config.theme != GuiTheme::Custom {
+        ui.same_line(0.0);
+        ui.text_colored(MODIFIED_COLOR, im_str!("(not in use)"));
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Select the Custom theme in the Configuration section to use this theme"));
+        }
+    }
+
+    let theme = &mut config.custom_themes[theme_index];
+
+    ui.text("Base Theme");
+    ui.same_line(0.0);
+    let mut base_selection = theme.base as i32;
+    ui.with_id("ThemeBase", || {
+        ui.radio_button(im_str!("Dark"), &mut base_selection, GuiTheme::Dark as i32);
+        ui.same_line(0.0);
+        ui.radio_button(im_str!("Light"), &mut base_selection, GuiTheme::Light as i32);
+    });
+    theme.base = num::FromPrimitive::from_i32(base_selection).unwrap();
+
+    ui.separator();
+
+    ui.columns(3, im_str!("ThemeColorCols"), false);
+    for (color_name, color) in theme.colors.named_colors_mut() {
+        ui.color_edit(&ImString::new(color_name), color).inputs(false).build();
+        ui.next_column();
+    }
+    ui.columns(1, im_str!("ThemeEditorEnd"), false);
+}
+
+fn ui_timeline(ui: &Ui, app_state: &mut AppState, session: &Session) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleTimeline", || {
+        // align the word 'Toggle' with other settings
+        ui.text("             ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.timeline_shown = !app_state.timeline_shown;
+        }
+    });
+    if app_state.timeline_shown {
+        ui.child_frame(im_str!("TimelineFrame"), (0.0, frame_height(ui, TIMELINE_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .show_scrollbar(true)
+            .build(|| {
+                timeline_ui(ui, app_state, session);
+            });
+    }
+}
+
+fn ui_vc_statistics(ui: &Ui, app_state: &mut AppState, session: &Session) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleVcStatistics", || {
+        // align the word 'Toggle' with other settings
+        ui.text("     ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.vc_stats_shown = !app_state.vc_stats_shown;
+        }
+    });
+    if app_state.vc_stats_shown {
+        ui.child_frame(im_str!("VcStatisticsFrame"), (0.0, frame_height(ui, VC_STATS_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .show_scrollbar(true)
+            .build(|| {
+                vc_statistics_ui(ui, &session.processing_stats.vc_history);
+            });
+    }
+}
+
+fn ui_resync(ui: &Ui, app_state: &mut AppState, session: &Session) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleResync", || {
+        // align the word 'Toggle' with other settings
+        ui.text("       ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.resync_shown = !app_state.resync_shown;
+        }
+    });
+    if app_state.resync_shown {
+        ui.child_frame(im_str!("ResyncFrame"), (0.0, frame_height(ui, RESYNC_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .show_scrollbar(true)
+            .build(|| {
+                resync_ui(ui, session);
+            });
+    }
+}
+
+// Plot the bytes the input's parser skipped in each second while searching for a valid
+// header. Skipped bytes that keep rising point to misconfigured framing or a corrupted link.
+fn resync_ui(ui: &Ui, session: &Session) {
+    let processing_stats = &session.processing_stats;
+
+    let color = if processing_stats.resync_count > 0 { Some(GAP_COLOR) } else { None };
+    stats_text(ui, color, format!("Resyncs: {}  Bytes Skipped: {}  Last Second: {}",
+                                  processing_stats.resync_count,
+                                  processing_stats.bytes_skipped,
+                                  session.skipped_history.back().map_or(0, |bytes_skipped| *bytes_skipped as u64)));
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The times the parser lost the packet boundaries and searched for a valid header, and the bytes it skipped"));
+    }
+
+    let skipped_history = session.skipped_history.iter().cloned().collect::<Vec<f32>>();
+    let (avail_width, avail_height) = ui.get_content_region_avail();
+    ui.plot_histogram(im_str!("##BytesSkipped"), &skipped_history)
+      .scale_min(0.0)
+      .overlay_text(im_str!("Bytes Skipped Per Second"))
+      .graph_size((avail_width, avail_height.max(1.0)))
+      .build();
+}
+
+fn ui_bridge(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState, bridge: &mut Option<Bridge>) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleBridge", || {
+        // align the word 'Toggle' with other settings
+        ui.text("               ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.bridge_shown = !app_state.bridge_shown;
+        }
+    });
+    if app_state.bridge_shown {
+        ui.child_frame(im_str!("BridgeFrame"), (0.0, frame_height(ui, BRIDGE_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .show_scrollbar(true)
+            .build(|| {
+                bridge_ui(ui, config, app_state, bridge);
+            });
+    }
+}
+
+// Start and stop the bridge, show the packets it has forwarded in each direction, and edit the
+// stream and APID filter of each side. Changes to the sides apply when the bridge is next started.
+fn bridge_ui(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState, bridge: &mut Option<Bridge>) {
+    let (source_names, sink_names) = plugin_names();
+    let status = bridge.as_ref().map(|bridge| bridge.status());
+    let running = status.as_ref().map_or(false, |status| status.state != BridgeState::Stopped);
+
+    if running {
+        if ui.small_button(im_str!("Stop Bridge")) {
+            info!("Stopping the bridge");
+            if let Some(ref bridge) = *bridge {
+                bridge.stop();
+            }
+        }
+    } else if ui.small_button(im_str!("Start Bridge")) {
+        let problems = bridge_problems(&config.bridge, &source_names, &sink_names);
+        if problems.is_empty() {
+            info!("Starting the bridge");
+            *bridge = Some(Bridge::start(config));
+        } else {
+            app_state.notify_error(format!("The bridge can not start:\n{}", problems.join("\n")));
+        }
+    }
+    ui.same_line(0.0);
+    ui.checkbox(im_str!("Start with Router"), &mut config.bridge.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Start the bridge when the router starts"));
+    }
+
+    if let Some(ref status) = status {
+        for (index, line) in bridge_stats_text(&config.bridge, status).lines().enumerate() {
+            let color = if index == 0 && status.error.is_some() { Some(GAP_COLOR) } else { None };
+            stats_text(ui, color, line.to_string());
+        }
+    }
+
+    ui.separator();
+
+    {
+        let side_a_label = config.bridge.side_a_label();
+        let side_b_label = config.bridge.side_b_label();
+        let sides = vec!((&mut config.bridge.side_a, side_a_label.clone(), side_b_label.clone(), BridgeDirection::AToB),
+                         (&mut config.bridge.side_b, side_b_label, side_a_label, BridgeDirection::BToA));
+        for (side, label, other_label, direction) in sides {
+            // the APIDs read from this side are listed for its filter
+            let observed_apids: BTreeSet<u16> = status.as_ref()
+                                                      .map(|status| status.direction(direction).apid_counts.keys().cloned().collect())
+                                                      .unwrap_or_default();
+
+            ui.with_id(format!("BridgeSide{:?}", direction).as_str(), || {
+                ui.text(format!("{}, forwarding to {}:", label, other_label));
+                input_string(ui, im_str!("Name"), &mut side.name, &mut app_state.imgui_str);
+                bridge_side_ui(ui, side, &observed_apids, &mut app_state.added_apid, &mut app_state.imgui_str);
+            });
+            ui.separator();
+        }
+    }
+
+    for problem in bridge_problems(&config.bridge, &source_names, &sink_names) {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
+    }
+}
+
+// Select the stream of a side of the bridge, which is both read and written, and the APIDs it
+// forwards to the other side
+fn bridge_side_ui(ui: &Ui, side: &mut BridgeSide, observed_apids: &BTreeSet<u16>, added_apid: &mut i32, imgui_str: &mut ImString) {
+    let mut selection: i32 = side.selection as i32;
+
+    ui.columns(3, im_str!("SelectBridgeSide"), false);
+    ui.radio_button(im_str!("TCP Client"), &mut selection, StreamOption::TcpClient as i32);
+    ui.next_column();
+    ui.radio_button(im_str!("TCP Server"), &mut selection, StreamOption::TcpServer as i32);
+    ui.next_column();
+    ui.radio_button(im_str!("Plugin"),     &mut selection, StreamOption::Plugin as i32);
+
+    side.selection = num::FromPrimitive::from_i32(selection).unwrap();
+
+    ui.columns(1, im_str!("default"), false);
+    match side.selection {
+        StreamOption::TcpClient => {
+            ui.columns(2, im_str!("BridgeSocketCols"), false);
+            ui_ip_port(ui, &mut side.settings.tcp_client.ip, &mut side.settings.tcp_client.port, imgui_str);
+        },
+
+        StreamOption::TcpServer => {
+            ui.columns(2, im_str!("BridgeSocketCols"), false);
+            ui_ip_port(ui, &mut side.settings.tcp_server.ip, &mut side.settings.tcp_server.port, imgui_str);
+        },
+
+        StreamOption::Plugin => {
+            ui_plugin(ui, &mut side.settings.plugin, &plugin_names().0, imgui_str);
+        },
+
+        _ => {
+            ui.text_colored(GAP_COLOR, im_str!("Select a stream that can be both read and written"));
+        },
+    }
+
+    ui.columns(1, im_str!("default"), false);
+    filter_apids_ui(ui, &mut side.allowed_apids, observed_apids, added_apid);
+}
+
+// List the frame statistics of each virtual channel, when transfer frame headers are read
+fn vc_statistics_ui(ui: &Ui, vc_history: &BTreeMap<VirtualChannel, VcStats>) {
+    if vc_history.is_empty() {
+        ui.text("No transfer frames seen. Set the transfer frame header in the frame settings to count them.");
+        return;
+    }
+
+    ui.columns(6, im_str!("VcStats"), true);
+
+    ui.text("  SCID/VC:");
+    ui.next_column();
+    ui.text("   Frames:");
+    ui.next_column();
+    ui.text(" Last Count:");
+    ui.next_column();
+    ui.text("     Gaps:");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Breaks in the virtual channel frame count"));
+    }
+    ui.next_column();
+    ui.text("   Missed:");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Frames missing in the breaks of the frame count"));
+    }
+    ui.next_column();
+    ui.text("  FEC Corrected:");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Frames the ground station's Reed-Solomon decoder corrected, and the symbols it corrected in them"));
+    }
+    ui.next_column();
+    ui.separator();
+
+    for (&(spacecraft_id, vcid), vc_stats) in vc_history.iter() {
+        let row_color = if vc_stats.gap_count > 0 { Some(GAP_COLOR) } else { None };
+
+        stats_text(ui, row_color, format!("  {:>4}/{}", spacecraft_id, vcid));
+        ui.next_column();
+        stats_text(ui, row_color, format!("  {:>8}", vc_stats.frame_count));
+        ui.next_column();
+        stats_text(ui, row_color, format!("  {:>8}", vc_stats.last_frame_count));
+        ui.next_column();
+        stats_text(ui, row_color, format!("  {:>8}", vc_stats.gap_count));
+        ui.next_column();
+        stats_text(ui, row_color, format!("  {:>8}", vc_stats.frames_missed));
+        ui.next_column();
+        stats_text(ui, row_color, format!("  {:>6} ({} symbols)", vc_stats.corrected_frames, vc_stats.corrected_symbols));
+        ui.next_column();
+    }
+
+    ui.columns(1, im_str!("default"), false);
+}
+
+// Draw the recent packet arrivals of each APID as marks along a row, with the
+// most recent arrivals on the right.
+fn timeline_ui(ui: &Ui, app_state: &mut AppState, session: &Session) {
+    ui.push_item_width(120.0);
+    ui.slider_float(im_str!("Seconds"), &mut app_state.timeline_seconds, MIN_TIMELINE_SECONDS, MAX_TIMELINE_SECONDS).build();
+    ui.pop_item_width();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The length of time shown in the timeline"));
+    }
+    ui.separator();
+
+    let window_secs = app_state.timeline_seconds as f64;
+    let now = SystemTime::now();
+
+    let label_width = ui.calc_text_size(im_str!("000000 "), false, -1.0).x;
+    let (avail_width, _) = ui.get_content_region_avail();
+    let plot_width = (avail_width - label_width).max(1.0);
+    let columns = plot_width as usize;
+
+    // mark which pixel columns have an arrival for each APID, so the number of lines
+    // drawn does not depend on the packet rate.
+    let mut marks: HashMap<u16, Vec<bool>> = HashMap::new();
+    for &(recv_time, apid) in session.arrivals.iter() {
+        if let Ok(age) = now.duration_since(recv_time) {
+            let age_secs = age.as_fractional_secs();
+            if age_secs < window_secs {
+                let column = ((1.0 - age_secs / window_secs) * columns as f64) as usize;
+                let row = marks.entry(apid).or_insert_with(|| vec!(false; columns + 1));
+                row[min(column, columns)] = true;
+            }
+        }
+    }
+
+    let mut apids: Vec<u16> = session.processing_stats.packet_history.keys().cloned().collect();
+    apids.sort();
+
+    let line_height = ui.get_text_line_height();
+    let draw_list = ui.get_window_draw_list();
+    for apid in apids {
+        let (x, y) = ui.get_cursor_screen_pos();
+        ui.text(format!("{:>5}", apid));
+
+        let plot_x = x + label_width;
+        draw_list.add_rect((plot_x, y), (plot_x + plot_width, y + line_height), TIMELINE_BACKGROUND_COLOR)
+                 .filled(true)
+                 .build();
+
+        if let Some(row) = marks.get(&apid) {
+            for (column, marked) in row.iter().enumerate() {
+                if *marked {
+                    let column_x = plot_x + column as f32;
+                    draw_list.add_line((column_x, y), (column_x, y + line_height), TIMELINE_COLOR).build();
+                }
+            }
+        }
+    }
+}
+
+fn packet_builder_ui(ui: &Ui, config: &AppConfig, app_state: &mut AppState, router: &Router) {
+    ui.columns(3, im_str!("PacketBuilderCols"), false);
+
+    let mut apid = app_state.packet_template.apid as i32;
+    ui.input_int(im_str!("APID"), &mut apid).build();
+    app_state.packet_template.apid = (apid as u16) & 0x07FF;
+    ui.next_column();
+
+    let mut sequence_count = app_state.packet_template.sequence_count as i32;
+    ui.input_int(im_str!("Seq Count"), &mut sequence_count).build();
+    app_state.packet_template.sequence_count = (sequence_count as u16) & CCSDS_SEQ_COUNT_MASK;
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The sequence count is incremented each time a packet is sent"));
+    }
+    ui.next_column();
+
+    let flag_names = [im_str!("Continuation"), im_str!("First"), im_str!("Last"), im_str!("Unsegmented")];
+    let mut flags_selection = app_state.packet_template.sequence_flags as i32;
+    ui.combo(im_str!("Seq Flags"), &mut flags_selection, &flag_names, 4);
+    app_state.packet_template.sequence_flags = SequenceFlags::from_index(flags_selection);
+    ui.next_column();
+
+    ui.checkbox(im_str!("Command"), &mut app_state.packet_template.is_command);
+    ui.next_column();
+    ui.checkbox(im_str!("Secondary Header"), &mut app_state.packet_template.secondary_header);
+    ui.next_column();
+    ui.checkbox(im_str!("Payload From File"), &mut app_state.builder_use_file);
+    ui.next_column();
+
+    ui.columns(1, im_str!("PacketBuilderPayload"), false);
+    if app_state.builder_use_file {
+        input_file_name(ui, im_str!("Payload File"), &mut app_state.builder_payload_file, &mut app_state.imgui_str, FileDialog::Open);
+    } else {
+        input_string(ui, im_str!("Payload (Hex)"), &mut app_state.builder_payload_hex, &mut app_state.imgui_str);
+    }
+
+    if ui.small_button(im_str!("Send Packet")) {
+        let payload;
+        if app_state.builder_use_file {
+            payload = read_payload_file(&app_state.builder_payload_file);
+        } else {
+            payload = parse_hex_bytes(&app_state.builder_payload_hex);
+        }
+
+        let packet_bytes = payload.and_then(|data| {
+            app_state.packet_template.data = data;
+            app_state.packet_template.to_bytes(config.little_endian_ccsds)
+        });
+
+        match packet_bytes {
+            Ok(bytes) => {
+                info!("Sending packet APID {} sequence count {} ({} bytes)",
+                      app_state.packet_template.apid,
+                      app_state.packet_template.sequence_count,
+                      bytes.len());
+                router.send(ProcessingMsg::SendPacket(app_state.packet_template.apid, bytes)).unwrap();
+                app_state.packet_template.sequence_count =
+                    next_seq(app_state.packet_template.sequence_count);
+            },
+
+            Err(err) => {
+                error!("Could not build packet: {}", err);
+            },
+        }
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Send the packet to each output that allows its APID. Outputs are only open while processing"));
+    }
+}
+
+fn read_payload_file(file_name: &String) -> Result<Vec<u8>, String> {
+    let mut file = File::open(file_name).map_err(|err| format!("Could not open payload file {}: {}", file_name, err))?;
+
+    let mut payload = Vec::new();
+    file.read_to_end(&mut payload).map_err(|err| format!("Could not read payload file {}: {}", file_name, err))?;
+
+    Ok(payload)
+}
+
+fn ui_log_view(ui: &Ui, log_buffer: &LogBuffer, app_state: &mut AppState) {
+    ui.same_line(0.0);
+    ui.with_id("ToggleLogView", || {
+        // align the word 'Toggle' with other settings
+        ui.text("                  ");
+        ui.same_line(0.0);
+        // button to show or hide section
+        if ui.small_button(im_str!("Toggle")) {
+            app_state.log_shown = !app_state.log_shown;
+        }
+    });
+    if app_state.log_shown {
+        ui.child_frame(im_str!("LogFrame"), (0.0, frame_height(ui, LOG_FRAME_LINES)))
+            .show_borders(true)
+            .collapsible(true)
+            .build(|| {
+                let level_names = [im_str!("Error"), im_str!("Warn"), im_str!("Info"), im_str!("Debug"), im_str!("Trace")];
+                ui.combo(im_str!("Level"), &mut app_state.log_level_selection, &level_names, 5);
+                ui.same_line(0.0);
+                ui.checkbox(im_str!("Auto Scroll"), &mut app_state.log_auto_scroll);
+                ui.separator();
+
+                ui.child_frame(im_str!("LogEntries"), (0.0, 0.0))
+                    .show_scrollbar(true)
+                    .build(|| {
+                        let max_level = level_from_index(app_state.log_level_selection);
+                        if let Ok(entries) = log_buffer.lock() {
+                            for entry in entries.iter().filter(|entry| entry.level <= max_level) {
+                                let line = format!("{} [{:5}] {}", entry.time.format("%H:%M:%S"), entry.level, entry.message);
+                                ui.text_colored(level_color(entry.level), &ImString::new(line));
+                            }
+                        }
+
+                        if app_state.log_auto_scroll {
+                            unsafe {
+                                imgui::sys::igSetScrollHere(1.0);
+                            }
+                        }
+                    });
+            });
+    }
+}
+
+fn ui_error_notifications(ui: &Ui, app_state: &mut AppState) {
+    // repeated errors are kept as one notification, but each is counted
+    let error_count: u64 = app_state.error_notifications.iter().map(|notification| notification.count).sum();
+    let label = ImString::new(format!("Errors: {}", error_count));
+
+    // the error count is highlighted when there are errors the user has not cleared
+    let clicked;
+    if error_count > 0 {
+        clicked = ui.with_color_var(ImGuiCol::Button, (0.7, 0.1, 0.1, 1.0), || ui.small_button(&label));
+    } else {
+        clicked = ui.small_button(&label);
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Show error details"));
+    }
+    if clicked {
+        ui.open_popup(im_str!("Error Details"));
+    }
+
+    // show the most recent error for a short time after it occurs
+    if let Some(notification) = app_state.error_notifications.back() {
+        let recent = notification.time.elapsed()
+                                      .map(|elapsed| elapsed < Duration::from_secs(ERROR_TOAST_SECONDS))
+                                      .unwrap_or(false);
+        if recent {
+            ui.same_line(0.0);
+            ui.text_colored((1.0, 0.3, 0.3, 1.0), &ImString::new(notification.message.clone()));
+        }
+    }
+
+    ui.popup_modal(im_str!("Error Details")).build(|| {
+        ui.child_frame(im_str!("ErrorDetailsFrame"), (POPUP_WIDTH, frame_height(ui, ERROR_DETAILS_FRAME_LINES)))
+            .show_borders(true)
+            .show_scrollbar(true)
+            .build(|| {
+                for notification in app_state.error_notifications.iter() {
+                    let time: DateTime<Local> = notification.time.into();
+                    if notification.count > 1 {
+                        ui.text_wrapped(&ImString::new(format!("{} {} (x{})", time.format("%H:%M:%S"), notification.message, notification.count)));
+                    } else {
+                        ui.text_wrapped(&ImString::new(format!("{} {}", time.format("%H:%M:%S"), notification.message)));
+                    }
+                }
+            });
+
+        if ui.small_button(im_str!("Clear")) {
+            app_state.error_notifications.clear();
+            ui.close_current_popup();
+        }
+
+        ui.same_line(0.0);
+
+        if ui.small_button(im_str!("Close")) {
+            ui.close_current_popup();
+        }
+    });
+}
+
+fn run_gui(config: &mut AppConfig,
+           config_file_name: &mut String,
+           sessions: &mut Vec<Session>,
+           bridge: &mut Option<Bridge>,
+           log_buffer: LogBuffer,
+           control_receiver: Option<&Receiver<ControlRequest>>,
+           config_error: Option<String>,
+           profile: Option<String>) {
+    let sdl_context = sdl2::init().unwrap();
+    let video = sdl_context.video().unwrap();
+
+    {
+        let gl_attr = video.gl_attr();
+        gl_attr.set_context_profile(sdl2::video::GLProfile::Core);
+        gl_attr.set_context_version(3, 0);
+    }
+
+    let mut layout = load_gui_layout().unwrap_or_default();
+
+    let window = video.window("CCSDS Packet Router", layout.window_width, layout.window_height)
+        .position_centered()
+        .resizable()
+        .opengl()
+        .allow_highdpi()
+        .build()
+        .unwrap();
+
+    let _gl_context = window.gl_create_context().expect("Couldn't create GL context");
+    gl::load_with(|s| video.gl_get_proc_address(s) as _);
+
+    let mut imgui = imgui::ImGui::init();
+    imgui.set_ini_filename(None);
+
+    let mut imgui_sdl2 = imgui_sdl2::ImguiSdl2::new(&mut imgui);
+
+    let renderer = imgui_opengl_renderer::Renderer::new(&mut imgui, |s| video.gl_get_proc_address(s) as _);
+
+    let mut event_pump = sdl_context.event_pump().unwrap();
+
+    let mut app_state: AppState = AppState::new();
+    app_state.config_file_name = config_file_name.clone();
+    app_state.saved_config = load_config(config_file_name, &profile).unwrap_or(None);
+    app_state.profiles = profile_names(config_file_name);
+    app_state.profile = profile;
+    if let Some(err) = config_error {
+        app_state.notify_error(format!("Could not load configuration '{}', using defaults: {}", config_file_name, err));
+    }
+    app_state.timestamp_selection = config.timestamp_setting.selection_index();
+    app_state.apply_layout(&layout);
+
+
+    /* Application State */
+    sessions[0].output_index = min(layout.output_index, config.output_selection.len() - 1);
+
+    let default_sizes = StyleSizes::from_style(imgui.style_mut());
+    let mut current_theme = config.theme;
+    let mut current_custom_theme = config.selected_custom_theme().cloned();
+    let mut current_scale = config.ui_scale;
+    set_theme(&mut imgui, current_theme, current_custom_theme.as_ref(), current_scale, &default_sizes);
+    let mut current_colors = theme_colors(imgui.style_mut());
+
+
+    // Main GUI event loop
+    'running: loop {
+        // the theme and scale can be changed in the GUI, and are applied before the next frame
+        if config.theme != current_theme ||
+           config.ui_scale != current_scale ||
+           config.selected_custom_theme() != current_custom_theme.as_ref() {
+            current_theme = config.theme;
+            current_custom_theme = config.selected_custom_theme().cloned();
+            current_scale = config.ui_scale;
+            set_theme(&mut imgui, current_theme, current_custom_theme.as_ref(), current_scale, &default_sizes);
+            current_colors = theme_colors(imgui.style_mut());
+        }
+
+        /* SDL Events */
+        use sdl2::event::Event;
+        use sdl2::keyboard::Keycode;
+
+        for event in event_pump.poll_iter() {
+            imgui_sdl2.handle_event(&mut imgui, &event);
+            if imgui_sdl2.ignore_event(&event) { continue; }
+
+            match event {
+                Event::Quit {..} | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    // confirm before exiting if there are changes that would be lost
+                    if app_state.config_modified(config) {
+                        app_state.exit_requested = true;
+                    } else {
+                        break 'running;
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        /* Commands from the Control Socket */
+        if let Some(control_receiver) = control_receiver {
+            while let Ok(request) = control_receiver.try_recv() {
+                if handle_control_request(request, config, &mut app_state.config_file_name, sessions) {
+                    app_state.saved_config = Some(config.clone());
+                    app_state.active_session = 0;
+                    app_state.timestamp_selection = config.timestamp_setting.selection_index();
+                }
+            }
+        }
+
+        /* Reload the Configuration, Applying it to the Running Sessions */
+        if app_state.reload_requested {
+            app_state.reload_requested = false;
+            match load_config(&app_state.config_file_name, &app_state.profile) {
+                Ok(Some(new_config)) => {
+                    info!("Reloading configuration {}", app_state.config_file_name);
+                    apply_config(config, new_config, sessions);
+                    app_state.saved_config = Some(config.clone());
+                    app_state.timestamp_selection = config.timestamp_setting.selection_index();
+                },
+
+                Ok(None) => {
+                    app_state.notify_error(format!("Configuration file '{}' does not exist", app_state.config_file_name));
+                },
+
+                Err(err) => {
+                    error!("Could not reload configuration '{}':\n{}", app_state.config_file_name, err);
+                    app_state.notify_error(format!("Could not reload configuration '{}': {}", app_state.config_file_name, err));
+                },
+            }
+        }
+
+        // the number of sessions changes when a configuration is loaded
+        sync_sessions(sessions, config.session_count());
+        app_state.active_session = min(app_state.active_session, sessions.len() - 1);
+
+        start_scheduled_sessions(config, sessions);
+        stop_scheduled_sessions(config, sessions);
+
+        /* Read Updates from each Session's Processing Thread */
+        let session_count = sessions.len();
+        let mut session_finished = false;
+        for (index, session) in sessions.iter_mut().enumerate() {
+            while let Ok(msg_result) = session.router.events().recv_timeout(Duration::from_millis(0)) {
+                if let GuiMessage::Finished = msg_result {
+                    session_finished = true;
+                }
+
+                match session.update(msg_result, config.session_config(index).packet_history_length) {
+                    Some(GuiMessage::Terminate) => {
+                        break 'running;
+                    },
+
+                    Some(GuiMessage::Error(error_msg)) | Some(GuiMessage::Failed(_, error_msg)) => {
+                        // errors are labeled with their session when there is more then one session
+                        let error_msg = if session_count > 1 {
+                            format!("{}: {}", config.session_config(index).session_name, error_msg)
+                        } else {
+                            error_msg
+                        };
+                        error!("{}", error_msg);
+                        app_state.notify_error(error_msg);
+                    },
+
+                    _ => {},
+                }
+            }
+
+            for alarm_msg in session.update_rates(&config.session_config(index).rate_alarms) {
+                let alarm_msg = if session_count > 1 {
+                    format!("{}: {}", config.session_config(index).session_name, alarm_msg)
+                } else {
+                    alarm_msg
+                };
+                app_state.notify_error(format!("Rate alarm: {}", alarm_msg));
+            }
+
+            // a loaded configuration may have fewer outputs then the session was showing
+            session.output_index = min(session.output_index, config.session_config(index).output_selection.len() - 1);
+        }
+
+        // a session paused while its input ends still reports that it has finished, so only idle sessions are done
+        if config.exit_on_finish && session_finished &&
+           sessions.iter().all(|session| !session.processing && !session.paused && session.scheduled_start.is_none()) {
+            info!("Processing finished, exiting");
+            break 'running;
+        }
+
+        /* IMGUI UI */
+        let ui = imgui_sdl2.frame(&window, &mut imgui, &event_pump.mouse_state());
+
+        let mut start_processing = false;
+
+        let (window_width, window_height) = window.size();
+
+        ui.window(im_str!(""))
+            .position((0.0, 0.0), ImGuiCond::FirstUseEver)
+            .size((window_width as f32, window_height as f32), ImGuiCond::Always)
+            .title_bar(false)
+            .movable(false)
+            .scrollable(false)
+            .resizable(false)
+            .collapsible(false)
+            .build(|| {
+                ui.text("Configuration");
+                ui_config_settings(&ui, config, &mut app_state);
+
+                /* Routing Sessions */
+                ui.text("Sessions");
+                ui_sessions(&ui, config, &mut app_state, sessions);
+
+                // the sections below show the settings and state of the selected session
+                let active = app_state.active_session;
+                let session = &mut sessions[active];
+
+                /* Source Selection */
+                ui.text("Input Settings");
+                ui_input_settings(&ui, config.session_config_mut(active), &mut app_state, &session.processing_stats, &session.observed_apids, session.processing);
+
+                /* Output Settings */
+                ui.text("Output Settings");
+                ui_output_settings(&ui, config.session_config_mut(active), &mut app_state, &mut session.output_index, &session.degraded_outputs, &session.observed_apids, &session.router);
+
+                /* CCSDS Packet Settings */
+                ui.text("CCSDS Settings");
+                ui.same_line(0.0);
+                ui.with_id("ToggleCcsdsSettings", || {
+                    // align the word 'Toggle' with other settings
+                    ui.text(" ");
+                    ui.same_line(0.0);
+                    // button to show or hide section
+                    if ui.small_button(im_str!("Toggle")) {
+                        app_state.ccsds_settings_shown = !app_state.ccsds_settings_shown;
+                    }
+                });
+                if app_state.ccsds_settings_shown {
+                    let timestamp_setting = config.session_config(active).timestamp_setting.clone();
+
+                    packet_settings_ui(&ui, config.session_config_mut(active), &mut app_state);
+
+                    // changes to the timing apply while processing, rather than on the next start
+                    let new_timestamp_setting = &config.session_config(active).timestamp_setting;
+                    if (session.processing || session.paused) && *new_timestamp_setting != timestamp_setting {
+                        session.router.send(ProcessingMsg::UpdateTiming(new_timestamp_setting.clone())).unwrap();
+                    }
+                }
+
+                /* Packet Builder */
+                ui.text("Packet Builder");
+                ui_packet_builder(&ui, config.session_config(active), &mut app_state, &session.router);
+
+                /* Theme Editor */
+                ui.text("Theme Editor");
+                ui_theme_editor(&ui, config, &mut app_state, &current_colors);
+
+                /* Timeline */
+                ui.text("Timeline");
+                ui_timeline(&ui, &mut app_state, session);
+
+                /* Virtual Channel Statistics */
+                ui.text("Virtual Channels");
+                ui_vc_statistics(&ui, &mut app_state, session);
+
+                /* Parser Resyncs */
+                ui.text("Parser Resyncs");
+                ui_resync(&ui, &mut app_state, session);
+
+                /* Bridge */
+                ui.text("Bridge");
+                ui_bridge(&ui, config, &mut app_state, bridge);
+
+                /* Packet Statistics */
+                ui.text("Packet Statistics");
+                let mut reserved_height = frame_height(&ui, STATS_RESERVED_LINES);
+                if app_state.log_shown {
+                    reserved_height += frame_height(&ui, LOG_FRAME_LINES);
+                }
+                if session.processing || session.paused {
+                    reserved_height += frame_height(&ui, TRANSPORT_CONTROL_LINES);
+                }
+                {
+                    let session_config = config.session_config_mut(active);
+                    packet_statistics_ui(&ui,
+                                         session,
+                                         &mut session_config.stale_timeout,
+                                         &mut session_config.packet_history_length,
+                                         &session_config.timestamp_def,
+                                         reserved_height);
+                }
+
+                /* Application Log */
+                ui.text("Log");
+                ui_log_view(&ui, &log_buffer, &mut app_state);
+
+                /* Error Notifications */
+                ui_error_notifications(&ui, &mut app_state);
+
+                /* Control Buttons */
+                if ui.small_button(im_str!("Clear Stats")) {
+                    info!("Clearing Statistics");
+                    session.clear_stats();
+                }
+
+                ui.same_line(0.0);
+
+                if app_state.all_shown() {
+                    if ui.small_button(im_str!("Collapse All")) {
+                      app_state.hide_all();
+                    }
+                } else {
+                    if ui.small_button(im_str!(" Expand All ")) {
+                      app_state.show_all();
+                    }
+                }
+
+                // if we are paused, ask to continue or cancel
+                if session.paused {
+                    if ui.small_button(im_str!("Continue ")) {
+                        info!("Continuing Processing");
+                        session.router.resume().unwrap();
+                        session.processing = true;
+                        session.paused = false;
+                        session.breakpoint = None;
+                        command_peer_from_gui(config, "continue", &mut app_state);
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Cancel")) {
+                        info!("Cancelled Processing");
+                        session.processing = false;
+                        session.paused = false;
+                        session.breakpoint = None;
+                        session.router.stop().unwrap();
+                        command_peer_from_gui(config, "cancel", &mut app_state);
+                    }
+                }
+                // if we are processing packets, ask to pause
+                else if session.processing {
+                    if ui.small_button(im_str!("  Pause  ")) {
+                        info!("Paused Processing");
+                        session.processing = false;
+                        session.paused = true;
+                        session.router.pause().unwrap();
+                        command_peer_from_gui(config, "pause", &mut app_state);
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Cancel")) {
+                        info!("Cancelled Processing");
+                        session.processing = false;
+                        session.paused = false;
+                        session.router.stop().unwrap();
+                        command_peer_from_gui(config, "cancel", &mut app_state);
+                    }
+                }
+                // if processing is starting on a schedule or with a peer router, show when it starts
+                else if let Some(start_time) = session.scheduled_start {
+                    let starting_with = if config.peer_settings.address.is_empty() { "Starting" } else { "Starting with the peer router" };
+                    ui.text_colored(BREAKPOINT_COLOR, &ImString::new(format!("{} {}", starting_with, scheduled_time_text(start_time))));
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Cancel")) {
+                        info!("Cancelled Scheduled Start");
+                        session.scheduled_start = None;
+                        command_peer_from_gui(config, "cancel", &mut app_state);
+                    }
+                }
+                // otherwise, ask if we want to start processing packets
+                else {
+                    if ui.small_button(im_str!("Start")) {
+                        let problems = config_problems(config.session_config(active));
+                        if problems.is_empty() {
+                            // a modified configuration is not saved without asking, so that
+                            // starting does not overwrite a configuration file unexpectedly.
+                            if app_state.config_modified(config) {
+                                ui.open_popup(im_str!("Save Configuration?"));
+                            } else {
+                                start_processing = true;
+                            }
+                        } else {
+                            for problem in problems.iter() {
+                                warn!("Not starting: {}", problem);
+                            }
+                            app_state.start_problems = problems;
+                            ui.open_popup(im_str!("Configuration Problems"));
+                        }
+                    }
+                }
+
+                ui.popup_modal(im_str!("Configuration Problems")).build(|| {
+                    ui.text("Processing was not started, as the configuration has these problems:");
+                    for problem in app_state.start_problems.iter() {
+                        ui.text_wrapped(&ImString::new(format!("- {}", problem)));
+                    }
+
+                    if ui.small_button(im_str!("Close")) {
+                        ui.close_current_popup();
+                    }
+                });
+
+                ui.popup_modal(im_str!("Save Configuration?")).build(|| {
+                    ui.text(format!("Save changes to '{}' before starting?", app_state.config_file_name));
+                    if ui.small_button(im_str!("Save and Start")) {
+                        save_config(config, &app_state.config_file_name.clone());
+                        app_state.saved_config = Some(config.clone());
+                        start_processing = true;
+                        ui.close_current_popup();
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Start Without Saving")) {
+                        warn!("Starting with a configuration that is not saved to {}", app_state.config_file_name);
+                        start_processing = true;
+                        ui.close_current_popup();
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Don't Start")) {
+                        ui.close_current_popup();
+                    }
+                });
+
+                // an output file that already exists is not overwritten without asking
+                if start_processing {
+                    app_state.existing_outputs = existing_output_files(config.session_config(active));
+                    if app_state.existing_outputs.is_empty() {
+                        start_from_gui(config, active, session, &mut app_state, false);
+                    } else {
+                        ui.open_popup(im_str!("Overwrite Files?"));
+                    }
+                }
+
+                ui.popup_modal(im_str!("Overwrite Files?")).build(|| {
+                    ui.text("These output files already exist:");
+                    for file_name in app_state.existing_outputs.iter() {
+                        ui.text_wrapped(&ImString::new(format!("- {}", file_name)));
+                    }
+
+                    if ui.small_button(im_str!("Overwrite")) {
+                        start_from_gui(config, active, session, &mut app_state, true);
+                        ui.close_current_popup();
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Write New Files")) {
+                        start_from_gui(config, active, session, &mut app_state, false);
+                        ui.close_current_popup();
+                    }
+                    if ui.is_item_hovered() {
+                        ui.tooltip_text(im_str!("Add a numeric suffix to the name of each file that exists"));
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Don't Start")) {
+                        ui.close_current_popup();
+                    }
+                });
+
+                if session.processing || session.paused {
+                    ui_transport_controls(&ui, config.session_config(active), session, &mut app_state.imgui_str);
+
+                    if let Some(stop_time) = session.scheduled_stop {
+                        ui.text_colored(BREAKPOINT_COLOR, &ImString::new(format!("Stopping {}", scheduled_time_text(stop_time))));
+                    }
+                }
+
+                // don't exit unless the user confirms their action
+                if ui.small_button(im_str!("Exit")) || app_state.exit_requested {
+                    app_state.exit_requested = false;
+                    ui.open_popup(im_str!("Exit?"));
+                }
+                ui.popup_modal(im_str!("Exit?")).build(|| {
+                    ui.text("Exit the application?");
+                    if app_state.config_modified(config) {
+                        ui.text_colored(MODIFIED_COLOR, im_str!("The configuration has unsaved changes."));
+
+                        if ui.small_button(im_str!("Save and Exit")) {
+                            save_config(config, &app_state.config_file_name.clone());
+                            app_state.saved_config = Some(config.clone());
+                            terminate_sessions(sessions);
+                        }
+
+                        ui.same_line(0.0);
+                    }
+
+                    if ui.small_button(im_str!("Exit")) {
+                        terminate_sessions(sessions);
+                    }
+
+                    ui.same_line(0.0);
+
+                    if ui.small_button(im_str!("Don't Exit")) {
+                        ui.close_current_popup();
+                    }
+                });
+            });
+
+
+        unsafe {
+            gl::ClearColor(0.2, 0.2, 0.2, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+
+        renderer.render(ui);
+
+        window.gl_swap_window();
+
+
+        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 30));
+    }
+
+    // save the layout so the GUI reopens the way it was left
+    let (window_width, window_height) = window.size();
+    layout.window_width = window_width;
+    layout.window_height = window_height;
+    layout.output_index = sessions[0].output_index;
+    app_state.record_layout(&mut layout);
+    save_gui_layout(&layout);
+
+    for session in sessions.iter_mut() {
+        session.terminate();
+    }
+}
+
+// Ask each session's processing thread to terminate. The GUI exits when a
+// session reports that it has terminated.
+fn terminate_sessions(sessions: &Vec<Session>) {
+    for session in sessions.iter() {
+        let _ = session.router.send(ProcessingMsg::Terminate);
+    }
+}
+
+// Act on a command from the control socket, sending the reply back to the client.
+// Returns true if a configuration was loaded.
+fn handle_control_request(request: ControlRequest,
+                          config: &mut AppConfig,
+                          config_file_name: &mut String,
+                          sessions: &mut Vec<Session>) -> bool {
+    let mut config_loaded = false;
+
+    let reply = match request.command {
+        ControlCommand::Start => {
+            let mut errors = Vec::new();
+
+            // the peer router is only told to start if there is a session to start with it
+            let start_time = if sessions.iter().any(|session| session_idle(session)) {
+                match coordinated_start_time(config) {
+                    Ok(start_time) => start_time,
+
+                    Err(err) => {
+                        error!("{}. Starting without the peer router", err);
+                        None
+                    },
+                }
+            } else {
+                None
+            };
+
+            for (index, session) in sessions.iter_mut().enumerate() {
+                if !session_idle(session) {
+                    continue;
+                }
+
+                let session_config = config.session_config(index);
+                match check_start_config(session_config) {
+                    Ok(()) => {
+                        info!("Start Processing Session '{}' from the control socket. Configuration file {}",
+                              session_config.session_name,
+                              config_file_name);
+                        start_or_schedule(session, session_config, start_time);
+                    },
+
+                    Err(err) => {
+                        error!("{}", err);
+                        errors.push(err);
+                    },
+                }
+            }
+
+            if errors.is_empty() {
+                "ok".to_string()
+            } else {
+                format!("error: {}", errors.join("; "))
+            }
+        },
+
+        ControlCommand::StartAt(start_time) => {
+            let mut errors = Vec::new();
+
+            for (index, session) in sessions.iter_mut().enumerate() {
+                if !session_idle(session) {
+                    continue;
+                }
+
+                let session_config = config.session_config(index);
+                match check_start_config(session_config) {
+                    Ok(()) => {
+                        info!("Scheduled Session '{}' to start from the control socket. Configuration file {}",
+                              session_config.session_name,
+                              config_file_name);
+                        session.scheduled_start = Some(start_time);
+                    },
+
+                    Err(err) => {
+                        error!("{}", err);
+                        errors.push(err);
+                    },
+                }
+            }
+
+            if errors.is_empty() {
+                "ok".to_string()
+            } else {
+                format!("error: {}", errors.join("; "))
+            }
+        },
+
+        // a command is only passed on to the peer router if it changed a session here, so
+        // routers that are each other's peer do not pass a command back and forth
+        ControlCommand::Pause => {
+            let mut changed = false;
+            for session in sessions.iter_mut().filter(|session| session.processing) {
+                info!("Paused Processing from the control socket");
+                session.processing = false;
+                session.paused = true;
+                session.router.pause().unwrap();
+                changed = true;
+            }
+            if changed {
+                command_peer_from_control(config, "pause");
+            }
+            "ok".to_string()
+        },
+
+        ControlCommand::Continue => {
+            let mut changed = false;
+            for session in sessions.iter_mut().filter(|session| session.paused) {
+                info!("Continuing Processing from the control socket");
+                session.processing = true;
+                session.paused = false;
+                session.breakpoint = None;
+                session.router.resume().unwrap();
+                changed = true;
+            }
+            if changed {
+                command_peer_from_control(config, "continue");
+            }
+            "ok".to_string()
+        },
+
+        ControlCommand::Cancel => {
+            let mut changed = false;
+            for session in sessions.iter_mut().filter(|session| !session_idle(session)) {
+                info!("Cancelled Processing from the control socket");
+                if session.processing || session.paused {
+                    session.router.stop().unwrap();
+                }
+                session.processing = false;
+                session.paused = false;
+                session.breakpoint = None;
+                session.scheduled_start = None;
+                changed = true;
+            }
+            if changed {
+                command_peer_from_control(config, "cancel");
+            }
+            "ok".to_string()
+        },
+
+        ControlCommand::Stats => {
+            let mut stats = session_stats_text(config, sessions);
+            stats.push_str("ok");
+            stats
+        },
+
+        ControlCommand::Load(file_name) => {
+            match load_config(&file_name, &None) {
+                Ok(Some(config_read)) => {
+                    info!("Loaded configuration {} from the control socket", file_name);
+                    *config = config_read;
+                    *config_file_name = file_name;
+                    sync_sessions(sessions, config.session_count());
+                    config_loaded = true;
+                    "ok".to_string()
+                },
+
+                Ok(None) => {
+                    format!("error: configuration file {} does not exist", file_name)
+                },
+
+                Err(err) => {
+                    error!("Could not load configuration '{}':\n{}", file_name, err);
+                    format!("error: could not load configuration file {}: {}", file_name, err.replace('\n', "; "))
+                },
+            }
+        },
+
+        ControlCommand::Help => {
+            CONTROL_HELP.to_string()
+        },
+    };
+
+    // the client may have disconnected, in which case there is no one to reply to
+    let _ = request.reply.send(reply);
+
+    config_loaded
+}
+
+// Whether a session is neither processing, paused, nor waiting for a scheduled start
+fn session_idle(session: &Session) -> bool {
+    !session.processing && !session.paused && session.scheduled_start.is_none()
+}
+
+// Start a session from the GUI, with the peer router if there is one. If the existing output
+// files are to be overwritten, the outputs that ask before overwriting are set to overwrite.
+fn start_from_gui(config: &AppConfig, active: usize, session: &mut Session, app_state: &mut AppState, overwrite: bool) {
+    let start_time = match coordinated_start_time(config) {
+        Ok(start_time) => start_time,
+
+        Err(err) => {
+            error!("{}. Starting without the peer router", err);
+            app_state.notify_error(format!("{}. Starting without the peer router", err));
+            None
+        },
+    };
+
+    info!("Start Processing Session '{}'. Configuration file {}",
+          config.session_config(active).session_name,
+          app_state.config_file_name);
+
+    let mut session_config = config.session_config(active).clone();
+    if overwrite {
+        for output_settings in session_config.output_settings.iter_mut() {
+            if output_settings.file.if_exists == FileExistsPolicy::Ask {
+                output_settings.file.if_exists = FileExistsPolicy::Overwrite;
+            }
+        }
+    }
+
+    start_or_schedule(session, &session_config, start_time);
+}
+
+// Start a session now, or schedule it to start at the given time. The session's own scheduled
+// start time is used over the given time.
+fn start_or_schedule(session: &mut Session, session_config: &AppConfig, start_time: Option<SystemTime>) {
+    match session_config.schedule.start_time(SystemTime::now()).or(start_time) {
+        Some(start_time) => session.scheduled_start = Some(start_time),
+
+        None => session.start(session_config),
+    }
+}
+
+// Start the sessions whose scheduled start time has been reached
+fn start_scheduled_sessions(config: &AppConfig, sessions: &mut Vec<Session>) {
+    for (index, session) in sessions.iter_mut().enumerate() {
+        if session.start_due() {
+            info!("Start Processing Session '{}' at its scheduled time", config.session_config(index).session_name);
+            session.start(config.session_config(index));
+        }
+    }
+}
+
+// Stop the sessions whose scheduled stop time has been reached
+fn stop_scheduled_sessions(config: &AppConfig, sessions: &mut Vec<Session>) {
+    for (index, session) in sessions.iter_mut().enumerate() {
+        if session.stop_due() {
+            info!("Stop Processing Session '{}' at its scheduled time", config.session_config(index).session_name);
+            session.router.stop().unwrap();
+            session.processing = false;
+            session.paused = false;
+            session.breakpoint = None;
+            session.scheduled_stop = None;
+        }
+    }
+}
+
+// A scheduled time as text, giving the UTC time and the time remaining until it
+fn scheduled_time_text(time: SystemTime) -> String {
+    let remaining_secs = time.duration_since(SystemTime::now())
+                             .map(|remaining| remaining.as_fractional_secs())
+                             .unwrap_or(0.0);
+    let utc: DateTime<Utc> = time.into();
+
+    let remaining = if remaining_secs >= 60.0 {
+        let whole_secs = remaining_secs as u64;
+        format!("{}:{:02}:{:02}", whole_secs / 3600, (whole_secs / 60) % 60, whole_secs % 60)
+    } else {
+        format!("{:.1} s", remaining_secs)
+    };
+
+    format!("at {} UTC, in {}", utc.format("%Y-%m-%d %H:%M:%S"), remaining)
+}
+
+// Send a command to the peer router, if there is one, so that it follows this router
+fn command_peer(config: &AppConfig, command: &str) -> Result<(), String> {
+    if config.peer_settings.address.is_empty() {
+        return Ok(());
+    }
+
+    send_peer_command(&config.peer_settings.address, &config.peer_settings.token, command)?;
+    info!("Peer router {} accepted '{}'", config.peer_settings.address, command);
+
+    Ok(())
+}
+
+fn command_peer_from_gui(config: &AppConfig, command: &str, app_state: &mut AppState) {
+    if let Err(err) = command_peer(config, command) {
+        error!("{}", err);
+        app_state.notify_error(err);
+    }
+}
+
+fn command_peer_from_control(config: &AppConfig, command: &str) {
+    if let Err(err) = command_peer(config, command) {
+        error!("{}", err);
+    }
+}
+
+// The time to start processing. With a peer router, the peer is told to start at the scheduled
+// start time, or a short time from now without one, and this router starts at the same time.
+// Without a peer router, processing starts now, unless a session's schedule says otherwise.
+fn coordinated_start_time(config: &AppConfig) -> Result<Option<SystemTime>, String> {
+    if config.peer_settings.address.is_empty() {
+        return Ok(None);
+    }
+
+    let delay_secs = config.peer_settings.start_delay_secs.max(0.0);
+    let start_time = config.schedule.start_time(SystemTime::now()).unwrap_or_else(|| {
+        SystemTime::now() + Duration::new(delay_secs as u64, (delay_secs.fract() * 1_000_000_000.0) as u32)
+    });
+    command_peer(config, &start_at_command(start_time))?;
+
+    Ok(Some(start_time))
+}
+
+// The statistics of each session as text, with a line per session followed by a line for each of its APIDs
+fn session_stats_text(config: &AppConfig, sessions: &Vec<Session>) -> String {
+    let mut stats = String::new();
+
+    for (index, session) in sessions.iter().enumerate() {
+        let status = if session.processing {
+            "processing"
+        } else if session.paused {
+            "paused"
+        } else if session.scheduled_start.is_some() {
+            "scheduled"
+        } else {
+            "idle"
+        };
+
+        let processing_stats = &session.processing_stats;
+        let drop_reasons = processing_stats.drop_reasons
+                                           .iter()
+                                           .map(|(drop_reason, count)| format!("{} {}", count, drop_reason.name()))
+                                           .collect::<Vec<String>>();
+        stats.push_str(&format!("session '{}' {}: {} packets/s, {} bytes/s, {} dropped{}, {} resyncs ({} bytes skipped)\n",
+                                config.session_config(index).session_name,
+                                status,
+                                processing_stats.packets_per_second,
+                                processing_stats.bytes_per_second,
+                                processing_stats.packets_dropped,
+                                if drop_reasons.is_empty() { "".to_string() } else { format!(" ({})", drop_reasons.join(", ")) },
+                                processing_stats.resync_count,
+                                processing_stats.bytes_skipped));
+
+        let mut apids: Vec<&u16> = processing_stats.packet_history.keys().collect();
+        apids.sort();
+        for apid in apids {
+            let packet_stats = &processing_stats.packet_history[apid];
+            stats.push_str(&format!("  apid {}: {} packets, {} bytes, {} gaps, last seq {}\n",
+                                    apid,
+                                    packet_stats.packet_count,
+                                    packet_stats.byte_count,
+                                    packet_stats.gap_count,
+                                    packet_stats.last_seq));
+        }
+    }
+
+    stats
+}
+
+// The state of the bridge, and the packets it has forwarded in each direction
+fn bridge_stats_text(bridge_settings: &BridgeSettings, status: &BridgeStatus) -> String {
+    let mut stats = String::new();
+
+    let state = match status.state {
+        BridgeState::Connecting => "connecting",
+        BridgeState::Running => "running",
+        BridgeState::Stopped => "stopped",
+    };
+    match status.error {
+        Some(ref error) => stats.push_str(&format!("bridge {}: {}\n", state, error)),
+
+        None => stats.push_str(&format!("bridge {}\n", state)),
+    }
+
+    let side_a_label = bridge_settings.side_a_label();
+    let side_b_label = bridge_settings.side_b_label();
+    for (direction, from_label, to_label) in [(BridgeDirection::AToB, &side_a_label, &side_b_label),
+                                              (BridgeDirection::BToA, &side_b_label, &side_a_label)].iter() {
+        let direction_stats = status.direction(*direction);
+        stats.push_str(&format!("  {} to {}: {} packets, {} bytes, {} filtered, {} bytes skipped\n",
+                                from_label,
+                                to_label,
+                                direction_stats.packet_count,
+                                direction_stats.byte_count,
+                                direction_stats.filtered_count,
+                                direction_stats.bytes_skipped));
+    }
+
+    stats
+}
+
+// Replace the configuration with a newly loaded one, without stopping the application.
+// Running sessions whose routing changed are restarted with their new settings, sessions
+// that were added are started if the configuration starts automatically, and sessions
+// that were removed are stopped.
+fn apply_config(config: &mut AppConfig, mut new_config: AppConfig, sessions: &mut Vec<Session>) {
+    fill_session_outputs(&mut new_config);
+
+    let old_config = std::mem::replace(config, new_config);
+    let old_session_count = sessions.len();
+    sync_sessions(sessions, config.session_count());
+
+    for (index, session) in sessions.iter_mut().enumerate() {
+        let session_config = config.session_config(index);
+
+        let restart = session.processing || session.paused;
+        if index < old_session_count {
+            if !restart || !session_route_changed(old_config.session_config(index), session_config) {
+                continue;
+            }
+        } else if !config.auto_start {
+            continue;
+        }
+
+        if let Err(err) = check_start_config(session_config) {
+            error!("Could not start session '{}': {}", session_config.session_name, err);
+            continue;
+        }
+
+        if restart {
+            info!("Restarting session '{}' with its new configuration", session_config.session_name);
+            session.router.stop().unwrap();
+        } else {
+            info!("Starting session '{}'", session_config.session_name);
+        }
+
+        session.start(session_config);
+    }
+}
+
+// Whether the routing of a session differs between two of its configurations. Settings that
+// only affect the GUI or the log, and the settings of other sessions, are not part of a session's routing.
+fn session_route_changed(old_config: &AppConfig, new_config: &AppConfig) -> bool {
+    let mut new_route = new_config.clone();
+    new_route.sessions = old_config.sessions.clone();
+    new_route.theme = old_config.theme.clone();
+    new_route.ui_scale = old_config.ui_scale;
+    new_route.custom_themes = old_config.custom_themes.clone();
+    new_route.custom_theme = old_config.custom_theme.clone();
+    new_route.stats_interval = old_config.stats_interval;
+    new_route.log_settings = old_config.log_settings.clone();
+
+    new_route != *old_config
+}
+
+// Make sure each session has at least one of the output settings
+fn fill_session_outputs(config: &mut AppConfig) {
+    for index in 0..config.session_count() {
+        let session_config = config.session_config_mut(index);
+        if session_config.output_settings.len() == 0 {
+            session_config.output_settings = vec!(Default::default());
+        }
+        if session_config.output_selection.len() == 0 {
+            session_config.output_selection = vec!(Default::default());
+        }
+        if session_config.allowed_output_apids.len() == 0 {
+            session_config.allowed_output_apids = vec!(None);
+        }
+    }
+}
+
+// Start or stop sessions so there is one for each session in the configuration
+fn sync_sessions(sessions: &mut Vec<Session>, session_count: usize) {
+    while sessions.len() < session_count {
+        sessions.push(Session::spawn());
+    }
+
+    while sessions.len() > session_count {
+        if let Some(mut session) = sessions.pop() {
+            session.terminate();
+        }
+    }
+}
+
+fn ui_sessions(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState, sessions: &mut Vec<Session>) {
+    // each session has a button, acting as a tab, which selects the session shown below
+    for index in 0..sessions.len() {
+        let status = if sessions[index].processing {
+            " (running)"
+        } else if sessions[index].paused {
+            " (paused)"
+        } else {
+            ""
+        };
+        let label = ImString::new(format!("{}{}", config.session_config(index).session_name, status));
+
+        ui.same_line(0.0);
+        let mut clicked = false;
+        ui.with_id(format!("Session{}", index).as_str(), || {
+            if index == app_state.active_session {
+                clicked = ui.with_color_var(ImGuiCol::Button, ACTIVE_SESSION_COLOR, || ui.small_button(&label));
+            } else {
+                clicked = ui.small_button(&label);
+            }
+        });
+        if clicked {
+            app_state.active_session = index;
+            app_state.timestamp_selection = config.session_config(index).timestamp_setting.selection_index();
+        }
+    }
+
+    // a new session starts as a copy of the selected session's route
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("New")) {
+        let mut session_config = config.session_config(app_state.active_session).clone();
+        session_config.sessions.clear();
+        session_config.session_name = format!("Session {}", config.session_count() + 1);
+        config.sessions.push(session_config);
+
+        sessions.push(Session::spawn());
+        app_state.active_session = sessions.len() - 1;
+    }
+
+    // the first session is the configuration's own route, so it can't be deleted
+    let active = app_state.active_session;
+    if active > 0 {
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Delete")) {
+            if sessions[active].processing || sessions[active].paused {
+                app_state.notify_error("Cancel processing before deleting a session".to_string());
+            } else {
+                info!("Deleting Session '{}'", config.session_config(active).session_name);
+                config.sessions.remove(active - 1);
+                sessions.remove(active).terminate();
+                app_state.active_session = active - 1;
+                app_state.timestamp_selection = config.session_config(active - 1).timestamp_setting.selection_index();
+            }
+        }
+    }
+
+    ui.same_line(0.0);
+    ui.push_item_width(120.0);
+    input_string(ui,
+                 im_str!("Session Name"),
+                 &mut config.session_config_mut(app_state.active_session).session_name,
+                 &mut app_state.imgui_str);
+    ui.pop_item_width();
+}
+
+/// The height of a section that can show a given number of lines of text
+fn frame_height(ui: &Ui, lines: f32) -> f32 {
+    lines * ui.get_text_line_height_with_spacing()
+}
+
+/* Gui Input Functions */
+fn input_port(ui: &Ui, label: &ImStr, port: &mut u16) {
+    let mut tmp = *port as i32;
+    ui.input_int(label, &mut tmp).build();
+    *port = tmp as u16;
+}
+
+fn configuration_ui(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState) {
+    ui.child_frame(im_str!("Configuration"), (0.0, frame_height(ui, CONFIG_SETTINGS_FRAME_LINES)))
+      .show_borders(true)
+      .collapsible(true)
+      .build(|| {
+          input_file_name(ui, im_str!("Configuration File"), &mut app_state.config_file_name, &mut app_state.imgui_str, FileDialog::Save);
+
+          let config_modified = app_state.config_modified(config);
+
+          if ui.small_button(im_str!("Save")) {
+              save_config(config, &app_state.config_file_name.clone());
+              app_state.saved_config = Some(config.clone());
+          }
+
+          ui.same_line(0.0);
+
+          // loading a configuration discards any changes, so ask before loading over them
+          if ui.small_button(im_str!("Load")) {
+              if config_modified {
+                  ui.open_popup(im_str!("Discard Changes?"));
+              } else {
+                  load_config_ui(config, app_state);
+              }
+          }
+          ui.popup_modal(im_str!("Discard Changes?")).build(|| {
+              ui.text("The configuration has unsaved changes. Load anyway?");
+              if ui.small_button(im_str!("Load")) {
+                  load_config_ui(config, app_state);
+                  ui.close_current_popup();
+              }
+
+              ui.same_line(0.0);
+
+              if ui.small_button(im_str!("Don't Load")) {
+                  ui.close_current_popup();
+              }
+          });
+
+          // reloading applies the configuration file to the running sessions, restarting them if needed
+          ui.same_line(0.0);
+          if ui.small_button(im_str!("Reload & Apply")) {
+              if config_modified {
+                  ui.open_popup(im_str!("Discard Changes and Reload?"));
+              } else {
+                  app_state.reload_requested = true;
+              }
+          }
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Load the configuration file, restarting the sessions whose settings changed"));
+          }
+          ui.popup_modal(im_str!("Discard Changes and Reload?")).build(|| {
+              ui.text("The configuration has unsaved changes. Reload anyway?");
+              if ui.small_button(im_str!("Reload")) {
+                  app_state.reload_requested = true;
+                  ui.close_current_popup();
+              }
+
+              ui.same_line(0.0);
+
+              if ui.small_button(im_str!("Don't Reload")) {
+                  ui.close_current_popup();
+              }
+          });
+
+          // the profile is used the next time the configuration is loaded
+          if app_state.profiles.len() > 0 {
+              ui.same_line(0.0);
+              ui.push_item_width(120.0);
+              let mut profile_selection = app_state.profile.as_ref()
+                                                   .and_then(|profile| app_state.profiles.iter().position(|name| name == profile))
+                                                   .map(|index| index as i32 + 1)
+                                                   .unwrap_or(0);
+              let mut profile_names: Vec<ImString> = vec!(ImString::new("(none)"));
+              profile_names.extend(app_state.profiles.iter().map(|name| ImString::new(name.clone())));
+              let profile_name_strs: Vec<&ImStr> = profile_names.iter().map(|name| name.as_ref()).collect();
+              if ui.combo(im_str!("Profile"), &mut profile_selection, &profile_name_strs, 5) {
+                  app_state.profile = match profile_selection {
+                      0 => None,
+
+                      index => Some(app_state.profiles[index as usize - 1].clone()),
+                  };
+              }
+              ui.pop_item_width();
+              if ui.is_item_hovered() {
+                  ui.tooltip_text(im_str!("The profile of the configuration file used when it is loaded"));
+              }
+          }
+
+          ui.same_line(0.0);
+          let mut theme_selection = config.theme as i32;
+          ui.radio_button(im_str!("Dark"), &mut theme_selection, GuiTheme::Dark as i32);
+          ui.same_line(0.0);
+          ui.radio_button(im_str!("Light"), &mut theme_selection, GuiTheme::Light as i32);
+          ui.same_line(0.0);
+          ui.radio_button(im_str!("Custom"), &mut theme_selection, GuiTheme::Custom as i32);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Use the theme selected in the Theme Editor"));
+          }
+          config.theme = num::FromPrimitive::from_i32(theme_selection).unwrap();
+
+          ui.same_line(0.0);
+          ui.push_item_width(100.0);
+          ui.slider_float(im_str!("UI Scale"), &mut config.ui_scale, MIN_UI_SCALE, MAX_UI_SCALE).build();
+          ui.pop_item_width();
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Scale text and widgets, such as for high resolution displays"));
+          }
+
+          if config_modified {
+              ui.same_line(0.0);
+              ui.text_colored(MODIFIED_COLOR, im_str!("(modified)"));
+              if ui.is_item_hovered() {
+                  ui.tooltip_text(im_str!("The configuration has changes that are not saved to the configuration file"));
+              }
+          }
+      });
+}
+
+// Load the configuration file named in the GUI, replacing the current configuration
+fn load_config_ui(config: &mut AppConfig, app_state: &mut AppState) {
+    app_state.profiles = profile_names(&app_state.config_file_name);
+
+    // a profile that is not in the newly loaded file is not used
+    if app_state.profile.as_ref().map_or(false, |profile| !app_state.profiles.contains(profile)) {
+        app_state.profile = None;
+    }
+
+    match load_config(&app_state.config_file_name.clone(), &app_state.profile.clone()) {
+      Ok(Some(config_read)) => {
+          *config = config_read;
+          app_state.saved_config = Some(config.clone());
+          app_state.active_session = 0;
+          app_state.timestamp_selection = config.timestamp_setting.selection_index();
+      },
+
+      Ok(None) => {
+          let err = format!("Configuration file '{}' does not exist", app_state.config_file_name);
+          error!("{}", err);
+          app_state.notify_error(err);
+      },
+
+      Err(err) => {
+          let err = format!("Could not load configuration '{}': {}", app_state.config_file_name, err);
+          error!("{}", err);
+          app_state.notify_error(err);
+      },
+    }
+}
+
+fn packet_settings_ui(ui: &Ui, config: &mut AppConfig, app_state: &mut AppState) {
+    ui.child_frame(im_str!("CcsdsSettingsFrame"), (0.0, frame_height(ui, CCSDS_SETTINGS_FRAME_LINES)))
+      .collapsible(true)
+      .show_borders(true)
+      .show_scrollbar(true)
+      .build(|| {
+          let timestamp_selection = &mut app_state.timestamp_selection;
+
+          ui.columns(2, im_str!("CcsdsSettingsCol"), false);
+          // Fixed or variable size packets
+          let mut fixed_size_packets: bool = config.packet_size != PacketSize::Variable;
+          ui.checkbox(im_str!("Fixed Size Packets"), &mut fixed_size_packets);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Packets have a fixed size- CCSDS Headers are ignored"));
+          }
+          if fixed_size_packets {
+              ui.same_line(0.0);
+              let mut packet_size: i32 = config.packet_size.num_bytes() as i32;
+              ui.input_int(im_str!("Packet Size (bytes)"), &mut packet_size).build();
+              config.packet_size = PacketSize::Fixed(packet_size as u32);
+          }
+          else {
+              config.packet_size = PacketSize::Variable;
+          }
+
+          ui.next_column();
+
+          // Endianness settings
+          ui.checkbox(im_str!("Little Endian CCSDS Primary Header"), &mut config.little_endian_ccsds);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Decode CCSDS Primary Header as Little Endian"));
+          }
+          ui.next_column();
+          ui.separator();
+
+          // Pre and post section settings
+          ui.text("Header Bytes: ");
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Size of frame header in front of each CCSDS Primary Header"));
+          }
+          ui.same_line(0.0);
+          ui.input_int(im_str!("Header"), &mut config.frame_settings.prefix_bytes).build();
+          ui.next_column();
+          ui.checkbox(im_str!("Keep Header Bytes"), &mut config.frame_settings.keep_prefix);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Keep frame header when forwarding packet to output"));
+          }
+          config.frame_settings.prefix_bytes = max(config.frame_settings.prefix_bytes, 0);
+          ui.next_column();
+
+          ui.text("Footer Bytes: ");
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Size of frame footer in front of each CCSDS Primary Header"));
+          }
+          ui.same_line(0.0);
+          ui.input_int(im_str!("Footer"), &mut config.frame_settings.postfix_bytes).build();
+          ui.next_column();
+          ui.checkbox(im_str!("Keep Footer Bytes"), &mut config.frame_settings.keep_postfix);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Keep frame footer when forwarding packet to output"));
+          }
+          config.frame_settings.postfix_bytes = max(config.frame_settings.postfix_bytes, 0);
+          ui.next_column();
+
+          let mut transfer_frame = config.frame_settings.transfer_frame_offset.is_some();
+          ui.checkbox(im_str!("Transfer Frame Header"), &mut transfer_frame);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("The frame header holds a TM transfer frame header, and statistics are kept for each virtual channel"));
+          }
+          ui.next_column();
+          if transfer_frame {
+              let mut offset = config.frame_settings.transfer_frame_offset.unwrap_or(0) as i32;
+              ui.input_int(im_str!("Frame Header Offset"), &mut offset).build();
+              if ui.is_item_hovered() {
+                  ui.tooltip_text(im_str!("Offset of the transfer frame header in the frame header, such as 4 to skip a sync marker"));
+              }
+              config.frame_settings.transfer_frame_offset = Some(max(offset, 0) as usize);
+          } else {
+              config.frame_settings.transfer_frame_offset = None;
+              config.frame_settings.fec_corrections_offset = None;
+          }
+          ui.next_column();
+
+          if transfer_frame {
+              let mut fec_corrections = config.frame_settings.fec_corrections_offset.is_some();
+              ui.checkbox(im_str!("FEC Corrections"), &mut fec_corrections);
+              if ui.is_item_hovered() {
+                  ui.tooltip_text(im_str!("The frame header has a byte giving the symbols the ground station's Reed-Solomon decoder corrected"));
+              }
+              ui.next_column();
+              if fec_corrections {
+                  let mut offset = config.frame_settings.fec_corrections_offset.unwrap_or(0) as i32;
+                  ui.input_int(im_str!("Corrections Offset"), &mut offset).build();
+                  config.frame_settings.fec_corrections_offset = Some(max(offset, 0) as usize);
+              } else {
+                  config.frame_settings.fec_corrections_offset = None;
+              }
+              ui.next_column();
+          }
+
+          ui.checkbox(im_str!("Strip Sync Marker"), &mut config.frame_settings.sync_marker.enabled);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Strip a sync marker from the start of each frame, before the frame header, searching for it to find each frame"));
+          }
+          ui.next_column();
+          if config.frame_settings.sync_marker.enabled {
+              ui.text(format!("Marker: {}", format_hex_bytes(&config.frame_settings.sync_marker.marker)));
+              if ui.is_item_hovered() {
+                  ui.tooltip_text(im_str!("The marker is set by 'sync_marker' in the frame settings of the configuration file"));
+              }
+          }
+          ui.next_column();
+
+          ui.columns(1, im_str!("Maximum Packet Size Section"), false);
+          ui.input_int(im_str!("Maximum Packet Size (Bytes)"), &mut config.max_length_bytes).build();
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Maximum packet size, ignoring frame header/footer, that will be forwarded to output"));
+          }
+          ui.checkbox(im_str!("Drop CRC Failures"), &mut config.drop_crc_failures);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Drop packets whose CRC-16, in their last two bytes, is not valid"));
+          }
+          ui.separator();
+
+          container_format_ui(ui, &mut config.container_format);
+          ui.separator();
+          
+          // Timestamp settings
+          ui.text("Time Settings");
+          ui.columns(4, im_str!("SelectTimestampOption"), false);
+          ui.radio_button(im_str!("Forward Through"), timestamp_selection, 1);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Process packets as fast as possible"));
+          }
+          ui.next_column();
+          ui.radio_button(im_str!("Replay"), timestamp_selection, 2);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Process packets according to their timestamps"));
+          }
+          ui.next_column();
+          ui.radio_button(im_str!("Delay"), timestamp_selection, 3);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Delay packets by a fixed amount"));
+          }
+          ui.next_column();
+          ui.radio_button(im_str!("Throttle"), timestamp_selection, 4);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Provide a minimum time between sending each packet"));
+          }
+          ui.next_column();
+
+          //ui.columns(2, im_str!("SelectTimestampSettings"), false);
+          match timestamp_selection {
+              // ASAP
+              1 => {
+                  // no options required- just go as fast as possible
+                  config.timestamp_setting = TimestampSetting::Asap;
+              },
+
+              // Replay
+              2 => {
+                  timestamp_def_ui(&ui, &mut config.timestamp_def, &mut app_state.imgui_str);
+                  config.timestamp_setting = TimestampSetting::Replay;
+              },
+
+              // Delay
+              3 => {
+                  match config.timestamp_setting {
+                      TimestampSetting::Delay(delay) => {
+                          ui.columns(2, im_str!("SpecificTimeSettings"), false);
+                          //ui.text("Delay Time");
+                          //ui.next_column();
+                          let mut delay_time = delay.as_fractional_secs() as f32;
+                          ui.input_float(im_str!("Delay Time"), &mut delay_time).build();
+                          config.timestamp_setting =
+                              TimestampSetting::Delay(Duration::new(delay_time as u64,
+                                                                    (delay_time.fract() * 1000000000.0) as u32));
+                      }
+
+                      _ => {
+                          config.timestamp_setting = TimestampSetting::Delay(Duration::new(0, 0));
+                      }
+                  }
+              },
+
+              // Throttle
+              4 => {
+                  match config.timestamp_setting {
+                      TimestampSetting::Throttle(delay) => {
+                          ui.columns(2, im_str!("SpecificTimeSettings"), false);
+                          //ui.text("Time Between Packets");
+                          //ui.next_column();
+                          let mut delay_time = delay.as_fractional_secs() as f32;
+                          ui.input_float(im_str!("Time Between Packets"), &mut delay_time).build();
+                          config.timestamp_setting =
+                              TimestampSetting::Throttle(Duration::new(delay_time as u64,
+                                                                       (delay_time.fract() * 1_000_000_000.0) as u32));
+                      }
+
+                      _ => {
+                          config.timestamp_setting = TimestampSetting::Throttle(Duration::new(0, 0));
+                      }
+                  }
+              },
+
+              _ => unreachable!(),
+
+          }
+
+          ui.columns(1, im_str!("PauseConditionsSection"), false);
+          ui.separator();
+          pause_conditions_ui(ui, &mut config.pause_conditions, app_state);
+
+          ui.separator();
+          trigger_settings_ui(ui, &mut config.trigger_settings, app_state);
+
+          ui.separator();
+          rate_alarms_ui(ui, &mut config.rate_alarms, &mut app_state.rate_alarm_editor);
+
+          ui.separator();
+          ui.checkbox(im_str!("Index Files"), &mut config.index_files);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Create an index of the packets in file inputs and outputs, used to seek to a packet number or time"));
+          }
+
+          let mut gui_updates_per_sec = config.gui_updates_per_sec as i32;
+          ui.push_item_width(80.0);
+          ui.input_int(im_str!("GUI Updates Per Second"), &mut gui_updates_per_sec).build();
+          ui.pop_item_width();
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("The most packet updates shown for each APID per second, or 0 for every packet. Counts are always accurate."));
+          }
+          config.gui_updates_per_sec = max(gui_updates_per_sec, 0) as u32;
+
+          ui.separator();
+          archive_settings_ui(ui, &mut config.archive_settings, app_state);
+          parquet_settings_ui(ui, &mut config.parquet_settings, app_state);
+
+          ui.separator();
+          csv_log_settings_ui(ui, &mut config.csv_log_settings, app_state);
+          decom_settings_ui(ui, &mut config.decom_settings, app_state);
+          raw_capture_settings_ui(ui, &mut config.raw_capture_settings, app_state);
+
+          ui.separator();
+          heartbeat_settings_ui(ui, &mut config.heartbeat_settings, app_state);
+          time_packet_settings_ui(ui, &mut config.time_packet_settings, app_state);
+
+          ui.separator();
+          schedule_settings_ui(ui, &mut config.schedule, &mut app_state.imgui_str);
+          outage_settings_ui(ui, &mut config.outage_settings, &mut app_state.imgui_str);
+
+          ui.separator();
+          priority_settings_ui(ui, &mut config.apid_priorities, app_state);
+
+          ui.separator();
+          ui.checkbox(im_str!("Dry Run"), &mut config.dry_run);
+          if ui.is_item_hovered() {
+              ui.tooltip_text(im_str!("Process packets without writing to the outputs, reporting what would be forwarded to each in the log and processing report"));
+          }
+      });
+}
+
+fn schedule_settings_ui(ui: &Ui, schedule: &mut ScheduleSettings, imgui_str: &mut ImString) {
+    ui.push_item_width(200.0);
+    input_string(ui, im_str!("Start At"), &mut schedule.start, imgui_str);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Start processing at a UTC time, such as 2020-01-01T12:00:00Z, or after a countdown from pressing Start, such as +90 or +00:01:30.\nEmpty starts straight away."));
+    }
+
+    ui.same_line(0.0);
+    input_string(ui, im_str!("Stop At"), &mut schedule.stop, imgui_str);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Stop processing at a UTC time, or once it has run for a time, such as +00:10:00.\nEmpty runs until the input ends."));
+    }
+    ui.pop_item_width();
+
+    for problem in schedule.problems() {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
+    }
+}
+
+fn outage_settings_ui(ui: &Ui, outage_settings: &mut OutageSettings, imgui_str: &mut ImString) {
+    ui.checkbox(im_str!("Simulated Outages"), &mut outage_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Drop everything written to the outputs during each outage, as at a ground station handover or loss of signal"));
+    }
+
+    if !outage_settings.enabled {
+        return;
+    }
+
+    ui.push_item_width(80.0);
+    ui.input_float(im_str!("Every (s)"), &mut outage_settings.period_secs).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds from the start of one repeating outage to the start of the next, or 0 for no repeating outages"));
+    }
+    ui.same_line(0.0);
+    ui.input_float(im_str!("For (s)"), &mut outage_settings.duration_secs).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds each repeating outage lasts"));
+    }
+    ui.same_line(0.0);
+    ui.input_float(im_str!("First After (s)"), &mut outage_settings.first_secs).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds from the start of processing until the first repeating outage"));
+    }
+    ui.pop_item_width();
+    outage_settings.period_secs = outage_settings.period_secs.max(0.0);
+    outage_settings.duration_secs = outage_settings.duration_secs.max(0.0);
+    outage_settings.first_secs = outage_settings.first_secs.max(0.0);
+
+    ui.with_id("OutageWindows", || {
+        let mut remove_index = None;
+        for (index, window) in outage_settings.windows.iter_mut().enumerate() {
+            ui.with_id(format!("OutageWindow{}", index).as_str(), || {
+                if ui.small_button(im_str!("Remove")) {
+                    remove_index = Some(index);
                 }
-            }
+                ui.same_line(0.0);
+                ui.push_item_width(200.0);
+                input_string(ui, im_str!("Outage At"), &mut window.start, imgui_str);
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(im_str!("Start an outage at a UTC time, such as 2020-01-01T12:00:00Z, or after processing starts, such as +90 or +00:01:30"));
+                }
+                ui.pop_item_width();
+                ui.same_line(0.0);
+                ui.push_item_width(80.0);
+                ui.input_float(im_str!("For (s)"), &mut window.duration_secs).build();
+                ui.pop_item_width();
+                window.duration_secs = window.duration_secs.max(0.0);
+            });
+        }
+        if let Some(index) = remove_index {
+            outage_settings.windows.remove(index);
+        }
+
+        if ui.small_button(im_str!("Add Outage")) {
+            outage_settings.windows.push(OutageWindow { start: "+60".to_string(), duration_secs: 10.0 });
         }
+    });
 
-        Err(_) => {},
+    for problem in outage_settings.problems() {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
     }
 }
 
-/* Gui Input Functions */
-fn input_port(ui: &Ui, label: &ImStr, port: &mut u16) {
-    let mut tmp = *port as i32;
-    ui.input_int(label, &mut tmp).build();
-    *port = tmp as u16;
+fn priority_settings_ui(ui: &Ui, priorities: &mut PrioritySettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("APID Priorities"), &mut priorities.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write the packets of high priority APIDs first, and of low priority APIDs last, on outputs with a rate limit or a store and forward backlog"));
+    }
+
+    if !priorities.enabled {
+        return;
+    }
+
+    let apids_text = |apids: &Vec<u16>| apids.iter().map(|apid| apid.to_string()).collect::<Vec<String>>().join(", ");
+    ui.text(format!("High Priority: {}", apids_text(&priorities.high_apids)));
+    ui.text(format!("Low Priority: {}", apids_text(&priorities.low_apids)));
+
+    ui.push_item_width(200.0);
+    input_string(ui, im_str!("APIDs"), &mut app_state.priority_apids_text, &mut app_state.imgui_str);
+    ui.pop_item_width();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("APIDs separated by commas or spaces, in decimal or as hex such as 0x64, to give high or low priority"));
+    }
+
+    let mut set_priority = None;
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Set High")) {
+        set_priority = Some(Priority::High);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Set Low")) {
+        set_priority = Some(Priority::Low);
+    }
+
+    if let Some(priority) = set_priority {
+        match parse_apid_list_text(&app_state.priority_apids_text, false) {
+            Ok(apids) => {
+                if priority == Priority::High {
+                    priorities.high_apids = apids;
+                } else {
+                    priorities.low_apids = apids;
+                }
+            },
+
+            Err(err) => {
+                app_state.notify_error(format!("Could not set {} priority APIDs: {}", priority.name(), err));
+            },
+        }
+    }
+
+    let mut starvation_limit = min(priorities.starvation_limit, i32::max_value() as u32) as i32;
+    ui.push_item_width(80.0);
+    ui.input_int(im_str!("Starvation Limit"), &mut starvation_limit).build();
+    ui.pop_item_width();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The most higher priority packets written in a row while a lower priority packet waits, or 0 to always write the highest priority first"));
+    }
+    priorities.starvation_limit = max(starvation_limit, 0) as u32;
+
+    for problem in priorities.problems() {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
+    }
 }
 
-fn configuration_ui(ui: &Ui, config: &mut AppConfig, config_file_name: &mut String, imgui_str: &mut ImString) {
-    ui.child_frame(im_str!("Configuration"), (WINDOW_WIDTH - 15.0, CONFIG_SETTINGS_FRAME_HEIGHT))
-      .show_borders(true)
-      .collapsible(true)
-      .build(|| {
-          input_string(ui, im_str!("Configuration File"), config_file_name, imgui_str);
+fn pause_conditions_ui(ui: &Ui, pause_conditions: &mut Vec<PauseCondition>, app_state: &mut AppState) {
+    ui.text("Pause Conditions");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Pause processing after forwarding a packet matching any of these conditions"));
+    }
 
-          if ui.small_button(im_str!("Save")) {
-              save_config(config, &config_file_name.clone());
-          }
+    ui.with_id("PauseConditions", || {
+        conditions_ui(ui, pause_conditions, &mut app_state.pause_condition_editor, &mut app_state.imgui_str);
+    });
+}
 
-          ui.same_line(0.0);
+fn trigger_settings_ui(ui: &Ui, trigger_settings: &mut TriggerSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Trigger Capture"), &mut trigger_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Only write packets to the outputs once a packet matches one of the trigger conditions"));
+    }
 
-          if ui.small_button(im_str!("Load")) {
-              match load_config(&config_file_name.clone()) {
-                Some(config_read) => {
-                    *config = config_read;
+    if trigger_settings.enabled {
+        ui.push_item_width(80.0);
+        ui.input_float(im_str!("Pre-Trigger (s)"), &mut trigger_settings.pre_trigger_secs).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Seconds of packets from before the trigger to write when it fires"));
+        }
+        ui.same_line(0.0);
+        ui.input_float(im_str!("Post-Trigger (s)"), &mut trigger_settings.post_trigger_secs).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Seconds to capture after the trigger before waiting for the next trigger (0 to capture until stopped)"));
+        }
+        ui.pop_item_width();
+        trigger_settings.pre_trigger_secs = trigger_settings.pre_trigger_secs.max(0.0);
+        trigger_settings.post_trigger_secs = trigger_settings.post_trigger_secs.max(0.0);
+
+        ui.with_id("TriggerConditions", || {
+            conditions_ui(ui, &mut trigger_settings.conditions, &mut app_state.trigger_condition_editor, &mut app_state.imgui_str);
+        });
+    }
+}
+
+fn rate_alarms_ui(ui: &Ui, rate_alarms: &mut Vec<RateAlarm>, editor: &mut RateAlarmEditor) {
+    ui.text("Rate Alarms");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Raise an alarm when the packets per second of an APID, or of all packets, crosses a limit"));
+    }
+
+    ui.with_id("RateAlarms", || {
+        let mut remove_index = None;
+        for (index, rate_alarm) in rate_alarms.iter().enumerate() {
+            ui.with_id(format!("RateAlarm{}", index).as_str(), || {
+                if ui.small_button(im_str!("Remove")) {
+                    remove_index = Some(index);
+                }
+                ui.same_line(0.0);
+                ui.text(rate_alarm.description());
+            });
+        }
+        if let Some(index) = remove_index {
+            rate_alarms.remove(index);
+        }
+
+        ui.push_item_width(80.0);
+        ui.input_int(im_str!("APID"), &mut editor.apid).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The APID whose rate is checked, or -1 for the rate of all packets"));
+        }
+        ui.same_line(0.0);
+        ui.input_int(im_str!("Min Rate"), &mut editor.min_rate).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The fewest packets per second allowed, or -1 for no minimum"));
+        }
+        ui.same_line(0.0);
+        ui.input_int(im_str!("Max Rate"), &mut editor.max_rate).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The most packets per second allowed, or -1 for no maximum"));
+        }
+        ui.pop_item_width();
+        // APIDs are 11 bits
+        editor.apid = min(max(editor.apid, -1), 0x7FF);
+        editor.min_rate = max(editor.min_rate, -1);
+        editor.max_rate = max(editor.max_rate, -1);
+
+        if ui.small_button(im_str!("Add Alarm")) {
+            let optional = |value: i32| if value < 0 { None } else { Some(value as usize) };
+            rate_alarms.push(RateAlarm { apid: optional(editor.apid).map(|apid| apid as u16),
+                                         min_rate: optional(editor.min_rate),
+                                         max_rate: optional(editor.max_rate),
+            });
+        }
+    });
+}
+
+fn archive_settings_ui(ui: &Ui, archive_settings: &mut ArchiveSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Packet Archive"), &mut archive_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Record the header information of each forwarded packet into an SQLite database"));
+    }
+
+    if archive_settings.enabled {
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Check CRC"), &mut archive_settings.check_crc);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Record whether the CRC-16 in the last two bytes of each packet is valid"));
+        }
+
+        input_file_name(ui, im_str!("Archive File"), &mut archive_settings.file_name, &mut app_state.imgui_str, FileDialog::Save);
+    }
+}
+
+fn parquet_settings_ui(ui: &Ui, parquet_settings: &mut ParquetSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Parquet Archive"), &mut parquet_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write the header fields and payload of each forwarded packet into a Parquet table per APID"));
+    }
+
+    if parquet_settings.enabled {
+        input_string(ui, im_str!("Parquet Directory"), &mut parquet_settings.directory, &mut app_state.imgui_str);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The directory the tables are written to, as apid_<APID>.parquet"));
+        }
+    }
+}
+
+fn csv_log_settings_ui(ui: &Ui, csv_log_settings: &mut CsvLogSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("CSV Packet Log"), &mut csv_log_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write a line per packet to a CSV file, with its receive time, APID, sequence count, length, timestamp, and outputs"));
+    }
+
+    if csv_log_settings.enabled {
+        input_file_name(ui, im_str!("CSV File"), &mut csv_log_settings.file_name, &mut app_state.imgui_str, FileDialog::Save);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Leave empty to write a new file to the logs directory each time processing starts"));
+        }
+    }
+}
+
+fn raw_capture_settings_ui(ui: &Ui, raw_capture_settings: &mut RawCaptureSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Raw Capture"), &mut raw_capture_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write the bytes read from the input to a file, exactly as read and before they are parsed"));
+    }
+
+    if raw_capture_settings.enabled {
+        input_file_name(ui, im_str!("Capture File"), &mut raw_capture_settings.file_name, &mut app_state.imgui_str, FileDialog::Save);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Leave empty to write a new file to the logs directory each time processing starts"));
+        }
+    }
+}
+
+// The parameters are defined in the configuration file, so they are only listed here
+fn decom_settings_ui(ui: &Ui, decom_settings: &mut DecomSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Decom CSV"), &mut decom_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write the values of the parameters defined in the configuration to a CSV file, for quick-look trending"));
+    }
+
+    if decom_settings.enabled {
+        input_file_name(ui, im_str!("Decom File"), &mut decom_settings.file_name, &mut app_state.imgui_str, FileDialog::Save);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Leave empty to write a new file to the logs directory each time processing starts"));
+        }
+
+        if decom_settings.parameters.is_empty() {
+            ui.text("No parameters are defined in 'decom_settings' of the configuration");
+        }
+
+        for parameter in decom_settings.parameters.iter() {
+            ui.text(format!("{}: APID {} byte {} {:?}", parameter.name, parameter.apid, parameter.offset, parameter.data_type));
+        }
+    }
+}
+
+fn heartbeat_settings_ui(ui: &Ui, heartbeat_settings: &mut HeartbeatSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Heartbeat"), &mut heartbeat_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write a heartbeat packet to each output that has not been sent a packet for the heartbeat interval"));
+    }
+
+    if heartbeat_settings.enabled {
+        let mut apid = heartbeat_settings.apid as i32;
+        ui.push_item_width(80.0);
+        ui.input_int(im_str!("Heartbeat APID"), &mut apid).build();
+        ui.same_line(0.0);
+        ui.input_float(im_str!("Interval (s)"), &mut heartbeat_settings.interval_secs).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Seconds an output can go without a packet before a heartbeat is written to it"));
+        }
+        ui.pop_item_width();
+        // APIDs are 11 bits
+        heartbeat_settings.apid = min(max(apid, 0), 0x7FF) as u16;
+        heartbeat_settings.interval_secs = heartbeat_settings.interval_secs.max(0.0);
+
+        let data_hex = heartbeat_settings.data.iter().map(|byte| format!("{:02X}", byte)).collect::<String>();
+        ui.text(format!("Heartbeat Data: {}", data_hex));
+        input_string(ui, im_str!("Data (Hex)"), &mut app_state.heartbeat_data_hex, &mut app_state.imgui_str);
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Set Data")) {
+            match parse_hex_bytes(&app_state.heartbeat_data_hex) {
+                Ok(ref data) if data.is_empty() => {
+                    app_state.notify_error("The heartbeat packet must have at least one byte of data".to_string());
                 },
 
-                None => {
-                    error!("Could not load configuration file: {}", config_file_name);
+                Ok(data) => heartbeat_settings.data = data,
+
+                Err(err) => {
+                    app_state.notify_error(format!("Could not set heartbeat data: {}", err));
                 },
-              }
-          }
-      });
+            }
+        }
+    }
 }
 
-fn packet_settings_ui(ui: &Ui, config: &mut AppConfig, timestamp_selection: &mut i32) {
-    ui.child_frame(im_str!("CcsdsSettingsFrame"), (WINDOW_WIDTH - 15.0, CCSDS_SETTINGS_FRAME_HEIGHT))
-      .collapsible(true)
-      .show_borders(true)
-      .build(|| {
-          ui.columns(2, im_str!("CcsdsSettingsCol"), false);
-          // Fixed or variable size packets
-          let mut fixed_size_packets: bool = config.packet_size != PacketSize::Variable;
-          ui.checkbox(im_str!("Fixed Size Packets"), &mut fixed_size_packets);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Packets have a fixed size- CCSDS Headers are ignored"));
-          }
-          if fixed_size_packets {
-              ui.same_line(0.0);
-              let mut packet_size: i32 = config.packet_size.num_bytes() as i32;
-              ui.input_int(im_str!("Packet Size (bytes)"), &mut packet_size).build();
-              config.packet_size = PacketSize::Fixed(packet_size as u32);
-          }
-          else {
-              config.packet_size = PacketSize::Variable;
-          }
+fn time_packet_settings_ui(ui: &Ui, time_packet_settings: &mut TimePacketSettings, app_state: &mut AppState) {
+    ui.checkbox(im_str!("Time Packets"), &mut time_packet_settings.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write a packet holding the current ground time to each output every interval"));
+    }
 
-          ui.next_column();
+    if time_packet_settings.enabled {
+        let mut apid = time_packet_settings.apid as i32;
+        ui.push_item_width(80.0);
+        ui.input_int(im_str!("Time APID"), &mut apid).build();
+        ui.same_line(0.0);
+        ui.input_float(im_str!("Time Interval (s)"), &mut time_packet_settings.interval_secs).build();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Seconds between time packets"));
+        }
+        ui.pop_item_width();
+        // APIDs are 11 bits
+        time_packet_settings.apid = min(max(apid, 0), 0x7FF) as u16;
+        time_packet_settings.interval_secs = time_packet_settings.interval_secs.max(0.0);
 
-          // Endianness settings
-          ui.checkbox(im_str!("Little Endian CCSDS Primary Header"), &mut config.little_endian_ccsds);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Decode CCSDS Primary Header as Little Endian"));
-          }
-          ui.next_column();
-          ui.separator();
+        ui.same_line(0.0);
+        ui.checkbox(im_str!("Secondary Header"), &mut time_packet_settings.secondary_header);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Set the secondary header flag of the time packets"));
+        }
 
-          // Pre and post section settings
-          ui.text("Header Bytes: ");
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Size of frame header in front of each CCSDS Primary Header"));
-          }
-          ui.same_line(0.0);
-          ui.input_int(im_str!("Header"), &mut config.frame_settings.prefix_bytes).build();
-          ui.next_column();
-          ui.checkbox(im_str!("Keep Header Bytes"), &mut config.frame_settings.keep_prefix);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Keep frame header when forwarding packet to output"));
-          }
-          config.frame_settings.prefix_bytes = max(config.frame_settings.prefix_bytes, 0);
-          ui.next_column();
+        let imgui_str = &mut app_state.imgui_str;
+        ui.with_id("TimePacket", || {
+            timestamp_def_ui(ui, &mut time_packet_settings.time_def, imgui_str);
+        });
+    }
+}
+
+// List a set of conditions, with controls to remove them or add a new condition
+fn conditions_ui(ui: &Ui, conditions: &mut Vec<PauseCondition>, editor: &mut ConditionEditor, imgui_str: &mut ImString) {
+    let mut remove_index = None;
+    for (index, condition) in conditions.iter().enumerate() {
+        ui.with_id(format!("Condition{}", index).as_str(), || {
+            if ui.small_button(im_str!("Remove")) {
+                remove_index = Some(index);
+            }
+            ui.same_line(0.0);
+            ui.text(condition.description());
+        });
+    }
+    if let Some(index) = remove_index {
+        conditions.remove(index);
+    }
+
+    let condition_names = [im_str!("APID Seen"), im_str!("Sequence Gap"), im_str!("Pattern Match"), im_str!("CRC Failure"), im_str!("Packet Count")];
+    ui.combo(im_str!("Condition"), &mut editor.condition, &condition_names, 5);
+
+    match editor.condition {
+        0 => {
+            ui.input_int(im_str!("APID"), &mut editor.apid).build();
+        },
+
+        2 => {
+            input_string(ui, im_str!("Pattern (Hex)"), &mut editor.pattern, imgui_str);
+            ui.input_int(im_str!("Pattern Offset"), &mut editor.offset).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Byte offset of the pattern in the packet, or -1 to match anywhere"));
+            }
+        },
+
+        4 => {
+            ui.input_int(im_str!("Packets"), &mut editor.packet_count).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("The condition is met once this many packets have been forwarded since processing started"));
+            }
+            editor.packet_count = max(editor.packet_count, 1);
+        },
+
+        _ => {},
+    }
+
+    if ui.small_button(im_str!("Add Condition")) {
+        match editor.condition {
+            0 => conditions.push(PauseCondition::ApidSeen(editor.apid as u16)),
+
+            1 => conditions.push(PauseCondition::SequenceGap),
+
+            2 => {
+                match parse_hex_bytes(&editor.pattern) {
+                    Ok(pattern) => {
+                        let offset = if editor.offset < 0 {
+                            None
+                        } else {
+                            Some(editor.offset as u32)
+                        };
+                        conditions.push(PauseCondition::PatternMatch { offset: offset, pattern: pattern });
+                    },
+
+                    Err(err) => {
+                        error!("Could not add condition: {}", err);
+                    },
+                }
+            },
+
+            3 => conditions.push(PauseCondition::CrcFailure),
 
-          ui.text("Footer Bytes: ");
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Size of frame footer in front of each CCSDS Primary Header"));
-          }
-          ui.same_line(0.0);
-          ui.input_int(im_str!("Footer"), &mut config.frame_settings.postfix_bytes).build();
-          ui.next_column();
-          ui.checkbox(im_str!("Keep Footer Bytes"), &mut config.frame_settings.keep_postfix);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Keep frame footer when forwarding packet to output"));
-          }
-          config.frame_settings.postfix_bytes = max(config.frame_settings.postfix_bytes, 0);
-          ui.next_column();
+            _ => conditions.push(PauseCondition::PacketCount(editor.packet_count as u64)),
+        }
+    }
+}
 
-          ui.columns(1, im_str!("Maximum Packet Size Section"), false);
-          ui.input_int(im_str!("Maximum Packet Size (Bytes)"), &mut config.max_length_bytes).build();
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Maximum packet size, ignoring frame header/footer, that will be forwarded to output"));
-          }
-          ui.separator();
-          
-          // Timestamp settings
-          ui.text("Time Settings");
-          ui.columns(4, im_str!("SelectTimestampOption"), false);
-          ui.radio_button(im_str!("Forward Through"), timestamp_selection, 1);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Process packets as fast as possible"));
-          }
-          ui.next_column();
-          ui.radio_button(im_str!("Replay"), timestamp_selection, 2);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Process packets according to their timestamps"));
-          }
-          ui.next_column();
-          ui.radio_button(im_str!("Delay"), timestamp_selection, 3);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Delay packets by a fixed amount"));
-          }
-          ui.next_column();
-          ui.radio_button(im_str!("Throttle"), timestamp_selection, 4);
-          if ui.is_item_hovered() {
-              ui.tooltip_text(im_str!("Provide a minimum time between sending each packet"));
-          }
-          ui.next_column();
+/// Format a packet timestamp as days, hours, minutes, and seconds
+fn format_timestamp(timestamp: Duration) -> String {
+    let total_secs = timestamp.as_secs();
+    let days = total_secs / 86400;
+    let hours = (total_secs % 86400) / 3600;
+    let minutes = (total_secs % 3600) / 60;
+    let secs = total_secs % 60;
+    let millis = timestamp.subsec_millis();
 
-          //ui.columns(2, im_str!("SelectTimestampSettings"), false);
-          match timestamp_selection {
-              // ASAP
-              1 => {
-                  // no options required- just go as fast as possible
-                  config.timestamp_setting = TimestampSetting::Asap;
-              },
+    format!("{}d {:02}:{:02}:{:02}.{:03}", days, hours, minutes, secs, millis)
+}
 
-              // Replay
-              2 => {
-                  timestamp_def_ui(&ui, &mut config.timestamp_def);
-                  config.timestamp_setting = TimestampSetting::Replay;
-              },
+// Describe the packets dropped for each reason, along with the APIDs removed by the input filter
+fn drop_reasons_text(processing_stats: &ProcessingStats) -> String {
+    let mut text = String::new();
 
-              // Delay
-              3 => {
-                  match config.timestamp_setting {
-                      TimestampSetting::Delay(delay) => {
-                          ui.columns(2, im_str!("SpecificTimeSettings"), false);
-                          //ui.text("Delay Time");
-                          //ui.next_column();
-                          let mut delay_time = delay.as_fractional_secs() as f32;
-                          ui.input_float(im_str!("Delay Time"), &mut delay_time).build();
-                          config.timestamp_setting =
-                              TimestampSetting::Delay(Duration::new(delay_time as u64,
-                                                                    (delay_time.fract() * 1000000000.0) as u32));
-                      }
+    for (drop_reason, count) in processing_stats.drop_reasons.iter() {
+        text.push_str(&format!("{}: {}\n", drop_reason.name(), count));
 
-                      _ => {
-                          config.timestamp_setting = TimestampSetting::Delay(Duration::new(0, 0));
-                      }
-                  }
-              },
+        if *drop_reason == DropReason::FilteredApid {
+            for (apid, count) in processing_stats.packets_filtered.iter() {
+                text.push_str(&format!("    APID {}: {}\n", apid, count));
+            }
+        }
 
-              // Throttle
-              4 => {
-                  match config.timestamp_setting {
-                      TimestampSetting::Throttle(delay) => {
-                          ui.columns(2, im_str!("SpecificTimeSettings"), false);
-                          //ui.text("Time Between Packets");
-                          //ui.next_column();
-                          let mut delay_time = delay.as_fractional_secs() as f32;
-                          ui.input_float(im_str!("Time Between Packets"), &mut delay_time).build();
-                          config.timestamp_setting =
-                              TimestampSetting::Throttle(Duration::new(delay_time as u64,
-                                                                       (delay_time.fract() * 1_000_000_000.0) as u32));
-                      }
+        if *drop_reason == DropReason::TooLong {
+            for (apid, count) in processing_stats.oversize_counts.iter() {
+                text.push_str(&format!("    APID {}: {}\n", apid, count));
+            }
+        }
+    }
 
-                      _ => {
-                          config.timestamp_setting = TimestampSetting::Throttle(Duration::new(0, 0));
-                      }
-                  }
-              },
+    text
+}
 
-              _ => unreachable!(),
+fn stats_text(ui: &Ui, color: Option<(f32, f32, f32, f32)>, text: String) {
+    match color {
+        Some(color) => ui.text_colored(color, &ImString::new(text)),
 
-          }
-      });
+        None => ui.text(text),
+    }
 }
 
 fn packet_summary_ui(ui: &Ui, packet_stats: &PacketStats) {
     if ui.is_item_hovered() {
         ui.tooltip(|| {
+            ui.text(format!("Sequence Gaps: {}", packet_stats.gap_count));
+            if !packet_stats.command_codes.is_empty() {
+                let codes = packet_stats.command_codes.iter().map(|code| code.to_string()).collect::<Vec<String>>();
+                ui.text(format!("cFS Command Codes: {}", codes.join(", ")));
+                if cfs_checksum_valid(&packet_stats.bytes) {
+                    ui.text("Last cFS Checksum: valid");
+                } else {
+                    ui.text_colored(GAP_COLOR, im_str!("Last cFS Checksum: invalid"));
+                }
+            }
             ui.text(format!("APID {} Hex Dump:", packet_stats.apid));
             hexdump_iter(&packet_stats.bytes).for_each(|s| ui.text(format!("{}", s)));
+            ui.text_disabled(im_str!("Right click to copy, save, or inspect the packet"));
         });
     }
+
+    // right clicking on a row offers actions on the packet's bytes
+    if ui.is_item_hovered() && ui.imgui().is_mouse_clicked(ImMouseButton::Right) {
+        ui.open_popup(&packet_actions_id(packet_stats.apid));
+    }
+}
+
+fn packet_actions_id(apid: u16) -> ImString {
+    ImString::new(format!("PacketActions{}", apid))
+}
+
+// The actions available when right clicking on a row of the statistics table. This
+// must only be called once per row, as the popup would otherwise be drawn more then once.
+// Returns whether the user asked to inspect the APID's packet history.
+fn packet_actions_ui(ui: &Ui, packet_stats: &PacketStats) -> bool {
+    let mut inspect = false;
+
+    ui.popup(&packet_actions_id(packet_stats.apid), || {
+        ui.text(format!("APID {} ({} bytes)", packet_stats.apid, packet_stats.bytes.len()));
+        ui.separator();
+
+        if ui.menu_item(im_str!("Copy Hex")).build() {
+            set_clipboard_text(&format_hex_bytes(&packet_stats.bytes));
+        }
+
+        if ui.menu_item(im_str!("Copy Hex Dump")).build() {
+            set_clipboard_text(&hex_dump_text(&packet_stats.bytes));
+        }
+
+        if ui.menu_item(im_str!("Save Bytes...")).build() {
+            save_packet_bytes(packet_stats.apid, packet_stats.last_seq, &packet_stats.bytes);
+        }
+
+        if ui.menu_item(im_str!("Inspect History...")).build() {
+            inspect = true;
+        }
+    });
+
+    inspect
+}
+
+// The packet inspector shows the packets kept in an APID's history, which can be
+// browsed from the most recent packet backwards.
+fn packet_inspector_ui(ui: &Ui,
+                       processing_stats: &ProcessingStats,
+                       inspect_apid: Option<u16>,
+                       inspect_index: &mut usize,
+                       packet_history_length: &mut usize) {
+    ui.popup_modal(im_str!("Packet Inspector")).build(|| {
+        match inspect_apid.and_then(|apid| processing_stats.packet_history.get(&apid)) {
+            Some(packet_stats) if packet_stats.history.len() > 0 => {
+                let count = packet_stats.history.len();
+                *inspect_index = min(*inspect_index, count - 1);
+                let record = &packet_stats.history[count - 1 - *inspect_index];
+
+                ui.text(format!("APID {}", packet_stats.apid));
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Older")) && *inspect_index + 1 < count {
+                    *inspect_index += 1;
+                }
+                ui.same_line(0.0);
+                ui.text(format!("{} of {}", *inspect_index + 1, count));
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Newer")) && *inspect_index > 0 {
+                    *inspect_index -= 1;
+                }
+                ui.same_line(0.0);
+                let recv_time: DateTime<Local> = record.recv_time.into();
+                ui.text(format!("Seq: {} Length: {} Received: {}",
+                                record.seq_count,
+                                record.bytes.len(),
+                                recv_time.format("%H:%M:%S%.3f")));
+
+                ui.child_frame(im_str!("InspectorHexDump"), (POPUP_WIDTH, frame_height(ui, ERROR_DETAILS_FRAME_LINES)))
+                    .show_borders(true)
+                    .show_scrollbar(true)
+                    .build(|| {
+                        hexdump_iter(&record.bytes).for_each(|s| ui.text(format!("{}", s)));
+                    });
+
+                if ui.small_button(im_str!("Copy Hex")) {
+                    set_clipboard_text(&format_hex_bytes(&record.bytes));
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Copy Hex Dump")) {
+                    set_clipboard_text(&hex_dump_text(&record.bytes));
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Save Bytes...")) {
+                    save_packet_bytes(packet_stats.apid, record.seq_count, &record.bytes);
+                }
+            },
+
+            _ => {
+                ui.text("No packets are kept for this APID");
+            },
+        }
+
+        ui.separator();
+
+        let mut history_length = *packet_history_length as i32;
+        ui.push_item_width(80.0);
+        ui.input_int(im_str!("Packets Kept"), &mut history_length).build();
+        ui.pop_item_width();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The number of recent packets kept for each APID"));
+        }
+        *packet_history_length = min(max(history_length, 0) as usize, MAX_PACKET_HISTORY);
+
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Close")) {
+            ui.close_current_popup();
+        }
+    });
+}
+
+// The oversize packet viewer shows the start of the frames of the packets dropped for being
+// longer than the maximum size, so a misaligned stream can be told from one carrying oversize
+// packets. The primary header follows any frame prefix in the hex dump.
+fn oversize_packets_ui(ui: &Ui, processing_stats: &ProcessingStats, oversize_index: &mut usize) {
+    ui.popup_modal(im_str!("Oversize Packets")).build(|| {
+        let counts = processing_stats.oversize_counts
+                                     .iter()
+                                     .map(|(apid, count)| format!("APID {}: {}", apid, count))
+                                     .collect::<Vec<String>>();
+        ui.text(format!("Dropped: {}", counts.join(", ")));
+
+        let count = processing_stats.oversize_captures.len();
+        if count > 0 {
+            *oversize_index = min(*oversize_index, count - 1);
+            let capture = &processing_stats.oversize_captures[count - 1 - *oversize_index];
+
+            if ui.small_button(im_str!("Older")) && *oversize_index + 1 < count {
+                *oversize_index += 1;
+            }
+            ui.same_line(0.0);
+            ui.text(format!("{} of {}", *oversize_index + 1, count));
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Newer")) && *oversize_index > 0 {
+                *oversize_index -= 1;
+            }
+
+            let recv_time: DateTime<Local> = capture.recv_time.into();
+            ui.text(format!("APID: {} Seq: {} Length: {} Offset: {} Received: {}",
+                            capture.header.control.apid(),
+                            capture.header.sequence.sequence_count(),
+                            capture.length,
+                            capture.offset,
+                            recv_time.format("%H:%M:%S%.3f")));
+            ui.text(format!("Header: {} at byte {} of the frame",
+                            format_hex_bytes(capture.header_bytes()),
+                            capture.header_offset));
+
+            ui.child_frame(im_str!("OversizeHexDump"), (POPUP_WIDTH, frame_height(ui, ERROR_DETAILS_FRAME_LINES)))
+                .show_borders(true)
+                .show_scrollbar(true)
+                .build(|| {
+                    hexdump_iter(&capture.bytes).for_each(|s| ui.text(format!("{}", s)));
+                });
+
+            if ui.small_button(im_str!("Copy Hex Dump")) {
+                set_clipboard_text(&hex_dump_text(&capture.bytes));
+            }
+            ui.same_line(0.0);
+        }
+
+        if ui.small_button(im_str!("Close")) {
+            ui.close_current_popup();
+        }
+    });
+}
+
+fn hex_dump_text(bytes: &[u8]) -> String {
+    hexdump_iter(bytes).map(|line| format!("{}", line))
+                       .collect::<Vec<String>>()
+                       .join("\n")
 }
 
-fn packet_statistics_ui(ui: &Ui, processing_stats: &ProcessingStats, app_state: &AppState, packets_dropped: usize) {
-    let mut dims = ImVec2::new(WINDOW_WIDTH - 15.0, STATS_FRAME_HEIGHT);
-    if !app_state.config_settings_shown {
-        dims.y += CONFIG_SETTINGS_FRAME_HEIGHT;
-        dims.y += 2.0;
+fn set_clipboard_text(text: &str) {
+    let text = ImString::new(text);
+    unsafe {
+        imgui::sys::igSetClipboardText(text.as_ptr());
     }
-    if !app_state.input_settings_shown {
-        dims.y += INPUT_SETTINGS_FRAME_HEIGHT;
-        dims.y += 2.0;
+}
+
+// Load an APID list from a text or CSV file chosen by the user
+fn import_apid_list(title: &str) -> Option<Vec<u16>> {
+    let file_name = tinyfiledialogs::open_file_dialog(title, "", None)?;
+
+    match load_apid_list(&file_name) {
+        Ok(apids) => {
+            info!("Loaded {} APIDs from {}", apids.len(), file_name);
+            Some(apids)
+        },
+
+        Err(err_string) => {
+            error!("{}", err_string);
+            None
+        },
     }
-    if !app_state.output_settings_shown {
-        dims.y += OUTPUT_SETTINGS_FRAME_HEIGHT;
-        dims.y += 2.0;
+}
+
+// Save an APID list to a file chosen by the user, as CSV if its name ends in '.csv'
+fn export_apid_list(title: &str, apids: &[u16]) {
+    if let Some(file_name) = tinyfiledialogs::save_file_dialog(title, "apids.txt") {
+        match save_apid_list(&file_name, apids) {
+            Ok(()) => info!("Saved {} APIDs to {}", apids.len(), file_name),
+
+            Err(err_string) => error!("{}", err_string),
+        }
     }
-    if !app_state.ccsds_settings_shown {
-        dims.y += CCSDS_SETTINGS_FRAME_HEIGHT;
-        dims.y += 2.0;
+}
+
+// Save the raw bytes of a packet to a file chosen by the user
+fn save_packet_bytes(apid: u16, seq_count: u16, bytes: &[u8]) {
+    let default_name = format!("apid_{}_seq_{}.bin", apid, seq_count);
+    if let Some(file_name) = tinyfiledialogs::save_file_dialog("Save Packet Bytes", &default_name) {
+        let result = File::create(&file_name).and_then(|mut file| file.write_all(bytes));
+
+        match result {
+            Ok(()) => info!("Saved APID {} packet to {}", apid, file_name),
+
+            Err(err) => error!("Could not save APID {} packet to {}: {}", apid, file_name, err),
+        }
     }
+}
 
-    ui.child_frame(im_str!("Apid Statistics"), dims)
+fn packet_statistics_ui(ui: &Ui,
+                        session: &mut Session,
+                        stale_timeout: &mut f32,
+                        packet_history_length: &mut usize,
+                        timestamp_def: &TimestampDef,
+                        reserved_height: f32) {
+    let Session { ref processing_stats,
+                  ref breakpoint,
+                  ref active_alarms,
+                  ref mut disabled_apids,
+                  ref router,
+                  ref mut inspect_apid,
+                  ref mut inspect_index,
+                  ref mut oversize_index,
+                  .. } = *session;
+    let packets_dropped = processing_stats.packets_dropped;
+
+    // the statistics table takes whatever space is left in the window, after
+    // leaving room for the sections below it.
+    let (_, avail_height) = ui.get_content_region_avail();
+    let height = (avail_height - reserved_height).max(frame_height(ui, STATS_MIN_LINES));
+
+    ui.child_frame(im_str!("Apid Statistics"), (0.0, height))
         .show_borders(true)
         .collapsible(true)
         .show_scrollbar(true)
@@ -861,6 +4558,21 @@ fn packet_statistics_ui(ui: &Ui, processing_stats: &ProcessingStats, app_state:
 
             ui.same_line(0.0);
             ui.text(format!("Packets Dropped: {:>4}", packets_dropped));
+            if ui.is_item_hovered() && !processing_stats.drop_reasons.is_empty() {
+                ui.tooltip_text(&ImString::new(drop_reasons_text(processing_stats)));
+            }
+
+            let mut open_oversize = false;
+            if !processing_stats.oversize_captures.is_empty() {
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Oversize...")) {
+                    *oversize_index = 0;
+                    open_oversize = true;
+                }
+                if ui.is_item_hovered() {
+                    ui.tooltip_text(im_str!("View the headers of packets dropped for being longer than the maximum size"));
+                }
+            }
 
             ui.same_line(0.0);
             ui.text(format!("Packets Per Second: {:>4}", processing_stats.packets_per_second));
@@ -868,9 +4580,46 @@ fn packet_statistics_ui(ui: &Ui, processing_stats: &ProcessingStats, app_state:
             ui.same_line(0.0);
             ui.text(format!("Bytes Per Second: {:>4}", processing_stats.bytes_per_second));
 
+            ui.same_line(0.0);
+            ui.push_item_width(60.0);
+            ui.input_float(im_str!("Stale (s)"), stale_timeout).build();
+            ui.pop_item_width();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Seconds without a packet before an APID is shown as stale (0 to disable)"));
+            }
+            *stale_timeout = stale_timeout.max(0.0);
+
+            // the APIDs not forwarded can be shared with other sessions and configurations
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Import Blocked")) {
+                if let Some(apids) = import_apid_list("Import Blocked APIDs") {
+                    *disabled_apids = apids;
+                    router.send(ProcessingMsg::UpdateFilter(disabled_apids.clone())).unwrap();
+                }
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Stop forwarding the APIDs listed in a text or CSV file"));
+            }
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Export Blocked")) {
+                export_apid_list("Export Blocked APIDs", disabled_apids);
+            }
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Save the APIDs that are not forwarded to a text or CSV file"));
+            }
+            let stale_duration = Duration::new(*stale_timeout as u64, (stale_timeout.fract() * 1_000_000_000.0) as u32);
+
+            if let Some((apid, ref description)) = *breakpoint {
+                ui.text_colored(BREAKPOINT_COLOR, &ImString::new(format!("Paused on '{}' (APID {})", description, apid)));
+            }
+
+            for description in active_alarms.values() {
+                ui.text_colored(GAP_COLOR, &ImString::new(format!("Rate alarm: {}", description)));
+            }
+
             ui.separator();
 
-            ui.columns(5, im_str!("PacketStats"), true);
+            ui.columns(7, im_str!("PacketStats"), true);
 
             ui.text("       Apid: ");
             ui.next_column();
@@ -881,28 +4630,95 @@ fn packet_statistics_ui(ui: &Ui, processing_stats: &ProcessingStats, app_state:
             ui.text("   Byte Len:");
             ui.next_column();
             ui.text("   Last Seq:");
+            ui.next_column();
+            ui.text("  Timestamp:");
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Timestamp of the last packet, decoded using the Replay time settings"));
+            }
+            ui.next_column();
+            ui.text(" Forward:");
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Enable or disable forwarding of each APID while processing"));
+            }
             ui.separator();
 
+            let mut open_inspector = false;
             for packet_stats in processing_stats.packet_history.values() {
+                // stale APIDs are shown in grey, and APIDs with sequence gaps in red
+                let row_color;
+                if *stale_timeout > 0.0 && packet_stats.is_stale(stale_duration) {
+                    row_color = Some(STALE_COLOR);
+                } else if packet_stats.gap_count > 0 {
+                    row_color = Some(GAP_COLOR);
+                } else {
+                    row_color = None;
+                }
+
                 ui.next_column();
-                ui.text(format!("      {:>5}", &packet_stats.apid.to_string()));
+                let apid_text = format!("      {:>5}", &packet_stats.apid.to_string());
+                match *breakpoint {
+                    Some((apid, _)) if apid == packet_stats.apid => {
+                        ui.text_colored(BREAKPOINT_COLOR, &ImString::new(apid_text));
+                    },
+
+                    _ => stats_text(ui, row_color, apid_text),
+                }
                 packet_summary_ui(ui, &packet_stats);
 
                 ui.next_column();
-                ui.text(format!("    {:>5}", packet_stats.packet_count.to_string()));
+                stats_text(ui, row_color, format!("    {:>5}", packet_stats.packet_count.to_string()));
                 packet_summary_ui(ui, &packet_stats);
 
                 ui.next_column();
-                ui.text(format!("  {:>9}", &packet_stats.byte_count.to_string()));
+                stats_text(ui, row_color, format!("  {:>9}", &packet_stats.byte_count.to_string()));
                 packet_summary_ui(ui, &packet_stats);
 
                 ui.next_column();
-                ui.text(format!("    {:>5}", &packet_stats.last_len.to_string()));
+                stats_text(ui, row_color, format!("    {:>5}", &packet_stats.last_len.to_string()));
                 packet_summary_ui(ui, &packet_stats);
 
                 ui.next_column();
-                ui.text(format!("    {:>5}", &packet_stats.last_seq.to_string()));
+                stats_text(ui, row_color, format!("    {:>5}", &packet_stats.last_seq.to_string()));
                 packet_summary_ui(ui, &packet_stats);
+
+                ui.next_column();
+                match timestamp_def.decode_fields(&packet_stats.bytes) {
+                    Some((secs, subsecs)) => {
+                        let timestamp = timestamp_def.decode(&packet_stats.bytes);
+                        let timestamp_text = timestamp_def.epoch
+                                                          .format_utc(timestamp.as_fractional_secs())
+                                                          .unwrap_or_else(|| format_timestamp(timestamp));
+                        stats_text(ui, row_color, timestamp_text);
+                        if ui.is_item_hovered() {
+                            ui.tooltip_text(&ImString::new(format!("Seconds: {} Subseconds: {}", secs, subsecs)));
+                        }
+                    },
+
+                    None => stats_text(ui, row_color, "  --".to_string()),
+                }
+
+                ui.next_column();
+                let mut forward_enabled = !disabled_apids.contains(&packet_stats.apid);
+                let mut forward_changed = false;
+                ui.with_id(format!("ForwardApid{}", packet_stats.apid).as_str(), || {
+                    forward_changed = ui.checkbox(im_str!("##Forward"), &mut forward_enabled);
+                });
+                if forward_changed {
+                    if forward_enabled {
+                        info!("Enabled forwarding of APID {}", packet_stats.apid);
+                        disabled_apids.retain(|apid| *apid != packet_stats.apid);
+                    } else {
+                        info!("Disabled forwarding of APID {}", packet_stats.apid);
+                        disabled_apids.push(packet_stats.apid);
+                    }
+                    router.send(ProcessingMsg::UpdateFilter(disabled_apids.clone())).unwrap();
+                }
+
+                if packet_actions_ui(ui, &packet_stats) {
+                    *inspect_apid = Some(packet_stats.apid);
+                    *inspect_index = 0;
+                    open_inspector = true;
+                }
             }
 
             if processing_stats.packet_history.len() > 0 {
@@ -921,10 +4737,20 @@ fn packet_statistics_ui(ui: &Ui, processing_stats: &ProcessingStats, app_state:
 
                 ui.next_column();
             }
+
+            if open_inspector {
+                ui.open_popup(im_str!("Packet Inspector"));
+            }
+            packet_inspector_ui(ui, processing_stats, *inspect_apid, inspect_index, packet_history_length);
+
+            if open_oversize {
+                ui.open_popup(im_str!("Oversize Packets"));
+            }
+            oversize_packets_ui(ui, processing_stats, oversize_index);
         });
 }
 
-fn timestamp_def_ui(ui: &Ui, timestamp_def: &mut TimestampDef) {
+fn timestamp_def_ui(ui: &Ui, timestamp_def: &mut TimestampDef, imgui_str: &mut ImString) {
      ui.columns(2, im_str!("TimeDefinitions"), false);
     let mut num_bytes_selection = timestamp_def.num_bytes_seconds.to_num_bytes() as i32;
     ui.input_int(im_str!("Byte For Seconds"), &mut num_bytes_selection).build();
@@ -941,10 +4767,74 @@ fn timestamp_def_ui(ui: &Ui, timestamp_def: &mut TimestampDef) {
     ui.next_column();
     ui.input_float(im_str!("Subsec Resolution"), &mut timestamp_def.subsecond_resolution).build();
 
-    ui.next_column();
-    ui.checkbox(im_str!("Little Endian"), &mut timestamp_def.is_little_endian);
-    if ui.is_item_hovered() {
-        ui.tooltip_text(im_str!("Decode timestamp as Little Endian (default is Big Endian)"));
+    ui.next_column();
+    ui.checkbox(im_str!("Little Endian"), &mut timestamp_def.is_little_endian);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Decode timestamp as Little Endian (default is Big Endian)"));
+    }
+    ui.columns(1, im_str!("default"), false);
+
+    time_epoch_ui(ui, &mut timestamp_def.epoch, imgui_str);
+}
+
+fn time_epoch_ui(ui: &Ui, epoch: &mut TimeEpoch, imgui_str: &mut ImString) {
+    ui.text("Timestamp Epoch");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The epoch of the timestamps, used to show them as UTC times"));
+    }
+
+    let mut epoch_selection: i32 = match epoch {
+        TimeEpoch::Relative => 0,
+        TimeEpoch::Unix => 1,
+        TimeEpoch::Gps => 2,
+        TimeEpoch::Custom { .. } => 3,
+    };
+    let last_selection = epoch_selection;
+
+    ui.columns(4, im_str!("SelectTimeEpoch"), false);
+    ui.radio_button(im_str!("Relative"), &mut epoch_selection, 0);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The timestamps are shown as days and time, with no calendar date"));
+    }
+    ui.next_column();
+    ui.radio_button(im_str!("UNIX"), &mut epoch_selection, 1);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds since 1970-01-01T00:00:00Z, not counting leap seconds"));
+    }
+    ui.next_column();
+    ui.radio_button(im_str!("GPS"), &mut epoch_selection, 2);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds since 1980-01-06T00:00:00Z, counting leap seconds"));
+    }
+    ui.next_column();
+    ui.radio_button(im_str!("Custom"), &mut epoch_selection, 3);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Seconds since a mission epoch given as a UTC time"));
+    }
+    ui.columns(1, im_str!("default"), false);
+
+    if epoch_selection != last_selection {
+        *epoch = match epoch_selection {
+            1 => TimeEpoch::Unix,
+            2 => TimeEpoch::Gps,
+            3 => TimeEpoch::Custom { utc: "2000-01-01T11:58:55.816Z".to_string(), count_leap_seconds: true },
+            _ => TimeEpoch::Relative,
+        };
+    }
+
+    if let TimeEpoch::Custom { ref mut utc, ref mut count_leap_seconds } = *epoch {
+        input_string(ui, im_str!("Epoch (UTC)"), utc, imgui_str);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The UTC time of the epoch, such as 2000-01-01T11:58:55.816Z for J2000"));
+        }
+        if let Err(err) = parse_utc(utc) {
+            ui.text_colored(GAP_COLOR, &ImString::new(err));
+        }
+
+        ui.checkbox(im_str!("Count Leap Seconds"), count_leap_seconds);
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The mission clock counts leap seconds, as TAI and GPS time do"));
+        }
     }
 }
 
@@ -956,14 +4846,69 @@ fn input_string(ui: &Ui, label: &ImStr, string: &mut String, imgui_str: &mut ImS
     string.push_str(&imgui_str.to_str());
 }
 
+/// The kind of file dialog to use when browsing for a file name
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum FileDialog {
+    /// Choose an existing file
+    Open,
+    /// Choose a file to write, which may not exist yet
+    Save,
+}
+
+fn input_file_name(ui: &Ui, label: &ImStr, file_name: &mut String, imgui_str: &mut ImString, file_dialog: FileDialog) {
+    let mut browse = false;
+    ui.with_id(label.to_str(), || {
+        browse = ui.small_button(im_str!("Browse"));
+    });
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Choose a file"));
+    }
+    ui.same_line(0.0);
+    input_string(ui, label, file_name, imgui_str);
+
+    if browse {
+        let chosen = match file_dialog {
+            FileDialog::Open => tinyfiledialogs::open_file_dialog(label.to_str(), file_name, None),
+
+            FileDialog::Save => tinyfiledialogs::save_file_dialog(label.to_str(), file_name),
+        };
+
+        if let Some(chosen_file_name) = chosen {
+            *file_name = chosen_file_name;
+        }
+    }
+}
+
+/// Check a configuration for problems that would stop processing before starting it
+fn check_start_config(config: &AppConfig) -> Result<(), String> {
+    let problems = config_problems(config);
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(problems.join("; "))
+    }
+}
+
 fn input_stream_ui(ui: &Ui,
                    selection: &mut StreamOption,
                    input_settings: &mut StreamSettings,
                    allowed_apids: &mut Option<Vec<u16>>,
+                   observed_apids: &BTreeSet<u16>,
+                   added_apid: &mut i32,
                    imgui_str: &mut ImString) {
+    input_stream_settings_ui(ui, selection, input_settings, imgui_str);
+
+    filter_apids_ui(ui, allowed_apids, observed_apids, added_apid);
+}
+
+fn input_stream_settings_ui(ui: &Ui,
+                            selection: &mut StreamOption,
+                            input_settings: &mut StreamSettings,
+                            imgui_str: &mut ImString) {
     let mut input_selection: i32 = *selection as i32;
 
-    ui.columns(4, im_str!("SelectInputType"), false);
+    ui.columns(5, im_str!("SelectInputType"), false);
     ui.radio_button(im_str!("File"),       &mut input_selection, StreamOption::File as i32);
     ui.next_column();
     ui.radio_button(im_str!("UDP"),        &mut input_selection, StreamOption::Udp as i32);
@@ -971,19 +4916,29 @@ fn input_stream_ui(ui: &Ui,
     ui.radio_button(im_str!("TCP Client"), &mut input_selection, StreamOption::TcpClient as i32);
     ui.next_column();
     ui.radio_button(im_str!("TCP Server"), &mut input_selection, StreamOption::TcpServer as i32);
+    ui.next_column();
+    ui.radio_button(im_str!("Plugin"),     &mut input_selection, StreamOption::Plugin as i32);
 
     *selection = num::FromPrimitive::from_i32(input_selection).unwrap();
 
     ui.columns(1, im_str!("default"), false); match selection {
         StreamOption::File => {
             ui.text(im_str!("Select Input File Parameters:"));
-            input_string(&ui, im_str!("File Name"), &mut input_settings.file.file_name, imgui_str);
+            input_file_name(&ui, im_str!("File Name"), &mut input_settings.file.file_name, imgui_str, FileDialog::Open);
         },
 
         StreamOption::Udp => {
             ui.text(im_str!("Select Udp Socket Parameters:"));
             ui.columns(2, im_str!("UdpSocketCols"), false);
             ui_ip_port(ui, &mut input_settings.udp.ip, &mut input_settings.udp.port, imgui_str);
+            ui.next_column();
+
+            let mut max_datagram_bytes = input_settings.udp.max_datagram_bytes as i32;
+            ui.input_int(im_str!("Max Datagram (bytes)"), &mut max_datagram_bytes).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("The largest datagram read. Larger datagrams are truncated."));
+            }
+            input_settings.udp.max_datagram_bytes = min(max(max_datagram_bytes, 1) as usize, MAX_DATAGRAM_BYTES);
         },
 
         StreamOption::TcpClient => {
@@ -997,9 +4952,12 @@ fn input_stream_ui(ui: &Ui,
             ui.columns(2, im_str!("UdpSocketCols"), false);
             ui_ip_port(ui, &mut input_settings.tcp_server.ip, &mut input_settings.tcp_server.port, imgui_str);
         },
-    }
 
-    filter_apids_ui(ui, allowed_apids, imgui_str);
+        StreamOption::Plugin => {
+            ui.text(im_str!("Select Input Plugin Parameters:"));
+            ui_plugin(ui, &mut input_settings.plugin, &plugin_names().0, imgui_str);
+        },
+    }
 }
 
 fn ui_ip_port(ui: &Ui, ip: &mut String, port: &mut u16, imgui_str: &mut ImString) {
@@ -1008,10 +4966,205 @@ fn ui_ip_port(ui: &Ui, ip: &mut String, port: &mut u16, imgui_str: &mut ImString
     input_port(ui, &mut im_str!("Port"), port);
 }
 
+fn output_file_buffering_ui(ui: &Ui, file_settings: &mut FileSettings) {
+    ui.columns(3, im_str!("OutputFileBuffering"), false);
+
+    let mut buffer_bytes = file_settings.buffer_bytes as i32;
+    ui.input_int(im_str!("Buffer (bytes)"), &mut buffer_bytes).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Bytes buffered before writing to the file, or 0 to write each packet"));
+    }
+    file_settings.buffer_bytes = max(buffer_bytes, 0) as usize;
+    ui.next_column();
+
+    let mut flush_packets = file_settings.flush_packets as i32;
+    ui.input_int(im_str!("Flush Packets"), &mut flush_packets).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write the buffer to the file after this many packets, or 0 to disable"));
+    }
+    file_settings.flush_packets = max(flush_packets, 0) as u32;
+    ui.next_column();
+
+    let mut flush_ms = file_settings.flush_ms as i32;
+    ui.input_int(im_str!("Flush (ms)"), &mut flush_ms).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write the buffer to the file once this many milliseconds have passed, or 0 to disable"));
+    }
+    file_settings.flush_ms = max(flush_ms, 0) as u32;
+
+    ui.columns(1, im_str!("default"), false);
+
+    ui.checkbox(im_str!("Metadata Sidecar"), &mut file_settings.metadata_sidecar);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write a .meta.jsonl file next to the output file, describing each packet written to it"));
+    }
+
+    let mut text_output = file_settings.format == FileFormat::Text;
+    ui.same_line(0.0);
+    ui.checkbox(im_str!("Text Output"), &mut text_output);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write a line of text per packet, rather than the packet's bytes. A file name of '-' writes to stdout."));
+    }
+    file_settings.format = if text_output { FileFormat::Text } else { FileFormat::Binary };
+
+    if text_output {
+        let mut text_payload_bytes = file_settings.text_payload_bytes as i32;
+        ui.same_line(0.0);
+        ui.push_item_width(100.0);
+        ui.input_int(im_str!("Payload Bytes Shown"), &mut text_payload_bytes).build();
+        ui.pop_item_width();
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("The payload bytes shown in hex on each line"));
+        }
+        file_settings.text_payload_bytes = max(text_payload_bytes, 0) as usize;
+    }
+
+    let if_exists_names = [im_str!("Ask"), im_str!("Overwrite"), im_str!("New Name")];
+    let mut if_exists_selection: i32 = match file_settings.if_exists {
+        FileExistsPolicy::Ask => 0,
+        FileExistsPolicy::Overwrite => 1,
+        FileExistsPolicy::NewName => 2,
+    };
+    ui.push_item_width(100.0);
+    ui.combo(im_str!("If File Exists"), &mut if_exists_selection, &if_exists_names, 3);
+    ui.pop_item_width();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Whether an existing file is overwritten, or a new file is written with a numeric suffix. The file name can include {time} and {session}."));
+    }
+    file_settings.if_exists = match if_exists_selection {
+        1 => FileExistsPolicy::Overwrite,
+        2 => FileExistsPolicy::NewName,
+        _ => FileExistsPolicy::Ask,
+    };
+}
+
+fn container_format_ui(ui: &Ui, container_format: &mut ContainerFormat) {
+    ui.text("Input Container");
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The recorder format whose records are unwrapped before parsing the packets within them"));
+    }
+
+    let mut format_selection: i32 = match container_format {
+        ContainerFormat::Raw => 0,
+        ContainerFormat::LengthPrefixed(_) => 1,
+        ContainerFormat::Sfdu(_) => 2,
+        ContainerFormat::HexText => 3,
+    };
+    let last_selection = format_selection;
+
+    ui.columns(4, im_str!("SelectContainerFormat"), false);
+    ui.radio_button(im_str!("Raw"), &mut format_selection, 0);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The input is a stream of packets"));
+    }
+    ui.next_column();
+    ui.radio_button(im_str!("Length Prefixed"), &mut format_selection, 1);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Each record has a header with a length field"));
+    }
+    ui.next_column();
+    ui.radio_button(im_str!("SFDU"), &mut format_selection, 2);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Each record is an SFDU, as written by ITOS and the DSN"));
+    }
+    ui.next_column();
+    ui.radio_button(im_str!("Hex Text"), &mut format_selection, 3);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The input is hex text, such as a pasted packet dump, converted to bytes line by line"));
+    }
+    ui.columns(1, im_str!("default"), false);
+
+    if format_selection != last_selection {
+        *container_format = match format_selection {
+            1 => ContainerFormat::LengthPrefixed(Default::default()),
+            2 => ContainerFormat::Sfdu(Default::default()),
+            3 => ContainerFormat::HexText,
+            _ => ContainerFormat::Raw,
+        };
+    }
+
+    match container_format {
+        ContainerFormat::Raw | ContainerFormat::HexText => {},
+
+        ContainerFormat::LengthPrefixed(settings) => {
+            ui.columns(3, im_str!("LengthPrefixedSettings"), false);
+
+            let mut length_offset = settings.length_offset as i32;
+            ui.input_int(im_str!("Length Offset"), &mut length_offset).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Bytes of the record header before the length field"));
+            }
+            settings.length_offset = max(length_offset, 0) as usize;
+            ui.next_column();
+
+            let mut length_bytes = settings.length_bytes as i32;
+            ui.input_int(im_str!("Length Bytes"), &mut length_bytes).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Size of the length field: 1, 2, or 4 bytes"));
+            }
+            settings.length_bytes = max(length_bytes, 1) as usize;
+            ui.next_column();
+
+            ui.checkbox(im_str!("Little Endian Length"), &mut settings.little_endian);
+            ui.next_column();
+
+            let mut header_bytes = settings.header_bytes as i32;
+            ui.input_int(im_str!("Record Header"), &mut header_bytes).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Bytes of the record header, including the length field"));
+            }
+            settings.header_bytes = max(header_bytes, 0) as usize;
+            ui.next_column();
+
+            let mut footer_bytes = settings.footer_bytes as i32;
+            ui.input_int(im_str!("Record Footer"), &mut footer_bytes).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Bytes of the record footer, which are dropped"));
+            }
+            settings.footer_bytes = max(footer_bytes, 0) as usize;
+            ui.next_column();
+
+            let mut length_adjust = settings.length_adjust as i32;
+            ui.input_int(im_str!("Length Adjust"), &mut length_adjust).build();
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Bytes added to the length field to give the length of the whole record"));
+            }
+            settings.length_adjust = length_adjust as i64;
+
+            ui.columns(1, im_str!("default"), false);
+        },
+
+        ContainerFormat::Sfdu(settings) => {
+            ui.checkbox(im_str!("DSN Data CHDO"), &mut settings.data_chdo);
+            if ui.is_item_hovered() {
+                ui.tooltip_text(im_str!("Parse only the data CHDO of each SFDU, skipping the DSN header CHDOs"));
+            }
+        },
+    }
+}
+
+// The plugin's settings are given in the configuration file, so only its name is edited here
+fn ui_plugin(ui: &Ui, plugin_settings: &mut PluginSettings, registered_names: &Vec<String>, imgui_str: &mut ImString) {
+    input_string(ui, im_str!("Plugin Name"), &mut plugin_settings.name, imgui_str);
+    if ui.is_item_hovered() {
+        if registered_names.is_empty() {
+            ui.tooltip_text(im_str!("No plugins are registered"));
+        } else {
+            ui.tooltip_text(&ImString::new(format!("Registered plugins: {}", registered_names.join(", "))));
+        }
+    }
+
+    for (name, value) in plugin_settings.parameters.iter() {
+        ui.text(format!("{} = {}", name, value));
+    }
+}
+
 fn output_stream_ui(ui: &Ui,
                     selection: &mut StreamOption,
                     output_settings: &mut StreamSettings,
                     allowed_output_apids: &mut Option<Vec<u16>>,
+                    observed_apids: &BTreeSet<u16>,
+                    added_apid: &mut i32,
                     imgui_str: &mut ImString) {
     let mut input_selection: i32 = *selection as i32;
 
@@ -1024,6 +5177,8 @@ fn output_stream_ui(ui: &Ui,
     ui.radio_button(im_str!("TCP Client"), &mut input_selection, StreamOption::TcpClient as i32);
     ui.next_column();
     ui.radio_button(im_str!("TCP Server"), &mut input_selection, StreamOption::TcpServer as i32);
+    ui.next_column();
+    ui.radio_button(im_str!("Plugin"),     &mut input_selection, StreamOption::Plugin as i32);
 
     *selection = num::FromPrimitive::from_i32(input_selection).unwrap();
 
@@ -1032,7 +5187,8 @@ fn output_stream_ui(ui: &Ui,
     match selection {
         StreamOption::File => {
             ui.text(im_str!("Select Input File Parameters:"));
-            input_string(&ui, im_str!("File Name"), &mut output_settings.file.file_name, imgui_str);
+            input_file_name(&ui, im_str!("File Name"), &mut output_settings.file.file_name, imgui_str, FileDialog::Save);
+            output_file_buffering_ui(ui, &mut output_settings.file);
         },
 
         StreamOption::Udp => {
@@ -1052,48 +5208,344 @@ fn output_stream_ui(ui: &Ui,
             ui.columns(2, im_str!("UdpSocketCols"), false);
             ui_ip_port(ui, &mut output_settings.tcp_server.ip, &mut output_settings.tcp_server.port, imgui_str);
         },
+
+        StreamOption::Plugin => {
+            ui.text(im_str!("Select Output Plugin Parameters:"));
+            ui_plugin(ui, &mut output_settings.plugin, &plugin_names().1, imgui_str);
+        },
+    }
+
+    ui.next_column();
+    filter_apids_ui(ui, allowed_output_apids, observed_apids, added_apid);
+
+    ui.columns(1, im_str!("default"), false);
+    output_rate_limit_ui(ui, &mut output_settings.rate_limit);
+
+    let mut open_lazily = output_settings.open_timing == OutputOpenTiming::Lazy;
+    ui.checkbox(im_str!("Open on First Packet"), &mut open_lazily);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Open this output when the first packet is forwarded to it, rather than when processing starts"));
+    }
+    output_settings.open_timing = if open_lazily { OutputOpenTiming::Lazy } else { OutputOpenTiming::Eager };
+
+    // a file output is not reopened once it fails, so it has nothing to store its packets for
+    if *selection != StreamOption::File {
+        output_store_forward_ui(ui, &mut output_settings.store_forward, imgui_str);
+    }
+
+    ui.checkbox(im_str!("Sync Marker"), &mut output_settings.sync_marker.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write a sync marker before each packet, or each frame of a frame output.\nThe marker is set by 'sync_marker' in the output's settings in the configuration file."));
+    }
+    if output_settings.sync_marker.enabled {
+        ui.same_line(0.0);
+        ui.text(format!("Marker: {}", format_hex_bytes(&output_settings.sync_marker.marker)));
+    }
+
+    // the frame settings share labels with the file buffering settings
+    ui.with_id("OutputFrame", || {
+        output_frame_ui(ui, &mut output_settings.frame_output);
+    });
+}
+
+fn output_frame_ui(ui: &Ui, frame_output: &mut FrameOutputSettings) {
+    ui.checkbox(im_str!("Frame Output"), &mut frame_output.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Pack the packets written to this output into fixed length TM or AOS transfer frames"));
+    }
+
+    if !frame_output.enabled {
+        return;
+    }
+
+    let mut frame_type_selection: i32 = match frame_output.frame_type {
+        FrameType::Tm => 0,
+        FrameType::Aos => 1,
+    };
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("TM"), &mut frame_type_selection, 0);
+    ui.same_line(0.0);
+    ui.radio_button(im_str!("AOS"), &mut frame_type_selection, 1);
+    frame_output.frame_type = if frame_type_selection == 1 { FrameType::Aos } else { FrameType::Tm };
+    ui.same_line(0.0);
+    ui.checkbox(im_str!("FECF"), &mut frame_output.fecf);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("End each frame with a CRC-16 frame error control field"));
+    }
+    ui.same_line(0.0);
+    ui.checkbox(im_str!("Split Packets"), &mut frame_output.split_packets);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Split packets that do not fit in the rest of a frame across frames.\nOtherwise the frame is completed with an idle packet, and the packet starts the next frame."));
+    }
+
+    ui.columns(5, im_str!("OutputFrameSettings"), false);
+
+    let mut spacecraft_id = frame_output.spacecraft_id as i32;
+    ui.input_int(im_str!("SCID"), &mut spacecraft_id).build();
+    frame_output.spacecraft_id = min(max(spacecraft_id, 0), 0x3FF) as u16;
+    ui.next_column();
+
+    let mut vcid = frame_output.vcid as i32;
+    ui.input_int(im_str!("VCID"), &mut vcid).build();
+    frame_output.vcid = min(max(vcid, 0), 63) as u8;
+    ui.next_column();
+
+    let mut frame_bytes = frame_output.frame_bytes as i32;
+    ui.input_int(im_str!("Frame (bytes)"), &mut frame_bytes).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The length of each frame, including its headers and frame error control field"));
+    }
+    frame_output.frame_bytes = min(max(frame_bytes, 0) as usize, MAX_FRAME_BYTES);
+    ui.next_column();
+
+    let mut flush_ms = frame_output.flush_ms as i32;
+    ui.input_int(im_str!("Flush (ms)"), &mut flush_ms).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Complete a partly filled frame with an idle packet once it has waited this long for more packets, or 0 to wait until it is full"));
     }
+    frame_output.flush_ms = max(flush_ms, 0) as u32;
+    ui.next_column();
+
+    let mut idle_ms = frame_output.idle_ms as i32;
+    ui.input_int(im_str!("Idle (ms)"), &mut idle_ms).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Write an idle frame once no frame has been written for this long, or 0 to not write idle frames"));
+    }
+    frame_output.idle_ms = max(idle_ms, 0) as u32;
+
+    ui.columns(1, im_str!("default"), false);
+
+    for problem in frame_output.problems() {
+        ui.text_colored(GAP_COLOR, &ImString::new(problem));
+    }
+}
+
+fn output_store_forward_ui(ui: &Ui, store_forward: &mut StoreForwardSettings, imgui_str: &mut ImString) {
+    ui.checkbox(im_str!("Store and Forward"), &mut store_forward.enabled);
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Keep the packets for this output in a backlog file while it is down, and write them once it reconnects"));
+    }
+
+    if !store_forward.enabled {
+        return;
+    }
+
+    ui.same_line(0.0);
+    ui.push_item_width(200.0);
+    input_string(ui, im_str!("Backlog Directory"), &mut store_forward.directory, imgui_str);
+    ui.pop_item_width();
 
+    ui.columns(2, im_str!("OutputStoreForward"), false);
+
+    let mut max_backlog_bytes = min(store_forward.max_backlog_bytes, i32::max_value() as u64) as i32;
+    ui.input_int(im_str!("Max Backlog (bytes)"), &mut max_backlog_bytes).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The largest the backlog file can grow. Packets that do not fit are dropped."));
+    }
+    store_forward.max_backlog_bytes = max(max_backlog_bytes, 0) as u64;
+    ui.next_column();
+
+    let mut drain_bytes_per_sec = min(store_forward.drain_bytes_per_sec, i32::max_value() as u64) as i32;
+    ui.input_int(im_str!("Drain Rate (bytes/s)"), &mut drain_bytes_per_sec).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The most bytes of the backlog written per second once the output reconnects, or 0 for as fast as it accepts them"));
+    }
+    store_forward.drain_bytes_per_sec = max(drain_bytes_per_sec, 0) as u64;
+
+    ui.columns(1, im_str!("default"), false);
+}
+
+fn output_rate_limit_ui(ui: &Ui, rate_limit: &mut RateLimitSettings) {
+    ui.columns(2, im_str!("OutputRateLimit"), false);
+
+    let mut bytes_per_sec = min(rate_limit.bytes_per_sec, i32::max_value() as u64) as i32;
+    ui.input_int(im_str!("Rate Limit (bytes/s)"), &mut bytes_per_sec).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The most bytes written to this output per second, or 0 for no limit"));
+    }
+    rate_limit.bytes_per_sec = max(bytes_per_sec, 0) as u64;
     ui.next_column();
-    filter_apids_ui(ui, allowed_output_apids, imgui_str);
+
+    let mut max_queued_bytes = min(rate_limit.max_queued_bytes, i32::max_value() as u64) as i32;
+    ui.input_int(im_str!("Max Queued (bytes)"), &mut max_queued_bytes).build();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("The most bytes queued while waiting for the rate limit. Packets that do not fit are dropped."));
+    }
+    rate_limit.max_queued_bytes = max(max_queued_bytes, 0) as u64;
+
+    ui.columns(1, im_str!("default"), false);
 }
 
-fn filter_apids_ui(ui: &Ui, allowed_apids: &mut Option<Vec<u16>>, imgui_str: &mut ImString) {
+// The APIDs allowed by a filter are chosen from the APIDs seen while processing, along with
+// the APIDs already allowed and any added by number, so a filter can be built by checking them
+fn filter_apids_ui(ui: &Ui, allowed_apids: &mut Option<Vec<u16>>, observed_apids: &BTreeSet<u16>, added_apid: &mut i32) {
     let mut filter_apids = allowed_apids.is_some();
 
     ui.checkbox(im_str!("Filter APIDs"), &mut filter_apids);
-    let mut apid_list;
-    if allowed_apids.is_none() {
-        apid_list = Vec::new();
-    } else {
-        apid_list = allowed_apids.clone().unwrap();
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Allow only the checked APIDs"));
     }
 
     if filter_apids {
-        let mut apid_list_str: String = "".to_string();
-        for apid in  apid_list.iter() {
-            apid_list_str.push_str(&apid.to_string());
-            apid_list_str.push(',');
+        let mut apid_list = allowed_apids.take().unwrap_or_default();
+
+        let mut listed_apids = observed_apids.clone();
+        listed_apids.extend(apid_list.iter().cloned());
+
+        if listed_apids.is_empty() {
+            ui.text("No APIDs have been seen yet. Start processing to list them, or add them by number.");
         }
-        input_string(&ui, im_str!("Allowed APIDs"), &mut apid_list_str, imgui_str);
-        apid_list.clear();
-        for apid_str in apid_list_str.split(",") {
-            apid_str.parse().map(|apid| apid_list.push(apid));
+
+        for (index, apid) in listed_apids.iter().enumerate() {
+            if index % APID_EDITOR_COLUMNS != 0 {
+                ui.same_line(0.0);
+            }
+
+            let mut allowed = apid_list.contains(apid);
+            if ui.checkbox(&ImString::new(format!("{:>4}", apid)), &mut allowed) {
+                if allowed {
+                    apid_list.push(*apid);
+                } else {
+                    apid_list.retain(|allowed_apid| allowed_apid != apid);
+                }
+            }
+            if ui.is_item_hovered() && !observed_apids.contains(apid) {
+                ui.tooltip_text(im_str!("This APID has not been seen while processing"));
+            }
+        }
+
+        if ui.small_button(im_str!("All")) {
+            apid_list = listed_apids.iter().cloned().collect();
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("None")) {
+            apid_list.clear();
+        }
+        ui.same_line(0.0);
+        ui.push_item_width(80.0);
+        ui.input_int(im_str!("##AddApid"), added_apid).build();
+        ui.pop_item_width();
+        *added_apid = max(0, min(*added_apid, MAX_APID as i32));
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Add APID")) {
+            let apid = *added_apid as u16;
+            if !apid_list.contains(&apid) {
+                apid_list.push(apid);
+            }
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Allow an APID that has not been seen while processing"));
         }
+
+        apid_list.sort();
         *allowed_apids = Some(apid_list);
     } else {
         *allowed_apids = None;
     }
+
+    // an imported list replaces the allowed APIDs, and turns the filter on
+    if ui.small_button(im_str!("Import APIDs")) {
+        if let Some(apids) = import_apid_list("Import Allowed APIDs") {
+            *allowed_apids = Some(apids);
+        }
+    }
+    if ui.is_item_hovered() {
+        ui.tooltip_text(im_str!("Allow only the APIDs listed in a text or CSV file"));
+    }
+    if let Some(ref apids) = *allowed_apids {
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Export APIDs")) {
+            export_apid_list("Export Allowed APIDs", apids);
+        }
+        if ui.is_item_hovered() {
+            ui.tooltip_text(im_str!("Save the allowed APIDs to a text or CSV file"));
+        }
+    }
+}
+
+fn set_theme(imgui: &mut ImGui,
+             theme: GuiTheme,
+             custom_theme: Option<&CustomTheme>,
+             scale: f32,
+             default_sizes: &StyleSizes) {
+    default_sizes.restore(imgui.style_mut());
+
+    match (theme, custom_theme) {
+        (GuiTheme::Custom, Some(custom_theme)) => {
+            set_base_style(imgui.style_mut(), custom_theme.base);
+            apply_theme_colors(imgui.style_mut(), &custom_theme.colors);
+        },
+
+        _ => {
+            set_base_style(imgui.style_mut(), theme);
+        },
+    }
+
+    scale_style(imgui.style_mut(), scale);
+    imgui.set_font_global_scale(scale);
+}
+
+// Set one of the built in styles. A custom theme that does not exist
+// uses the dark theme.
+fn set_base_style(style: &mut ImGuiStyle, theme: GuiTheme) {
+    match theme {
+        GuiTheme::Light => {
+            set_style_light(style);
+        },
+
+        GuiTheme::Dark | GuiTheme::Custom => {
+            set_style_dark(style);
+        },
+    }
+}
+
+fn load_gui_layout() -> Option<GuiLayout> {
+    let mut file = File::open(GUI_LAYOUT_FILE).ok()?;
+
+    let mut layout_str = String::new();
+    file.read_to_string(&mut layout_str).ok()?;
+
+    serde_json::from_str(&layout_str).ok()
 }
 
-fn load_config(file_name: &String) -> Option<AppConfig> {
-    let mut file = File::open(file_name).ok()?;
+fn save_gui_layout(layout: &GuiLayout) {
+    let result = File::create(GUI_LAYOUT_FILE)
+                 .map_err(|err| err.to_string())
+                 .and_then(|mut file| {
+                     let layout_str = serde_json::to_string_pretty(layout).map_err(|err| err.to_string())?;
+                     file.write_all(layout_str.as_bytes()).map_err(|err| err.to_string())
+                 });
+
+    if let Err(err) = result {
+        warn!("Could not save GUI layout to {}: {}", GUI_LAYOUT_FILE, err);
+    }
+}
+
+// Load a configuration file, using the settings of a profile if one is given. A file that does
+// not exist gives None, while a file that can not be read or parsed, or that has unknown fields,
+// gives a description of each problem.
+fn load_config(file_name: &String, profile: &Option<String>) -> Result<Option<AppConfig>, String> {
+    if !Path::new(file_name).exists() {
+        return Ok(None);
+    }
 
     let mut config_str = String::new();
+    File::open(file_name).and_then(|mut file| file.read_to_string(&mut config_str))
+                         .map_err(|err| format!("Could not read {}: {}", file_name, err))?;
+
+    check_config(file_name, &config_str, profile.as_ref().map(|profile| profile.as_str())).map(|config| Some(config))
+                                        .map_err(|problems| problems.join("\n"))
+}
+
+// The configuration used when there is no configuration file
+fn default_config() -> AppConfig {
+    let mut config: AppConfig = Default::default();
 
-    file.read_to_string(&mut config_str).unwrap();
+    // the default max length is 0xFFFF in the length field, 
+    // plus the size of a CCSDS Primary header, plus 1.
+    config.max_length_bytes = 65535 + 6 + 1;
 
-    serde_json::from_str(&config_str).ok()
+    config
 }
 
 fn save_config(config: &AppConfig, config_file_name: &String) {
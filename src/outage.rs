@@ -0,0 +1,146 @@
+use std::time::{Duration, SystemTime};
+
+use floating_duration::TimeAsFloat;
+
+use schedule::*;
+
+
+/// The outage schedule simulates a link that goes down, as it does at a ground station handover
+/// or loss of signal, by dropping everything that would be written to the outputs during each
+/// outage. This tests the store and forward of the systems downstream.
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct OutageSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The seconds from the start of one repeating outage to the start of the next, or 0 for no
+    /// repeating outages
+    #[serde(default)]
+    pub period_secs: f32,
+
+    /// The seconds each repeating outage lasts
+    #[serde(default)]
+    pub duration_secs: f32,
+
+    /// The seconds from when processing starts until the first repeating outage
+    #[serde(default)]
+    pub first_secs: f32,
+
+    /// Outages at given times, along with any repeating outages
+    #[serde(default)]
+    pub windows: Vec<OutageWindow>,
+}
+
+/// A single outage at a given time
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct OutageWindow {
+    /// When the outage starts, as a UTC time such as '2020-01-01T12:00:00Z', or a time after
+    /// processing starts, such as '+90' or '+00:01:30'
+    #[serde(default)]
+    pub start: String,
+
+    /// The seconds the outage lasts
+    #[serde(default)]
+    pub duration_secs: f32,
+}
+
+impl OutageSettings {
+    /// A description of each problem with the outage schedule
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.enabled {
+            return problems;
+        }
+
+        if !(self.period_secs >= 0.0) {
+            problems.push(format!("The outage period must not be negative, but is {} seconds", self.period_secs));
+        } else if self.period_secs > 0.0 {
+            if !(self.duration_secs > 0.0) {
+                problems.push(format!("Each repeating outage must last more than 0 seconds, but lasts {} seconds", self.duration_secs));
+            } else if self.duration_secs >= self.period_secs {
+                problems.push(format!("Each repeating outage lasts {} seconds, so the {} second outage period would never have the link up",
+                                      self.duration_secs,
+                                      self.period_secs));
+            }
+
+            if !(self.first_secs >= 0.0) {
+                problems.push(format!("The time until the first outage must not be negative, but is {} seconds", self.first_secs));
+            }
+        }
+
+        for (index, window) in self.windows.iter().enumerate() {
+            match parse_schedule_time(&window.start) {
+                Ok(Some(_)) => {},
+
+                Ok(None) => problems.push(format!("Outage {} has no start time", index + 1)),
+
+                Err(problem) => problems.push(format!("The start time of outage {} is not valid: {}", index + 1, problem)),
+            }
+
+            if !(window.duration_secs > 0.0) {
+                problems.push(format!("Outage {} must last more than 0 seconds, but lasts {} seconds", index + 1, window.duration_secs));
+            }
+        }
+
+        if !(self.period_secs > 0.0) && self.windows.is_empty() {
+            problems.push("The outage schedule is enabled, but has no repeating outages or outages at given times".to_string());
+        }
+
+        problems
+    }
+}
+
+/// The outages of a run of processing, with the times of each outage at a given time found from
+/// when processing started
+#[derive(Debug, Clone)]
+pub struct OutageSchedule {
+    started: SystemTime,
+
+    period_secs: f64,
+    duration_secs: f64,
+    first_secs: f64,
+
+    /// The start and end of each outage at a given time
+    windows: Vec<(SystemTime, SystemTime)>,
+}
+
+impl OutageSchedule {
+    /// The outages of the given settings, for processing started at the given time
+    pub fn new(settings: &OutageSettings, started: SystemTime) -> OutageSchedule {
+        let windows = settings.windows.iter().filter_map(|window| {
+            let start = parse_schedule_time(&window.start).ok()??.from(started);
+            let duration_secs = window.duration_secs.max(0.0) as f64;
+
+            Some((start, start + Duration::new(duration_secs as u64, (duration_secs.fract() * 1_000_000_000.0) as u32)))
+        }).collect();
+
+        OutageSchedule {
+            started: started,
+            period_secs: settings.period_secs as f64,
+            duration_secs: settings.duration_secs as f64,
+            first_secs: settings.first_secs.max(0.0) as f64,
+            windows: windows,
+        }
+    }
+
+    /// Whether the link is down at the given time
+    pub fn in_outage(&self, now: SystemTime) -> bool {
+        self.windows.iter().any(|(start, end)| *start <= now && now < *end) || self.in_repeating_outage(now)
+    }
+
+    fn in_repeating_outage(&self, now: SystemTime) -> bool {
+        if !(self.period_secs > 0.0) {
+            return false;
+        }
+
+        match now.duration_since(self.started) {
+            Ok(since_start) => {
+                let since_first_secs = since_start.as_fractional_secs() - self.first_secs;
+                since_first_secs >= 0.0 && since_first_secs % self.period_secs < self.duration_secs
+            },
+
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+
+use ccsds_primary_header::primary_header::*;
+use ccsds_primary_header::parser::{CcsdsParser, CcsdsParserConfig};
+
+use types::*;
+use stream::*;
+use processing::{parser_config, packet_from_bytes};
+
+
+/// The number of bytes read from a packet file at a time
+const READ_CHUNK_BYTES: usize = 4096;
+
+/// A PacketFileReader reads the packets of a file using the same parser settings as
+/// processing, for tools that work with packet files without routing them. Unlike
+/// processing, every APID is read, as the allowed input APIDs are not applied.
+pub struct PacketFileReader {
+    file_name: String,
+    reader: BufReader<File>,
+    parser: CcsdsParser,
+    parser_config: CcsdsParserConfig,
+    bytes_read: u64,
+
+    /// The bytes of a packet's frame that are not kept as part of the packet
+    removed_frame_bytes: u64,
+    at_end: bool,
+}
+
+impl PacketFileReader {
+    pub fn open(file_name: &str, config: &AppConfig) -> Result<PacketFileReader, String> {
+        let file = File::open(file_name)
+                        .map_err(|err| format!("Could not open {}: {}", file_name, err))?;
+
+        let parser_config = parser_config(config);
+
+        let mut removed_frame_bytes: u64 = 0;
+        if !parser_config.keep_sync {
+            removed_frame_bytes += parser_config.sync_bytes.len() as u64;
+        }
+        if !parser_config.keep_header {
+            removed_frame_bytes += parser_config.num_header_bytes as u64;
+        }
+        if !parser_config.keep_footer {
+            removed_frame_bytes += parser_config.num_footer_bytes as u64;
+        }
+
+        Ok(PacketFileReader {
+            file_name: file_name.to_string(),
+            reader: BufReader::new(file),
+            parser: CcsdsParser::with_config(parser_config.clone()),
+            parser_config: parser_config,
+            bytes_read: 0,
+            removed_frame_bytes: removed_frame_bytes,
+            at_end: false,
+        })
+    }
+
+    /// Read the next packet, along with the offset of its frame in the file. None is
+    /// returned at the end of the file.
+    pub fn next_packet(&mut self) -> Result<Option<(Packet, u64)>, String> {
+        loop {
+            if let Some(packet_bytes) = self.parser.pull_packet() {
+                // the packet's frame ends where the bytes remaining in the parser begin
+                let frame_length = packet_bytes.len() as u64 + self.removed_frame_bytes;
+                let offset = (self.bytes_read - self.parser.bytes.len() as u64).saturating_sub(frame_length);
+
+                let packet = packet_from_bytes(packet_bytes.freeze(), self.parser_config.little_endian_header);
+
+                // the remaining data starts a new parser, as when processing
+                let remaining_bytes = self.parser.bytes.take();
+                self.parser = CcsdsParser::with_config(self.parser_config.clone());
+                self.parser.bytes.extend_from_slice(&remaining_bytes);
+
+                return Ok(Some((packet, offset)));
+            }
+
+            if self.at_end {
+                return Ok(None);
+            }
+
+            // the parser skips bytes that do not start a packet, so a buffer larger then the
+            // largest packet that still has no packet can not be parsed
+            let max_packet_bytes = CCSDS_MAX_LENGTH as usize +
+                                   self.parser_config.sync_bytes.len() +
+                                   self.parser_config.num_header_bytes as usize +
+                                   self.parser_config.num_footer_bytes as usize;
+            if self.parser.bytes.len() > max_packet_bytes {
+                return Err(format!("{}: unrecoverable parsing error at offset {}",
+                                   self.file_name,
+                                   self.bytes_read - self.parser.bytes.len() as u64));
+            }
+
+            let mut chunk = [0; READ_CHUNK_BYTES];
+            let num_bytes_read = self.reader.read(&mut chunk)
+                                            .map_err(|err| format!("Could not read {}: {}", self.file_name, err))?;
+            if num_bytes_read == 0 {
+                self.at_end = true;
+            } else {
+                self.bytes_read += num_bytes_read as u64;
+                self.parser.bytes.extend_from_slice(&chunk[..num_bytes_read]);
+            }
+        }
+    }
+
+    /// The number of bytes read from the file so far
+    pub fn position(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// The number of bytes read that are not part of a packet yet, such as a partial
+    /// packet at the end of the file
+    pub fn unparsed_bytes(&self) -> u64 {
+        self.parser.bytes.len() as u64
+    }
+}
+
+/// A PacketFileWriter writes packets to a file, one after another
+pub struct PacketFileWriter {
+    pub file_name: String,
+    writer: BufWriter<File>,
+
+    /// The number of packets written so far
+    pub packet_count: u64,
+}
+
+impl PacketFileWriter {
+    pub fn create(file_name: &str) -> Result<PacketFileWriter, String> {
+        let file = File::create(file_name)
+                        .map_err(|err| format!("Could not create {}: {}", file_name, err))?;
+
+        Ok(PacketFileWriter {
+            file_name: file_name.to_string(),
+            writer: BufWriter::new(file),
+            packet_count: 0,
+        })
+    }
+
+    pub fn write_packet(&mut self, packet: &Packet) -> Result<(), String> {
+        self.writer.write_all(&packet.bytes)
+                   .map_err(|err| format!("Could not write {}: {}", self.file_name, err))?;
+        self.packet_count += 1;
+
+        Ok(())
+    }
+
+    /// Write any buffered packets to the file
+    pub fn finish(&mut self) -> Result<(), String> {
+        self.writer.flush()
+                   .map_err(|err| format!("Could not write {}: {}", self.file_name, err))
+    }
+}
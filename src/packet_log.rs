@@ -0,0 +1,158 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::time::SystemTime;
+
+use chrono::prelude::*;
+
+use ccsds_primary_header::primary_header::*;
+
+use floating_duration::TimeAsFloat;
+
+use types::*;
+use stream::*;
+
+
+const CSV_HEADER: &str = "recv_time,apid,seq_count,length,timestamp,outputs\n";
+
+/// A PacketTextFormat writes a packet as a line of text for an output written as text, giving
+/// the packet's UTC receive time, APID, sequence count, length, and the first bytes of its
+/// payload in hex, such as:
+///
+/// 2020-01-01T12:00:00.000000Z APID 100 SEQ 12 LEN 64 | 01 02 03 04 ...
+#[derive(Debug, Clone, PartialEq)]
+pub struct PacketTextFormat {
+    /// The payload bytes shown on each line
+    pub payload_bytes: usize,
+
+    pub little_endian_header: bool,
+}
+
+impl PacketTextFormat {
+    pub fn line(&self, apid: u16, packet_bytes: &[u8], recv_time: SystemTime) -> String {
+        let recv_time: DateTime<Utc> = recv_time.into();
+        let header_bytes = CCSDS_PRI_HEADER_SIZE_BYTES as usize;
+
+        // the sequence count is the second 16 bit word of the primary header
+        let seq = if packet_bytes.len() >= header_bytes {
+            let (high_byte, low_byte) = if self.little_endian_header {
+                (packet_bytes[3], packet_bytes[2])
+            } else {
+                (packet_bytes[2], packet_bytes[3])
+            };
+            (((high_byte as u16) << 8) | low_byte as u16) & CCSDS_SEQ_COUNT_MASK
+        } else {
+            0
+        };
+
+        let payload = &packet_bytes[packet_bytes.len().min(header_bytes)..];
+        let shown = &payload[..payload.len().min(self.payload_bytes)];
+        let mut payload_hex = shown.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<String>>().join(" ");
+        if shown.len() < payload.len() {
+            payload_hex.push_str(" ...");
+        }
+
+        format!("{} APID {} SEQ {} LEN {} | {}\n",
+                recv_time.format("%Y-%m-%dT%H:%M:%S%.6fZ"),
+                apid,
+                seq,
+                packet_bytes.len(),
+                payload_hex)
+    }
+}
+
+/// A PacketCsvLog writes one line per packet to a CSV file, separate from the
+/// application log, for analysis in a spreadsheet. Each line has the packet's
+/// receive time, APID, sequence count, length, embedded timestamp in seconds,
+/// and the outputs it was written to, separated by spaces.
+pub struct PacketCsvLog {
+    writer: BufWriter<File>,
+    file_name: String,
+    timestamp_def: TimestampDef,
+}
+
+impl PacketCsvLog {
+    /// Create the CSV file. If no file name is given, a file named by the session
+    /// and the current time is created in the logs directory, with a number added
+    /// if another session started in the same second has the same name.
+    pub fn create(csv_log_settings: &CsvLogSettings, session_name: &str, timestamp_def: &TimestampDef) -> Result<PacketCsvLog, String> {
+        let file_name;
+        if csv_log_settings.file_name.is_empty() {
+            file_name = unique_file_name(&format!("{}/ccsds_router_packets_{}_{}.csv",
+                                                  LOG_DIRECTORY,
+                                                  session_name,
+                                                  Local::now().format("%Y%m%d_%H_%M_%S")));
+        } else {
+            file_name = csv_log_settings.file_name.clone();
+        }
+
+        let csv_file = File::create(&file_name)
+                            .map_err(|err| format!("Could not create packet log {}: {}", file_name, err))?;
+
+        let mut csv_log = PacketCsvLog {
+            writer: BufWriter::new(csv_file),
+            file_name: file_name,
+            timestamp_def: timestamp_def.clone(),
+        };
+
+        csv_log.writer.write_all(CSV_HEADER.as_bytes())
+               .map_err(|err| format!("Could not write packet log {}: {}", csv_log.file_name, err))?;
+
+        Ok(csv_log)
+    }
+
+    /// Write the line for a packet. The output mask has bit N set if the packet
+    /// was written to output N.
+    pub fn log_packet(&mut self, packet: &Packet, recv_time: SystemTime, output_mask: u32) -> Result<(), String> {
+        let recv_time: DateTime<Local> = recv_time.into();
+        let timestamp_secs = self.timestamp_def.decode(&packet.bytes).as_fractional_secs();
+        let outputs = (0..32).filter(|index| output_mask & (1 << *index) != 0)
+                             .map(|index: u32| index.to_string())
+                             .collect::<Vec<String>>()
+                             .join(" ");
+
+        writeln!(self.writer,
+                 "{},{},{},{},{:.6},{}",
+                 recv_time.format("%Y-%m-%d %H:%M:%S%.6f"),
+                 packet.header.control.apid(),
+                 packet.header.sequence.sequence_count(),
+                 packet.bytes.len(),
+                 timestamp_secs,
+                 outputs)
+            .map_err(|err| format!("Could not write packet log {}: {}", self.file_name, err))
+    }
+}
+
+/// A RawCapture writes the bytes read from the input stream exactly as they were read, before
+/// they are unwrapped from a container or parsed, so that a stream the parser could not make
+/// sense of can be analyzed again offline.
+pub struct RawCapture {
+    writer: BufWriter<File>,
+    file_name: String,
+}
+
+impl RawCapture {
+    /// Create the capture file. If no file name is given, a file named by the current
+    /// time is created in the logs directory.
+    pub fn create(raw_capture_settings: &RawCaptureSettings) -> Result<RawCapture, String> {
+        let file_name;
+        if raw_capture_settings.file_name.is_empty() {
+            file_name = format!("{}/{}", LOG_DIRECTORY, Local::now().format("ccsds_router_raw_%Y%m%d_%H_%M_%S.bin"));
+        } else {
+            file_name = raw_capture_settings.file_name.clone();
+        }
+
+        let capture_file = File::create(&file_name)
+                                .map_err(|err| format!("Could not create raw capture {}: {}", file_name, err))?;
+
+        Ok(RawCapture {
+            writer: BufWriter::new(capture_file),
+            file_name: file_name,
+        })
+    }
+
+    /// Write the bytes of a read from the input stream
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer.write_all(bytes)
+            .map_err(|err| format!("Could not write raw capture {}: {}", self.file_name, err))
+    }
+}
@@ -0,0 +1,261 @@
+use std::collections::BTreeMap;
+use std::fs;
+#[cfg(feature = "parquet")]
+use std::fs::File;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "parquet")]
+use std::sync::Arc;
+
+use floating_duration::TimeAsFloat;
+
+#[cfg(feature = "parquet")]
+use parquet::column::writer::ColumnWriter;
+#[cfg(feature = "parquet")]
+use parquet::data_type::ByteArray;
+#[cfg(feature = "parquet")]
+use parquet::file::properties::WriterProperties;
+#[cfg(feature = "parquet")]
+use parquet::file::writer::{FileWriter, RowGroupWriter, SerializedFileWriter};
+#[cfg(feature = "parquet")]
+use parquet::schema::parser::parse_message_type;
+
+use ccsds_primary_header::primary_header::*;
+
+use types::*;
+use stream::*;
+
+
+/// The number of packets of an APID buffered before they are written to its table as a row group
+const PARQUET_ROW_GROUP_ROWS: usize = 10000;
+
+/// The schema of each APID's table. The header fields are decoded from the primary header, and
+/// the payload is the bytes following it.
+#[cfg(feature = "parquet")]
+const PACKET_SCHEMA: &str =
+    "message packet {
+        REQUIRED DOUBLE recv_time;
+        REQUIRED DOUBLE timestamp;
+        REQUIRED INT32 apid;
+        REQUIRED INT32 packet_type;
+        REQUIRED INT32 sec_header_flag;
+        REQUIRED INT32 seq_flags;
+        REQUIRED INT32 seq_count;
+        REQUIRED INT32 packet_length;
+        REQUIRED BINARY payload;
+    }";
+
+/// The Parquet archive writes the header fields and payload of each forwarded packet into a
+/// table per APID, so a test can be loaded directly into pandas.
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ParquetSettings {
+    pub enabled: bool,
+
+    /// The directory the tables are written to, as a file per APID named 'apid_<APID>.parquet'
+    pub directory: String,
+}
+
+/// The rows of a table not yet written, as a column of values per field
+#[derive(Default)]
+#[cfg_attr(not(feature = "parquet"), allow(dead_code))]
+struct PacketRows {
+    recv_time: Vec<f64>,
+    timestamp: Vec<f64>,
+    apid: Vec<i32>,
+    packet_type: Vec<i32>,
+    sec_header_flag: Vec<i32>,
+    seq_flags: Vec<i32>,
+    seq_count: Vec<i32>,
+    packet_length: Vec<i32>,
+    payload: Vec<Vec<u8>>,
+}
+
+impl PacketRows {
+    fn len(&self) -> usize {
+        self.recv_time.len()
+    }
+}
+
+#[cfg(feature = "parquet")]
+type TableWriter = SerializedFileWriter<File>;
+
+#[cfg(not(feature = "parquet"))]
+type TableWriter = ();
+
+/// The table of an APID's packets
+struct ApidTable {
+    file_name: String,
+    writer: TableWriter,
+    rows: PacketRows,
+}
+
+/// A ParquetArchive writes the packets forwarded while processing into Parquet files, one for
+/// each APID. The packets are buffered and written in row groups, so a table is only readable
+/// once the archive is closed.
+pub struct ParquetArchive {
+    directory: String,
+    timestamp_def: TimestampDef,
+    little_endian_header: bool,
+    tables: BTreeMap<u16, ApidTable>,
+}
+
+impl ParquetArchive {
+    /// Open the archive, creating its directory if it does not exist. The table of an APID is
+    /// created when its first packet is written.
+    pub fn open(parquet_settings: &ParquetSettings, timestamp_def: &TimestampDef, little_endian_header: bool) -> Result<ParquetArchive, String> {
+        if !cfg!(feature = "parquet") {
+            return Err("This build does not include the Parquet archive. Build with the 'parquet' feature to use it".to_string());
+        }
+
+        fs::create_dir_all(&parquet_settings.directory)
+           .map_err(|err| format!("Could not create Parquet archive directory {}: {}", parquet_settings.directory, err))?;
+
+        Ok(ParquetArchive {
+            directory: parquet_settings.directory.clone(),
+            timestamp_def: timestamp_def.clone(),
+            little_endian_header: little_endian_header,
+            tables: BTreeMap::new(),
+        })
+    }
+
+    /// Add a packet to the table of its APID
+    pub fn write_packet(&mut self, packet: &Packet, recv_time: SystemTime) -> Result<(), String> {
+        let bytes = &packet.bytes;
+        if bytes.len() < CCSDS_PRI_HEADER_SIZE_BYTES as usize {
+            return Ok(());
+        }
+
+        let control = self.header_word(bytes, 0);
+        let sequence = self.header_word(bytes, 1);
+        let apid = control & 0x7FF;
+
+        if !self.tables.contains_key(&apid) {
+            let file_name = Path::new(&self.directory).join(format!("apid_{}.parquet", apid))
+                                                      .to_string_lossy()
+                                                      .into_owned();
+            let writer = create_writer(&file_name)?;
+            self.tables.insert(apid, ApidTable { file_name: file_name, writer: writer, rows: Default::default() });
+        }
+
+        let timestamp = self.timestamp_def.decode(bytes).as_fractional_secs();
+        let table = self.tables.get_mut(&apid).unwrap();
+
+        table.rows.recv_time.push(recv_time.duration_since(UNIX_EPOCH).map(|since_epoch| since_epoch.as_fractional_secs()).unwrap_or(0.0));
+        table.rows.timestamp.push(timestamp);
+        table.rows.apid.push(apid as i32);
+        table.rows.packet_type.push(((control >> 12) & 1) as i32);
+        table.rows.sec_header_flag.push(((control >> 11) & 1) as i32);
+        table.rows.seq_flags.push((sequence >> 14) as i32);
+        table.rows.seq_count.push((sequence & CCSDS_SEQ_COUNT_MASK) as i32);
+        table.rows.packet_length.push(bytes.len() as i32);
+        table.rows.payload.push(bytes[CCSDS_PRI_HEADER_SIZE_BYTES as usize..].to_vec());
+
+        if table.rows.len() >= PARQUET_ROW_GROUP_ROWS {
+            write_row_group(&mut table.writer, &table.rows)
+                .map_err(|err| format!("Could not write Parquet table {}: {}", table.file_name, err))?;
+            table.rows = Default::default();
+        }
+
+        Ok(())
+    }
+
+    /// Write the remaining rows of each table and close them, returning the number of tables
+    pub fn close(self) -> Result<usize, String> {
+        let num_tables = self.tables.len();
+
+        for (_, mut table) in self.tables.into_iter() {
+            if table.rows.len() > 0 {
+                write_row_group(&mut table.writer, &table.rows)
+                    .map_err(|err| format!("Could not write Parquet table {}: {}", table.file_name, err))?;
+            }
+
+            close_writer(table.writer)
+                .map_err(|err| format!("Could not close Parquet table {}: {}", table.file_name, err))?;
+        }
+
+        Ok(num_tables)
+    }
+
+    /// A 16 bit word of the primary header
+    fn header_word(&self, bytes: &[u8], index: usize) -> u16 {
+        let (high_byte, low_byte) = if self.little_endian_header {
+            (bytes[index * 2 + 1], bytes[index * 2])
+        } else {
+            (bytes[index * 2], bytes[index * 2 + 1])
+        };
+
+        ((high_byte as u16) << 8) | low_byte as u16
+    }
+}
+
+#[cfg(feature = "parquet")]
+fn create_writer(file_name: &str) -> Result<TableWriter, String> {
+    let file = File::create(file_name)
+                    .map_err(|err| format!("Could not create Parquet table {}: {}", file_name, err))?;
+
+    let schema = parse_message_type(PACKET_SCHEMA).map_err(|err| format!("Parquet schema error: {}", err))?;
+    let properties = WriterProperties::builder().build();
+
+    SerializedFileWriter::new(file, Arc::new(schema), Arc::new(properties))
+        .map_err(|err| format!("Could not create Parquet table {}: {}", file_name, err))
+}
+
+#[cfg(not(feature = "parquet"))]
+fn create_writer(file_name: &str) -> Result<TableWriter, String> {
+    Err(format!("Could not create Parquet table {}: this build does not include the Parquet archive", file_name))
+}
+
+#[cfg(feature = "parquet")]
+fn write_row_group(writer: &mut TableWriter, rows: &PacketRows) -> Result<(), String> {
+    let mut row_group_writer = writer.next_row_group().map_err(|err| err.to_string())?;
+
+    let mut column_index = 0;
+    while let Some(mut column_writer) = row_group_writer.next_column().map_err(|err| err.to_string())? {
+        let written = match column_writer {
+            ColumnWriter::DoubleColumnWriter(ref mut typed_writer) => {
+                let values = if column_index == 0 { &rows.recv_time } else { &rows.timestamp };
+                typed_writer.write_batch(values, None, None)
+            },
+
+            ColumnWriter::Int32ColumnWriter(ref mut typed_writer) => {
+                let values = match column_index {
+                    2 => &rows.apid,
+                    3 => &rows.packet_type,
+                    4 => &rows.sec_header_flag,
+                    5 => &rows.seq_flags,
+                    6 => &rows.seq_count,
+                    _ => &rows.packet_length,
+                };
+                typed_writer.write_batch(values, None, None)
+            },
+
+            ColumnWriter::ByteArrayColumnWriter(ref mut typed_writer) => {
+                let values = rows.payload.iter().map(|payload| ByteArray::from(payload.clone())).collect::<Vec<ByteArray>>();
+                typed_writer.write_batch(&values, None, None)
+            },
+
+            _ => Ok(0),
+        };
+        written.map_err(|err| err.to_string())?;
+
+        row_group_writer.close_column(column_writer).map_err(|err| err.to_string())?;
+        column_index += 1;
+    }
+
+    writer.close_row_group(row_group_writer).map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn write_row_group(_writer: &mut TableWriter, _rows: &PacketRows) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn close_writer(mut writer: TableWriter) -> Result<(), String> {
+    writer.close().map(|_| ()).map_err(|err| err.to_string())
+}
+
+#[cfg(not(feature = "parquet"))]
+fn close_writer(_writer: TableWriter) -> Result<(), String> {
+    Ok(())
+}
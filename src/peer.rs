@@ -0,0 +1,66 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use floating_duration::TimeAsFloat;
+
+
+/// The longest time to wait for a peer router to connect, or to reply to a command
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Send a command to the control socket of a peer router, such as 'pause', returning an error
+/// if the peer can not be reached or does not accept the command. If the peer requires a token,
+/// it is given with 'auth' before the command.
+pub fn send_peer_command(address: &str, token: &str, command: &str) -> Result<(), String> {
+    let socket_addr = address.to_socket_addrs()
+                             .map_err(|err| format!("Could not resolve peer router address '{}': {}", address, err))?
+                             .next()
+                             .ok_or_else(|| format!("Peer router address '{}' did not resolve to an address", address))?;
+
+    let stream = TcpStream::connect_timeout(&socket_addr, PEER_TIMEOUT)
+                           .map_err(|err| format!("Could not connect to peer router {}: {}", address, err))?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT))
+          .map_err(|err| format!("Could not set a timeout on the connection to peer router {}: {}", address, err))?;
+
+    let mut writer = stream.try_clone()
+                           .map_err(|err| format!("Peer router {} connection error: {}", address, err))?;
+    let mut reader = BufReader::new(stream);
+
+    let token = token.trim();
+    if !token.is_empty() {
+        writeln!(writer, "auth {}", token)
+                .map_err(|err| format!("Could not authenticate with peer router {}: {}", address, err))?;
+
+        // the token is not repeated in errors, as they are logged
+        peer_reply(&mut reader, address, "auth")?;
+    }
+
+    writeln!(writer, "{}", command)
+            .map_err(|err| format!("Could not send '{}' to peer router {}: {}", command, address, err))?;
+
+    peer_reply(&mut reader, address, command)
+}
+
+// Read the reply of a peer router to a command, returning an error unless it accepted it
+fn peer_reply(reader: &mut BufReader<TcpStream>, address: &str, command: &str) -> Result<(), String> {
+    let mut reply = String::new();
+    reader.read_line(&mut reply)
+          .map_err(|err| format!("No reply to '{}' from peer router {}: {}", command, address, err))?;
+
+    match reply.trim() {
+        "ok" => Ok(()),
+
+        "" => Err(format!("Peer router {} closed the connection without replying to '{}'", address, command)),
+
+        reply => Err(format!("Peer router {} replied to '{}' with {}", address, command, reply)),
+    }
+}
+
+/// The command starting a peer router at the given time, as given to its control socket
+pub fn start_at_command(start_time: SystemTime) -> String {
+    let start_secs = start_time.duration_since(UNIX_EPOCH)
+                               .map(|since_epoch| since_epoch.as_fractional_secs())
+                               .unwrap_or(0.0);
+
+    format!("start_at {:.6}", start_secs)
+}
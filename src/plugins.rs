@@ -0,0 +1,58 @@
+use std::io::{self, Read, Write};
+
+use bytes::BytesMut;
+
+use ccsds_router_core::stream::*;
+use ccsds_router_core::bench::*;
+
+
+/// Register the stream plugins built into the application. Site specific transports are
+/// added here with register_source and register_sink, and are then selected by name in
+/// a stream's plugin settings, such as 'plugin:stdio' on the command line.
+pub fn register_plugins() {
+    register_source("stdio", open_stdin_source);
+    register_sink("stdio", open_stdout_sink);
+    register_source(GENERATOR_PLUGIN, open_generator_source);
+}
+
+/// Reads packets from standard input, so packets can be piped into the router
+struct StdinSource {
+    stdin: io::Stdin,
+}
+
+fn open_stdin_source(_plugin_settings: &PluginSettings) -> Result<Box<PacketSource>, String> {
+    Ok(Box::new(StdinSource { stdin: io::stdin() }))
+}
+
+impl PacketSource for StdinSource {
+    fn read(&mut self, bytes: &mut BytesMut, num_bytes: usize) -> Result<usize, String> {
+        let mut chunk = vec![0; num_bytes];
+
+        let num_bytes_read = self.stdin.lock()
+                                       .read(&mut chunk)
+                                       .map_err(|err| format!("Standard Input Read Error: {}", err))?;
+
+        bytes.extend_from_slice(&chunk[..num_bytes_read]);
+
+        Ok(num_bytes_read)
+    }
+}
+
+/// Writes packets to standard output, so the router's output can be piped into another program
+struct StdoutSink {
+    stdout: io::Stdout,
+}
+
+fn open_stdout_sink(_plugin_settings: &PluginSettings) -> Result<Box<PacketSink>, String> {
+    Ok(Box::new(StdoutSink { stdout: io::stdout() }))
+}
+
+impl PacketSink for StdoutSink {
+    fn send(&mut self, packet: &[u8]) -> Result<(), String> {
+        let mut stdout = self.stdout.lock();
+
+        stdout.write_all(packet)
+              .and_then(|_| stdout.flush())
+              .map_err(|err| format!("Standard Output Write Error: {}", err))
+    }
+}
@@ -0,0 +1,157 @@
+use apid_list::*;
+
+
+/// The number of priorities a packet can have
+pub const PRIORITY_LEVELS: usize = 3;
+
+/// The priority of a packet's APID on an output that can not write packets as fast as they are
+/// forwarded, such as one with a rate limit or writing its backlog
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy)]
+pub enum Priority {
+    High,
+    Normal,
+    Low,
+}
+
+impl Priority {
+    /// The index of the priority, from 0 for the highest priority
+    pub fn level(&self) -> usize {
+        match self {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Priority::High => "high",
+            Priority::Normal => "normal",
+            Priority::Low => "low",
+        }
+    }
+
+    pub fn from_level(level: usize) -> Priority {
+        match level {
+            0 => Priority::High,
+            1 => Priority::Normal,
+            _ => Priority::Low,
+        }
+    }
+}
+
+/// APID priorities order the packets waiting for a constrained output, so that critical
+/// housekeeping is written before bulk science data. APIDs that are not listed have normal
+/// priority.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PrioritySettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The APIDs written before any others
+    #[serde(default)]
+    pub high_apids: Vec<u16>,
+
+    /// The APIDs written after any others, such as bulk data
+    #[serde(default)]
+    pub low_apids: Vec<u16>,
+
+    /// The most packets of higher priority written in a row while a packet of lower priority
+    /// waits, after which the waiting packet is written so it is not starved, or 0 to always
+    /// write the highest priority first
+    #[serde(default = "default_starvation_limit")]
+    pub starvation_limit: u32,
+}
+
+fn default_starvation_limit() -> u32 {
+    10
+}
+
+impl Default for PrioritySettings {
+    fn default() -> Self {
+        PrioritySettings {
+            enabled: false,
+            high_apids: Vec::new(),
+            low_apids: Vec::new(),
+            starvation_limit: default_starvation_limit(),
+        }
+    }
+}
+
+impl PrioritySettings {
+    /// The priority of an APID, which is normal if priorities are not enabled
+    pub fn priority(&self, apid: u16) -> Priority {
+        if !self.enabled {
+            Priority::Normal
+        } else if self.high_apids.contains(&apid) {
+            Priority::High
+        } else if self.low_apids.contains(&apid) {
+            Priority::Low
+        } else {
+            Priority::Normal
+        }
+    }
+
+    /// A description of each problem with the priorities
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.enabled {
+            return problems;
+        }
+
+        for apid in self.high_apids.iter().chain(self.low_apids.iter()) {
+            if *apid > MAX_APID {
+                problems.push(format!("APID {} is given a priority, but APIDs are at most {}", apid, MAX_APID));
+            }
+        }
+
+        for apid in self.high_apids.iter().filter(|apid| self.low_apids.contains(apid)) {
+            problems.push(format!("APID {} is given both high and low priority", apid));
+        }
+
+        problems
+    }
+}
+
+/// A PriorityScheduler chooses the priority of the next packet written to an output from the
+/// priorities with packets waiting. The highest priority waiting is chosen, unless a lower
+/// priority has been passed over the starvation limit times in a row.
+#[derive(Debug, Clone)]
+pub struct PriorityScheduler {
+    starvation_limit: u32,
+
+    /// The packets written from higher priorities while each priority had packets waiting
+    passed_over: [u32; PRIORITY_LEVELS],
+}
+
+impl PriorityScheduler {
+    pub fn new(starvation_limit: u32) -> PriorityScheduler {
+        PriorityScheduler {
+            starvation_limit: starvation_limit,
+            passed_over: [0; PRIORITY_LEVELS],
+        }
+    }
+
+    /// The priority to write a packet from next, given whether each priority has packets
+    /// waiting, or None if none do
+    pub fn next(&mut self, waiting: [bool; PRIORITY_LEVELS]) -> Option<Priority> {
+        let starved = if self.starvation_limit > 0 {
+            (0..PRIORITY_LEVELS).rev().find(|level| waiting[*level] && self.passed_over[*level] >= self.starvation_limit)
+        } else {
+            None
+        };
+
+        let chosen = starved.or_else(|| (0..PRIORITY_LEVELS).find(|level| waiting[*level]))?;
+
+        for level in 0..PRIORITY_LEVELS {
+            if level == chosen || !waiting[level] {
+                self.passed_over[level] = 0;
+            } else if level > chosen {
+                self.passed_over[level] += 1;
+            }
+        }
+
+        Some(Priority::from_level(chosen))
+    }
+}
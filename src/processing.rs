@@ -1,263 +1,3174 @@
 use std::default::Default;
-use std::sync::mpsc::{SyncSender, Sender, Receiver, RecvTimeoutError, sync_channel};
-use std::time::{SystemTime, Duration};
-use std::io::Cursor;
+use std::sync::mpsc::{SyncSender, Sender, Receiver, RecvTimeoutError, TryRecvError, TrySendError, SendError, sync_channel, channel};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, Instant, Duration};
 use std::thread;
-use std::cmp::min;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::{self, Read, ErrorKind};
+use std::borrow::Cow;
+use std::path::Path;
+use std::fs::metadata;
+use std::mem;
 
-use bytes::{Buf};
 use byteorder::{LittleEndian};
 
+use bytes::{Bytes, BytesMut};
+
+use mio;
+use mio::{Poll, Token, Ready, PollOpt, Events};
+
+use floating_duration::TimeAsFloat;
+
+use chrono::{DateTime, Local, Utc};
+
 use ccsds_primary_header::primary_header::*;
 use ccsds_primary_header::parser::{CcsdsParser, CcsdsParserConfig};
 
 use types::*;
 use stream::*;
+use report::*;
+use archive::*;
+use index::*;
+use packet_log::*;
+use transform::*;
+use sidecar::*;
+use container::*;
+use decom::*;
+use parquet_archive::*;
+use cfs::*;
+use transfer_frame::*;
+use outage::*;
+use backlog::*;
+use priority::*;
+use gap_fill::*;
+use reorder::*;
 
 
 #[derive(Debug, Clone)]
 enum PacketMsg {
-    StreamOpenError,
+    StreamOpenError(String),
     ReadError(String),
-    Packet(Packet, SystemTime),
-    PacketDropped(CcsdsPrimaryHeader),
+    /// A packet, the time it was received, the offset of its frame in the input stream, and
+    /// the frame's header, if it has one
+    Packet(Packet, SystemTime, u64, Option<Bytes>),
+    /// A packet was dropped by the input, for the given reason
+    PacketDropped(CcsdsPrimaryHeader, DropReason),
+    /// The parser resynchronized the given number of times, skipping the given number of bytes
+    /// while searching for a valid header
+    BytesSkipped(u64, u64),
+    /// The input stream was read
+    InputRead(InputReadUpdate),
+    /// The raw capture of the input could not be written, and was stopped
+    CaptureError(String),
     StreamParseError,
-    StreamEnd,
+    /// The input stream ended, with the number of packets and bytes read from it
+    StreamEnd(EndReason, u64, u64),
+    InputPosition(u64),
+    SeekComplete,
+    /// The input is waiting for the described stream to connect, or None once it is no longer waiting
+    Waiting(Option<String>),
+    /// The backup input could not be opened or read, which does not stop processing
+    BackupInputError(String),
+    /// The packets of the backup input that filled gaps, and the duplicates removed, since the last update
+    GapFill(GapFillStats),
+    /// The packets the reorder buffer released out of the order they arrived in, and the late
+    /// packets, since the last update
+    Reorder(ReorderStats),
+}
+
+/// An InputMsg is a message from the processing thread to the input stream thread.
+#[derive(Debug, Clone)]
+enum InputMsg {
+    Seek(u64),
 }
 
 #[derive(Debug, Clone)]
 struct TimeState {
   timestamp_setting: TimestampSetting,
   timestamp_def: TimestampDef,
-  system_to_packet_time: Option<SystemTime>,
-  last_send_time: SystemTime,
+  /// The time and packet timestamp that replay timing is measured from. Pacing uses the
+  /// monotonic clock, so it is not disturbed if the system clock is stepped.
+  replay_anchor: Option<(Instant, Duration)>,
+  last_send_time: Instant,
+  speed: f32,
 }
 
+/// Limits the packet updates sent to the GUI to a number per APID per second, so that the
+/// display does not slow down processing at high packet rates. Packets that are not sent
+/// are combined into the next update of their APID, so the GUI's counts stay accurate.
+struct GuiUpdateLimiter {
+    /// The shortest time between updates of an APID, where zero sends every packet
+    min_interval: Duration,
 
-fn input_stream_thread(packet_sender: SyncSender<PacketMsg>,
-                       read_stream_settings: StreamSettings,
-                       input_selection: StreamOption,
-                       ccsds_parser_config: CcsdsParserConfig) {
-    match input_selection.open_input(&read_stream_settings) {
-        Ok(ref mut in_stream) => {
-            let mut ccsds_parser = CcsdsParser::with_config(ccsds_parser_config.clone());
-            ccsds_parser.bytes.reserve(4096);
+    /// The time of the last update sent for each APID, and the update waiting to be sent
+    apids: HashMap<u16, (Option<Instant>, Option<PacketUpdate>)>,
 
-            'processing_loop: loop {
-                // NOTE need to handle timing out for network reads and still responding to
-                // control messages.
-                // NOTE need to handle reading from files that may grow, and ones that will not
-                // NOTE have a way to signal nominal end of stream for files, and report back
-                // differently
-                // NOTE magic number 4096 is used.
-                let current_num_bytes = ccsds_parser.bytes.len();
-                let num_bytes_avail = ccsds_parser.bytes.capacity();
-                match in_stream.stream_read(&mut ccsds_parser.bytes, num_bytes_avail - current_num_bytes) {
-                    Err(e) => {
-                        packet_sender.send(PacketMsg::ReadError(e)).unwrap();
-                        break;
-                    },
+    /// The time of the last update sent for each virtual channel, and the update waiting to be sent
+    virtual_channels: HashMap<VirtualChannel, (Option<Instant>, Option<FrameUpdate>)>,
 
-                    _ => {
-                        // loop, reading all new packets and sending them along.
-                        // if there are no new packets, go back to reading the stream for bytes
-                        let mut any_packets = false;
-                        while let Some(packet_bytes) = ccsds_parser.pull_packet() {
-                            let recv_time = SystemTime::now();
+    /// The time of the last count of filtered packets sent, and the packets of each APID
+    /// filtered since then
+    filtered: (Option<Instant>, BTreeMap<u16, u64>),
 
-                            let mut packet: Packet
-                                = Packet { header: Default::default(),
-                                           bytes: Vec::with_capacity(packet_bytes.len()),
-                            };
+    /// The time of the last count of skipped bytes sent, and the resyncs and bytes skipped
+    /// since then
+    skipped: (Option<Instant>, u64, u64),
 
-                            let bytes = packet_bytes.freeze();
-                            if ccsds_parser_config.little_endian_header {
-                                let little_header: PrimaryHeader<LittleEndian> = PrimaryHeader::from_slice(&bytes).unwrap();
-                                packet.header = little_header.to_big_endian();
-                            } else {
-                                packet.header = CcsdsPrimaryHeader::from_slice(&bytes).unwrap();
-                            }
-                            packet.bytes.extend(bytes);
-
-                            packet_sender.send(PacketMsg::Packet(packet, recv_time)).unwrap();
-
-                            any_packets = true;
-                        }
-
-                        // if we processed a series of packets, reset the remaining data to the
-                        // start of a new parser.
-                        if any_packets {
-                            let remaining_bytes = ccsds_parser.bytes.freeze();
-                            ccsds_parser = CcsdsParser::with_config(ccsds_parser_config.clone());
-                            ccsds_parser.bytes.reserve(4096);
-                            ccsds_parser.bytes.extend(remaining_bytes);
-                        } else if ccsds_parser.bytes.capacity() == ccsds_parser.bytes.len() {
-                            // attempt to extend the capacity to accomidate a larger packet.
-                            // if this doesn't work, exit.
-                            let max_packet_bytes = CCSDS_MAX_LENGTH +
-                                                   ccsds_parser.config.num_header_bytes +
-                                                   ccsds_parser.config.num_footer_bytes;
-                            if ccsds_parser.bytes.len() < max_packet_bytes as usize {
-                                let new_capacity = min(max_packet_bytes, (ccsds_parser.bytes.capacity() * 2) as u32);
-                                ccsds_parser.bytes.reserve(new_capacity as usize);
-                            } else {
-                                // NOTE this situation should not happen. The CCSDS parser should
-                                // advance over bytes that do not contain a header, and we have
-                                // grown the buffer large enough for the largest packet.
-                                packet_sender.send(PacketMsg::StreamParseError).unwrap();
-                                break 'processing_loop;
-                            }
-                        }
-                    },
-                }
-            }
-        },
+    /// The time of the last input read update sent, and the reads since then
+    input_reads: (Option<Instant>, Option<InputReadUpdate>),
+}
 
-        Err(e) => {
-            packet_sender.send(PacketMsg::StreamOpenError).unwrap();
+impl GuiUpdateLimiter {
+    fn new(updates_per_sec: u32) -> GuiUpdateLimiter {
+        let min_interval = if updates_per_sec > 0 {
+            Duration::from_secs(1) / updates_per_sec
+        } else {
+            Duration::from_secs(0)
+        };
+
+        GuiUpdateLimiter {
+            min_interval: min_interval,
+            apids: HashMap::new(),
+            virtual_channels: HashMap::new(),
+            filtered: (None, BTreeMap::new()),
+            skipped: (None, 0, 0),
+            input_reads: (None, None),
         }
     }
 
-    packet_sender.send(PacketMsg::StreamEnd).unwrap();
-}
+    /// Count a packet removed by the input APID filter
+    fn filtered(&mut self, apid: u16, sender: &Sender<GuiMessage>) {
+        *self.filtered.1.entry(apid).or_insert(0) += 1;
+
+        if update_due(self.filtered.0, Instant::now(), self.min_interval) {
+            self.send_filtered(sender);
+        }
+    }
 
-// Decode a timestamp from a vector of bytes into a Duration
-// The TimestampDef describes the layout of the timestamp
-fn decode_timestamp(bytes: &Vec<u8>, timestamp_def: &TimestampDef) -> Duration {
-    let timestamp: Duration;
+    fn send_filtered(&mut self, sender: &Sender<GuiMessage>) {
+        if !self.filtered.1.is_empty() {
+            self.filtered.0 = Some(Instant::now());
+            let apid_counts = mem::replace(&mut self.filtered.1, BTreeMap::new());
+            sender.send(GuiMessage::PacketsFiltered(apid_counts)).unwrap();
+        }
+    }
+
+    /// Count the bytes the input's parser skipped searching for a valid header
+    fn skipped(&mut self, resyncs: u64, bytes_skipped: u64, sender: &Sender<GuiMessage>) {
+        self.skipped.1 += resyncs;
+        self.skipped.2 += bytes_skipped;
+
+        if update_due(self.skipped.0, Instant::now(), self.min_interval) {
+            self.send_skipped(sender);
+        }
+    }
 
-    let num_secs: u64;
-    let num_subsecs: u64;
+    fn send_skipped(&mut self, sender: &Sender<GuiMessage>) {
+        if self.skipped.2 > 0 {
+            let (_, resyncs, bytes_skipped) = mem::replace(&mut self.skipped, (Some(Instant::now()), 0, 0));
+            sender.send(GuiMessage::BytesSkipped(resyncs, bytes_skipped)).unwrap();
+        }
+    }
 
-    let time_start_byte = CCSDS_PRI_HEADER_SIZE_BYTES as usize + timestamp_def.offset as usize;
+    /// Count a read from the input stream
+    fn input_read(&mut self, input_read: InputReadUpdate, sender: &Sender<GuiMessage>) {
+        match self.input_reads.1 {
+            Some(ref mut pending) => pending.combine(input_read),
 
-    let time_length_bytes = timestamp_def.num_bytes_seconds.to_num_bytes() +
-                            timestamp_def.num_bytes_subseconds.to_num_bytes();
+            None => self.input_reads.1 = Some(input_read),
+        }
 
-    let last_byte_offset = time_start_byte + time_length_bytes as usize;
+        if update_due(self.input_reads.0, Instant::now(), self.min_interval) {
+            self.send_input_reads(sender);
+        }
+    }
 
-    // make sure there is space in the packet for the timestamp
-    if last_byte_offset as usize > bytes.len() {
-        return Duration::from_millis(0);
+    fn send_input_reads(&mut self, sender: &Sender<GuiMessage>) {
+        if let Some(input_read) = self.input_reads.1.take() {
+            self.input_reads.0 = Some(Instant::now());
+            sender.send(GuiMessage::InputRead(input_read)).unwrap();
+        }
     }
 
-    let timestamp_slice = &bytes[time_start_byte..last_byte_offset];
-    let mut cursor = Cursor::new(timestamp_slice);
+    fn frame(&mut self, frame_update: FrameUpdate, sender: &Sender<GuiMessage>) {
+        let now = Instant::now();
+        let min_interval = self.min_interval;
 
-    match timestamp_def.num_bytes_seconds {
-        TimeSize::ZeroBytes => num_secs = 0,
+        let &mut (ref mut last_sent, ref mut pending) =
+            self.virtual_channels.entry(frame_update.virtual_channel).or_insert((None, None));
 
-        TimeSize::OneByte => num_secs = cursor.get_u8() as u64,
+        let frame_update = match pending.take() {
+            Some(mut pending_update) => {
+                pending_update.combine(frame_update);
+                pending_update
+            },
 
-        TimeSize::TwoBytes => {
-            if timestamp_def.is_little_endian {
-                num_secs = cursor.get_u16_le() as u64;
-            } else {
-                num_secs = cursor.get_u16_be() as u64;
+            None => frame_update,
+        };
+
+        if update_due(*last_sent, now, min_interval) {
+            *last_sent = Some(now);
+            sender.send(GuiMessage::FrameUpdate(frame_update)).unwrap();
+        } else {
+            *pending = Some(frame_update);
+        }
+    }
+
+    fn packet(&mut self, packet_update: PacketUpdate, sender: &Sender<GuiMessage>) {
+        let now = Instant::now();
+        let min_interval = self.min_interval;
+
+        let &mut (ref mut last_sent, ref mut pending) =
+            self.apids.entry(packet_update.apid).or_insert((None, None));
+
+        let packet_update = match pending.take() {
+            Some(mut pending_update) => {
+                pending_update.combine(packet_update);
+                pending_update
+            },
+
+            None => packet_update,
+        };
+
+        if update_due(*last_sent, now, min_interval) {
+            *last_sent = Some(now);
+            sender.send(GuiMessage::PacketUpdate(packet_update)).unwrap();
+        } else {
+            *pending = Some(packet_update);
+        }
+    }
+
+    /// Send the waiting updates of APIDs and virtual channels whose interval has passed, so an
+    /// APID's last packet is shown even if no more of its packets arrive
+    fn send_due(&mut self, sender: &Sender<GuiMessage>) {
+        let now = Instant::now();
+        let min_interval = self.min_interval;
+
+        for &mut (ref mut last_sent, ref mut pending) in self.apids.values_mut() {
+            if pending.is_some() && update_due(*last_sent, now, min_interval) {
+                *last_sent = Some(now);
+                sender.send(GuiMessage::PacketUpdate(pending.take().unwrap())).unwrap();
             }
-        },
+        }
 
-        TimeSize::FourBytes => {
-            if timestamp_def.is_little_endian {
-                num_secs = cursor.get_u32_le() as u64;
-            } else {
-                num_secs = cursor.get_u32_be() as u64;
+        for &mut (ref mut last_sent, ref mut pending) in self.virtual_channels.values_mut() {
+            if pending.is_some() && update_due(*last_sent, now, min_interval) {
+                *last_sent = Some(now);
+                sender.send(GuiMessage::FrameUpdate(pending.take().unwrap())).unwrap();
             }
-        },
+        }
+
+        if update_due(self.filtered.0, now, min_interval) {
+            self.send_filtered(sender);
+        }
+
+        if update_due(self.skipped.0, now, min_interval) {
+            self.send_skipped(sender);
+        }
+
+        if update_due(self.input_reads.0, now, min_interval) {
+            self.send_input_reads(sender);
+        }
     }
 
-    match timestamp_def.num_bytes_subseconds {
-        TimeSize::ZeroBytes => num_subsecs = 0,
+    /// Send every waiting update, such as when processing stops
+    fn send_all(&mut self, sender: &Sender<GuiMessage>) {
+        let now = Instant::now();
 
-        TimeSize::OneByte => num_subsecs = cursor.get_u8() as u64,
+        for &mut (ref mut last_sent, ref mut pending) in self.apids.values_mut() {
+            if let Some(packet_update) = pending.take() {
+                *last_sent = Some(now);
+                sender.send(GuiMessage::PacketUpdate(packet_update)).unwrap();
+            }
+        }
 
-        TimeSize::TwoBytes => {
-            if timestamp_def.is_little_endian {
-                num_subsecs = cursor.get_u16_le() as u64;
-            } else {
-                num_subsecs = cursor.get_u16_be() as u64;
+        for &mut (ref mut last_sent, ref mut pending) in self.virtual_channels.values_mut() {
+            if let Some(frame_update) = pending.take() {
+                *last_sent = Some(now);
+                sender.send(GuiMessage::FrameUpdate(frame_update)).unwrap();
             }
-        },
+        }
 
-        TimeSize::FourBytes => {
-            if timestamp_def.is_little_endian {
-                num_subsecs = cursor.get_u32_le() as u64;
-            } else {
-                num_subsecs = cursor.get_u32_be() as u64;
+        self.send_filtered(sender);
+        self.send_skipped(sender);
+        self.send_input_reads(sender);
+    }
+}
+
+fn update_due(last_sent: Option<Instant>, now: Instant, min_interval: Duration) -> bool {
+    last_sent.map_or(true, |last_sent| now.duration_since(last_sent) >= min_interval)
+}
+
+/// The most packets kept from before a trigger, limiting memory use at high packet rates
+const MAX_PRE_TRIGGER_PACKETS: usize = 100_000;
+
+/// The state of trigger based capture. Until a trigger fires, packets are kept in a
+/// buffer covering the pre-trigger time rather than being written to the outputs.
+#[derive(Debug, Clone)]
+struct TriggerState {
+  pre_trigger: Duration,
+  post_trigger: Option<Duration>,
+  /// The time each packet was kept, the packet, and the time it was received
+  buffer: VecDeque<(Instant, Packet, SystemTime)>,
+  capturing: bool,
+  capture_end: Option<Instant>,
+}
+
+impl TriggerState {
+    fn new(trigger_settings: &TriggerSettings) -> TriggerState {
+        let post_trigger;
+        if trigger_settings.post_trigger_secs > 0.0 {
+            post_trigger = Some(secs_duration(trigger_settings.post_trigger_secs));
+        } else {
+            post_trigger = None;
+        }
+
+        TriggerState {
+            pre_trigger: secs_duration(trigger_settings.pre_trigger_secs.max(0.0)),
+            post_trigger: post_trigger,
+            buffer: VecDeque::new(),
+            capturing: false,
+            capture_end: None,
+        }
+    }
+
+    /// Check whether a capture of fixed length has ended, returning true if it ended
+    /// at this time.
+    fn check_capture_end(&mut self, now: Instant) -> bool {
+        match self.capture_end {
+            Some(capture_end) if self.capturing && now >= capture_end => {
+                self.capturing = false;
+                self.capture_end = None;
+                true
+            },
+
+            _ => false,
+        }
+    }
+
+    /// Start capturing, returning the packets kept from before the trigger
+    fn start_capture(&mut self, now: Instant) -> Vec<(Instant, Packet, SystemTime)> {
+        self.capturing = true;
+        self.capture_end = self.post_trigger.map(|post_trigger| now + post_trigger);
+        self.buffer.drain(..).collect()
+    }
+
+    /// Keep a packet in case a trigger fires soon, dropping packets older then the
+    /// pre-trigger time.
+    fn keep_packet(&mut self, now: Instant, packet: &Packet, recv_time: SystemTime) {
+        self.buffer.push_back((now, packet.clone(), recv_time));
+
+        let pre_trigger = self.pre_trigger;
+        while self.buffer.len() > MAX_PRE_TRIGGER_PACKETS ||
+              self.buffer.front().map_or(false, |&(time, _, _)| now.duration_since(time) > pre_trigger) {
+            self.buffer.pop_front();
+        }
+    }
+}
+
+fn secs_duration(secs: f32) -> Duration {
+    Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32)
+}
+
+/// The time remaining until a deadline, or zero once it has passed
+fn duration_until(deadline: Instant) -> Duration {
+    let now = Instant::now();
+
+    if deadline > now {
+        deadline - now
+    } else {
+        Duration::from_secs(0)
+    }
+}
+
+// Write out the report for the current run of processing, if there is one.
+// The report is only written once, when processing finishes, is cancelled,
+// or the processing thread terminates.
+fn write_report(report: &mut Option<ProcessingReport>, outcome: &str, output_streams: &[Output], sender: &Sender<GuiMessage>) {
+    if let Some(mut report) = report.take() {
+        report.deliveries(output_streams.iter().map(|output| output.delivery()).collect());
+
+        // the result of a dry run is logged, as it is what the dry run was run to see
+        if report.dry_run {
+            info!("Dry run {}\n{}", outcome, report.outputs_text());
+        } else {
+            for reconciliation in report.reconcile().iter().filter(|reconciliation| !reconciliation.complete) {
+                warn!("{} is incomplete: {} of {} packets read were lost, {} were dropped at the input, and {} are unaccounted for",
+                      reconciliation.label, reconciliation.lost, reconciliation.packets_read, reconciliation.input_dropped, reconciliation.unaccounted);
             }
-        },
+        }
+
+        match report.write(outcome, LOG_DIRECTORY) {
+            Ok((json_name, text_name)) => {
+                info!("Wrote processing report to {} and {}", json_name, text_name);
+            },
+
+            Err(err_string) => {
+                let _ = sender.send(GuiMessage::Error(err_string));
+            },
+        }
     }
+}
+
+
+/// The parsing of the bytes read from an input stream into packets, shared by the threaded
+/// and polled input engines.
+struct InputParser {
+    config: CcsdsParserConfig,
+    read_settings: ReadSettings,
+    parser: CcsdsParser,
+
+    /// The settings of the parser, which always keeps the frame header so that it can be given
+    /// with each packet. The header is removed from the packet here if it is not kept.
+    parser_config: CcsdsParserConfig,
+
+    /// The number of bytes requested in the next read
+    chunk_bytes: usize,
+
+    /// The offset of the end of the bytes read within the input stream
+    bytes_read: u64,
+
+    /// The number of packets and bytes read from the input stream, including any seeked over
+    packet_count: u64,
+    byte_count: u64,
+
+    // frame headers and footers that are not kept are not part of a packet's bytes,
+    // but are part of its frame within the input stream.
+    removed_frame_bytes: u64,
+
+    // the largest frame the parser can hold. If the parser holds more bytes than this
+    // without finding a packet, the stream can not be parsed.
+    max_frame_bytes: usize,
+
+    /// The records of a stream in a container format, which are unwrapped before parsing
+    container: Option<ContainerReader>,
+
+    /// The number of bytes unwrapped from the container's records
+    unwrapped_bytes: u64,
+
+    /// The position within the unwrapped bytes of each record's data, and the offset of the
+    /// record in the input stream, for the records that the parser holds bytes from
+    records: VecDeque<(u64, u64)>,
 
-    let subseconds = num_subsecs as f32 * timestamp_def.subsecond_resolution;
-    timestamp = Duration::from_secs(num_secs) +
-                Duration::from_nanos((1_000_000_000.0 * subseconds.fract()) as u64);
+    /// Whether the parser was skipping bytes at the end of the last parse, so a run of skipped
+    /// bytes spanning several reads is counted as a single resync
+    resyncing: bool,
 
-    timestamp
+    /// The file the bytes read from the input stream are written to, if capturing
+    raw_capture: Option<RawCapture>,
 }
 
-// Determine the timeout we can wait before we need to act again
-fn determine_timeout(time_state: &mut TimeState,
-                     packet: &Packet) -> Duration {
-    let timeout: Duration;
+impl InputParser {
+    fn new(config: CcsdsParserConfig,
+           read_settings: ReadSettings,
+           container_format: &ContainerFormat,
+           raw_capture: Option<RawCapture>) -> InputParser {
+        let mut removed_frame_bytes: u64 = 0;
+        if !config.keep_sync {
+            removed_frame_bytes += config.sync_bytes.len() as u64;
+        }
+        if !config.keep_header {
+            removed_frame_bytes += config.num_header_bytes as u64;
+        }
+        if !config.keep_footer {
+            removed_frame_bytes += config.num_footer_bytes as u64;
+        }
 
-    match time_state.timestamp_setting {
-        // Process as fast as possible
-        TimestampSetting::Asap => {
-            timeout = Duration::from_secs(0);
+        let max_frame_bytes = (CCSDS_MAX_LENGTH + config.num_header_bytes + config.num_footer_bytes) as usize + config.sync_bytes.len();
+
+        let chunk_bytes = read_settings.next_chunk_bytes(0, 0);
+
+        // a sync marker is not kept as part of the frame header given with each packet
+        let mut parser_config = config.clone();
+        parser_config.keep_header = true;
+
+        let mut parser = CcsdsParser::with_config(parser_config.clone());
+        parser.bytes.reserve(chunk_bytes);
+
+        InputParser {
+            config: config,
+            read_settings: read_settings,
+            parser: parser,
+            parser_config: parser_config,
+            chunk_bytes: chunk_bytes,
+            bytes_read: 0,
+            packet_count: 0,
+            byte_count: 0,
+            removed_frame_bytes: removed_frame_bytes,
+            max_frame_bytes: max_frame_bytes,
+            container: ContainerReader::new(container_format),
+            unwrapped_bytes: 0,
+            records: VecDeque::new(),
+            resyncing: false,
+            raw_capture: raw_capture,
+        }
+    }
+
+    /// The bytes which reads from the input stream are added to. These are the parser's bytes,
+    /// unless the stream is in a container format.
+    fn bytes(&mut self) -> &mut BytesMut {
+        match self.container {
+            Some(ref mut container) => container.bytes(),
+
+            None => &mut self.parser.bytes,
         }
+    }
 
-        // Replaying packets- use the packet's timestamp as an offset
-        TimestampSetting::Replay => {
-           let timestamp = decode_timestamp(&packet.bytes, &time_state.timestamp_def);
+    /// Discard any partial packet, continuing from the given offset in the input stream
+    fn restart(&mut self, offset: u64) {
+        self.parser = CcsdsParser::with_config(self.parser_config.clone());
+        self.parser.bytes.reserve(self.chunk_bytes);
+        self.bytes_read = offset;
 
-            match time_state.system_to_packet_time {
-                None => {
-                    time_state.system_to_packet_time = Some(SystemTime::now() - timestamp);
-                    timeout = Duration::from_secs(0);
-                },
+        if let Some(ref mut container) = self.container {
+            container.clear();
+        }
+        self.records.clear();
+        self.resyncing = false;
+    }
 
-                Some(time_offset) =>
-                {
-                    let timestamp_sys_time = time_offset + timestamp;
+    /// Write the bytes of a read to the raw capture, if capturing. These are the last bytes
+    /// added by the read. If the capture can not be written, it is stopped.
+    fn capture(&mut self, num_bytes_read: usize, msgs: &mut VecDeque<PacketMsg>) {
+        let result = match self.raw_capture {
+            Some(ref mut raw_capture) => {
+                let bytes = match self.container {
+                    Some(ref mut container) => container.bytes(),
+
+                    None => &mut self.parser.bytes,
+                };
+                let start = bytes.len().saturating_sub(num_bytes_read);
+                raw_capture.write(&bytes[start..])
+            },
+
+            None => Ok(()),
+        };
 
-                    match timestamp_sys_time.duration_since(SystemTime::now()) {
-                        Ok(remaining_time) => timeout = remaining_time,
+        if let Err(err_string) = result {
+            self.raw_capture = None;
+            msgs.push_back(PacketMsg::CaptureError(err_string));
+        }
+    }
+
+    /// Unwrap the records completed by a read, adding their data to the parser's bytes.
+    /// Returns the number of bytes added, or None if the stream is not in the container format.
+    fn unwrap_records(&mut self, num_bytes_read: usize, end_of_stream: bool) -> Option<usize> {
+        let container = match self.container {
+            Some(ref mut container) => container,
+
+            None => return Some(num_bytes_read),
+        };
+
+        let mut num_bytes_unwrapped = 0;
+        loop {
+            let record_offset = self.bytes_read - container.bytes().len() as u64;
+
+            let record = match container.pull_record() {
+                Ok(None) if end_of_stream => {
+                    // a record cut short at the end of the input is dropped, as with a partial packet
+                    Ok(container.pull_final().unwrap_or_else(|err_string| {
+                        warn!("Input record error at byte {}: {}", record_offset, err_string);
+                        None
+                    }))
+                },
+
+                record => record,
+            };
 
-                        _ => timeout = Duration::from_secs(0),
+            match record {
+                Ok(Some(record_data)) => {
+                    if record_data.is_empty() {
+                        continue;
                     }
+
+                    self.records.push_back((self.unwrapped_bytes, record_offset));
+                    self.unwrapped_bytes += record_data.len() as u64;
+                    num_bytes_unwrapped += record_data.len();
+                    self.parser.bytes.extend_from_slice(&record_data);
+                },
+
+                Ok(None) => return Some(num_bytes_unwrapped),
+
+                Err(err_string) => {
+                    error!("Input record error at byte {}: {}", record_offset, err_string);
+                    return None;
                 },
             }
-        },
+        }
+    }
 
-        // delay for a fixed duration
-        TimestampSetting::Delay(duration) => {
-            timeout = duration;
-        },
+    /// The offset in the input stream of a packet's frame, given the number of bytes that
+    /// follow the frame in the parser. A packet unwrapped from a container is given the
+    /// offset of the record it starts in, where reading can restart.
+    fn frame_offset(&mut self, frame_length: u64) -> u64 {
+        if self.container.is_none() {
+            return (self.bytes_read - self.parser.bytes.len() as u64).saturating_sub(frame_length);
+        }
 
-        // Throttle packet processing to a fixed rate
-        // This is different from Delay in that it only delays if necessary to
-        // space out packets.
-        TimestampSetting::Throttle(duration) => {
-            match duration.checked_sub(time_state.last_send_time.elapsed().unwrap()) {
-                Some(remaining_time) => timeout = remaining_time,
+        let frame_start = (self.unwrapped_bytes - self.parser.bytes.len() as u64).saturating_sub(frame_length);
 
-                None => timeout = Duration::from_millis(0),
+        // records before the one the frame starts in are not needed again
+        while self.records.len() > 1 && self.records[1].0 <= frame_start {
+            self.records.pop_front();
+        }
+
+        self.records.front().map_or(0, |record| record.1)
+    }
+
+    /// The message ending the input stream for the given reason
+    fn stream_end(&self, reason: EndReason) -> PacketMsg {
+        PacketMsg::StreamEnd(reason, self.packet_count, self.byte_count)
+    }
+
+    /// Parse the packets completed by a read of the given number of bytes, adding the messages
+    /// for the processing thread to msgs. The packets are given the time the bytes were received
+    /// at if the stream provides it, and otherwise the time they are parsed at. At the end of the
+    /// stream, any last record of a container is unwrapped. Returns false if the stream can not
+    /// be parsed.
+    fn parse(&mut self, num_bytes_read: usize, recv_time: Option<SystemTime>, end_of_stream: bool, msgs: &mut VecDeque<PacketMsg>) -> bool {
+        self.capture(num_bytes_read, msgs);
+        self.bytes_read += num_bytes_read as u64;
+        self.byte_count += num_bytes_read as u64;
+        self.chunk_bytes = self.read_settings.next_chunk_bytes(self.chunk_bytes, num_bytes_read);
+        msgs.push_back(PacketMsg::InputPosition(self.bytes_read));
+
+        let num_bytes_added = match self.unwrap_records(num_bytes_read, end_of_stream) {
+            Some(num_bytes_added) => num_bytes_added,
+
+            None => {
+                msgs.push_back(PacketMsg::StreamParseError);
+                return false;
+            },
+        };
+
+        // bytes the parser consumes beyond the frames of the packets it returns were skipped
+        // while searching for a valid header
+        let mut parser_bytes = self.parser.bytes.len();
+        let mut resyncs = 0;
+        let mut bytes_skipped = 0;
+
+        // loop, reading all new packets and sending them along.
+        // if there are no new packets, go back to reading the stream for bytes
+        let mut any_packets = false;
+        while let Some(packet_bytes) = self.parser.pull_packet() {
+            let recv_time = recv_time.unwrap_or_else(SystemTime::now);
+            let mut packet_bytes = packet_bytes.freeze();
+
+            let header_bytes = (self.config.num_header_bytes as usize).min(packet_bytes.len());
+            let frame_header = if header_bytes == 0 {
+                None
+            } else if self.config.keep_header {
+                Some(packet_bytes.slice(0, header_bytes))
+            } else {
+                Some(packet_bytes.split_to(header_bytes))
+            };
+
+            // the packet's frame ends where the bytes remaining in the parser begin
+            let frame_length = packet_bytes.len() as u64 + self.removed_frame_bytes;
+            let offset = self.frame_offset(frame_length);
+
+            let skipped = (parser_bytes.saturating_sub(self.parser.bytes.len()) as u64).saturating_sub(frame_length);
+            if skipped > 0 && !self.resyncing {
+                resyncs += 1;
             }
-        },
+            bytes_skipped += skipped;
+            parser_bytes = self.parser.bytes.len();
+            self.resyncing = false;
+
+            let packet = packet_from_bytes(packet_bytes, self.config.little_endian_header);
+
+            msgs.push_back(PacketMsg::Packet(packet, recv_time, offset, frame_header));
+
+            self.packet_count += 1;
+            any_packets = true;
+        }
+
+        let skipped = parser_bytes.saturating_sub(self.parser.bytes.len()) as u64;
+        if skipped > 0 {
+            if !self.resyncing {
+                resyncs += 1;
+            }
+            bytes_skipped += skipped;
+            self.resyncing = true;
+        }
+        if bytes_skipped > 0 {
+            msgs.push_back(PacketMsg::BytesSkipped(resyncs, bytes_skipped));
+        }
+
+        // a packet cut short by the end of the stream is dropped, and reported if its header was read
+        if end_of_stream {
+            let header_start = self.config.sync_bytes.len() + self.config.num_header_bytes as usize;
+            if self.parser.bytes.len() > header_start {
+                if let Some(header) = primary_header(&self.parser.bytes[header_start..], self.config.little_endian_header) {
+                    msgs.push_back(PacketMsg::PacketDropped(header, DropReason::ParseError));
+                }
+            }
+        }
+
+        // if we processed a series of packets, reset the remaining data to the
+        // start of a new parser.
+        if any_packets {
+            let remaining_bytes = self.parser.bytes.take().freeze();
+            self.parser = CcsdsParser::with_config(self.parser_config.clone());
+            self.parser.bytes.reserve(remaining_bytes.len() + self.chunk_bytes);
+            self.parser.bytes.extend(remaining_bytes);
+        } else if self.parser.bytes.len() > self.max_frame_bytes + self.chunk_bytes.max(num_bytes_added) {
+            // NOTE this situation should not happen. The CCSDS parser should
+            // advance over bytes that do not contain a header, and the buffer
+            // grows with each read, so it can hold the largest packet.
+            msgs.push_back(PacketMsg::StreamParseError);
+            return false;
+        }
+
+        msgs.push_back(PacketMsg::InputRead(InputReadUpdate {
+            byte_count: num_bytes_read as u64,
+            buffered_bytes: self.parser.bytes.len(),
+            max_buffered_bytes: self.max_frame_bytes,
+            read_time: recv_time.unwrap_or_else(SystemTime::now),
+        }));
+
+        true
     }
+}
 
-    timeout
+/// The thread reading the input stream, as seen by the processing thread. Dropping the handle
+/// stops the thread, closing the input stream, including when it is waiting on a network read.
+struct InputHandle {
+    control_sender: Sender<InputMsg>,
+    stop: Arc<AtomicBool>,
+
+    /// Unblocks the input thread's reads, once it has opened the input stream
+    interrupt: Arc<Mutex<Option<ReadInterrupt>>>,
+
+    /// The backup input filling the gaps in this input, which is stopped along with it
+    backup: Option<Box<InputHandle>>,
+}
+
+impl InputHandle {
+    fn send(&self, input_msg: InputMsg) {
+        let _ = self.control_sender.send(input_msg);
+    }
+}
+
+impl Drop for InputHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Ok(interrupt) = self.interrupt.lock() {
+            if let Some(ref interrupt) = *interrupt {
+                interrupt.interrupt();
+            }
+        }
+    }
+}
+
+fn input_stream_thread(packet_sender: SyncSender<PacketMsg>,
+                       control_receiver: Receiver<InputMsg>,
+                       stop: Arc<AtomicBool>,
+                       interrupt: Arc<Mutex<Option<ReadInterrupt>>>,
+                       open_wait: OpenWait,
+                       read_stream_settings: StreamSettings,
+                       input_selection: StreamOption,
+                       ccsds_parser_config: CcsdsParserConfig,
+                       read_settings: ReadSettings,
+                       container_format: ContainerFormat,
+                       raw_capture: Option<RawCapture>,
+                       query_ranges: Option<Vec<(u64, u64)>>) {
+    let open_result = open_input_waiting(input_selection, &read_stream_settings, &open_wait, &packet_sender);
+
+    // with a replay query, only the parts of the input file holding the selected packets are read
+    let open_result = match (open_result, query_ranges) {
+        (Ok(ReadStream::File(reader)), Some(query_ranges)) => Ok(ReadStream::Query(QueryReader::new(reader.into_inner(), query_ranges))),
+
+        (open_result, _) => open_result,
+    };
+
+    let stream_end;
+
+    // the processing thread stops the input thread when it no longer uses the input stream,
+    // in which case nothing more is sent to it, and the input stream is closed when it is dropped.
+    if stop.load(Ordering::SeqCst) {
+        return;
+    }
+
+    match open_result {
+        Ok(ref mut in_stream) => {
+            if let Ok(mut interrupt) = interrupt.lock() {
+                *interrupt = in_stream.read_interrupt();
+            }
+
+            let mut input_parser = InputParser::new(ccsds_parser_config, read_settings, &container_format, raw_capture);
+
+            let mut msgs: VecDeque<PacketMsg> = VecDeque::new();
+
+            'processing_loop: loop {
+                // handle requests to move within the input stream. Any partial packet
+                // is discarded when the stream is moved.
+                loop {
+                    match control_receiver.try_recv() {
+                        Ok(InputMsg::Seek(offset)) => {
+                            match in_stream.stream_seek(offset) {
+                                Ok(()) => {
+                                    input_parser.restart(offset);
+                                },
+
+                                Err(e) => {
+                                    let _ = packet_sender.send(PacketMsg::ReadError(e));
+                                },
+                            }
+
+                            let _ = packet_sender.send(PacketMsg::SeekComplete);
+                        },
+
+                        Err(TryRecvError::Empty) => break,
+
+                        Err(TryRecvError::Disconnected) => return,
+                    }
+                }
+
+                // network reads time out while no data arrives, so control messages and
+                // stopping the thread are still handled.
+                // NOTE need to handle reading from files that may grow, and ones that will not
+                let chunk_bytes = input_parser.chunk_bytes;
+                let read_result = in_stream.stream_read_timed(input_parser.bytes(), chunk_bytes);
+
+                // a read interrupted to stop the thread is not reported
+                if stop.load(Ordering::SeqCst) {
+                    return;
+                }
+
+                match read_result {
+                    Err(e) => {
+                        let _ = packet_sender.send(PacketMsg::ReadError(e));
+                        stream_end = input_parser.stream_end(EndReason::Error);
+                        break;
+                    },
+
+                    Ok(StreamRead::TimedOut) => {},
+
+                    // an empty datagram is not the end of a UDP input, which has no end
+                    Ok(StreamRead::Bytes(0, _)) if chunk_bytes > 0 && !in_stream.is_datagram() => {
+                        // the last record of a container may only be complete at the end of the stream
+                        let parsed = input_parser.parse(0, None, true, &mut msgs);
+
+                        for msg in msgs.drain(..) {
+                            if packet_sender.send(msg).is_err() {
+                                return;
+                            }
+                        }
+
+                        if parsed {
+                            stream_end = input_parser.stream_end(EndReason::EndOfFile);
+                        } else {
+                            stream_end = input_parser.stream_end(EndReason::Error);
+                        }
+                        break;
+                    },
+
+                    Ok(StreamRead::Bytes(num_bytes_read, recv_time)) => {
+                        let parsed = input_parser.parse(num_bytes_read, recv_time, false, &mut msgs);
+
+                        for msg in msgs.drain(..) {
+                            if packet_sender.send(msg).is_err() {
+                                return;
+                            }
+                        }
+
+                        if !parsed {
+                            stream_end = input_parser.stream_end(EndReason::Error);
+                            break 'processing_loop;
+                        }
+                    },
+                }
+            }
+        },
+
+        Err(e) => {
+            let _ = packet_sender.send(PacketMsg::StreamOpenError(format!("Could not open the input stream: {}", e)));
+            stream_end = PacketMsg::StreamEnd(EndReason::Error, 0, 0);
+        }
+    }
+
+    let _ = packet_sender.send(stream_end);
+}
+
+/// The most time the polled input engine waits for a socket to become readable before
+/// checking for new inputs and control messages.
+const POLL_TIMEOUT_MS: u64 = 100;
+
+/// The time the polled input engine waits while an input has messages that its processing
+/// thread has not yet accepted.
+const POLL_BACKLOG_TIMEOUT_MS: u64 = 5;
+
+lazy_static! {
+    /// The sender of new inputs to the polled input engine's thread, which is started
+    /// when the first input is polled.
+    static ref POLL_ENGINE: Mutex<Option<Sender<PolledInput>>> = Mutex::new(None);
+}
+
+/// A network input socket, read without blocking by the polled input engine
+enum PolledSocket {
+    /// A UDP socket, and the largest datagram read from it
+    Udp(mio::net::UdpSocket, usize),
+    Tcp(mio::net::TcpStream),
+}
+
+impl PolledSocket {
+    fn from_stream(in_stream: ReadStream) -> Result<PolledSocket, String> {
+        match in_stream {
+            ReadStream::Udp(sock, max_datagram_bytes) => {
+                mio::net::UdpSocket::from_socket(sock)
+                    .map(|sock| PolledSocket::Udp(sock, max_datagram_bytes))
+                    .map_err(|err| format!("Could not poll the UDP input: {}", err))
+            },
+
+            ReadStream::Tcp(sock) => {
+                mio::net::TcpStream::from_stream(sock)
+                    .map(PolledSocket::Tcp)
+                    .map_err(|err| format!("Could not poll the TCP input: {}", err))
+            },
+
+            _ => Err("Only network inputs can be polled".to_string()),
+        }
+    }
+
+    fn register(&self, poll: &Poll, token: Token) -> io::Result<()> {
+        match self {
+            PolledSocket::Udp(sock, _) => poll.register(sock, token, Ready::readable(), PollOpt::edge()),
+
+            PolledSocket::Tcp(sock) => poll.register(sock, token, Ready::readable(), PollOpt::edge()),
+        }
+    }
+
+    /// Read the bytes available from the socket, without blocking, adding them to the given
+    /// bytes, along with the time they were received at if the kernel records it. A UDP read
+    /// adds one datagram to the bytes not yet parsed, as with the threaded engine.
+    fn read(&mut self, bytes: &mut BytesMut, num_bytes: usize) -> io::Result<(usize, Option<SystemTime>)> {
+        let result;
+
+        match self {
+            PolledSocket::Udp(sock, max_datagram_bytes) => {
+                let current_len = bytes.len();
+                bytes.resize(current_len + *max_datagram_bytes, 0);
+                result = sock.recv_timestamped(&mut bytes[current_len..]);
+                bytes.truncate(current_len + result.as_ref().map(|&(num_bytes_read, _)| num_bytes_read).unwrap_or(0));
+            },
+
+            PolledSocket::Tcp(sock) => {
+                let current_len = bytes.len();
+                bytes.resize(current_len + num_bytes, 0);
+                result = sock.read(&mut bytes[current_len..]).map(|num_bytes_read| (num_bytes_read, None));
+                bytes.truncate(current_len + result.as_ref().map(|&(num_bytes_read, _)| num_bytes_read).unwrap_or(0));
+            },
+        }
+
+        result
+    }
+}
+
+/// An input read by the polled input engine, with the messages it has not yet sent to its
+/// processing thread.
+struct PolledInput {
+    socket: PolledSocket,
+    input_parser: InputParser,
+    packet_sender: SyncSender<PacketMsg>,
+    control_receiver: Receiver<InputMsg>,
+    pending: VecDeque<PacketMsg>,
+
+    /// Whether the socket may have bytes to read. The socket is read until it would block.
+    readable: bool,
+
+    /// Whether the input has ended. It is removed from the engine once its pending messages are sent.
+    finished: bool,
+}
+
+impl PolledInput {
+    /// Handle control messages, send pending messages, and read the socket while the processing
+    /// thread is keeping up. Returns false once the input should be removed from the engine.
+    fn update(&mut self) -> bool {
+        loop {
+            match self.control_receiver.try_recv() {
+                Ok(InputMsg::Seek(_)) => {
+                    self.pending.push_back(PacketMsg::ReadError("Seeking is only supported for file inputs".to_string()));
+                    self.pending.push_back(PacketMsg::SeekComplete);
+                },
+
+                Err(TryRecvError::Empty) => break,
+
+                // the processing thread no longer uses this input
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+
+        if !self.send_pending() {
+            return false;
+        }
+
+        while self.readable && !self.finished && self.pending.is_empty() {
+            self.read();
+
+            if !self.send_pending() {
+                return false;
+            }
+        }
+
+        !(self.finished && self.pending.is_empty())
+    }
+
+    fn read(&mut self) {
+        let chunk_bytes = self.input_parser.chunk_bytes;
+        match self.socket.read(self.input_parser.bytes(), chunk_bytes) {
+            Ok((0, _)) if self.is_stream() => {
+                if self.input_parser.parse(0, None, true, &mut self.pending) {
+                    self.end(EndReason::EndOfFile);
+                } else {
+                    self.end(EndReason::Error);
+                }
+            },
+
+            Ok((num_bytes_read, recv_time)) => {
+                if !self.input_parser.parse(num_bytes_read, recv_time, false, &mut self.pending) {
+                    self.end(EndReason::Error);
+                }
+            },
+
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                self.readable = false;
+            },
+
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => {},
+
+            Err(err) => {
+                self.finish(PacketMsg::ReadError(format!("Stream Read Error: {}", err)));
+            },
+        }
+    }
+
+    fn is_stream(&self) -> bool {
+        match self.socket {
+            PolledSocket::Tcp(_) => true,
+            PolledSocket::Udp(_, _) => false,
+        }
+    }
+
+    /// End the input with an error
+    fn finish(&mut self, msg: PacketMsg) {
+        self.pending.push_back(msg);
+        self.end(EndReason::Error);
+    }
+
+    fn end(&mut self, reason: EndReason) {
+        let stream_end = self.input_parser.stream_end(reason);
+        self.pending.push_back(stream_end);
+        self.finished = true;
+    }
+
+    /// Send pending messages until the processing thread's channel is full. Returns false
+    /// if the processing thread no longer receives them.
+    fn send_pending(&mut self) -> bool {
+        while let Some(msg) = self.pending.pop_front() {
+            match self.packet_sender.try_send(msg) {
+                Ok(()) => {},
+
+                Err(TrySendError::Full(msg)) => {
+                    self.pending.push_front(msg);
+                    break;
+                },
+
+                Err(TrySendError::Disconnected(_)) => return false,
+            }
+        }
+
+        true
+    }
+}
+
+/// Add an input to the polled input engine, starting the engine's thread if it is not running
+fn poll_input(input: PolledInput) {
+    let mut poll_engine = POLL_ENGINE.lock().unwrap();
+
+    let input = match *poll_engine {
+        Some(ref input_sender) => {
+            match input_sender.send(input) {
+                Ok(()) => return,
+
+                // the engine's thread has exited, so start it again
+                Err(SendError(input)) => input,
+            }
+        },
+
+        None => input,
+    };
+
+    let (input_sender, input_receiver) = channel();
+    input_sender.send(input).unwrap();
+
+    thread::spawn(move || {
+        poll_engine_thread(input_receiver);
+    });
+
+    *poll_engine = Some(input_sender);
+}
+
+/// The polled input engine, which reads every polled input on a single thread
+fn poll_engine_thread(input_receiver: Receiver<PolledInput>) {
+    let poll = match Poll::new() {
+        Ok(poll) => poll,
+
+        Err(err) => {
+            error!("Could not start the polled input engine: {}", err);
+            return;
+        },
+    };
+
+    let mut events = Events::with_capacity(1024);
+    let mut inputs: HashMap<usize, PolledInput> = HashMap::new();
+    let mut next_token: usize = 0;
+
+    loop {
+        while let Ok(mut input) = input_receiver.try_recv() {
+            match input.socket.register(&poll, Token(next_token)) {
+                Ok(()) => {
+                    inputs.insert(next_token, input);
+                    next_token = next_token.wrapping_add(1);
+                },
+
+                Err(err) => {
+                    input.finish(PacketMsg::ReadError(format!("Could not poll the input stream: {}", err)));
+                    input.send_pending();
+                },
+            }
+        }
+
+        let timeout;
+        if inputs.values().any(|input| !input.pending.is_empty()) {
+            timeout = Duration::from_millis(POLL_BACKLOG_TIMEOUT_MS);
+        } else {
+            timeout = Duration::from_millis(POLL_TIMEOUT_MS);
+        }
+
+        if let Err(err) = poll.poll(&mut events, Some(timeout)) {
+            if err.kind() != ErrorKind::Interrupted {
+                error!("Polled input engine error: {}", err);
+                thread::sleep(timeout);
+            }
+        }
+
+        for event in events.iter() {
+            let Token(token) = event.token();
+            if let Some(input) = inputs.get_mut(&token) {
+                input.readable = true;
+            }
+        }
+
+        // removing an input closes its socket
+        inputs.retain(|_, input| input.update());
+    }
+}
+
+/// Open an input on a thread of its own, which can block while connecting, and add it to
+/// the polled input engine.
+fn start_polled_input(packet_sender: SyncSender<PacketMsg>,
+                      control_receiver: Receiver<InputMsg>,
+                      open_wait: OpenWait,
+                      read_stream_settings: StreamSettings,
+                      input_selection: StreamOption,
+                      ccsds_parser_config: CcsdsParserConfig,
+                      read_settings: ReadSettings,
+                      container_format: ContainerFormat,
+                      raw_capture: Option<RawCapture>) {
+    thread::spawn(move || {
+        let socket = open_input_waiting(input_selection, &read_stream_settings, &open_wait, &packet_sender)
+                         .map_err(|e| format!("Could not open the input stream: {}", e))
+                         .and_then(PolledSocket::from_stream);
+
+        match socket {
+            Ok(socket) => {
+                poll_input(PolledInput {
+                    socket: socket,
+                    input_parser: InputParser::new(ccsds_parser_config, read_settings, &container_format, raw_capture),
+                    packet_sender: packet_sender,
+                    control_receiver: control_receiver,
+                    pending: VecDeque::new(),
+                    readable: true,
+                    finished: false,
+                });
+            },
+
+            Err(e) => {
+                let _ = packet_sender.send(PacketMsg::StreamOpenError(e));
+                let _ = packet_sender.send(PacketMsg::StreamEnd(EndReason::Error, 0, 0));
+            },
+        }
+    });
+}
+
+/// Compute the CRC-16-CCITT (polynomial 0x1021, initial value 0xFFFF) of a slice of bytes.
+/// This is the CRC commonly used for the CCSDS packet error control field.
+pub fn crc16_ccitt(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+
+    for byte in bytes.iter() {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            if crc & 0x8000 != 0 {
+                crc = (crc << 1) ^ 0x1021;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+
+    crc
+}
+
+/// Check the CRC-16 in the last two bytes of a packet, stored big endian
+pub fn crc_valid(bytes: &[u8]) -> bool {
+    if bytes.len() < 2 {
+        return false;
+    }
+
+    let crc_offset = bytes.len() - 2;
+    let packet_crc = ((bytes[crc_offset] as u16) << 8) | bytes[crc_offset + 1] as u16;
+
+    crc16_ccitt(&bytes[..crc_offset]) == packet_crc
+}
+
+// Check whether a pattern of bytes occurs in a packet, either at a given offset or
+// anywhere within the packet.
+fn pattern_matches(bytes: &[u8], offset: Option<u32>, pattern: &[u8]) -> bool {
+    if pattern.len() == 0 {
+        return false;
+    }
+
+    match offset {
+        Some(offset) => {
+            let offset = offset as usize;
+            offset + pattern.len() <= bytes.len() && &bytes[offset..offset + pattern.len()] == pattern
+        },
+
+        None => bytes.windows(pattern.len()).any(|window| window == pattern),
+    }
+}
+
+// Check a packet against the pause conditions, returning a description of the first
+// condition that the packet triggers. The packet count includes the packet if it was forwarded.
+fn check_pause_conditions(pause_conditions: &Vec<PauseCondition>,
+                          packet: &Packet,
+                          last_seq: Option<u16>,
+                          forwarded: bool,
+                          packets_forwarded: u64) -> Option<String> {
+    let apid = packet.header.control.apid();
+    let seq_count = packet.header.sequence.sequence_count();
+
+    for condition in pause_conditions.iter() {
+        let triggered = match condition {
+            PauseCondition::ApidSeen(condition_apid) => *condition_apid == apid,
+
+            // sequence counts are 14 bits, so the expected count wraps around
+            PauseCondition::SequenceGap =>
+                last_seq.map_or(false, |last_seq| is_seq_gap(last_seq, seq_count)),
+
+            PauseCondition::PatternMatch { offset, pattern } =>
+                pattern_matches(&packet.bytes, *offset, pattern),
+
+            PauseCondition::CrcFailure => !crc_valid(&packet.bytes),
+
+            // only the packet that brings the count up to the condition triggers it, as packets
+            // that are not forwarded leave the count where it is, so processing can be continued past it
+            PauseCondition::PacketCount(count) => forwarded && packets_forwarded == *count,
+        };
+
+        if triggered {
+            return Some(condition.description());
+        }
+    }
+
+    None
+}
+
+// Scale a duration by a playback speed multiplier, so a speed of 2.0 halves the duration.
+fn scale_duration(duration: Duration, speed: f32) -> Duration {
+    if speed <= 0.0 {
+        return duration;
+    }
+
+    let secs = duration.as_fractional_secs() / speed as f64;
+    Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32)
+}
+
+// Determine the timeout we can wait before we need to act again
+fn determine_timeout(time_state: &mut TimeState,
+                     packet: &Packet) -> Duration {
+    let timeout: Duration;
+
+    match time_state.timestamp_setting {
+        // Process as fast as possible
+        TimestampSetting::Asap => {
+            timeout = Duration::from_secs(0);
+        }
+
+        // Replaying packets- use the packet's timestamp as an offset
+        TimestampSetting::Replay => {
+           let timestamp = time_state.timestamp_def.decode(&packet.bytes);
+
+            match time_state.replay_anchor {
+                None => {
+                    time_state.replay_anchor = Some((Instant::now(), timestamp));
+                    timeout = Duration::from_secs(0);
+                },
+
+                Some((anchor_time, anchor_timestamp)) =>
+                {
+                    let packet_offset = timestamp.checked_sub(anchor_timestamp).unwrap_or(Duration::from_secs(0));
+                    let send_time = anchor_time + scale_duration(packet_offset, time_state.speed);
+
+                    timeout = duration_until(send_time);
+                },
+            }
+        },
+
+        // delay for a fixed duration
+        TimestampSetting::Delay(duration) => {
+            timeout = scale_duration(duration, time_state.speed);
+        },
+
+        // Throttle packet processing to a fixed rate
+        // This is different from Delay in that it only delays if necessary to
+        // space out packets.
+        TimestampSetting::Throttle(duration) => {
+            match scale_duration(duration, time_state.speed).checked_sub(time_state.last_send_time.elapsed()) {
+                Some(remaining_time) => timeout = remaining_time,
+
+                None => timeout = Duration::from_millis(0),
+            }
+        },
+    }
+
+    timeout
+}
+
+/// An output stream that packets are forwarded to while processing. An output that fails is
+/// degraded- it is not sent packets, while the other outputs continue, until it is reconnected.
+struct Output {
+    stream: WriteStream,
+
+    /// When the output failed, or when reconnecting it was last attempted, if it has failed
+    degraded: Option<Instant>,
+
+    /// Whether the output was opened. A file output that was opened is not reopened, as that
+    /// would overwrite it.
+    opened: bool,
+
+    /// The transforms applied to each packet before it is written
+    transforms: Vec<Transform>,
+
+    /// The time a packet was last written to the output, or the output was opened
+    last_sent: Instant,
+
+    /// Whether the input is held while this output is failed, so that it does not miss packets
+    pauses_input: bool,
+
+    /// The packet being written when the output failed, if it pauses the input. This packet
+    /// is written first when the output reconnects.
+    unsent: Option<Vec<u8>>,
+
+    /// The queue of packets waiting for the output's rate limit, if it has one
+    shaper: Option<Shaper>,
+
+    /// The offset in the output of the next packet written to it
+    position: u64,
+
+    /// The metadata sidecar of a file output, if one is written
+    sidecar: Option<SidecarWriter>,
+
+    /// The format of an output written as a line of text per packet
+    text_format: Option<PacketTextFormat>,
+
+    /// The primary headers of the packets forwarded are little endian
+    little_endian_header: bool,
+
+    /// The stream of an output opened lazily, which is opened when the first packet is
+    /// forwarded to it rather than when processing starts, until it is opened
+    lazy_open: Option<(StreamOption, StreamSettings)>,
+
+    /// The packets routed to the output that did not reach it, for the processing report
+    delivery: OutputDelivery,
+
+    /// Whether the output is enabled. A disabled output is not opened or sent packets.
+    enabled: bool,
+
+    /// How the output is identified in messages, by its number and name
+    label: String,
+
+    /// Whether the output is in a simulated link outage, during which nothing is written to it
+    in_outage: bool,
+
+    /// The priorities of the APIDs, which order the packets waiting for the output's rate limit
+    /// or in its backlog
+    priorities: PrioritySettings,
+
+    /// The backlog the output's packets are kept in while it is down, if it stores and forwards them
+    store_forward: Option<StoreForward>,
+
+    /// The bytes of the sync marker the output's stream writes before each packet
+    sync_marker_bytes: usize,
+}
+
+impl Output {
+    fn new(stream: WriteStream, transforms: Vec<Transform>) -> Output {
+        Output { stream: stream,
+                 degraded: None,
+                 opened: true,
+                 transforms: transforms,
+                 last_sent: Instant::now(),
+                 pauses_input: false,
+                 unsent: None,
+                 shaper: None,
+                 position: 0,
+                 sidecar: None,
+                 text_format: None,
+                 little_endian_header: false,
+                 lazy_open: None,
+                 delivery: OutputDelivery::default(),
+                 enabled: true,
+                 label: String::new(),
+                 in_outage: false,
+                 priorities: PrioritySettings::default(),
+                 store_forward: None,
+                 sync_marker_bytes: 0,
+        }
+    }
+
+    /// An output that is turned off for this run, keeping its place among the outputs
+    fn disabled(transforms: Vec<Transform>) -> Output {
+        let mut output = Output::new(WriteStream::Null, transforms);
+        output.opened = false;
+        output.enabled = false;
+        output
+    }
+
+    /// An output opened when the first packet is forwarded to it
+    fn lazy(selection: StreamOption, settings: StreamSettings, transforms: Vec<Transform>) -> Output {
+        let mut output = Output::new(WriteStream::Null, transforms);
+        output.opened = false;
+        output.lazy_open = Some((selection, settings));
+        output
+    }
+
+    /// Open an output opened lazily, if it has not been opened yet, returning whether it is open.
+    /// A network output is given a short time to connect, so processing does not stall waiting
+    /// for it, and is otherwise degraded and reconnected like any failed output.
+    fn open_lazily(&mut self, index: usize, sender: &Sender<GuiMessage>) -> bool {
+        let (selection, settings) = match self.lazy_open.take() {
+            Some(lazy_open) => lazy_open,
+
+            None => return true,
+        };
+
+        let wait = OpenWait::new(Some(Duration::from_millis(RETRY_CONNECT_TIMEOUT_MS)), Arc::new(AtomicBool::new(false)));
+        match selection.open_output_with(&settings, &wait) {
+            Ok(stream) => {
+                info!("{} opened for its first packet", self.label);
+                self.reopened(stream);
+                true
+            },
+
+            Err(err_string) => {
+                self.fail(index, err_string, sender);
+                false
+            },
+        }
+    }
+
+    /// An output that could not be opened when processing started
+    fn unopened(index: usize, label: String, err_string: String, transforms: Vec<Transform>, sender: &Sender<GuiMessage>) -> Output {
+        let mut output = Output::new(WriteStream::Null, transforms);
+        output.opened = false;
+        output.label = label;
+        output.fail(index, err_string, sender);
+        output
+    }
+
+    /// Use a reopened stream for an output that failed
+    fn reopened(&mut self, stream: WriteStream) {
+        self.stream = stream;
+        self.degraded = None;
+        self.opened = true;
+        self.last_sent = Instant::now();
+    }
+
+    /// Write a packet to the output through its transforms, returning whether it was written.
+    /// An output that can not be written to is degraded.
+    fn send(&mut self, index: usize, apid: u16, bytes: &[u8], recv_time: SystemTime, sender: &Sender<GuiMessage>) -> bool {
+        let mut packet_bytes = match apply_transforms(&self.transforms, apid, bytes, self.little_endian_header) {
+            Some(packet_bytes) => packet_bytes,
+
+            None => {
+                self.delivery.transform_excluded += 1;
+                return false;
+            },
+        };
+
+        // a text output describes the packet as it was forwarded, so the transforms only
+        // select the packets written
+        if let Some(ref text_format) = self.text_format {
+            packet_bytes = Cow::Owned(text_format.line(apid, bytes, recv_time).into_bytes());
+        }
+        let written_length = packet_bytes.len() + self.sync_marker_bytes;
+
+        if self.lazy_open.is_some() && !self.open_lazily(index, sender) && self.store_forward.is_none() {
+            self.hold_unsent(packet_bytes.into_owned());
+            return false;
+        }
+
+        // while a store and forward output is down, or its backlog is still being written, packets
+        // join the backlog so that they are written in the order they were forwarded
+        if self.stores() {
+            return self.store(apid, bytes, &packet_bytes, recv_time, sender);
+        }
+
+        // a rate limited output is written from its queue, as its rate limit allows
+        let queued = match self.shaper {
+            Some(ref mut shaper) => {
+                if !shaper.push(self.priorities.priority(apid), packet_bytes.into_owned()) {
+                    if shaper.dropped == 1 {
+                        warn!("{} is being forwarded packets faster than its rate limit, and its queue is full, so packets are being dropped", self.label);
+                        let _ = sender.send(GuiMessage::Error(format!("{} is dropping packets, as its rate limit queue is full", self.label)));
+                    }
+                    if let Some(header) = primary_header(bytes, self.little_endian_header) {
+                        let _ = sender.send(GuiMessage::PacketDropped(header, DropReason::QueueOverflow));
+                    }
+                    self.delivery.queue_overflow += 1;
+                    return false;
+                }
+                true
+            },
+
+            None => false,
+        };
+
+        if queued {
+            self.record(apid, bytes, written_length, recv_time, sender);
+            self.write_shaped(index, sender);
+            return true;
+        }
+
+        match self.stream.stream_send(&packet_bytes) {
+            Ok(()) => {
+                self.last_sent = Instant::now();
+                self.record(apid, bytes, written_length, recv_time, sender);
+                true
+            },
+
+            Err(err_string) => {
+                self.fail(index, err_string, sender);
+                if self.store_forward.is_some() {
+                    return self.store(apid, bytes, &packet_bytes, recv_time, sender);
+                }
+
+                self.hold_unsent(packet_bytes.into_owned());
+                false
+            },
+        }
+    }
+
+    /// Whether packets forwarded to the output are added to its backlog, because it is down or
+    /// its backlog has not all been written yet
+    fn stores(&self) -> bool {
+        self.store_forward.as_ref().map_or(false, |store_forward| self.degraded.is_some() || !store_forward.is_empty())
+    }
+
+    /// Add a packet to the output's backlog, returning whether it was kept to be written once
+    /// the output reconnects. A backlog that can not be written to is given up, and the packets
+    /// for the output are dropped while it is down, as they are without store and forward.
+    fn store(&mut self, apid: u16, bytes: &[u8], packet_bytes: &[u8], recv_time: SystemTime, sender: &Sender<GuiMessage>) -> bool {
+        let result = match self.store_forward {
+            Some(ref mut store_forward) => store_forward.push(self.priorities.priority(apid), packet_bytes),
+
+            None => Ok(false),
+        };
+
+        match result {
+            Ok(true) => {
+                self.record(apid, bytes, packet_bytes.len() + self.sync_marker_bytes, recv_time, sender);
+                true
+            },
+
+            Ok(false) => {
+                if self.delivery.backlog_overflow == 0 {
+                    warn!("{} has filled its backlog, so packets are being dropped until it reconnects", self.label);
+                    let _ = sender.send(GuiMessage::Error(format!("{} is dropping packets, as its backlog is full", self.label)));
+                }
+                self.delivery.backlog_overflow += 1;
+                false
+            },
+
+            Err(err_string) => {
+                let _ = sender.send(GuiMessage::Error(format!("{} stopped storing packets: {}", self.label, err_string)));
+                if let Some(store_forward) = self.store_forward.take() {
+                    self.delivery.backlog_unwritten += store_forward.len();
+                }
+                self.delivery.output_failed += 1;
+                false
+            },
+        }
+    }
+
+    /// Write the output's backlog once it has reconnected, as fast as its drain rate allows.
+    /// Packets queued for a rate limit are older than the backlog, so they are written first.
+    fn drain_backlog(&mut self, index: usize, sender: &Sender<GuiMessage>) {
+        if self.degraded.is_some() || self.shaper.as_ref().map_or(false, |shaper| !shaper.is_empty()) {
+            return;
+        }
+
+        let mut backlog_error = None;
+        let mut send_error = None;
+        let mut drained = false;
+        if let Some(ref mut store_forward) = self.store_forward {
+            store_forward.refill();
+
+            for _ in 0..BACKLOG_DRAIN_PACKETS {
+                if !store_forward.allows_write() {
+                    break;
+                }
+
+                let level = match store_forward.next_level() {
+                    Some(level) => level,
+
+                    None => break,
+                };
+
+                let written = match store_forward.backlogs[level].front() {
+                    Ok(Some(packet_bytes)) => self.stream.stream_send(packet_bytes).map(|_| packet_bytes.len()),
+
+                    Ok(None) => break,
+
+                    Err(err_string) => {
+                        backlog_error = Some(err_string);
+                        break;
+                    },
+                };
+
+                match written {
+                    Ok(written_length) => {
+                        self.last_sent = Instant::now();
+                        store_forward.wrote(written_length);
+                        if let Err(err_string) = store_forward.backlogs[level].pop() {
+                            backlog_error = Some(err_string);
+                            break;
+                        }
+                        drained = store_forward.is_empty();
+                    },
+
+                    Err(err_string) => {
+                        send_error = Some(err_string);
+                        break;
+                    },
+                }
+            }
+        }
+
+        if drained {
+            info!("{} has written its backlog", self.label);
+        }
+
+        if let Some(err_string) = backlog_error {
+            let _ = sender.send(GuiMessage::Error(format!("{} stopped storing packets: {}", self.label, err_string)));
+            if let Some(store_forward) = self.store_forward.take() {
+                self.delivery.backlog_unwritten += store_forward.len();
+            }
+        }
+
+        if let Some(err_string) = send_error {
+            self.fail(index, err_string, sender);
+        }
+    }
+
+    /// Write a packet made by the router, such as a heartbeat, rather than routed from the
+    /// input, so it is not counted in the output's delivery
+    fn send_generated(&mut self, index: usize, apid: u16, bytes: &[u8], sender: &Sender<GuiMessage>) {
+        let delivery = self.delivery.clone();
+        self.send(index, apid, bytes, SystemTime::now(), sender);
+        self.delivery = delivery;
+    }
+
+    /// Keep a packet that could not be written, to write it once the output reconnects if the
+    /// output pauses the input, or otherwise count it as not delivered
+    fn hold_unsent(&mut self, packet_bytes: Vec<u8>) {
+        if self.pauses_input {
+            self.unsent = Some(packet_bytes);
+        } else {
+            self.delivery.output_failed += 1;
+        }
+    }
+
+    /// The packets routed to the output that did not reach it, counting the packets still
+    /// waiting to be written as not delivered
+    fn delivery(&self) -> OutputDelivery {
+        let mut delivery = self.delivery.clone();
+
+        if let Some(ref shaper) = self.shaper {
+            delivery.queue_unwritten += shaper.len() as u64;
+        }
+        if let Some(ref store_forward) = self.store_forward {
+            delivery.backlog_unwritten += store_forward.len();
+        }
+        if self.unsent.is_some() {
+            delivery.output_failed += 1;
+        }
+
+        delivery
+    }
+
+    /// Account for a packet accepted by the output, adding it to the output's metadata sidecar if
+    /// it has one. The packet's bytes are as forwarded, before the output's transforms.
+    fn record(&mut self, apid: u16, bytes: &[u8], written_length: usize, recv_time: SystemTime, sender: &Sender<GuiMessage>) {
+        let offset = self.position;
+        self.position += written_length as u64;
+
+        let result = match self.sidecar {
+            Some(ref mut sidecar) => sidecar.write_record(offset, apid, bytes, written_length, recv_time),
+
+            None => Ok(()),
+        };
+
+        if let Err(err_string) = result {
+            let _ = sender.send(GuiMessage::Error(err_string));
+            self.sidecar = None;
+        }
+    }
+
+    /// Write the queued packets that the output's rate limit allows
+    fn write_shaped(&mut self, index: usize, sender: &Sender<GuiMessage>) {
+        if self.degraded.is_some() {
+            return;
+        }
+
+        let mut result = Ok(());
+        if let Some(ref mut shaper) = self.shaper {
+            shaper.refill();
+            while let Some((priority, packet_bytes)) = shaper.pop() {
+                result = self.stream.stream_send(&packet_bytes);
+                if result.is_err() {
+                    // the packet stays queued, and is written first once the output reconnects
+                    shaper.requeue(priority, packet_bytes);
+                    break;
+                }
+                self.last_sent = Instant::now();
+            }
+        }
+
+        if let Err(err_string) = result {
+            self.fail(index, err_string, sender);
+        }
+    }
+
+    /// Whether the output has packets queued for its rate limit, or in its backlog, that can
+    /// still be written
+    fn has_queued(&self) -> bool {
+        self.degraded.is_none() &&
+        (self.shaper.as_ref().map_or(false, |shaper| !shaper.is_empty()) ||
+         self.store_forward.as_ref().map_or(false, |store_forward| !store_forward.is_empty()))
+    }
+
+    /// Mark the output as degraded, reporting the error that caused it
+    fn fail(&mut self, index: usize, err_string: String, sender: &Sender<GuiMessage>) {
+        warn!("{} failed, and will not be sent packets until it is reconnected: {}", self.label, err_string);
+        self.degraded = Some(Instant::now());
+        let _ = sender.send(GuiMessage::OutputDegraded(index, self.label.clone(), err_string));
+    }
+}
+
+/// The most time of writing a rate limited output can save up while it is idle, which limits
+/// the size of the bursts it writes
+const SHAPER_BURST_SECS: f64 = 0.1;
+
+/// A token bucket queueing the packets of a rate limited output until its rate allows them
+/// to be written
+struct Shaper {
+    bytes_per_sec: f64,
+
+    max_queued_bytes: u64,
+
+    /// The bytes that can be written now. A packet is written whenever this is positive, so
+    /// it goes negative after a packet larger than the bytes available, delaying the next.
+    tokens: f64,
+
+    last_refill: Instant,
+
+    /// The packets waiting for the rate limit, in a queue for each priority
+    queues: [VecDeque<Vec<u8>>; PRIORITY_LEVELS],
+
+    scheduler: PriorityScheduler,
+
+    queued_bytes: u64,
+
+    /// The packets dropped because the queue was full
+    dropped: u64,
+}
+
+impl Shaper {
+    fn new(rate_limit: &RateLimitSettings, priorities: &PrioritySettings) -> Shaper {
+        let bytes_per_sec = rate_limit.bytes_per_sec as f64;
+
+        Shaper {
+            bytes_per_sec: bytes_per_sec,
+            max_queued_bytes: rate_limit.max_queued_bytes,
+            tokens: bytes_per_sec * SHAPER_BURST_SECS,
+            last_refill: Instant::now(),
+            queues: [VecDeque::new(), VecDeque::new(), VecDeque::new()],
+            scheduler: PriorityScheduler::new(priorities.starvation_limit),
+            queued_bytes: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Add the bytes allowed by the time since the last refill
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed_secs = now.duration_since(self.last_refill).as_fractional_secs();
+        self.tokens = (self.tokens + elapsed_secs * self.bytes_per_sec).min(self.bytes_per_sec * SHAPER_BURST_SECS);
+        self.last_refill = now;
+    }
+
+    /// Queue a packet with the priority of its APID, returning false if it is dropped because
+    /// the queue is full
+    fn push(&mut self, priority: Priority, packet_bytes: Vec<u8>) -> bool {
+        if self.queued_bytes + packet_bytes.len() as u64 > self.max_queued_bytes {
+            self.dropped += 1;
+            return false;
+        }
+
+        self.queued_bytes += packet_bytes.len() as u64;
+        self.queues[priority.level()].push_back(packet_bytes);
+        true
+    }
+
+    /// The next queued packet, by priority, with its priority, if the rate allows it to be written now
+    fn pop(&mut self) -> Option<(Priority, Vec<u8>)> {
+        if self.tokens <= 0.0 {
+            return None;
+        }
+
+        let waiting = [!self.queues[0].is_empty(), !self.queues[1].is_empty(), !self.queues[2].is_empty()];
+        let priority = self.scheduler.next(waiting)?;
+
+        let packet_bytes = self.queues[priority.level()].pop_front()?;
+        self.queued_bytes -= packet_bytes.len() as u64;
+        self.tokens -= packet_bytes.len() as f64;
+
+        Some((priority, packet_bytes))
+    }
+
+    /// Return a packet that could not be written to the front of its queue, so it is the next
+    /// written once the output can be written again
+    fn requeue(&mut self, priority: Priority, packet_bytes: Vec<u8>) {
+        self.queued_bytes += packet_bytes.len() as u64;
+        self.tokens += packet_bytes.len() as f64;
+        self.queues[priority.level()].push_front(packet_bytes);
+    }
+
+    /// The packets queued
+    fn len(&self) -> usize {
+        self.queues.iter().map(|queue| queue.len()).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queues.iter().all(|queue| queue.is_empty())
+    }
+}
+
+/// The most packets of a backlog written each time the outputs are written, so that writing a
+/// large backlog does not stall processing
+const BACKLOG_DRAIN_PACKETS: usize = 1000;
+
+/// The backlog of a store and forward output, and the rate it is written at once the output
+/// reconnects, which is limited by a token bucket like a rate limit. With APID priorities, there
+/// is a backlog for each priority, and otherwise a single backlog.
+struct StoreForward {
+    backlogs: Vec<Backlog>,
+
+    /// The most bytes the backlogs can hold between them
+    max_bytes: u64,
+
+    scheduler: PriorityScheduler,
+
+    /// The most bytes of the backlog written per second, or 0 for no limit
+    bytes_per_sec: f64,
+
+    tokens: f64,
+
+    last_refill: Instant,
+}
+
+impl StoreForward {
+    /// Create the backlog files of a session's output
+    fn create(session_name: &str, index: usize, settings: &StoreForwardSettings, priorities: &PrioritySettings) -> Result<StoreForward, String> {
+        let backlogs = if priorities.enabled {
+            let mut backlogs = Vec::new();
+            for level in 0..PRIORITY_LEVELS {
+                backlogs.push(Backlog::create(&settings.directory, session_name, index, Some(Priority::from_level(level)), settings.max_backlog_bytes)?);
+            }
+            backlogs
+        } else {
+            vec!(Backlog::create(&settings.directory, session_name, index, None, settings.max_backlog_bytes)?)
+        };
+        let bytes_per_sec = settings.drain_bytes_per_sec as f64;
+
+        Ok(StoreForward {
+            backlogs: backlogs,
+            max_bytes: settings.max_backlog_bytes,
+            scheduler: PriorityScheduler::new(priorities.starvation_limit),
+            bytes_per_sec: bytes_per_sec,
+            tokens: bytes_per_sec * SHAPER_BURST_SECS,
+            last_refill: Instant::now(),
+        })
+    }
+
+    /// Store a packet in the backlog of its priority, returning false if the backlogs are full
+    fn push(&mut self, priority: Priority, packet_bytes: &[u8]) -> Result<bool, String> {
+        let stored_bytes: u64 = self.backlogs.iter().map(|backlog| backlog.bytes()).sum();
+        if stored_bytes + (BACKLOG_LENGTH_BYTES + packet_bytes.len()) as u64 > self.max_bytes {
+            return Ok(false);
+        }
+
+        let level = if self.backlogs.len() > 1 { priority.level() } else { 0 };
+        self.backlogs[level].push(packet_bytes)
+    }
+
+    /// The backlog to write the next packet from, by priority, or None if they are all empty
+    fn next_level(&mut self) -> Option<usize> {
+        if self.backlogs.len() == 1 {
+            return if self.backlogs[0].is_empty() { None } else { Some(0) };
+        }
+
+        let waiting = [!self.backlogs[0].is_empty(), !self.backlogs[1].is_empty(), !self.backlogs[2].is_empty()];
+        self.scheduler.next(waiting).map(|priority| priority.level())
+    }
+
+    /// The packets stored and not yet written to the output
+    fn len(&self) -> u64 {
+        self.backlogs.iter().map(|backlog| backlog.len()).sum()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.backlogs.iter().all(|backlog| backlog.is_empty())
+    }
+
+    /// Add the bytes allowed by the time since the last refill
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed_secs = now.duration_since(self.last_refill).as_fractional_secs();
+        self.tokens = (self.tokens + elapsed_secs * self.bytes_per_sec).min(self.bytes_per_sec * SHAPER_BURST_SECS);
+        self.last_refill = now;
+    }
+
+    /// Whether the drain rate allows another packet to be written now
+    fn allows_write(&self) -> bool {
+        self.bytes_per_sec <= 0.0 || self.tokens > 0.0
+    }
+
+    fn wrote(&mut self, written_length: usize) {
+        if self.bytes_per_sec > 0.0 {
+            self.tokens -= written_length as f64;
+        }
+    }
+}
+
+// Write the packets queued for each rate limited output that their rate limits allow, and the
+// backlog of each store and forward output that has reconnected
+fn write_shaped_outputs(output_streams: &mut Vec<Output>, sender: &Sender<GuiMessage>) {
+    for (index, output) in output_streams.iter_mut().enumerate() {
+        if output.shaper.is_some() {
+            output.write_shaped(index, sender);
+        }
+
+        if output.store_forward.is_some() {
+            output.drain_backlog(index, sender);
+        }
+    }
+}
+
+// Wait for the packets queued for rate limited outputs to be written once the input ends, so
+// the end of the input is not lost from them. If processing is cancelled or terminated while
+// waiting, the message is returned and the remaining packets are not written.
+fn finish_shaped_outputs(output_streams: &mut Vec<Output>, receiver: &Receiver<ProcessingMsg>, sender: &Sender<GuiMessage>) -> Option<ProcessingMsg> {
+    loop {
+        write_shaped_outputs(output_streams, sender);
+        if !output_streams.iter().any(|output| output.has_queued()) {
+            return None;
+        }
+
+        match receiver.recv_timeout(Duration::from_millis(SHAPER_POLL_MS)) {
+            Ok(msg @ ProcessingMsg::Cancel) | Ok(msg @ ProcessingMsg::Terminate) => return Some(msg),
+
+            Ok(msg) => {
+                debug!("Ignoring {} while writing the packets queued for rate limited outputs", msg.name());
+            },
+
+            Err(RecvTimeoutError::Timeout) => {},
+
+            Err(RecvTimeoutError::Disconnected) => return Some(ProcessingMsg::Terminate),
+        }
+    }
+}
+
+// Send a packet to each output stream that allows its APID, returning a mask with
+// bit N set if the packet was sent to output N. Only the first 32 outputs are in the mask.
+fn forward_packet(output_streams: &mut Vec<Output>,
+                  allowed_output_apids: &Vec<Option<Vec<u16>>>,
+                  apid: u16,
+                  bytes: &[u8],
+                  recv_time: SystemTime,
+                  sender: &Sender<GuiMessage>) -> u32 {
+    let mut output_mask = 0;
+
+    for index in 0..output_streams.len() {
+        if !output_streams[index].enabled {
+            continue;
+        }
+
+        let apid_allowed;
+
+        match allowed_output_apids[index] {
+            Some(ref apids) => {
+                apid_allowed = apids.contains(&apid);
+            },
+
+            None => apid_allowed = true,
+        }
+
+        if !apid_allowed {
+            output_streams[index].delivery.apid_excluded += 1;
+        } else if output_streams[index].in_outage {
+            output_streams[index].delivery.outage_dropped += 1;
+        } else if output_streams[index].degraded.is_some() && output_streams[index].store_forward.is_none() {
+            output_streams[index].delivery.output_failed += 1;
+        } else if output_streams[index].send(index, apid, bytes, recv_time, sender) && index < 32 {
+            output_mask |= 1 << index;
+        }
+    }
+
+    output_mask
+}
+
+// Write a heartbeat packet to each output that has not been sent a packet within the heartbeat
+// interval. Heartbeats pass through each output's transforms, but are not limited by its allowed APIDs.
+fn send_heartbeats(output_streams: &mut Vec<Output>, app_config: &AppConfig, heartbeat_seq: &mut u16, sender: &Sender<GuiMessage>) {
+    let heartbeat_settings = &app_config.heartbeat_settings;
+    if !heartbeat_settings.enabled || !(heartbeat_settings.interval_secs > 0.0) {
+        return;
+    }
+    let interval = secs_duration(heartbeat_settings.interval_secs);
+
+    // outputs opened lazily are not opened for a heartbeat
+    let heartbeat_due = |output: &Output| {
+        output.enabled && !output.in_outage && !output.stores() && output.lazy_open.is_none() && output.degraded.is_none() &&
+        output.last_sent.elapsed() >= interval
+    };
+    if !output_streams.iter().any(&heartbeat_due) {
+        return;
+    }
+
+    let template = PacketTemplate { apid: heartbeat_settings.apid,
+                                    is_command: false,
+                                    secondary_header: false,
+                                    sequence_flags: SequenceFlags::Unsegmented,
+                                    sequence_count: *heartbeat_seq,
+                                    data: heartbeat_settings.data.clone(),
+    };
+    let bytes = match template.to_bytes(app_config.little_endian_ccsds) {
+        Ok(bytes) => bytes,
+
+        Err(err_string) => {
+            // the outputs are treated as sent to, so the error is reported once an interval
+            // rather than on every check
+            let _ = sender.send(GuiMessage::Error(format!("Heartbeat packet could not be built: {}", err_string)));
+            for output in output_streams.iter_mut() {
+                output.last_sent = Instant::now();
+            }
+            return;
+        },
+    };
+    *heartbeat_seq = next_seq(*heartbeat_seq);
+
+    for (index, output) in output_streams.iter_mut().enumerate() {
+        if heartbeat_due(output) {
+            output.send_generated(index, heartbeat_settings.apid, &bytes, sender);
+        }
+    }
+}
+
+// Start or end a simulated link outage of every output, as the outage schedule gives, and tell
+// the GUI when the link goes down or comes back up
+fn update_outage(output_streams: &mut Vec<Output>, outage: &Option<OutageSchedule>, sender: &Sender<GuiMessage>) {
+    let in_outage = outage.as_ref().map_or(false, |outage| outage.in_outage(SystemTime::now()));
+
+    let changed = output_streams.iter().any(|output| output.in_outage != in_outage);
+    for output in output_streams.iter_mut() {
+        output.in_outage = in_outage;
+    }
+
+    if changed {
+        let _ = sender.send(GuiMessage::Outage(in_outage));
+    }
+}
+
+/// The times used to write time packets while processing
+struct TimePacketState {
+    /// When processing started, which time packets with a relative epoch count from
+    started: Instant,
+
+    /// When the last time packet was written
+    last_sent: Instant,
+
+    /// The sequence count of the next time packet
+    sequence_count: u16,
+}
+
+impl TimePacketState {
+    fn new() -> TimePacketState {
+        TimePacketState {
+            started: Instant::now(),
+            last_sent: Instant::now(),
+            sequence_count: 0,
+        }
+    }
+}
+
+// Write a time packet holding the current ground time to each output once the time packet
+// interval has passed. Like heartbeats, time packets pass through each output's transforms, but
+// are not limited by its allowed APIDs.
+fn send_time_packets(output_streams: &mut Vec<Output>, app_config: &AppConfig, time_packets: &mut TimePacketState, sender: &Sender<GuiMessage>) {
+    let time_packet_settings = &app_config.time_packet_settings;
+    if !time_packet_settings.enabled ||
+       !(time_packet_settings.interval_secs > 0.0) ||
+       time_packets.last_sent.elapsed() < secs_duration(time_packet_settings.interval_secs) {
+        return;
+    }
+    time_packets.last_sent = Instant::now();
+
+    let time_def = &time_packet_settings.time_def;
+    let ground_secs = time_def.epoch
+                              .from_utc(&Utc::now())
+                              .unwrap_or_else(|| time_packets.started.elapsed().as_fractional_secs());
+
+    let mut data = vec![0; time_def.offset.max(0) as usize];
+    data.extend(time_def.encode_fields(ground_secs));
+
+    let template = PacketTemplate { apid: time_packet_settings.apid,
+                                    is_command: false,
+                                    secondary_header: time_packet_settings.secondary_header,
+                                    sequence_flags: SequenceFlags::Unsegmented,
+                                    sequence_count: time_packets.sequence_count,
+                                    data: data,
+    };
+    let bytes = match template.to_bytes(app_config.little_endian_ccsds) {
+        Ok(bytes) => bytes,
+
+        Err(err_string) => {
+            let _ = sender.send(GuiMessage::Error(format!("Time packet could not be built: {}", err_string)));
+            return;
+        },
+    };
+    time_packets.sequence_count = next_seq(time_packets.sequence_count);
+
+    for (index, output) in output_streams.iter_mut().enumerate() {
+        if output.enabled && !output.in_outage && !output.stores() && output.degraded.is_none() && output.lazy_open.is_none() {
+            output.send_generated(index, time_packet_settings.apid, &bytes, sender);
+        }
+    }
+}
+
+/// The longest time processing waits for a packet before checking for control messages
+const INPUT_IDLE_MS: u64 = 100;
+
+/// The time allowed for a degraded network output to reconnect before trying again later
+const RETRY_CONNECT_TIMEOUT_MS: u64 = 250;
+
+/// The time between checks for messages while waiting for the outputs to open
+const OPEN_POLL_MS: u64 = 100;
+
+/// The time between writes to rate limited outputs while waiting for their queues to empty
+const SHAPER_POLL_MS: u64 = 10;
+
+/// The result of opening the output streams when processing starts
+enum OutputsOpened {
+    /// The result of opening each output. An output only fails to open here if the open
+    /// policy is to continue with the outputs that opened.
+    Opened(Vec<Result<WriteStream, String>>),
+    Failed(String),
+    /// Processing was cancelled while waiting for an output to connect
+    Cancelled,
+    Terminated,
+}
+
+/// The output files of a configuration, as their file settings, with their name templates
+/// expanded for a given start time. Outputs written to stdout are not included.
+fn output_files<'a>(app_config: &'a mut AppConfig, start_time: &DateTime<Local>) -> Vec<&'a mut FileSettings> {
+    let session_name = app_config.session_name.clone();
+
+    let output_enabled = (0..app_config.output_selection.len()).map(|index| app_config.is_output_enabled(index)).collect::<Vec<bool>>();
+
+    app_config.output_selection.iter()
+                               .zip(app_config.output_settings.iter_mut())
+                               .zip(output_enabled.into_iter())
+                               .filter(|((selection, output_settings), enabled)| {
+                                   *enabled && **selection == StreamOption::File && output_settings.file.file_name != STDOUT_FILE_NAME
+                               })
+                               .map(|((_, output_settings), _)| {
+                                   output_settings.file.file_name = expand_file_name(&output_settings.file.file_name, &session_name, start_time);
+                                   &mut output_settings.file
+                               })
+                               .collect()
+}
+
+/// The output files that already exist, and that the GUI should ask about overwriting before
+/// processing starts
+pub fn existing_output_files(app_config: &AppConfig) -> Vec<String> {
+    let mut app_config = app_config.clone();
+
+    output_files(&mut app_config, &Local::now()).into_iter()
+                                                .filter(|file_settings| file_settings.if_exists == FileExistsPolicy::Ask)
+                                                .filter(|file_settings| Path::new(&file_settings.file_name).exists())
+                                                .map(|file_settings| file_settings.file_name.clone())
+                                                .collect()
+}
+
+// Expand the output file name templates, and give each output file that already exists a new
+// name, unless it is to be overwritten.
+fn resolve_output_files(app_config: &mut AppConfig) {
+    for file_settings in output_files(app_config, &Local::now()) {
+        if file_settings.if_exists != FileExistsPolicy::Overwrite {
+            let file_name = unique_file_name(&file_settings.file_name);
+            if file_name != file_settings.file_name {
+                info!("Output file {} already exists, writing {} instead", file_settings.file_name, file_name);
+                file_settings.file_name = file_name;
+            }
+        }
+    }
+}
+
+// Open the output streams on a thread of their own, so that processing can be cancelled while
+// waiting for network outputs to connect. Changes to the playback speed, timing, and disabled
+// APIDs made while waiting are kept.
+fn open_outputs(app_config: &mut AppConfig,
+                receiver: &Receiver<ProcessingMsg>,
+                sender: &Sender<GuiMessage>,
+                playback_speed: &mut f32,
+                disabled_apids: &mut Vec<u16>) -> OutputsOpened {
+    // a dry run opens none of its outputs, so nothing is written.
+    if app_config.dry_run {
+        return OutputsOpened::Opened(app_config.output_settings.iter().map(|_| Ok(WriteStream::Null)).collect());
+    }
+
+    let cancel = Arc::new(AtomicBool::new(false));
+    let wait = open_wait(app_config, cancel.clone());
+    let output_selection = app_config.output_selection.clone();
+    let output_settings = app_config.output_settings.clone();
+    let output_enabled = (0..output_selection.len()).map(|index| app_config.is_output_enabled(index)).collect::<Vec<bool>>();
+    let output_labels = (0..output_selection.len()).map(|index| app_config.output_label(index)).collect::<Vec<String>>();
+    let output_open_policy = app_config.output_open_policy;
+    let status_sender = sender.clone();
+    let (opened_sender, opened_receiver) = channel();
+
+    thread::spawn(move || {
+        let mut output_streams = Vec::new();
+
+        for (index, (selection, settings)) in output_selection.iter().zip(output_settings.iter()).enumerate() {
+            // an output opened lazily is opened when the first packet is forwarded to it, and a
+            // disabled output is not opened at all
+            if settings.open_timing == OutputOpenTiming::Lazy || !output_enabled[index] {
+                output_streams.push(Ok(WriteStream::Null));
+                continue;
+            }
+
+            if selection.connects() {
+                let description = format!("{} {}", output_labels[index], stream_spec(*selection, settings));
+                let _ = status_sender.send(GuiMessage::WaitingForConnection(Some(description)));
+            }
+
+            let open_result = selection.open_output_with(settings, &wait);
+
+            if selection.connects() {
+                let _ = status_sender.send(GuiMessage::WaitingForConnection(None));
+            }
+
+            match open_result {
+                Err(err_string) if output_open_policy == OutputOpenPolicy::AllOrNothing => {
+                    let _ = opened_sender.send(Err(err_string));
+                    return;
+                },
+
+                open_result => output_streams.push(open_result),
+            }
+        }
+
+        let _ = opened_sender.send(Ok(output_streams));
+    });
+
+    loop {
+        match opened_receiver.recv_timeout(Duration::from_millis(OPEN_POLL_MS)) {
+            Ok(Ok(streams)) => return OutputsOpened::Opened(streams),
+
+            Ok(Err(err_string)) => return OutputsOpened::Failed(err_string),
+
+            Err(RecvTimeoutError::Timeout) => {},
+
+            Err(RecvTimeoutError::Disconnected) => return OutputsOpened::Failed("The outputs could not be opened".to_string()),
+        }
+
+        loop {
+            match receiver.try_recv() {
+                Ok(ProcessingMsg::Cancel) => {
+                    info!("Cancelled while waiting for the outputs to connect");
+                    cancel.store(true, Ordering::SeqCst);
+                    return OutputsOpened::Cancelled;
+                },
+
+                Ok(ProcessingMsg::Terminate) | Err(TryRecvError::Disconnected) => {
+                    cancel.store(true, Ordering::SeqCst);
+                    return OutputsOpened::Terminated;
+                },
+
+                Ok(ProcessingMsg::SetSpeed(speed)) => {
+                    *playback_speed = speed;
+                },
+
+                Ok(ProcessingMsg::UpdateTiming(timestamp_setting)) => {
+                    app_config.timestamp_setting = timestamp_setting;
+                },
+
+                Ok(ProcessingMsg::UpdateFilter(apids)) => {
+                    *disabled_apids = apids;
+                },
+
+                Ok(msg) => {
+                    let _ = sender.send(GuiMessage::Error(format!("Unexpected message while waiting for the outputs to connect {}", msg.name())));
+                },
+
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+    }
+}
+
+// Attempt to reconnect each degraded output once the retry interval has passed since it
+// failed or was last attempted.
+fn retry_outputs(output_streams: &mut Vec<Output>, app_config: &AppConfig, sender: &Sender<GuiMessage>) {
+    if app_config.output_retry_secs <= 0.0 {
+        return;
+    }
+    let retry_interval = secs_duration(app_config.output_retry_secs);
+
+    for (index, output) in output_streams.iter_mut().enumerate() {
+        let retry_due = output.degraded.map_or(false, |degraded_time| degraded_time.elapsed() >= retry_interval);
+        if retry_due {
+            if let Err(err_string) = retry_output(index, output, app_config, sender) {
+                debug!("{} could not be reconnected: {}", output.label, err_string);
+            }
+        }
+    }
+}
+
+// Attempt to reconnect a degraded output. A file output is only opened if it failed to open
+// when processing started, as reopening it would overwrite it.
+fn retry_output(index: usize, output: &mut Output, app_config: &AppConfig, sender: &Sender<GuiMessage>) -> Result<(), String> {
+    if output.degraded.is_none() {
+        return Ok(());
+    }
+
+    let output_selection = app_config.output_selection[index];
+    if output_selection == StreamOption::File && output.opened {
+        return Err(format!("{} is a file, which is not reopened as that would overwrite it", output.label));
+    }
+
+    // a short timeout keeps processing from stalling on an output that is still down
+    let wait = OpenWait::new(Some(Duration::from_millis(RETRY_CONNECT_TIMEOUT_MS)), Arc::new(AtomicBool::new(false)));
+    match output_selection.open_output_with(&app_config.output_settings[index], &wait) {
+        Ok(stream) => {
+            output.reopened(stream);
+
+            // the packet that failed to be written is written first, so the output does not miss it
+            if let Some(unsent) = output.unsent.take() {
+                if let Err(err_string) = output.stream.stream_send(&unsent) {
+                    output.unsent = Some(unsent);
+                    output.degraded = Some(Instant::now());
+                    return Err(err_string);
+                }
+                output.delivery.written_on_reconnect += 1;
+            }
+
+            match output.store_forward {
+                Some(ref store_forward) if !store_forward.is_empty() => {
+                    info!("{} reconnected, and is writing its backlog of {} packets", output.label, store_forward.len());
+                },
+
+                _ => info!("{} reconnected", output.label),
+            }
+            let _ = sender.send(GuiMessage::OutputRecovered(index, output.label.clone()));
+            Ok(())
+        },
+
+        Err(err_string) => {
+            output.degraded = Some(Instant::now());
+            Err(err_string)
+        },
+    }
+}
+
+// Reopen a failed output when the user asks to, reporting why if it can not be
+fn retry_output_now(index: usize, output_streams: &mut Vec<Output>, app_config: &AppConfig, sender: &Sender<GuiMessage>) {
+    let result = match output_streams.get_mut(index) {
+        Some(output) => retry_output(index, output, app_config, sender),
+
+        None => Err(format!("There is no output {} to reconnect", index)),
+    };
+
+    if let Err(err_string) = result {
+        let _ = sender.send(GuiMessage::Error(format!("{} could not be reconnected: {}", app_config.output_label(index), err_string)));
+    }
+}
+
+// Write out the packets buffered by the output streams, reporting any errors. When processing
+// stops the outputs are closed, syncing output files to disk, and then dropped.
+fn flush_outputs(output_streams: &mut Vec<Output>, sender: &Sender<GuiMessage>) {
+    for output in output_streams.iter_mut().filter(|output| output.degraded.is_none()) {
+        if let Err(err_string) = output.stream.stream_flush() {
+            let _ = sender.send(GuiMessage::Error(err_string));
+        }
+    }
+}
+
+// Write out the packets of outputs whose flush interval has passed. An output that can not
+// be written to is degraded.
+fn flush_outputs_due(output_streams: &mut Vec<Output>, sender: &Sender<GuiMessage>) {
+    for (index, output) in output_streams.iter_mut().enumerate() {
+        if output.degraded.is_none() {
+            if let Err(err_string) = output.stream.stream_flush_due() {
+                output.fail(index, err_string, sender);
+            }
+        }
+    }
+}
+
+fn close_outputs(output_streams: &mut Vec<Output>, output_index_writers: &mut Vec<Option<IndexWriter>>, sender: &Sender<GuiMessage>) {
+    // an output opened lazily that was never sent a packet was not created, so its sidecar
+    // and index are discarded
+    for (index, output) in output_streams.iter_mut().enumerate() {
+        if output.lazy_open.is_some() {
+            if let Some(sidecar) = output.sidecar.take() {
+                sidecar.abandon();
+            }
+            if let Some(index_writer) = output_index_writers.get_mut(index).and_then(|index_writer| index_writer.take()) {
+                index_writer.abandon();
+            }
+        }
+    }
+
+    for output in output_streams.iter_mut() {
+        if let Some(sidecar) = output.sidecar.take() {
+            match sidecar.finish() {
+                Ok(sidecar_name) => info!("Wrote metadata sidecar {}", sidecar_name),
+
+                Err(err_string) => {
+                    let _ = sender.send(GuiMessage::Error(err_string));
+                },
+            }
+        }
+    }
+
+    for output in output_streams.iter() {
+        if let Some(ref shaper) = output.shaper {
+            if shaper.dropped > 0 || !shaper.is_empty() {
+                warn!("{} dropped {} packets over its rate limit, and {} queued packets were not written",
+                      output.label, shaper.dropped, shaper.len());
+            }
+        }
+    }
+
+    for output in output_streams.iter_mut().filter(|output| output.degraded.is_none()) {
+        if let Err(err_string) = output.stream.stream_close() {
+            let _ = sender.send(GuiMessage::Error(err_string));
+        }
+    }
+
+    output_streams.clear();
+}
+
+// Record a forwarded packet in the report of the current run of processing, if there is one
+fn report_forwarded(report: &mut Option<ProcessingReport>, output_mask: u32, apid: u16, length: usize) {
+    if let Some(ref mut report) = report {
+        report.forwarded(output_mask, apid, length);
+    }
+}
+
+// Add a forwarded packet to the indices of the output files it was written to. If an index
+// can not be written the error is reported and the index is discarded.
+fn index_outputs(output_index_writers: &mut Vec<Option<IndexWriter>>,
+                 output_mask: u32,
+                 apid: u16,
+                 bytes: &[u8],
+                 timestamp_def: &TimestampDef,
+                 sender: &Sender<GuiMessage>) {
+    let timestamp_secs = timestamp_def.decode(bytes).as_fractional_secs();
+
+    for index in 0..output_index_writers.len() {
+        if index >= 32 || output_mask & (1 << index) == 0 {
+            continue;
+        }
+
+        let result = match output_index_writers[index] {
+            Some(ref mut index_writer) => index_writer.append(bytes.len(), apid, timestamp_secs),
+
+            None => Ok(()),
+        };
+
+        if let Err(err_string) = result {
+            let _ = sender.send(GuiMessage::Error(err_string));
+            if let Some(index_writer) = output_index_writers[index].take() {
+                index_writer.abandon();
+            }
+        }
+    }
+}
+
+// Finish the indices of the output files, and discard the index of the input file if it
+// was not completed by reading to the end of the file.
+fn close_indices(input_index_writer: &mut Option<IndexWriter>,
+                 output_index_writers: &mut Vec<Option<IndexWriter>>,
+                 sender: &Sender<GuiMessage>) {
+    if let Some(index_writer) = input_index_writer.take() {
+        index_writer.abandon();
+    }
+
+    for index_writer in output_index_writers.drain(..) {
+        if let Some(index_writer) = index_writer {
+            match index_writer.finish() {
+                Ok(index_name) => info!("Wrote packet index {}", index_name),
+
+                Err(err_string) => {
+                    let _ = sender.send(GuiMessage::Error(err_string));
+                },
+            }
+        }
+    }
+}
+
+// Open the indices used while processing. A file input with a current index uses it for
+// seeking, and otherwise an index is created for it as it is read. An index is created for
+// each file output.
+fn open_indices(app_config: &AppConfig,
+                input_index: &mut Option<PacketIndex>,
+                input_index_writer: &mut Option<IndexWriter>,
+                output_index_writers: &mut Vec<Option<IndexWriter>>,
+                sender: &Sender<GuiMessage>) {
+    // a replay query reads only part of the input file, so the file's index is not used or created
+    if app_config.input_selection == StreamOption::File && !app_config.replay_query.enabled {
+        let file_name = &app_config.input_settings.file.file_name;
+
+        if PacketIndex::is_current(file_name) {
+            match PacketIndex::load(file_name) {
+                Ok(index) => {
+                    info!("Loaded packet index for {} with {} packets", file_name, index.entries.len());
+                    *input_index = Some(index);
+                },
+
+                Err(err_string) => {
+                    let _ = sender.send(GuiMessage::Error(err_string));
+                },
+            }
+        }
+
+        if input_index.is_none() {
+            match IndexWriter::create(file_name) {
+                Ok(index_writer) => *input_index_writer = Some(index_writer),
+
+                Err(err_string) => {
+                    let _ = sender.send(GuiMessage::Error(err_string));
+                },
+            }
+        }
+    }
+
+    for index in 0..app_config.output_selection.len() {
+        let mut output_index_writer = None;
+
+        if output_indexed(app_config, index) {
+            match IndexWriter::create(&app_config.output_settings[index].file.file_name) {
+                Ok(index_writer) => output_index_writer = Some(index_writer),
+
+                Err(err_string) => {
+                    let _ = sender.send(GuiMessage::Error(err_string));
+                },
+            }
+        }
+
+        output_index_writers.push(output_index_writer);
+    }
+}
+
+// Whether an output is a file whose packets are written at the offsets an index gives, which
+// is the packets forwarded to it, one after another, with nothing added
+fn output_indexed(app_config: &AppConfig, index: usize) -> bool {
+    let output_settings = &app_config.output_settings[index];
+
+    // the packets of an output written in frames, or after sync markers, are not at the
+    // offsets an index would give, and neither are packets whose length a transform changes.
+    // A text output has a line for each packet rather than the packet itself, and heartbeat and
+    // time packets are written between the packets forwarded.
+    let framed = output_settings.frame_output.enabled || output_settings.sync_marker.enabled;
+    let text = output_settings.file.format == FileFormat::Text;
+    let generated = app_config.heartbeat_settings.enabled || app_config.time_packet_settings.enabled;
+    let resized = app_config.output_transforms.get(index)
+                                              .map_or(false, |transforms| transforms.iter().any(|transform| transform.changes_length()));
+
+    app_config.output_selection[index] == StreamOption::File && app_config.is_output_enabled(index) && !framed && !resized && !text && !generated
+}
+
+// The byte ranges of the input file holding the packets selected by the replay query, or None if
+// the whole input is read. The packets are found with the file's index, which must be current.
+fn replay_query_ranges(app_config: &AppConfig) -> Result<Option<Vec<(u64, u64)>>, String> {
+    let replay_query = &app_config.replay_query;
+    if !replay_query.enabled || app_config.input_selection != StreamOption::File {
+        return Ok(None);
+    }
+
+    let file_name = &app_config.input_settings.file.file_name;
+    if !PacketIndex::is_current(file_name) {
+        return Err(format!("The replay query needs an index of {} that is newer than the file. Read the file once with Index Files checked to create it", file_name));
+    }
+
+    let index = PacketIndex::load(file_name)?;
+    let file_len = metadata(file_name).map(|file_metadata| file_metadata.len())
+                                      .map_err(|err| format!("Could not read the size of {}: {}", file_name, err))?;
+
+    let epoch = &app_config.timestamp_def.epoch;
+    let parse_time = |time_str: &str| -> Result<Option<f64>, String> {
+        if time_str.trim().is_empty() {
+            Ok(None)
+        } else {
+            epoch.parse_time(time_str).map(Some)
+        }
+    };
+    let start_secs = parse_time(&replay_query.start)?;
+    let end_secs = parse_time(&replay_query.end)?;
+
+    let query_ranges = index.query_ranges(file_len, &replay_query.apids, start_secs, end_secs);
+    info!("Replaying {} bytes of {} selected by the replay query",
+          query_ranges.iter().map(|(start, end)| end - start).sum::<u64>(),
+          file_name);
+
+    Ok(Some(query_ranges))
+}
+
+// Start moving the input stream for a seek message, returning whether the seek was started.
+// Seeking to a packet number or time uses the input file's index. Any index being created
+// for the input file is discarded, as it would no longer be in the order of the file.
+fn start_seek(msg: &ProcessingMsg,
+              input_index: &Option<PacketIndex>,
+              input_index_writer: &mut Option<IndexWriter>,
+              input: &Option<InputHandle>,
+              sender: &Sender<GuiMessage>) -> bool {
+    let offset_result = match (msg, input_index) {
+        (ProcessingMsg::Seek(offset), _) => Ok(*offset),
+
+        (ProcessingMsg::SeekPacket(packet_number), Some(index)) =>
+            index.packet_offset(*packet_number)
+                 .ok_or(format!("Packet {} is past the end of the input file", packet_number)),
+
+        (ProcessingMsg::SeekTime(timestamp_secs), Some(index)) =>
+            index.time_offset(*timestamp_secs)
+                 .ok_or(format!("No packet in the input file is at or after time {}", timestamp_secs)),
+
+        (_, None) => Err("The input file has no index to seek with. Enable index files and process the file to create one".to_string()),
+    };
+
+    match (offset_result, input) {
+        (Ok(offset), Some(input)) => {
+            input.send(InputMsg::Seek(offset));
+            if let Some(index_writer) = input_index_writer.take() {
+                index_writer.abandon();
+            }
+            true
+        },
+
+        (Ok(_), None) => false,
+
+        (Err(err_string), _) => {
+            let _ = sender.send(GuiMessage::Error(err_string));
+            false
+        },
+    }
+}
+
+// Write a packet's line to the CSV packet log, if logging. If the log can not be written
+// the error is reported and logging stops until processing is restarted.
+fn log_packet(csv_log: &mut Option<PacketCsvLog>,
+              packet: &Packet,
+              recv_time: SystemTime,
+              output_mask: u32,
+              sender: &Sender<GuiMessage>) {
+    let result = match csv_log {
+        Some(ref mut csv_log) => csv_log.log_packet(packet, recv_time, output_mask),
+
+        None => Ok(()),
+    };
+
+    if let Err(err_string) = result {
+        let _ = sender.send(GuiMessage::Error(err_string));
+        *csv_log = None;
+    }
+}
+
+// Add a forwarded packet to the Parquet archive, if enabled. If the archive can not be
+// written the error is reported and the archive is abandoned until processing is restarted.
+fn parquet_packet(parquet_archive: &mut Option<ParquetArchive>,
+                  packet: &Packet,
+                  recv_time: SystemTime,
+                  sender: &Sender<GuiMessage>) {
+    let result = match parquet_archive {
+        Some(ref mut parquet_archive) => parquet_archive.write_packet(packet, recv_time),
+
+        None => Ok(()),
+    };
+
+    if let Err(err_string) = result {
+        let _ = sender.send(GuiMessage::Error(err_string));
+        *parquet_archive = None;
+    }
+}
+
+// Write out the tables of the Parquet archive, if there is one
+fn close_parquet_archive(parquet_archive: &mut Option<ParquetArchive>, sender: &Sender<GuiMessage>) {
+    if let Some(parquet_archive) = parquet_archive.take() {
+        match parquet_archive.close() {
+            Ok(num_tables) => info!("Wrote {} Parquet archive tables", num_tables),
+
+            Err(err_string) => {
+                let _ = sender.send(GuiMessage::Error(err_string));
+            },
+        }
+    }
+}
+
+// Write the parameters in a packet to the decom CSV, if enabled. If the CSV can not be
+// written the error is reported and decom stops until processing is restarted.
+fn decom_packet(decom_csv: &mut Option<DecomCsv>,
+                packet: &Packet,
+                recv_time: SystemTime,
+                sender: &Sender<GuiMessage>) {
+    let result = match decom_csv {
+        Some(ref mut decom_csv) => decom_csv.write_packet(packet, recv_time),
+
+        None => Ok(()),
+    };
+
+    if let Err(err_string) = result {
+        let _ = sender.send(GuiMessage::Error(err_string));
+        *decom_csv = None;
+    }
+}
+
+// Record a forwarded packet in the archive, if archiving. If the archive can not be
+// written the error is reported and archiving stops until processing is restarted.
+fn archive_packet(archive: &mut Option<PacketArchive>,
+                  packet: &Packet,
+                  recv_time: SystemTime,
+                  output_mask: u32,
+                  sender: &Sender<GuiMessage>) {
+    let result = match archive {
+        Some(ref mut archive) => archive.insert(packet, recv_time, output_mask),
+
+        None => Ok(()),
+    };
+
+    if let Err(err_string) = result {
+        let _ = sender.send(GuiMessage::Error(err_string));
+        *archive = None;
+    }
+}
+
+/// Create a packet from the bytes pulled from the CCSDS parser, decoding its primary header.
+/// The packet shares the parser's bytes rather than copying them.
+pub fn packet_from_bytes(bytes: Bytes, little_endian_header: bool) -> Packet {
+    let header = primary_header(&bytes, little_endian_header).unwrap();
+
+    Packet { header: header, bytes: bytes }
+}
+
+/// The primary header at the start of a packet's bytes, or None if there are too few bytes to hold it
+pub fn primary_header(bytes: &[u8], little_endian_header: bool) -> Option<CcsdsPrimaryHeader> {
+    if bytes.len() < CCSDS_PRI_HEADER_SIZE_BYTES as usize {
+        return None;
+    }
+
+    let header;
+    if little_endian_header {
+        let little_header: PrimaryHeader<LittleEndian> = PrimaryHeader::from_slice(bytes).unwrap();
+        header = little_header.to_big_endian();
+    } else {
+        header = CcsdsPrimaryHeader::from_slice(bytes).unwrap();
+    }
+
+    Some(header)
+}
+
+// Open the input stream, reporting when it is waiting for a network stream to connect
+fn open_input_waiting(input_selection: StreamOption,
+                      read_stream_settings: &StreamSettings,
+                      open_wait: &OpenWait,
+                      packet_sender: &SyncSender<PacketMsg>) -> Result<ReadStream, String> {
+    if !input_selection.connects() {
+        return input_selection.open_input_with(read_stream_settings, open_wait);
+    }
+
+    let description = format!("input {}", stream_spec(input_selection, read_stream_settings));
+    let _ = packet_sender.send(PacketMsg::Waiting(Some(description)));
+
+    let open_result = input_selection.open_input_with(read_stream_settings, open_wait);
+
+    let _ = packet_sender.send(PacketMsg::Waiting(None));
+
+    open_result
+}
+
+/// The limits on waiting for streams to connect when processing starts
+fn open_wait(app_config: &AppConfig, cancel: Arc<AtomicBool>) -> OpenWait {
+    let timeout = if app_config.connect_timeout_secs > 0.0 {
+        Some(secs_duration(app_config.connect_timeout_secs))
+    } else {
+        None
+    };
+
+    OpenWait::new(timeout, cancel)
+}
+
+fn start_input_thread(app_config: AppConfig,
+                      sender: SyncSender<PacketMsg>,
+                      raw_capture: Option<RawCapture>,
+                      query_ranges: Option<Vec<(u64, u64)>>) -> InputHandle {
+    let input_settings = app_config.input_settings.clone();
+    let input_selection = app_config.input_selection;
+    let ccsds_parser_config = parser_config(&app_config);
+    let read_settings = app_config.read_settings.clone();
+    let container_format = app_config.container_format.clone();
+
+    let (control_sender, control_receiver) = channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let interrupt = Arc::new(Mutex::new(None));
+    let open_wait = open_wait(&app_config, stop.clone());
+
+    let polled = match input_selection {
+        StreamOption::Udp | StreamOption::TcpClient | StreamOption::TcpServer => app_config.io_engine == IoEngine::Polled,
+
+        _ => false,
+    };
+
+    if polled {
+        start_polled_input(sender,
+                           control_receiver,
+                           open_wait,
+                           input_settings,
+                           input_selection,
+                           ccsds_parser_config,
+                           read_settings,
+                           container_format,
+                           raw_capture);
+    } else {
+        let thread_stop = stop.clone();
+        let thread_interrupt = interrupt.clone();
+        let input_stream_thread = thread::spawn(move || {
+            input_stream_thread(sender,
+                                control_receiver,
+                                thread_stop,
+                                thread_interrupt,
+                                open_wait,
+                                input_settings,
+                                input_selection,
+                                ccsds_parser_config,
+                                read_settings,
+                                container_format,
+                                raw_capture,
+                                query_ranges);
+        });
+    }
+
+    InputHandle {
+        control_sender: control_sender,
+        stop: stop,
+        interrupt: interrupt,
+        backup: None,
+    }
+}
+
+/// The input a message was read from, when gap filling merges the messages of two inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputSource {
+    Prime,
+    Backup,
+}
+
+/// The time between updates of the gap filling and reorder buffer statistics sent to the
+/// processing thread
+const MERGE_STATS_UPDATE_MS: u64 = 1000;
+
+/// Start the input thread, along with the backup input's thread and a thread merging the two
+/// when gap filling is enabled, and a thread reordering the input's packets when the reorder
+/// buffer is enabled
+fn start_inputs(app_config: AppConfig,
+                mut sender: SyncSender<PacketMsg>,
+                raw_capture: Option<RawCapture>,
+                query_ranges: Option<Vec<(u64, u64)>>) -> InputHandle {
+    // the merged input's packets are reordered on their way to processing
+    if app_config.reorder.enabled {
+        let (reorder_sender, reorder_receiver) = sync_channel(100);
+
+        let reorder_settings = app_config.reorder.clone();
+        let timestamp_def = app_config.timestamp_def.clone();
+        let reordered_sender = mem::replace(&mut sender, reorder_sender);
+        thread::spawn(move || {
+            reorder_thread(reorder_receiver, reordered_sender, reorder_settings, timestamp_def);
+        });
+    }
+
+    if !app_config.gap_fill.enabled {
+        return start_input_thread(app_config, sender, raw_capture, query_ranges);
+    }
+
+    let hold = app_config.gap_fill.hold();
+
+    // the backup input is parsed with the same packet settings, but is not captured or queried
+    let mut backup_config = app_config.clone();
+    backup_config.input_selection = app_config.gap_fill.selection;
+    backup_config.input_settings = app_config.gap_fill.settings.clone();
+
+    let (prime_sender, prime_receiver) = sync_channel(100);
+    let (backup_sender, backup_receiver) = sync_channel(100);
+    let (merged_sender, merged_receiver) = sync_channel(100);
+
+    let backup = start_input_thread(backup_config, backup_sender, None, None);
+    let mut input = start_input_thread(app_config, prime_sender, raw_capture, query_ranges);
+    input.backup = Some(Box::new(backup));
+
+    for (source, receiver) in vec!((InputSource::Prime, prime_receiver), (InputSource::Backup, backup_receiver)) {
+        let merged_sender = merged_sender.clone();
+        thread::spawn(move || {
+            for packet_msg in receiver.iter() {
+                if merged_sender.send((source, packet_msg)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    thread::spawn(move || {
+        gap_fill_thread(merged_receiver, sender, hold);
+    });
+
+    input
+}
+
+/// Merge the messages of the input and the backup input, filling the gaps in the input's packets
+/// with the backup's and removing duplicates. Once the input ends, the backup is waited on until
+/// it ends as well, or for the hold time, so a backup file can fill the end of an input file.
+fn gap_fill_thread(merged_receiver: Receiver<(InputSource, PacketMsg)>, sender: SyncSender<PacketMsg>, hold: Duration) {
+    let mut gap_filler = GapFiller::new(hold);
+    let mut released = VecDeque::new();
+
+    let mut stream_end: Option<(Instant, PacketMsg)> = None;
+    let mut backup_ended = false;
+
+    let mut sent_stats: GapFillStats = Default::default();
+    let mut last_stats_update = Instant::now();
+
+    loop {
+        let idle = Duration::from_millis(INPUT_IDLE_MS);
+        let timeout = gap_filler.next_release(Instant::now()).map_or(idle, |until_release| until_release.min(idle));
+
+        match merged_receiver.recv_timeout(timeout) {
+            Ok((source, packet_msg)) => {
+                let key = match packet_msg {
+                    PacketMsg::Packet(ref packet, _, _, _) => Some((packet.header.control.apid(), packet.header.sequence.sequence_count())),
+
+                    _ => None,
+                };
+
+                match (source, packet_msg) {
+                    (InputSource::Prime, PacketMsg::SeekComplete) => {
+                        gap_filler.restart(&mut released);
+                        released.push_back(PacketMsg::SeekComplete);
+                    },
+
+                    (InputSource::Prime, packet_msg @ PacketMsg::StreamEnd(_, _, _)) => {
+                        stream_end = Some((Instant::now(), packet_msg));
+                    },
+
+                    (InputSource::Prime, packet_msg) => {
+                        gap_filler.push_prime(key, packet_msg, Instant::now());
+                    },
+
+                    (InputSource::Backup, packet_msg @ PacketMsg::Packet(_, _, _, _)) => {
+                        if let Some((apid, seq_count)) = key {
+                            gap_filler.push_backup(apid, seq_count, packet_msg, Instant::now());
+                        }
+                    },
+
+                    (InputSource::Backup, PacketMsg::StreamOpenError(err_string)) |
+                    (InputSource::Backup, PacketMsg::ReadError(err_string)) => {
+                        released.push_back(PacketMsg::BackupInputError(err_string));
+                    },
+
+                    (InputSource::Backup, PacketMsg::StreamParseError) => {
+                        released.push_back(PacketMsg::BackupInputError("There was a unrecoverable parsing error while streaming data".to_string()));
+                    },
+
+                    (InputSource::Backup, PacketMsg::Waiting(Some(description))) => {
+                        info!("The backup input is waiting for {} to connect", description);
+                    },
+
+                    (InputSource::Backup, PacketMsg::StreamEnd(_, packet_count, byte_count)) => {
+                        info!("End of backup input after {} packets ({} bytes)", packet_count, byte_count);
+                        backup_ended = true;
+                    },
+
+                    // the backup's reads and positions are not those of the input
+                    (InputSource::Backup, _) => {},
+                }
+            },
+
+            Err(RecvTimeoutError::Timeout) => {},
+
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        gap_filler.release(Instant::now(), &mut released);
+
+        let finished = match stream_end {
+            Some((ended, _)) => backup_ended || Instant::now() >= ended + hold,
+
+            None => false,
+        };
+        if finished {
+            gap_filler.finish(&mut released);
+        }
+
+        let stats = gap_filler.stats();
+        if stats != sent_stats && (finished || last_stats_update.elapsed() >= Duration::from_millis(MERGE_STATS_UPDATE_MS)) {
+            released.push_back(PacketMsg::GapFill(GapFillStats {
+                filled: stats.filled - sent_stats.filled,
+                duplicates: stats.duplicates - sent_stats.duplicates,
+            }));
+            sent_stats = stats;
+            last_stats_update = Instant::now();
+        }
+
+        if finished {
+            if let Some((_, packet_msg)) = stream_end.take() {
+                released.push_back(packet_msg);
+            }
+        }
+
+        for packet_msg in released.drain(..) {
+            if sender.send(packet_msg).is_err() {
+                return;
+            }
+        }
+
+        if finished {
+            return;
+        }
+    }
+}
+
+/// Release the input's packets in timestamp or sequence order through a reorder buffer. Other
+/// messages are passed on as they arrive, except that the end of the input or a seek releases
+/// every packet held first.
+fn reorder_thread(receiver: Receiver<PacketMsg>, sender: SyncSender<PacketMsg>, reorder_settings: ReorderSettings, timestamp_def: TimestampDef) {
+    let mut reorder_buffer = ReorderBuffer::new(&reorder_settings);
+    let mut released = VecDeque::new();
+
+    let mut sent_stats: ReorderStats = Default::default();
+    let mut last_stats_update = Instant::now();
+
+    loop {
+        let idle = Duration::from_millis(INPUT_IDLE_MS);
+        let timeout = reorder_buffer.next_release(Instant::now()).map_or(idle, |until_release| until_release.min(idle));
+
+        let mut finished = false;
+        match receiver.recv_timeout(timeout) {
+            Ok(PacketMsg::Packet(packet, recv_time, offset, frame_header)) => {
+                let apid = packet.header.control.apid();
+                let seq_count = packet.header.sequence.sequence_count();
+                let timestamp = timestamp_def.decode(&packet.bytes);
+
+                reorder_buffer.push(apid, seq_count, timestamp, PacketMsg::Packet(packet, recv_time, offset, frame_header), Instant::now(), &mut released);
+            },
+
+            Ok(PacketMsg::SeekComplete) => {
+                reorder_buffer.restart(&mut released);
+                released.push_back(PacketMsg::SeekComplete);
+            },
+
+            Ok(packet_msg @ PacketMsg::StreamEnd(_, _, _)) => {
+                reorder_buffer.finish(&mut released);
+                released.push_back(packet_msg);
+                finished = true;
+            },
+
+            Ok(packet_msg) => {
+                released.push_back(packet_msg);
+            },
+
+            Err(RecvTimeoutError::Timeout) => {},
+
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        reorder_buffer.release(Instant::now(), &mut released);
+
+        let stats = reorder_buffer.stats();
+        if stats != sent_stats && (finished || last_stats_update.elapsed() >= Duration::from_millis(MERGE_STATS_UPDATE_MS)) {
+            let update = PacketMsg::Reorder(ReorderStats {
+                reordered: stats.reordered - sent_stats.reordered,
+                late: stats.late - sent_stats.late,
+                late_dropped: stats.late_dropped - sent_stats.late_dropped,
+            });
+
+            // the update is sent before the end of the input, so it is counted
+            if finished {
+                let end_msg = released.pop_back();
+                released.push_back(update);
+                released.extend(end_msg);
+            } else {
+                released.push_back(update);
+            }
+
+            sent_stats = stats;
+            last_stats_update = Instant::now();
+        }
+
+        for packet_msg in released.drain(..) {
+            if sender.send(packet_msg).is_err() {
+                return;
+            }
+        }
+
+        if finished {
+            return;
+        }
+    }
 }
 
-fn start_input_thread(app_config: AppConfig, sender: SyncSender<PacketMsg>) {
-    let frame_settings = app_config.frame_settings.clone();
-    let input_settings = app_config.input_settings;
-    let input_selection = app_config.input_selection;
-    let packet_size = app_config.packet_size;
-
+/// The settings of the CCSDS parser, from the packet settings of a configuration
+pub fn parser_config(app_config: &AppConfig) -> CcsdsParserConfig {
     let mut ccsds_parser_config: CcsdsParserConfig = CcsdsParserConfig::new();
 
-    ccsds_parser_config.allowed_apids = app_config.allowed_input_apids.clone();
+    // the allowed input APIDs are not given to the parser, which would discard the other packets
+    // without a trace. They are filtered after parsing instead, where they are counted.
+    ccsds_parser_config.allowed_apids = None;
 
     match app_config.packet_size {
         PacketSize::Variable =>
@@ -268,19 +3179,17 @@ fn start_input_thread(app_config: AppConfig, sender: SyncSender<PacketMsg>) {
     }
     ccsds_parser_config.num_header_bytes = app_config.frame_settings.prefix_bytes as u32;
     ccsds_parser_config.keep_header = app_config.frame_settings.keep_prefix;
-    ccsds_parser_config.keep_sync = app_config.frame_settings.keep_prefix;
+
+    // the sync marker is stripped, and is not part of the prefix
+    ccsds_parser_config.sync_bytes = app_config.frame_settings.sync_marker.bytes().to_vec();
+    ccsds_parser_config.keep_sync = false;
 
     ccsds_parser_config.num_footer_bytes = app_config.frame_settings.postfix_bytes as u32;
     ccsds_parser_config.keep_footer = app_config.frame_settings.keep_postfix;
 
     ccsds_parser_config.little_endian_header = app_config.little_endian_ccsds;
 
-    let input_stream_thread = thread::spawn(move || {
-        input_stream_thread(sender,
-                            input_settings,
-                            input_selection,
-                            ccsds_parser_config);
-    });
+    ccsds_parser_config
 }
 
 /* Packet Processing Thread */
@@ -289,7 +3198,7 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
   
     let packet: Packet
         = Packet { header: Default::default(),
-                   bytes: Vec::with_capacity(4096),
+                   bytes: Bytes::new(),
     };
 
     let mut output_streams = vec!();
@@ -302,10 +3211,74 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
 
     let mut app_config: AppConfig = Default::default();
 
+    // the thread reading the input stream, which is stopped when the handle is dropped
+    let mut input: Option<InputHandle> = None;
+
+    // when seeking, packets read before the seek are discarded until the input
+    // thread reports that the seek is complete.
+    let mut seeking = false;
+
+    // when stepping, a single packet is processed and then processing pauses again.
+    let mut step_once = false;
+
+    let mut playback_speed: f32 = 1.0;
+
+    // APIDs that are not forwarded to any output, set while processing from the GUI
+    let mut disabled_apids: Vec<u16> = Vec::new();
+
+    // the last sequence count seen for each APID, used to detect sequence gaps
+    let mut last_seqs: HashMap<u16, u16> = HashMap::new();
+
+    // the packets forwarded since processing started, used by the packet count conditions
+    let mut packets_forwarded: u64 = 0;
+
+    // the last frame count seen for each virtual channel, used to detect frame continuity gaps
+    let mut last_vc_frame_counts: HashMap<VirtualChannel, u8> = HashMap::new();
+
+    // the sequence count of the next heartbeat packet
+    let mut heartbeat_seq: u16 = 0;
+
+    let mut time_packets = TimePacketState::new();
+
+    // the simulated link outages of the current run of processing, if they are enabled
+    let mut outage: Option<OutageSchedule> = None;
+
+    let mut trigger_state = TriggerState::new(&Default::default());
+
+    let mut gui_updates = GuiUpdateLimiter::new(0);
+
+    // the summary of the current run of processing, written out when processing ends
+    let mut report: Option<ProcessingReport> = None;
+
+    // the database that forwarded packets are recorded into, if archiving is enabled
+    let mut archive: Option<PacketArchive> = None;
+
+    // the CSV file that each packet is logged to, if enabled
+    let mut csv_log: Option<PacketCsvLog> = None;
+
+    // the Parquet tables that forwarded packets are written to, if enabled
+    let mut parquet_archive: Option<ParquetArchive> = None;
+
+    // the CSV file that selected parameters are written to, if enabled
+    let mut decom_csv: Option<DecomCsv> = None;
+
+    // the index of the input file used for seeking, or the index being created for it if
+    // there is none, and the indices being created for each output file.
+    let mut input_index: Option<PacketIndex> = None;
+    let mut input_index_writer: Option<IndexWriter> = None;
+    let mut output_index_writers: Vec<Option<IndexWriter>> = Vec::new();
+
     'state_loop: loop {
         match state {
             ProcessingState::Idle => {
-                output_streams = vec!();
+                // stopping the input thread closes the input stream
+                input.take();
+                close_outputs(&mut output_streams, &mut output_index_writers, &sender);
+                archive = None;
+                close_parquet_archive(&mut parquet_archive, &sender);
+                csv_log = None;
+                decom_csv = None;
+                close_indices(&mut input_index_writer, &mut output_index_writers, &sender);
 
                 let msg_result = receiver.recv().ok();
                 match msg_result {
@@ -313,6 +3286,9 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                     Some(ProcessingMsg::Start(config)) => {
                         app_config = config;
 
+                        // an output file is not truncated unless it is set to be overwritten
+                        resolve_output_files(&mut app_config);
+
                         // get endianness to use
                         if app_config.little_endian_ccsds {
                             endianness = Endianness::Little;
@@ -321,24 +3297,208 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                             endianness = Endianness::Big;
                         }
 
-                        // open streams
-                        for index in 0..app_config.output_settings.len() {
-                            let output_stream = app_config.output_selection[index]
-                                                .open_output(&app_config.output_settings[index]);
-                                                                   
-                            match output_stream {
-                                Ok(stream) => {
-                                    output_streams.push(stream)
+                        // open streams. a dry run opens none of its outputs, so nothing is written.
+                        if app_config.dry_run {
+                            info!("Dry run of session {}- packets will not be written to the outputs", app_config.session_name);
+                        }
+                        match open_outputs(&mut app_config, &receiver, &sender, &mut playback_speed, &mut disabled_apids) {
+                            OutputsOpened::Opened(opened) => {
+                                output_streams = Vec::new();
+                                for (index, open_result) in opened.into_iter().enumerate() {
+                                    let transforms = app_config.output_transforms.get(index).cloned().unwrap_or_default();
+
+                                    let output_selection = app_config.output_selection[index];
+                                    let output_settings = &app_config.output_settings[index];
+                                    let label = app_config.output_label(index);
+                                    if !app_config.is_output_enabled(index) {
+                                        info!("{} is disabled", label);
+                                        let mut output = Output::disabled(transforms);
+                                        output.label = label;
+                                        output_streams.push(output);
+                                        continue;
+                                    }
+
+                                    let mut output = match open_result {
+                                        Ok(_) if output_settings.open_timing == OutputOpenTiming::Lazy && !app_config.dry_run => {
+                                            Output::lazy(output_selection, output_settings.clone(), transforms)
+                                        },
+
+                                        Ok(stream) => Output::new(stream, transforms),
+
+                                        Err(err_string) => Output::unopened(index, label.clone(), err_string, transforms, &sender),
+                                    };
+                                    output.label = label;
+
+                                    output.little_endian_header = app_config.little_endian_ccsds;
+                                    output.priorities = app_config.apid_priorities.clone();
+                                    output.sync_marker_bytes = output_settings.sync_marker.bytes().len();
+
+                                    output.pauses_input = app_config.pause_on_disconnect &&
+                                                          (output_selection == StreamOption::TcpClient || output_selection == StreamOption::TcpServer);
+
+                                    let rate_limit = &output_settings.rate_limit;
+                                    if rate_limit.bytes_per_sec > 0 {
+                                        output.shaper = Some(Shaper::new(rate_limit, &app_config.apid_priorities));
+                                    }
+
+                                    // an output that stores and forwards its packets keeps them while it is
+                                    // down, so the input does not need to wait for it
+                                    let store_forward = &output_settings.store_forward;
+                                    if store_forward.enabled && !app_config.dry_run {
+                                        match StoreForward::create(&app_config.session_name, index, store_forward, &app_config.apid_priorities) {
+                                            Ok(store_forward) => {
+                                                output.pauses_input = false;
+                                                output.store_forward = Some(store_forward);
+                                            },
+
+                                            Err(err_string) => {
+                                                sender.send(GuiMessage::Error(format!("{} can not store and forward: {}", output.label, err_string))).unwrap();
+                                            },
+                                        }
+                                    }
+
+                                    let file_settings = &output_settings.file;
+                                    if output_selection == StreamOption::File && file_settings.format == FileFormat::Text {
+                                        output.text_format = Some(PacketTextFormat {
+                                            payload_bytes: file_settings.text_payload_bytes,
+                                            little_endian_header: app_config.little_endian_ccsds,
+                                        });
+                                    }
+
+                                    if output_selection == StreamOption::File &&
+                                       file_settings.metadata_sidecar &&
+                                       file_settings.file_name != STDOUT_FILE_NAME &&
+                                       !app_config.dry_run {
+                                        match SidecarWriter::create(&file_settings.file_name,
+                                                                    &app_config.timestamp_def,
+                                                                    app_config.little_endian_ccsds) {
+                                            Ok(sidecar) => output.sidecar = Some(sidecar),
+
+                                            Err(err_string) => {
+                                                sender.send(GuiMessage::Error(err_string)).unwrap();
+                                            },
+                                        }
+                                    }
+                                    output_streams.push(output);
+                                }
+                            },
+
+                            OutputsOpened::Failed(err_string) => {
+                                sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                                sender.send(GuiMessage::Finished).unwrap();
+                                state = ProcessingState::Idle;
+                                continue 'state_loop;
+                            },
+
+                            OutputsOpened::Cancelled => {
+                                sender.send(GuiMessage::Finished).unwrap();
+                                state = ProcessingState::Idle;
+                                continue 'state_loop;
+                            },
+
+                            OutputsOpened::Terminated => {
+                                state = ProcessingState::Terminating;
+                                continue 'state_loop;
+                            },
+                        }
+
+                        if app_config.archive_settings.enabled && !app_config.dry_run {
+                            match PacketArchive::open(&app_config.archive_settings, &app_config.timestamp_def) {
+                                Ok(packet_archive) => {
+                                    archive = Some(packet_archive);
+                                },
+
+                                Err(err_string) => {
+                                    sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                                    sender.send(GuiMessage::Finished).unwrap();
+                                    state = ProcessingState::Idle;
+                                    output_streams = vec!();
+                                    continue 'state_loop;
+                                },
+                            }
+                        }
+
+                        if app_config.parquet_settings.enabled && !app_config.dry_run {
+                            match ParquetArchive::open(&app_config.parquet_settings, &app_config.timestamp_def, app_config.little_endian_ccsds) {
+                                Ok(opened) => {
+                                    parquet_archive = Some(opened);
+                                },
+
+                                Err(err_string) => {
+                                    sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                                    sender.send(GuiMessage::Finished).unwrap();
+                                    state = ProcessingState::Idle;
+                                    output_streams = vec!();
+                                    continue 'state_loop;
+                                },
+                            }
+                        }
+
+                        if app_config.csv_log_settings.enabled && !app_config.dry_run {
+                            match PacketCsvLog::create(&app_config.csv_log_settings, &app_config.session_name, &app_config.timestamp_def) {
+                                Ok(packet_csv_log) => {
+                                    csv_log = Some(packet_csv_log);
+                                },
+
+                                Err(err_string) => {
+                                    sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                                    sender.send(GuiMessage::Finished).unwrap();
+                                    state = ProcessingState::Idle;
+                                    output_streams = vec!();
+                                    continue 'state_loop;
+                                },
+                            }
+                        }
+
+                        if app_config.decom_settings.enabled && !app_config.dry_run {
+                            match DecomCsv::create(&app_config.decom_settings, &app_config.timestamp_def) {
+                                Ok(csv) => {
+                                    decom_csv = Some(csv);
+                                },
+
+                                Err(err_string) => {
+                                    sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                                    sender.send(GuiMessage::Finished).unwrap();
+                                    state = ProcessingState::Idle;
+                                    output_streams = vec!();
+                                    continue 'state_loop;
+                                },
+                            }
+                        }
+
+                        let query_ranges = match replay_query_ranges(&app_config) {
+                            Ok(query_ranges) => query_ranges,
+
+                            Err(err_string) => {
+                                sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                                sender.send(GuiMessage::Finished).unwrap();
+                                state = ProcessingState::Idle;
+                                output_streams = vec!();
+                                continue 'state_loop;
+                            },
+                        };
+
+                        // the raw capture is written by the input thread, as the bytes are read
+                        let mut raw_capture = None;
+                        if app_config.raw_capture_settings.enabled && !app_config.dry_run {
+                            match RawCapture::create(&app_config.raw_capture_settings) {
+                                Ok(capture) => {
+                                    raw_capture = Some(capture);
                                 },
 
                                 Err(err_string) => {
-                                    sender.send(GuiMessage::Error(err_string)).unwrap();
+                                    sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
                                     sender.send(GuiMessage::Finished).unwrap();
                                     state = ProcessingState::Idle;
                                     output_streams = vec!();
                                     continue 'state_loop;
                                 },
-                             }
+                            }
+                        }
+
+                        input_index = None;
+                        if app_config.index_files && !app_config.dry_run {
+                            open_indices(&app_config, &mut input_index, &mut input_index_writer, &mut output_index_writers, &sender);
                         }
 
                         // spawn off a thread for reading the input stream
@@ -346,14 +3506,44 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                         let (sender, receiver) = sync_channel(100);
                         packet_receiver = receiver;
 
-                        start_input_thread(app_config.clone(), sender);
+                        input = Some(start_inputs(app_config.clone(), sender, raw_capture, query_ranges));
+                        seeking = false;
+                        step_once = false;
+                        last_seqs.clear();
+                        packets_forwarded = 0;
+                        last_vc_frame_counts.clear();
+                        time_packets = TimePacketState::new();
+                        outage = if app_config.outage_settings.enabled {
+                            Some(OutageSchedule::new(&app_config.outage_settings, SystemTime::now()))
+                        } else {
+                            None
+                        };
+                        trigger_state = TriggerState::new(&app_config.trigger_settings);
+                        gui_updates = GuiUpdateLimiter::new(app_config.gui_updates_per_sec);
+                        report = Some(ProcessingReport::new(&app_config));
                         state = ProcessingState::Processing;
                     },
 
+                    Some(ProcessingMsg::SetSpeed(speed)) => {
+                        playback_speed = speed;
+                    },
+
+                    Some(ProcessingMsg::UpdateTiming(timestamp_setting)) => {
+                        app_config.timestamp_setting = timestamp_setting;
+                    },
+
+                    Some(ProcessingMsg::UpdateFilter(apids)) => {
+                        disabled_apids = apids;
+                    },
+
                     Some(ProcessingMsg::Terminate) => {
                         state = ProcessingState::Terminating;
                     },
 
+                    Some(ProcessingMsg::SendPacket(_, _)) => {
+                        sender.send(GuiMessage::Error("Packets can only be sent while processing".to_string())).unwrap();
+                    },
+
                     Some(msg) => {
                         sender.send(GuiMessage::Error(format!("Unexpected message while waiting to process {}", msg.name()))).unwrap();
                     }
@@ -374,6 +3564,7 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                     },
 
                     Some(ProcessingMsg::Cancel) => {
+                        write_report(&mut report, "Cancelled", &output_streams, &sender);
                         state = ProcessingState::Idle;
                     },
 
@@ -381,6 +3572,40 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                         state = ProcessingState::Terminating;
                     },
 
+                    Some(ProcessingMsg::Step) => {
+                        step_once = true;
+                        state = ProcessingState::Processing;
+                    },
+
+                    Some(msg @ ProcessingMsg::Seek(_)) |
+                    Some(msg @ ProcessingMsg::SeekPacket(_)) |
+                    Some(msg @ ProcessingMsg::SeekTime(_)) => {
+                        if start_seek(&msg, &input_index, &mut input_index_writer, &input, &sender) {
+                            seeking = true;
+                        }
+                    },
+
+                    Some(ProcessingMsg::SetSpeed(speed)) => {
+                        playback_speed = speed;
+                    },
+
+                    Some(ProcessingMsg::UpdateTiming(timestamp_setting)) => {
+                        app_config.timestamp_setting = timestamp_setting;
+                    },
+
+                    Some(ProcessingMsg::UpdateFilter(apids)) => {
+                        disabled_apids = apids;
+                    },
+
+                    Some(ProcessingMsg::SendPacket(apid, bytes)) => {
+                        let output_mask = forward_packet(&mut output_streams, &app_config.allowed_output_apids, apid, &bytes, SystemTime::now(), &sender);
+                        index_outputs(&mut output_index_writers, output_mask, apid, &bytes, &app_config.timestamp_def, &sender);
+                    },
+
+                    Some(ProcessingMsg::RetryOutput(index)) => {
+                        retry_output_now(index, &mut output_streams, &app_config, &sender);
+                    },
+
                     Some(msg) => {
                         sender.send(GuiMessage::Error(format!("Unexpected message while paused {}", msg.name()))).unwrap();
                     }
@@ -398,32 +3623,225 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                 let mut time_state = TimeState{
                                  timestamp_setting: app_config.timestamp_setting.clone(),
                                  timestamp_def: app_config.timestamp_def.clone(),
-                                 system_to_packet_time: None,
-                                 last_send_time: SystemTime::now(),
+                                 replay_anchor: None,
+                                 last_send_time: Instant::now(),
+                                 speed: playback_speed,
                 };
 
+                // the output the input is being held for, if any
+                let mut held_for: Option<usize> = None;
 
                 while state == ProcessingState::Processing {
+                    // while an output that pauses the input is disconnected, packets are left in the
+                    // input until it reconnects
+                    let held_by = output_streams.iter().position(|output| output.pauses_input && output.degraded.is_some());
+                    if held_by != held_for {
+                        match held_by {
+                            Some(index) => {
+                                info!("Holding the input until {} reconnects", output_streams[index].label);
+                                let _ = sender.send(GuiMessage::WaitingForConnection(Some(output_streams[index].label.clone())));
+                            },
+
+                            None => {
+                                info!("Output reconnected, reading the input again");
+                                let _ = sender.send(GuiMessage::WaitingForConnection(None));
+
+                                // replay timing starts again rather than catching up on the time spent waiting
+                                time_state.replay_anchor = None;
+                            },
+                        }
+                        held_for = held_by;
+                    }
+
                     /* Process a Packet */
-                    let packet_msg = packet_receiver.recv();
+                    let packet_msg = if held_by.is_some() {
+                        thread::sleep(Duration::from_millis(INPUT_IDLE_MS));
+                        Err(RecvTimeoutError::Timeout)
+                    } else {
+                        packet_receiver.recv_timeout(Duration::from_millis(INPUT_IDLE_MS))
+                    };
+
+                    gui_updates.send_due(&sender);
+                    flush_outputs_due(&mut output_streams, &sender);
+                    retry_outputs(&mut output_streams, &app_config, &sender);
+                    write_shaped_outputs(&mut output_streams, &sender);
+                    update_outage(&mut output_streams, &outage, &sender);
+                    send_heartbeats(&mut output_streams, &app_config, &mut heartbeat_seq, &sender);
+                    send_time_packets(&mut output_streams, &app_config, &mut time_packets, &sender);
 
                     match packet_msg {
-                        Ok(PacketMsg::Packet(packet, recv_time)) => {
-                            // determine delay to use from time settings
+                        // while no packets arrive, control messages are still handled
+                        Err(RecvTimeoutError::Timeout) => {
+                            loop {
+                                match receiver.try_recv() {
+                                    Ok(ProcessingMsg::Pause) => {
+                                        state = ProcessingState::Paused;
+                                    },
+
+                                    Ok(ProcessingMsg::Cancel) => {
+                                        write_report(&mut report, "Cancelled", &output_streams, &sender);
+                                        state = ProcessingState::Idle;
+                                        continue 'state_loop;
+                                    },
+
+                                    Ok(ProcessingMsg::Terminate) => {
+                                        state = ProcessingState::Terminating;
+                                        continue 'state_loop;
+                                    },
+
+                                    Ok(msg @ ProcessingMsg::Seek(_)) |
+                                    Ok(msg @ ProcessingMsg::SeekPacket(_)) |
+                                    Ok(msg @ ProcessingMsg::SeekTime(_)) => {
+                                        if start_seek(&msg, &input_index, &mut input_index_writer, &input, &sender) {
+                                            seeking = true;
+                                        }
+                                    },
+
+                                    Ok(ProcessingMsg::SetSpeed(speed)) => {
+                                        playback_speed = speed;
+                                        time_state.speed = speed;
+                                        time_state.replay_anchor = None;
+                                    },
+
+                                    Ok(ProcessingMsg::UpdateTiming(timestamp_setting)) => {
+                                        info!("Timing changed to {:?}", timestamp_setting);
+                                        time_state.timestamp_setting = timestamp_setting.clone();
+                                        time_state.replay_anchor = None;
+                                        app_config.timestamp_setting = timestamp_setting;
+                                    },
+
+                                    Ok(ProcessingMsg::UpdateFilter(apids)) => {
+                                        disabled_apids = apids;
+                                    },
+
+                                    Ok(ProcessingMsg::SendPacket(apid, bytes)) => {
+                                        let output_mask = forward_packet(&mut output_streams, &app_config.allowed_output_apids, apid, &bytes, SystemTime::now(), &sender);
+                                        index_outputs(&mut output_index_writers, output_mask, apid, &bytes, &app_config.timestamp_def, &sender);
+                                    },
+
+                                    Ok(ProcessingMsg::RetryOutput(index)) => {
+                                        retry_output_now(index, &mut output_streams, &app_config, &sender);
+                                    },
+
+                                    Ok(msg) => {
+                                        sender.send(GuiMessage::Error(format!("Unexpected message while processing {}", msg.name()))).unwrap();
+                                    },
+
+                                    Err(TryRecvError::Empty) => break,
+
+                                    Err(TryRecvError::Disconnected) => {
+                                        let _ = sender.send(GuiMessage::Error("Message queue error while processing".to_string()));
+                                        state = ProcessingState::Terminating;
+                                        continue 'state_loop;
+                                    },
+                                }
+                            }
+                        },
+
+                        Ok(PacketMsg::Packet(packet, recv_time, offset, frame_header)) => {
+                            // packets read before a seek are not forwarded
+                            if seeking {
+                                continue;
+                            }
+
+                            // every frame is counted in the statistics of its virtual channel,
+                            // whether or not its packet is forwarded
+                            let frame = frame_header.as_ref().and_then(|frame_header| FrameHeader::from_prefix(frame_header, &app_config.frame_settings));
+                            if let Some(frame) = frame {
+                                let last_frame_count = last_vc_frame_counts.insert(frame.virtual_channel(), frame.vc_frame_count);
+                                gui_updates.frame(FrameUpdate::new(&frame, last_frame_count, recv_time), &sender);
+                            }
+
+                            // every packet read from the input file is indexed, whether or not it is forwarded
+                            let index_result = match input_index_writer {
+                                Some(ref mut index_writer) => {
+                                    let timestamp_secs = app_config.timestamp_def.decode(&packet.bytes).as_fractional_secs();
+                                    index_writer.write_entry(offset, packet.header.control.apid(), timestamp_secs)
+                                },
+
+                                None => Ok(()),
+                            };
+                            if let Err(err_string) = index_result {
+                                sender.send(GuiMessage::Error(err_string)).unwrap();
+                                if let Some(index_writer) = input_index_writer.take() {
+                                    index_writer.abandon();
+                                }
+                            }
+
+                            // packets with APIDs not accepted from the input are counted rather than forwarded
+                            if let Some(ref allowed_apids) = app_config.allowed_input_apids {
+                                let apid = packet.header.control.apid();
+                                if !allowed_apids.contains(&apid) {
+                                    if let Some(ref mut report) = report {
+                                        report.packet_filtered(apid);
+                                    }
+                                    gui_updates.filtered(apid, &sender);
+                                    continue;
+                                }
+                            }
+
+                            // the maximum size does not include the frame header and footer
+                            let mut kept_frame_bytes = 0;
+                            if app_config.frame_settings.keep_prefix {
+                                kept_frame_bytes += app_config.frame_settings.prefix_bytes.max(0) as usize;
+                            }
+                            if app_config.frame_settings.keep_postfix {
+                                kept_frame_bytes += app_config.frame_settings.postfix_bytes.max(0) as usize;
+                            }
+
+                            let packet_length = packet.bytes.len().saturating_sub(kept_frame_bytes);
+                            let drop_reason = if packet_length > app_config.max_length_bytes.max(0) as usize {
+                                // the start of the frame is kept, to tell a misaligned stream from an oversize packet
+                                let capture = OversizeCapture::new(&packet,
+                                                                   frame_header.as_ref().map(|frame_header| &frame_header[..]),
+                                                                   app_config.frame_settings.keep_prefix,
+                                                                   packet_length,
+                                                                   offset,
+                                                                   recv_time);
+                                sender.send(GuiMessage::OversizePacket(capture)).unwrap();
+                                if let Some(ref mut report) = report {
+                                    report.packet_oversize(packet.header.control.apid());
+                                }
+
+                                Some(DropReason::TooLong)
+                            } else if app_config.drop_crc_failures && !crc_valid(&packet.bytes) {
+                                Some(DropReason::CrcFailure)
+                            } else {
+                                None
+                            };
+
+                            if let Some(drop_reason) = drop_reason {
+                                if let Some(ref mut report) = report {
+                                    report.packet_dropped(drop_reason);
+                                }
+                                sender.send(GuiMessage::PacketDropped(packet.header, drop_reason)).unwrap();
+                                continue;
+                            }
+
+                            // determine delay to use from time settings. A single step is
+                            // forwarded immediately.
                             timeout = determine_timeout(&mut time_state, &packet);
+                            if step_once {
+                                timeout = Duration::from_secs(0);
+                            }
 
                             /* Check for Control Messages */
-                            let time_to_send = SystemTime::now() + timeout;
+                            let time_to_send = Instant::now() + timeout;
 
                             // process at least one message. continue to process messages until we have
                             // reached the timeout period for processing this packet.
                             let mut processed_at_least_once = false;
                             let mut remaining_timeout = timeout;
-                            while !processed_at_least_once || SystemTime::now() < time_to_send {
-                                match receiver.recv_timeout(remaining_timeout) {
+                            while !processed_at_least_once || Instant::now() < time_to_send {
+                                // the wait is broken up so heartbeats are sent during long delays
+                                match receiver.recv_timeout(remaining_timeout.min(Duration::from_millis(INPUT_IDLE_MS))) {
                                     Err(RecvTimeoutError::Timeout) => {
                                         // timing out means that we are ready to process the next packet,
                                         // so this is not an error condition
+                                        write_shaped_outputs(&mut output_streams, &sender);
+                                        update_outage(&mut output_streams, &outage, &sender);
+                                        send_heartbeats(&mut output_streams, &app_config, &mut heartbeat_seq, &sender);
+                                        send_time_packets(&mut output_streams, &app_config, &mut time_packets, &sender);
                                     },
 
                                     Ok(ProcessingMsg::Pause) => {
@@ -432,6 +3850,7 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                                     },
 
                                     Ok(ProcessingMsg::Cancel) => {
+                                        write_report(&mut report, "Cancelled", &output_streams, &sender);
                                         state = ProcessingState::Idle;
                                         continue 'state_loop;
                                     },
@@ -441,6 +3860,43 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                                         continue 'state_loop;
                                     },
 
+                                    Ok(msg @ ProcessingMsg::Seek(_)) |
+                                    Ok(msg @ ProcessingMsg::SeekPacket(_)) |
+                                    Ok(msg @ ProcessingMsg::SeekTime(_)) => {
+                                        // the packet being waited on was read before the seek, so
+                                        // it is dropped rather than forwarded.
+                                        if start_seek(&msg, &input_index, &mut input_index_writer, &input, &sender) {
+                                            seeking = true;
+                                            continue 'state_loop;
+                                        }
+                                    },
+
+                                    Ok(ProcessingMsg::SetSpeed(speed)) => {
+                                        playback_speed = speed;
+                                        time_state.speed = speed;
+                                        time_state.replay_anchor = None;
+                                    },
+
+                                    Ok(ProcessingMsg::UpdateTiming(timestamp_setting)) => {
+                                        info!("Timing changed to {:?}", timestamp_setting);
+                                        time_state.timestamp_setting = timestamp_setting.clone();
+                                        time_state.replay_anchor = None;
+                                        app_config.timestamp_setting = timestamp_setting;
+                                    },
+
+                                    Ok(ProcessingMsg::UpdateFilter(apids)) => {
+                                        disabled_apids = apids;
+                                    },
+
+                                    Ok(ProcessingMsg::SendPacket(apid, bytes)) => {
+                                        let output_mask = forward_packet(&mut output_streams, &app_config.allowed_output_apids, apid, &bytes, SystemTime::now(), &sender);
+                                        index_outputs(&mut output_index_writers, output_mask, apid, &bytes, &app_config.timestamp_def, &sender);
+                                    },
+
+                                    Ok(ProcessingMsg::RetryOutput(index)) => {
+                                        retry_output_now(index, &mut output_streams, &app_config, &sender);
+                                    },
+
                                     Ok(msg) => {
                                         sender.send(GuiMessage::Error(format!("Unexpected message while processing {}", msg.name()))).unwrap();
                                     },
@@ -458,61 +3914,241 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
 
                                 // the remaining timeout is the duration from now to the send time. if the
                                 // send time is in the past, use a duration of 0.
-                                remaining_timeout = SystemTime::now().duration_since(time_to_send).unwrap_or(Duration::from_secs(0));
+                                remaining_timeout = duration_until(time_to_send);
                             }
 
-                            // send output to each stream, filtering by allowed apids
-                            for index in 0..output_streams.len() {
-                                let apid_allowed;
+                            let apid = packet.header.control.apid();
+                            let last_seq = last_seqs.insert(apid, packet.header.sequence.sequence_count());
+                            let forward = !disabled_apids.contains(&apid);
+                            if forward {
+                                packets_forwarded += 1;
+                            }
 
-                                match app_config.allowed_output_apids[index] {
-                                    Some(ref apids) => {
-                                        apid_allowed = apids.contains(&packet.header.control.apid());
-                                    },
+                            if let Some(ref mut report) = report {
+                                report.packet(&packet, recv_time, last_seq);
+                            }
 
-                                    None => apid_allowed = true,
+                            if app_config.trigger_settings.enabled {
+                                // with trigger based capture, packets are only written once a trigger fires
+                                let now = Instant::now();
+
+                                if trigger_state.check_capture_end(now) {
+                                    sender.send(GuiMessage::CaptureStatus(None)).unwrap();
+                                }
+
+                                if !trigger_state.capturing {
+                                    if let Some(description) = check_pause_conditions(&app_config.trigger_settings.conditions, &packet, last_seq, forward, packets_forwarded) {
+                                        for (_, kept_packet, kept_recv_time) in trigger_state.start_capture(now) {
+                                            let output_mask = forward_packet(&mut output_streams,
+                                                                             &app_config.allowed_output_apids,
+                                                                             kept_packet.header.control.apid(),
+                                                                             &kept_packet.bytes,
+                                                                             kept_recv_time,
+                                                                             &sender);
+                                            report_forwarded(&mut report, output_mask, kept_packet.header.control.apid(), kept_packet.bytes.len());
+                                            archive_packet(&mut archive, &kept_packet, kept_recv_time, output_mask, &sender);
+                                            parquet_packet(&mut parquet_archive, &kept_packet, kept_recv_time, &sender);
+                                            log_packet(&mut csv_log, &kept_packet, kept_recv_time, output_mask, &sender);
+                                            decom_packet(&mut decom_csv, &kept_packet, kept_recv_time, &sender);
+                                            index_outputs(&mut output_index_writers,
+                                                          output_mask,
+                                                          kept_packet.header.control.apid(),
+                                                          &kept_packet.bytes,
+                                                          &app_config.timestamp_def,
+                                                          &sender);
+                                        }
+                                        sender.send(GuiMessage::CaptureStatus(Some((apid, description)))).unwrap();
+                                    }
                                 }
-                                
-                                if apid_allowed {
-                                    output_streams[index].stream_send(&packet.bytes);
+
+                                if forward {
+                                    if trigger_state.capturing {
+                                        let output_mask = forward_packet(&mut output_streams, &app_config.allowed_output_apids, apid, &packet.bytes, recv_time, &sender);
+                                        report_forwarded(&mut report, output_mask, apid, packet.bytes.len());
+                                        archive_packet(&mut archive, &packet, recv_time, output_mask, &sender);
+                                        parquet_packet(&mut parquet_archive, &packet, recv_time, &sender);
+                                        log_packet(&mut csv_log, &packet, recv_time, output_mask, &sender);
+                                        decom_packet(&mut decom_csv, &packet, recv_time, &sender);
+                                        index_outputs(&mut output_index_writers, output_mask, apid, &packet.bytes, &app_config.timestamp_def, &sender);
+                                    } else {
+                                        // packets waiting for a trigger are logged if they are written when it fires
+                                        trigger_state.keep_packet(now, &packet, recv_time);
+                                    }
+                                } else {
+                                    log_packet(&mut csv_log, &packet, recv_time, 0, &sender);
+                                    decom_packet(&mut decom_csv, &packet, recv_time, &sender);
                                 }
+                            } else if forward {
+                                // send output to each stream, filtering by allowed apids
+                                let output_mask = forward_packet(&mut output_streams,
+                                                                 &app_config.allowed_output_apids,
+                                                                 apid,
+                                                                 &packet.bytes,
+                                                                 recv_time,
+                                                                 &sender);
+                                report_forwarded(&mut report, output_mask, apid, packet.bytes.len());
+                                archive_packet(&mut archive, &packet, recv_time, output_mask, &sender);
+                                parquet_packet(&mut parquet_archive, &packet, recv_time, &sender);
+                                log_packet(&mut csv_log, &packet, recv_time, output_mask, &sender);
+                                decom_packet(&mut decom_csv, &packet, recv_time, &sender);
+                                index_outputs(&mut output_index_writers, output_mask, apid, &packet.bytes, &app_config.timestamp_def, &sender);
+                            } else {
+                                // packets from disabled APIDs are logged with no outputs
+                                log_packet(&mut csv_log, &packet, recv_time, 0, &sender);
+                                decom_packet(&mut decom_csv, &packet, recv_time, &sender);
                             }
 
                             /* Report packet to GUI */
-                            let mut packet_update = PacketUpdate { apid: packet.header.control.apid(),
-                                                                   packet_length: packet.bytes.len() as u16,
-                                                                   seq_count: packet.header.sequence.sequence_count(),
-                                                                   recv_time: recv_time,
-                                                                   bytes: Vec::new(),
-                                                                 };
+                            // the update shares the packet's bytes with the outputs, so the GUI
+                            // is sent the packet without copying it
+                            let seq_count = packet.header.sequence.sequence_count();
+                            let gap = last_seq.map_or(false, |last_seq| is_seq_gap(last_seq, seq_count));
+                            let packet_update = PacketUpdate { apid: packet.header.control.apid(),
+                                                               packet_length: packet.bytes.len() as u16,
+                                                               seq_count: seq_count,
+                                                               recv_time: recv_time,
+                                                               bytes: packet.bytes.clone(),
+                                                               packet_count: 1,
+                                                               byte_count: packet.bytes.len() as u64,
+                                                               gap_count: if gap { 1 } else { 0 },
+                                                               command_codes: cfs_function_code(&packet.bytes, app_config.little_endian_ccsds).into_iter().collect(),
+                                                             };
+
+                            time_state.last_send_time = Instant::now();
+
+                            gui_updates.packet(packet_update, &sender);
+
+                            if step_once {
+                                step_once = false;
+                                state = ProcessingState::Paused;
+                            }
+
+                            // check whether this packet should pause processing
+                            if let Some(description) = check_pause_conditions(&app_config.pause_conditions, &packet, last_seq, forward, packets_forwarded) {
+                                state = ProcessingState::Paused;
+                                sender.send(GuiMessage::Breakpoint(apid, description)).unwrap();
+                            }
+                        }
 
-                            packet_update.bytes.extend(packet.bytes.clone());
+                        Ok(PacketMsg::InputPosition(position)) => {
+                            if !seeking {
+                                sender.send(GuiMessage::InputPosition(position)).unwrap();
+                            }
+                        }
 
-                            time_state.last_send_time = SystemTime::now();
+                        Ok(PacketMsg::SeekComplete) => {
+                            seeking = false;
+                            time_state.replay_anchor = None;
+                        }
 
-                            sender.send(GuiMessage::PacketUpdate(packet_update)).unwrap();
+                        Ok(PacketMsg::Waiting(waiting)) => {
+                            sender.send(GuiMessage::WaitingForConnection(waiting)).unwrap();
                         }
 
-                        Ok(PacketMsg::PacketDropped(header)) => {
-                                sender.send(GuiMessage::PacketDropped(header)).unwrap();
+                        Ok(PacketMsg::PacketDropped(header, drop_reason)) => {
+                                if let Some(ref mut report) = report {
+                                    report.packet_dropped(drop_reason);
+                                }
+                                sender.send(GuiMessage::PacketDropped(header, drop_reason)).unwrap();
                         } 
 
+                        Ok(PacketMsg::CaptureError(err_string)) => {
+                            if let Some(ref mut report) = report {
+                                report.error();
+                            }
+                            sender.send(GuiMessage::Error(err_string)).unwrap();
+                        }
+
+                        Ok(PacketMsg::BytesSkipped(resyncs, bytes_skipped)) => {
+                            if !seeking {
+                                if let Some(ref mut report) = report {
+                                    report.bytes_skipped(resyncs, bytes_skipped);
+                                }
+                                gui_updates.skipped(resyncs, bytes_skipped, &sender);
+                            }
+                        }
+
+                        Ok(PacketMsg::InputRead(input_read)) => {
+                            gui_updates.input_read(input_read, &sender);
+                        }
+
+                        Ok(PacketMsg::BackupInputError(err_string)) => {
+                            if let Some(ref mut report) = report {
+                                report.error();
+                            }
+                            sender.send(GuiMessage::Error(format!("Backup input: {}", err_string))).unwrap();
+                        }
+
+                        Ok(PacketMsg::GapFill(gap_fill)) => {
+                            if let Some(ref mut report) = report {
+                                report.gap_fill(gap_fill);
+                            }
+                            sender.send(GuiMessage::GapFill(gap_fill)).unwrap();
+                        }
+
+                        Ok(PacketMsg::Reorder(reorder)) => {
+                            if let Some(ref mut report) = report {
+                                report.reorder(reorder);
+                            }
+                            sender.send(GuiMessage::Reorder(reorder)).unwrap();
+                        }
+
                         Ok(PacketMsg::StreamParseError) => {
-                            // NOTE this could be presented as an error, rather than panicing
-                            panic!("There was a unrecoverable parsing error while streaming data!");
+                            write_report(&mut report, "Parse Error", &output_streams, &sender);
+                            if let Some(index_writer) = input_index_writer.take() {
+                                index_writer.abandon();
+                            }
+                            sender.send(GuiMessage::Failed(FailureKind::Parse,
+                                                           "There was a unrecoverable parsing error while streaming data".to_string())).unwrap();
+                            state = ProcessingState::Idle;
                         } 
 
                         Ok(PacketMsg::ReadError(e)) => {
+                                if let Some(ref mut report) = report {
+                                    report.error();
+                                }
+                                // the input file was not read completely, so its index is incomplete
+                                if let Some(index_writer) = input_index_writer.take() {
+                                    index_writer.abandon();
+                                }
                                 sender.send(GuiMessage::Error(e)).unwrap();
                         }
 
-                        Ok(PacketMsg::StreamOpenError) => {
-                            // NOTE this could be presented as an error, rather than panicing
-                            panic!("The packet stream could not be opened!")
+                        Ok(PacketMsg::StreamOpenError(err_string)) => {
+                            write_report(&mut report, "Open Error", &output_streams, &sender);
+                            if let Some(index_writer) = input_index_writer.take() {
+                                index_writer.abandon();
+                            }
+                            sender.send(GuiMessage::Failed(FailureKind::Open, err_string)).unwrap();
+                            state = ProcessingState::Idle;
                         }
 
-                        Ok(PacketMsg::StreamEnd) => {
-                            state = ProcessingState::Idle;
+                        Ok(PacketMsg::StreamEnd(end_reason, packet_count, byte_count)) => {
+                            sender.send(GuiMessage::StreamEnd(end_reason, packet_count, byte_count)).unwrap();
+
+                            // the whole input file has been read, so its index is complete
+                            if let Some(index_writer) = input_index_writer.take() {
+                                match index_writer.finish() {
+                                    Ok(index_name) => info!("Wrote packet index {}", index_name),
+
+                                    Err(err_string) => sender.send(GuiMessage::Error(err_string)).unwrap(),
+                                }
+                            }
+
+                            let stop_msg = finish_shaped_outputs(&mut output_streams, &receiver, &sender);
+
+                            // the report is written once the queued packets are, so they are reconciled as delivered
+                            match end_reason {
+                                EndReason::EndOfFile => write_report(&mut report, "Finished", &output_streams, &sender),
+
+                                EndReason::Error => write_report(&mut report, "Input Error", &output_streams, &sender),
+                            }
+
+                            state = match stop_msg {
+                                Some(ProcessingMsg::Terminate) => ProcessingState::Terminating,
+
+                                _ => ProcessingState::Idle,
+                            };
                         }
 
                         Err(e) => {
@@ -522,10 +4158,24 @@ pub fn process_thread(sender: Sender<GuiMessage>, receiver: Receiver<ProcessingM
                     }
                 }
 
-                sender.send(GuiMessage::Finished).unwrap();
+                // the GUI is shown every packet processed before stopping, and the packets
+                // forwarded so far are written out, whether processing paused or stopped
+                gui_updates.send_all(&sender);
+                flush_outputs(&mut output_streams, &sender);
+
+                if state == ProcessingState::Paused {
+                    sender.send(GuiMessage::Paused).unwrap();
+                } else {
+                    sender.send(GuiMessage::Finished).unwrap();
+                }
             },
 
             ProcessingState::Terminating => {
+                input.take();
+                write_report(&mut report, "Terminated", &output_streams, &sender);
+                close_outputs(&mut output_streams, &mut output_index_writers, &sender);
+                close_parquet_archive(&mut parquet_archive, &sender);
+                close_indices(&mut input_index_writer, &mut output_index_writers, &sender);
                 break;
             },
         } // match state
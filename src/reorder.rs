@@ -0,0 +1,356 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use types::*;
+
+
+/// The most packets the reorder buffer can be set to hold
+pub const MAX_REORDER_PACKETS: usize = 100_000;
+
+/// Sequence counts less than half the sequence count range ahead of another are after it
+const SEQ_HALF_RANGE: u16 = 0x2000;
+
+/// The order packets are released from the reorder buffer in
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ReorderOrder {
+    /// The order of the timestamps in the packets, as given by the timestamp settings
+    Timestamp,
+    /// The order of the sequence counts of each APID. The packets of different APIDs are not
+    /// reordered against each other.
+    Sequence,
+}
+
+impl Default for ReorderOrder {
+    fn default() -> Self {
+        ReorderOrder::Timestamp
+    }
+}
+
+/// The reorder buffer holds the packets of an input that delivers them slightly out of order,
+/// releasing them in timestamp or sequence order for outputs that can not handle disorder.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ReorderSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub order: ReorderOrder,
+
+    /// The most packets held. Once more are held, the first in order is released.
+    #[serde(default = "default_max_packets")]
+    pub max_packets: usize,
+
+    /// The most seconds a packet is held waiting for the packets before it
+    #[serde(default = "default_max_latency_secs")]
+    pub max_latency_secs: f32,
+
+    /// Drop the packets that arrive after a packet following them has been released, rather
+    /// than releasing them out of order
+    #[serde(default)]
+    pub drop_late: bool,
+}
+
+fn default_max_packets() -> usize {
+    100
+}
+
+fn default_max_latency_secs() -> f32 {
+    0.5
+}
+
+impl Default for ReorderSettings {
+    fn default() -> Self {
+        ReorderSettings {
+            enabled: false,
+            order: Default::default(),
+            max_packets: default_max_packets(),
+            max_latency_secs: default_max_latency_secs(),
+            drop_late: false,
+        }
+    }
+}
+
+impl ReorderSettings {
+    /// A description of each problem with the reorder buffer
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.enabled {
+            return problems;
+        }
+
+        if self.max_packets == 0 {
+            problems.push("The reorder buffer must hold at least 1 packet".to_string());
+        } else if self.max_packets > MAX_REORDER_PACKETS {
+            problems.push(format!("The reorder buffer holds at most {} packets, but is set to {}", MAX_REORDER_PACKETS, self.max_packets));
+        }
+
+        if !(self.max_latency_secs > 0.0) {
+            problems.push(format!("The reorder buffer latency must be more than 0 seconds, but is {} seconds", self.max_latency_secs));
+        }
+
+        problems
+    }
+
+    pub fn max_latency(&self) -> Duration {
+        let secs = self.max_latency_secs.max(0.0);
+        Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32)
+    }
+}
+
+/// The packets released from the reorder buffer ahead of a packet that arrived before them,
+/// and the packets that arrived too late to be released in order
+#[derive(Default, PartialEq, Eq, Debug, Clone, Copy)]
+pub struct ReorderStats {
+    pub reordered: u64,
+    pub late: u64,
+    pub late_dropped: u64,
+}
+
+/// The position of a held packet in the release order. Packets in timestamp order all have
+/// APID 0, so they are ordered by timestamp alone. Ties are kept in the order they arrived.
+type ReorderKey = (u16, u64, u64);
+
+/// A ReorderBuffer holds packets, releasing them in order once they have been held for the
+/// latency, or once too many are held. A packet is released along with every held packet before
+/// it, so none is held much longer than the latency. In sequence order, a packet that is the
+/// next of its APID is released without waiting.
+pub struct ReorderBuffer<T> {
+    order: ReorderOrder,
+    max_packets: usize,
+    latency: Duration,
+    drop_late: bool,
+
+    /// The packets held, by their position in the release order, with the time they arrived
+    held: BTreeMap<ReorderKey, (Instant, T)>,
+
+    /// The keys of the packets held, in the order they arrived. Packets already released are
+    /// removed as they reach the front.
+    arrivals: VecDeque<(Instant, ReorderKey)>,
+
+    /// The packets pushed, used to keep packets with the same timestamp or sequence count in
+    /// the order they arrived
+    arrival_count: u64,
+
+    /// The timestamp of the last packet released, in timestamp order
+    last_timestamp: Option<u64>,
+
+    /// The sequence count of the last packet pushed for each APID, counting on past wrapping,
+    /// and of the next packet to release, in sequence order
+    last_seqs: HashMap<u16, u64>,
+    next_seqs: HashMap<u16, u64>,
+
+    stats: ReorderStats,
+}
+
+impl<T> ReorderBuffer<T> {
+    pub fn new(settings: &ReorderSettings) -> ReorderBuffer<T> {
+        ReorderBuffer {
+            order: settings.order,
+            max_packets: settings.max_packets.max(1),
+            latency: settings.max_latency(),
+            drop_late: settings.drop_late,
+            held: BTreeMap::new(),
+            arrivals: VecDeque::new(),
+            arrival_count: 0,
+            last_timestamp: None,
+            last_seqs: HashMap::new(),
+            next_seqs: HashMap::new(),
+            stats: Default::default(),
+        }
+    }
+
+    pub fn stats(&self) -> ReorderStats {
+        self.stats
+    }
+
+    pub fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Add a packet, with its APID, sequence count, and timestamp. A packet after one that has
+    /// already been released is released at once, or dropped if late packets are dropped.
+    pub fn push(&mut self, apid: u16, seq_count: u16, timestamp: Duration, item: T, now: Instant, released: &mut VecDeque<T>) {
+        let arrival = self.arrival_count;
+        self.arrival_count += 1;
+
+        let key = match self.order {
+            ReorderOrder::Timestamp => {
+                let timestamp = timestamp.as_secs().saturating_mul(1_000_000_000).saturating_add(timestamp.subsec_nanos() as u64);
+
+                if self.last_timestamp.map_or(false, |last_timestamp| timestamp < last_timestamp) {
+                    self.release_late(item, released);
+                    return;
+                }
+
+                (0, timestamp, arrival)
+            },
+
+            ReorderOrder::Sequence => {
+                let seq = self.unwrap_seq(apid, seq_count);
+
+                if self.next_seqs.get(&apid).map_or(false, |next_seq| seq < *next_seq) {
+                    self.release_late(item, released);
+                    return;
+                }
+
+                (apid, seq, arrival)
+            },
+        };
+
+        self.held.insert(key, (now, item));
+        self.arrivals.push_back((now, key));
+
+        if self.order == ReorderOrder::Sequence {
+            self.release_next(apid, released);
+        }
+
+        self.release(now, released);
+    }
+
+    /// The time until the next packet is due to be released, if any are held
+    pub fn next_release(&mut self, now: Instant) -> Option<Duration> {
+        self.clean_arrivals();
+
+        let deadline = self.arrivals.front()?.0 + self.latency;
+        if deadline > now {
+            Some(deadline.duration_since(now))
+        } else {
+            Some(Duration::from_secs(0))
+        }
+    }
+
+    /// Release the packets that have been held for the latency, along with the packets before
+    /// them, and the first packets in order while too many are held
+    pub fn release(&mut self, now: Instant, released: &mut VecDeque<T>) {
+        loop {
+            self.clean_arrivals();
+
+            let (arrived, oldest_key) = match self.arrivals.front() {
+                Some(oldest) => *oldest,
+
+                None => break,
+            };
+
+            if self.held.len() <= self.max_packets && now < arrived + self.latency {
+                break;
+            }
+
+            // the first packet in order, or in sequence order the first of the oldest's APID
+            let key = match self.order {
+                ReorderOrder::Timestamp => *self.held.keys().next().unwrap(),
+
+                ReorderOrder::Sequence => *self.held.range((oldest_key.0, 0, 0)..).next().unwrap().0,
+            };
+
+            self.release_key(key, released);
+
+            if self.order == ReorderOrder::Sequence {
+                self.release_next(key.0, released);
+            }
+        }
+    }
+
+    /// Release every packet held, in order, such as at the end of the input
+    pub fn finish(&mut self, released: &mut VecDeque<T>) {
+        while let Some(key) = self.held.keys().next().cloned() {
+            self.release_key(key, released);
+        }
+        self.arrivals.clear();
+    }
+
+    /// Start again from a new position in the input, such as after a seek, releasing the
+    /// packets held and forgetting the order of those released
+    pub fn restart(&mut self, released: &mut VecDeque<T>) {
+        self.finish(released);
+        self.last_timestamp = None;
+        self.last_seqs.clear();
+        self.next_seqs.clear();
+    }
+
+    /// Release the held packets of an APID that follow on from the last released, in sequence order
+    fn release_next(&mut self, apid: u16, released: &mut VecDeque<T>) {
+        loop {
+            let next_seq = match self.next_seqs.get(&apid) {
+                Some(next_seq) => *next_seq,
+
+                None => return,
+            };
+
+            let key = match self.held.range((apid, 0, 0)..).next() {
+                Some((key, _)) if key.0 == apid && key.1 == next_seq => *key,
+
+                _ => return,
+            };
+
+            self.release_key(key, released);
+        }
+    }
+
+    fn release_key(&mut self, key: ReorderKey, released: &mut VecDeque<T>) {
+        let (_, item) = match self.held.remove(&key) {
+            Some(held) => held,
+
+            None => return,
+        };
+
+        // a packet that arrived before this one is still held
+        self.clean_arrivals();
+        if self.arrivals.front().map_or(false, |oldest| (oldest.1).2 < key.2) {
+            self.stats.reordered += 1;
+        }
+
+        match self.order {
+            ReorderOrder::Timestamp => {
+                self.last_timestamp = Some(key.1);
+            },
+
+            ReorderOrder::Sequence => {
+                self.next_seqs.insert(key.0, key.1 + 1);
+            },
+        }
+
+        released.push_back(item);
+    }
+
+    fn release_late(&mut self, item: T, released: &mut VecDeque<T>) {
+        if self.drop_late {
+            self.stats.late_dropped += 1;
+        } else {
+            self.stats.late += 1;
+            released.push_back(item);
+        }
+    }
+
+    /// Remove the packets already released from the front of the arrivals
+    fn clean_arrivals(&mut self) {
+        while self.arrivals.front().map_or(false, |oldest| !self.held.contains_key(&oldest.1)) {
+            self.arrivals.pop_front();
+        }
+    }
+
+    /// The sequence count of a packet counting on past wrapping, found from the last pushed for
+    /// its APID
+    fn unwrap_seq(&mut self, apid: u16, seq_count: u16) -> u64 {
+        let seq = match self.last_seqs.get(&apid) {
+            Some(last_seq) => {
+                let delta = seq_delta((*last_seq & CCSDS_SEQ_COUNT_MASK as u64) as u16, seq_count) as u64;
+                if delta < SEQ_HALF_RANGE as u64 {
+                    last_seq + delta
+                } else {
+                    last_seq - (CCSDS_SEQ_COUNT_MASK as u64 + 1 - delta)
+                }
+            },
+
+            // start far enough from 0 that packets before the first can be counted back to
+            None => (1 << 32) + seq_count as u64,
+        };
+
+        self.last_seqs.insert(apid, seq);
+        seq
+    }
+}
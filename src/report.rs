@@ -0,0 +1,575 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::time::SystemTime;
+
+use serde_json;
+
+use chrono::prelude::*;
+
+use floating_duration::TimeAsFloat;
+
+use types::*;
+use stream::*;
+use gap_fill::*;
+use reorder::*;
+
+
+/// The most sequence gaps listed for each APID in a report. Gaps beyond this
+/// are still counted.
+pub const MAX_REPORTED_GAPS: usize = 1000;
+
+/// A gap in an APID's sequence counts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SequenceGap {
+    pub expected: u16,
+    pub received: u16,
+    pub missing: u16,
+}
+
+/// A summary of the packets of one APID seen during processing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApidReport {
+    pub apid: u16,
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub gap_count: u64,
+    pub gaps: Vec<SequenceGap>,
+    pub first_received: String,
+    pub last_received: String,
+    /// The first and last packet timestamps, decoded using the timestamp definition
+    pub first_timestamp_secs: f64,
+    pub last_timestamp_secs: f64,
+}
+
+/// The packets forwarded to one output during processing, or that would have been
+/// forwarded during a dry run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutputReport {
+    /// The output's number and name, and its stream
+    pub label: String,
+    pub output: String,
+    /// A disabled output is not sent packets, and is not reconciled
+    pub enabled: bool,
+    pub packet_count: u64,
+    pub byte_count: u64,
+
+    /// The number of packets forwarded of each APID
+    pub apid_counts: BTreeMap<u16, u64>,
+}
+
+/// The packets routed to an output that did not reach it, or that reached it outside of the
+/// packets counted as forwarded, as counted by the output while processing
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct OutputDelivery {
+    /// Packets not allowed by the output's APIDs
+    pub apid_excluded: u64,
+
+    /// Packets removed by the output's transforms
+    pub transform_excluded: u64,
+
+    /// Packets not written because the output had failed, or could not be opened
+    pub output_failed: u64,
+
+    /// Packets dropped because the output's rate limit queue was full
+    pub queue_overflow: u64,
+
+    /// Packets queued for the output's rate limit that were never written, as the output
+    /// failed or processing stopped first
+    pub queue_unwritten: u64,
+
+    /// Packets held while the output had failed that were written once it reconnected
+    pub written_on_reconnect: u64,
+
+    /// Packets dropped during a simulated link outage
+    pub outage_dropped: u64,
+
+    /// Packets dropped because the output's store and forward backlog was full
+    pub backlog_overflow: u64,
+
+    /// Packets kept in the output's backlog that were never written, as the output did not
+    /// reconnect or processing stopped first
+    pub backlog_unwritten: u64,
+}
+
+/// The reconciliation of the packets read from the input against the packets delivered to
+/// one output. Each packet read is counted in exactly one of the other counts, so an output
+/// is complete when nothing was dropped by error, lost, or is unaccounted for.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutputReconciliation {
+    pub label: String,
+    pub output: String,
+
+    /// The packets read from the input
+    pub packets_read: u64,
+
+    /// The packets removed on purpose before they reached the output: by the input APID filter,
+    /// by disabling their APID, or by waiting for a trigger that did not capture them, and
+    /// then by the output's own APIDs and transforms, and by simulated link outages
+    pub input_filtered: u64,
+    pub not_forwarded: u64,
+    pub apid_excluded: u64,
+    pub transform_excluded: u64,
+    pub outage_dropped: u64,
+
+    /// The packets dropped from the input for an error, such as a CRC failure
+    pub input_dropped: u64,
+
+    /// The packets written to the output
+    pub delivered: u64,
+
+    /// The packets meant for the output that were not written to it
+    pub lost: u64,
+
+    /// The packets that could not be placed in any other count, which should always be 0
+    pub unaccounted: u64,
+
+    pub complete: bool,
+}
+
+/// A RunSummary describes how a run without the GUI ended, and what each of its
+/// sessions processed. It is printed as a single line of JSON for scripts to check.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunSummary {
+    pub outcome: String,
+    pub exit_code: i32,
+    pub sessions: Vec<SessionSummary>,
+}
+
+/// The totals of a session in a RunSummary, along with the errors it reported
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionSummary {
+    pub name: String,
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub packets_dropped: u64,
+    pub packets_filtered: u64,
+    pub gap_count: u64,
+    pub resync_count: u64,
+    pub bytes_skipped: u64,
+    pub errors: Vec<String>,
+}
+
+impl SessionSummary {
+    pub fn new(name: &str, processing_stats: &ProcessingStats, errors: Vec<String>) -> SessionSummary {
+        let packet_history = processing_stats.packet_history.values();
+
+        SessionSummary {
+            name: name.to_string(),
+            packet_count: packet_history.clone().map(|packet_stats| packet_stats.packet_count).sum(),
+            byte_count: packet_history.clone().map(|packet_stats| packet_stats.byte_count).sum(),
+            packets_dropped: processing_stats.packets_dropped,
+            packets_filtered: processing_stats.packets_filtered.values().sum(),
+            gap_count: packet_history.map(|packet_stats| packet_stats.gap_count).sum(),
+            resync_count: processing_stats.resync_count,
+            bytes_skipped: processing_stats.bytes_skipped,
+            errors: errors,
+        }
+    }
+}
+
+/// A ProcessingReport summarizes a single run of processing, from Start until processing
+/// finishes or is cancelled. The report is written to the logs directory as both JSON and
+/// text when processing ends.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcessingReport {
+    pub outcome: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub duration_secs: f64,
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub packets_dropped: u64,
+    /// The packets dropped for each reason
+    pub drop_reasons: BTreeMap<DropReason, u64>,
+    /// The packets of each APID removed by the input APID filter
+    pub packets_filtered: BTreeMap<u16, u64>,
+    /// The packets of each APID dropped for being longer than the maximum packet size
+    pub oversize_packets: BTreeMap<u16, u64>,
+    /// The times the input's parser searched for a valid header, and the bytes it skipped
+    pub resync_count: u64,
+    pub bytes_skipped: u64,
+    /// The packets of the backup input that filled gaps in the input, and the packets of
+    /// either input removed as duplicates
+    pub gaps_filled: u64,
+    pub duplicates_removed: u64,
+    /// The packets the reorder buffer released out of the order they arrived in, and the
+    /// packets that arrived too late to release in order, and of those the ones dropped
+    pub packets_reordered: u64,
+    pub late_packets: u64,
+    pub late_packets_dropped: u64,
+    pub error_count: u64,
+    pub dry_run: bool,
+    pub apids: Vec<ApidReport>,
+    pub outputs: Vec<OutputReport>,
+    /// The input packets reconciled against each output when processing ended
+    pub reconciliation: Vec<OutputReconciliation>,
+    pub config: AppConfig,
+
+    #[serde(skip)]
+    start: SystemTime,
+
+    #[serde(skip)]
+    apid_reports: BTreeMap<u16, ApidReport>,
+
+    /// The packets passed to the outputs, whether or not each output took them
+    #[serde(skip)]
+    packets_routed: u64,
+
+    #[serde(skip)]
+    deliveries: Vec<OutputDelivery>,
+}
+
+impl ProcessingReport {
+    pub fn new(config: &AppConfig) -> ProcessingReport {
+        let start = SystemTime::now();
+
+        ProcessingReport {
+            outcome: "".to_string(),
+            start_time: format_system_time(start),
+            end_time: "".to_string(),
+            duration_secs: 0.0,
+            packet_count: 0,
+            byte_count: 0,
+            packets_dropped: 0,
+            drop_reasons: BTreeMap::new(),
+            packets_filtered: BTreeMap::new(),
+            oversize_packets: BTreeMap::new(),
+            resync_count: 0,
+            bytes_skipped: 0,
+            gaps_filled: 0,
+            duplicates_removed: 0,
+            packets_reordered: 0,
+            late_packets: 0,
+            late_packets_dropped: 0,
+            error_count: 0,
+            dry_run: config.dry_run,
+            apids: Vec::new(),
+            outputs: config.output_selection.iter()
+                                            .zip(config.output_settings.iter())
+                                            .enumerate()
+                                            .map(|(index, (selection, settings))| OutputReport {
+                                                label: config.output_label(index),
+                                                output: stream_spec(*selection, settings),
+                                                enabled: config.is_output_enabled(index),
+                                                packet_count: 0,
+                                                byte_count: 0,
+                                                apid_counts: BTreeMap::new(),
+                                            })
+                                            .collect(),
+            reconciliation: Vec::new(),
+            config: config.clone(),
+
+            start: start,
+            apid_reports: BTreeMap::new(),
+            packets_routed: 0,
+            deliveries: vec![OutputDelivery::default(); config.output_selection.len()],
+        }
+    }
+
+    /// Record a packet, along with the previous sequence count seen for its APID
+    pub fn packet(&mut self, packet: &Packet, recv_time: SystemTime, last_seq: Option<u16>) {
+        let apid = packet.header.control.apid();
+        let seq_count = packet.header.sequence.sequence_count();
+        let timestamp_secs = self.config.timestamp_def.decode(&packet.bytes).as_fractional_secs();
+        let received = format_system_time(recv_time);
+
+        self.packet_count += 1;
+        self.byte_count += packet.bytes.len() as u64;
+
+        let apid_report = self.apid_reports.entry(apid).or_insert_with(|| {
+            ApidReport {
+                apid: apid,
+                packet_count: 0,
+                byte_count: 0,
+                gap_count: 0,
+                gaps: Vec::new(),
+                first_received: received.clone(),
+                last_received: "".to_string(),
+                first_timestamp_secs: timestamp_secs,
+                last_timestamp_secs: 0.0,
+            }
+        });
+
+        apid_report.packet_count += 1;
+        apid_report.byte_count += packet.bytes.len() as u64;
+        apid_report.last_received = received;
+        apid_report.last_timestamp_secs = timestamp_secs;
+
+        if let Some(last_seq) = last_seq {
+            if is_seq_gap(last_seq, seq_count) {
+                apid_report.gap_count += 1;
+                if apid_report.gaps.len() < MAX_REPORTED_GAPS {
+                    apid_report.gaps.push(SequenceGap {
+                        expected: next_seq(last_seq),
+                        received: seq_count,
+                        missing: seq_missing(last_seq, seq_count),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Record a packet forwarded to the outputs in a mask, with bit N set for output N
+    pub fn forwarded(&mut self, output_mask: u32, apid: u16, length: usize) {
+        self.packets_routed += 1;
+
+        for (index, output_report) in self.outputs.iter_mut().enumerate().take(32) {
+            if output_mask & (1 << index) != 0 {
+                output_report.packet_count += 1;
+                output_report.byte_count += length as u64;
+                *output_report.apid_counts.entry(apid).or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub fn packet_dropped(&mut self, drop_reason: DropReason) {
+        self.packets_dropped += 1;
+        *self.drop_reasons.entry(drop_reason).or_insert(0) += 1;
+    }
+
+    /// Record a packet removed by the input APID filter
+    pub fn packet_filtered(&mut self, apid: u16) {
+        self.packet_dropped(DropReason::FilteredApid);
+        *self.packets_filtered.entry(apid).or_insert(0) += 1;
+    }
+
+    /// Record the APID of a packet longer than the maximum packet size. The drop itself is
+    /// recorded with packet_dropped.
+    pub fn packet_oversize(&mut self, apid: u16) {
+        *self.oversize_packets.entry(apid).or_insert(0) += 1;
+    }
+
+    /// Record the bytes the input's parser skipped searching for a valid header
+    pub fn bytes_skipped(&mut self, resyncs: u64, bytes_skipped: u64) {
+        self.resync_count += resyncs;
+        self.bytes_skipped += bytes_skipped;
+    }
+
+    /// Record the packets merged from the backup input
+    pub fn gap_fill(&mut self, gap_fill: GapFillStats) {
+        self.gaps_filled += gap_fill.filled;
+        self.duplicates_removed += gap_fill.duplicates;
+    }
+
+    /// Record the packets released by the reorder buffer
+    pub fn reorder(&mut self, reorder: ReorderStats) {
+        self.packets_reordered += reorder.reordered;
+        self.late_packets += reorder.late;
+        self.late_packets_dropped += reorder.late_dropped;
+    }
+
+    pub fn error(&mut self) {
+        self.error_count += 1;
+    }
+
+    /// Record how the packets routed to each output were delivered, in the order of the outputs
+    pub fn deliveries(&mut self, deliveries: Vec<OutputDelivery>) {
+        self.deliveries = deliveries;
+    }
+
+    /// Reconcile the packets read from the input against the packets delivered to each output
+    pub fn reconcile(&self) -> Vec<OutputReconciliation> {
+        let input_filtered = self.drop_reasons.get(&DropReason::FilteredApid).cloned().unwrap_or(0);
+        let input_dropped = self.packets_dropped - input_filtered;
+        let packets_read = self.packet_count + self.packets_dropped;
+        let not_forwarded = self.packet_count.saturating_sub(self.packets_routed);
+
+        self.outputs.iter().zip(self.deliveries.iter()).filter(|(output_report, _)| output_report.enabled).map(|(output_report, delivery)| {
+            let lost = delivery.output_failed + delivery.queue_overflow + delivery.queue_unwritten +
+                       delivery.backlog_overflow + delivery.backlog_unwritten;
+            let delivered = (output_report.packet_count + delivery.written_on_reconnect).saturating_sub(delivery.queue_unwritten + delivery.backlog_unwritten);
+            let accounted = input_filtered + input_dropped + not_forwarded +
+                            delivery.apid_excluded + delivery.transform_excluded + delivery.outage_dropped +
+                            delivered + lost;
+            let unaccounted = packets_read.saturating_sub(accounted);
+
+            OutputReconciliation {
+                label: output_report.label.clone(),
+                output: output_report.output.clone(),
+                packets_read: packets_read,
+                input_filtered: input_filtered,
+                not_forwarded: not_forwarded,
+                apid_excluded: delivery.apid_excluded,
+                transform_excluded: delivery.transform_excluded,
+                outage_dropped: delivery.outage_dropped,
+                input_dropped: input_dropped,
+                delivered: delivered,
+                lost: lost,
+                unaccounted: unaccounted,
+                complete: input_dropped == 0 && lost == 0 && unaccounted == 0,
+            }
+        }).collect()
+    }
+
+    /// Complete the report with the outcome of processing, such as "Finished" or "Cancelled",
+    /// and write it to the given directory. Returns the names of the files written.
+    pub fn write(mut self, outcome: &str, directory: &str) -> Result<(String, String), String> {
+        let end = SystemTime::now();
+        self.outcome = outcome.to_string();
+        self.end_time = format_system_time(end);
+        self.duration_secs = end.duration_since(self.start).map(|duration| duration.as_fractional_secs()).unwrap_or(0.0);
+        self.apids = self.apid_reports.values().cloned().collect();
+        self.reconciliation = self.reconcile();
+
+        // sessions started together are told apart by their names, and by a number if they share one
+        let start_time: DateTime<Local> = self.start.into();
+        let json_name = unique_file_name(&format!("{}/ccsds_router_report_{}_{}.json",
+                                                  directory,
+                                                  self.config.session_name,
+                                                  start_time.format("%Y%m%d_%H_%M_%S")));
+        let text_name = format!("{}.txt", json_name.trim_end_matches(".json"));
+
+        let json_str = serde_json::to_string_pretty(&self).map_err(|err| err.to_string())?;
+        File::create(&json_name).and_then(|mut file| file.write_all(json_str.as_bytes()))
+                                .map_err(|err| format!("Could not write report {}: {}", json_name, err))?;
+
+        File::create(&text_name).and_then(|mut file| file.write_all(self.to_text().as_bytes()))
+                                .map_err(|err| format!("Could not write report {}: {}", text_name, err))?;
+
+        Ok((json_name, text_name))
+    }
+
+    /// A human readable version of the report
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str("CCSDS Router Processing Report\n");
+        if self.dry_run {
+            text.push_str("Dry run- no packets were written to the outputs\n");
+        }
+        text.push_str(&format!("Outcome:  {}\n", self.outcome));
+        text.push_str(&format!("Started:  {}\n", self.start_time));
+        text.push_str(&format!("Ended:    {}\n", self.end_time));
+        text.push_str(&format!("Duration: {:.3} s\n", self.duration_secs));
+        text.push_str(&format!("Packets: {}  Bytes: {}  Dropped: {}  Errors: {}\n",
+                               self.packet_count, self.byte_count, self.packets_dropped, self.error_count));
+        if !self.drop_reasons.is_empty() {
+            let drop_reasons = self.drop_reasons.iter()
+                                                .map(|(drop_reason, count)| format!("{} ({})", drop_reason.name(), count))
+                                                .collect::<Vec<String>>();
+            text.push_str(&format!("Dropped by reason: {}\n", drop_reasons.join(", ")));
+        }
+        if !self.packets_filtered.is_empty() {
+            let filtered = self.packets_filtered.iter()
+                                                .map(|(apid, count)| format!("{} ({})", apid, count))
+                                                .collect::<Vec<String>>();
+            text.push_str(&format!("Filtered by input APID: {}\n", filtered.join(", ")));
+        }
+        if !self.oversize_packets.is_empty() {
+            let oversize = self.oversize_packets.iter()
+                                                .map(|(apid, count)| format!("{} ({})", apid, count))
+                                                .collect::<Vec<String>>();
+            text.push_str(&format!("Too long by APID: {}\n", oversize.join(", ")));
+        }
+        if self.resync_count > 0 {
+            text.push_str(&format!("Resyncs: {}  Bytes skipped: {}\n", self.resync_count, self.bytes_skipped));
+        }
+        if self.config.gap_fill.enabled {
+            text.push_str(&format!("Gaps filled from backup input: {}  Duplicates removed: {}\n", self.gaps_filled, self.duplicates_removed));
+        }
+        if self.config.reorder.enabled {
+            text.push_str(&format!("Packets reordered: {}  Late packets: {}  Late packets dropped: {}\n",
+                                   self.packets_reordered,
+                                   self.late_packets,
+                                   self.late_packets_dropped));
+        }
+        text.push_str("\n");
+
+        text.push_str(&format!("{:>6} {:>10} {:>12} {:>6} {:>32} {:>32}\n",
+                               "APID", "Packets", "Bytes", "Gaps", "First Received", "Last Received"));
+        for apid_report in self.apids.iter() {
+            text.push_str(&format!("{:>6} {:>10} {:>12} {:>6} {:>32} {:>32}\n",
+                                   apid_report.apid,
+                                   apid_report.packet_count,
+                                   apid_report.byte_count,
+                                   apid_report.gap_count,
+                                   apid_report.first_received,
+                                   apid_report.last_received));
+        }
+
+        if self.apids.iter().any(|apid_report| apid_report.gap_count > 0) {
+            text.push_str("\nSequence Gaps:\n");
+            for apid_report in self.apids.iter() {
+                for gap in apid_report.gaps.iter() {
+                    text.push_str(&format!("  APID {}: expected {} received {} ({} missing)\n",
+                                           apid_report.apid, gap.expected, gap.received, gap.missing));
+                }
+                if apid_report.gap_count > apid_report.gaps.len() as u64 {
+                    text.push_str(&format!("  APID {}: {} more gaps not listed\n",
+                                           apid_report.apid, apid_report.gap_count - apid_report.gaps.len() as u64));
+                }
+            }
+        }
+
+        text.push_str("\n");
+        text.push_str(&self.outputs_text());
+
+        text.push_str("\n");
+        text.push_str(&self.reconciliation_text());
+
+        text.push_str("\nConfiguration:\n");
+        text.push_str(&serde_json::to_string_pretty(&self.config).unwrap_or_default());
+        text.push_str("\n");
+
+        text
+    }
+
+    /// The packets forwarded to each output, as text
+    pub fn outputs_text(&self) -> String {
+        let mut text = String::new();
+
+        if self.dry_run {
+            text.push_str("Outputs (packets that would have been forwarded):\n");
+        } else {
+            text.push_str("Outputs:\n");
+        }
+
+        for output_report in self.outputs.iter() {
+            if !output_report.enabled {
+                text.push_str(&format!("  {} {}: disabled\n", output_report.label, output_report.output));
+                continue;
+            }
+
+            text.push_str(&format!("  {} {}: {} packets, {} bytes\n",
+                                   output_report.label, output_report.output, output_report.packet_count, output_report.byte_count));
+            for (apid, packet_count) in output_report.apid_counts.iter() {
+                text.push_str(&format!("    APID {}: {} packets\n", apid, packet_count));
+            }
+        }
+
+        text
+    }
+
+    /// The reconciliation of the input against each output, as text
+    pub fn reconciliation_text(&self) -> String {
+        let mut text = String::new();
+
+        text.push_str("Reconciliation (packets read from the input against packets delivered):\n");
+        for reconciliation in self.reconciliation.iter() {
+            text.push_str(&format!("  {} {}: {}\n",
+                                   reconciliation.label,
+                                   reconciliation.output,
+                                   if reconciliation.complete { "COMPLETE" } else { "INCOMPLETE" }));
+            text.push_str(&format!("    Read: {}  Delivered: {}  Lost: {}  Dropped at input: {}  Unaccounted: {}\n",
+                                   reconciliation.packets_read,
+                                   reconciliation.delivered,
+                                   reconciliation.lost,
+                                   reconciliation.input_dropped,
+                                   reconciliation.unaccounted));
+            text.push_str(&format!("    Excluded- input APID filter: {}  not forwarded: {}  output APIDs: {}  transforms: {}  outages: {}\n",
+                                   reconciliation.input_filtered,
+                                   reconciliation.not_forwarded,
+                                   reconciliation.apid_excluded,
+                                   reconciliation.transform_excluded,
+                                   reconciliation.outage_dropped));
+        }
+
+        text
+    }
+}
+
+fn format_system_time(time: SystemTime) -> String {
+    let time: DateTime<Local> = time.into();
+    time.to_rfc3339()
+}
@@ -0,0 +1,127 @@
+use std::sync::mpsc::{channel, Sender, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use types::*;
+use processing::*;
+
+
+/// A Router forwards packets from an input stream to its outputs on its own processing thread.
+/// It is configured with an AppConfig, started and stopped, and reports what it does as events.
+///
+/// ```no_run
+/// use ccsds_router_core::router::Router;
+/// use ccsds_router_core::types::*;
+///
+/// let mut router = Router::new(AppConfig::default());
+/// router.start().unwrap();
+///
+/// while let Ok(event) = router.events().recv() {
+///     match event {
+///         GuiMessage::PacketUpdate(packet_update) => println!("APID {}", packet_update.apid),
+///
+///         GuiMessage::Finished => break,
+///
+///         _ => {},
+///     }
+/// }
+///
+/// router.shutdown();
+/// ```
+pub struct Router {
+    config: AppConfig,
+    sender: Sender<ProcessingMsg>,
+    receiver: Receiver<GuiMessage>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Router {
+    /// Create a router, spawning its processing thread. Processing waits until the router is started.
+    pub fn new(config: AppConfig) -> Router {
+        let (event_sender, event_receiver) = channel::<GuiMessage>();
+        let (proc_sender, proc_receiver) = channel::<ProcessingMsg>();
+
+        let processing_thread = thread::spawn(move || {
+            process_thread(event_sender, proc_receiver);
+        });
+
+        Router {
+            config: config,
+            sender: proc_sender,
+            receiver: event_receiver,
+            thread: Some(processing_thread),
+        }
+    }
+
+    /// Set the configuration used the next time the router is started
+    pub fn configure(&mut self, config: AppConfig) {
+        self.config = config;
+    }
+
+    pub fn config(&self) -> &AppConfig {
+        &self.config
+    }
+
+    /// Start processing with the router's configuration
+    pub fn start(&self) -> Result<(), String> {
+        self.send(ProcessingMsg::Start(self.config.clone()))
+    }
+
+    pub fn pause(&self) -> Result<(), String> {
+        self.send(ProcessingMsg::Pause)
+    }
+
+    pub fn resume(&self) -> Result<(), String> {
+        self.send(ProcessingMsg::Continue)
+    }
+
+    /// Stop processing, closing the input and outputs. The router can be started again.
+    pub fn stop(&self) -> Result<(), String> {
+        self.send(ProcessingMsg::Cancel)
+    }
+
+    /// Send a message to the processing thread, such as a seek or a packet to send
+    pub fn send(&self, msg: ProcessingMsg) -> Result<(), String> {
+        self.sender.send(msg).map_err(|_| "The router's processing thread has exited".to_string())
+    }
+
+    /// A sender for messages to the processing thread, for use from another thread
+    pub fn sender(&self) -> Sender<ProcessingMsg> {
+        self.sender.clone()
+    }
+
+    /// The events reported by processing, such as packets forwarded, errors, and processing finishing
+    pub fn events(&self) -> &Receiver<GuiMessage> {
+        &self.receiver
+    }
+
+    /// Terminate the processing thread and wait for it to exit, logging any errors it
+    /// reports while shutting down. The router can not be started again.
+    pub fn shutdown(&mut self) {
+        if self.send(ProcessingMsg::Terminate).is_ok() {
+            while let Ok(msg) = self.receiver.recv_timeout(Duration::from_millis(500)) {
+                match msg {
+                    GuiMessage::Error(error_msg) | GuiMessage::Failed(_, error_msg) => {
+                        error!("{}", error_msg);
+                    },
+
+                    GuiMessage::Terminate => {
+                        break;
+                    },
+
+                    _ => {}, // ignore other messages
+                }
+            }
+        }
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Router {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -0,0 +1,105 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use epoch::*;
+
+
+/// The schedule starts and stops processing at given times without an operator present, such
+/// as at the start of a pass and at loss of signal
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleSettings {
+    /// When processing starts, as a UTC time such as '2020-01-01T12:00:00Z', or a countdown from
+    /// when processing is started, such as '+90' or '+00:01:30'. If empty, processing starts
+    /// as soon as it is started.
+    #[serde(default)]
+    pub start: String,
+
+    /// When processing stops, as a UTC time, or a duration from when processing starts. If
+    /// empty, processing runs until the input ends or it is stopped.
+    #[serde(default)]
+    pub stop: String,
+}
+
+impl ScheduleSettings {
+    /// The time processing starts if it is started at the given time, or None if it starts
+    /// straight away
+    pub fn start_time(&self, now: SystemTime) -> Option<SystemTime> {
+        parse_schedule_time(&self.start).ok().and_then(|schedule_time| schedule_time.map(|schedule_time| schedule_time.from(now)))
+    }
+
+    /// The time processing stops if it starts at the given time, or None if it does not stop
+    /// on a schedule
+    pub fn stop_time(&self, start_time: SystemTime) -> Option<SystemTime> {
+        parse_schedule_time(&self.stop).ok().and_then(|schedule_time| schedule_time.map(|schedule_time| schedule_time.from(start_time)))
+    }
+
+    /// A description of each problem with the schedule
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Err(problem) = parse_schedule_time(&self.start) {
+            problems.push(format!("The scheduled start time is not valid: {}", problem));
+        }
+
+        if let Err(problem) = parse_schedule_time(&self.stop) {
+            problems.push(format!("The scheduled stop time is not valid: {}", problem));
+        }
+
+        if let (Ok(Some(ScheduleTime::At(start_time))), Ok(Some(ScheduleTime::At(stop_time)))) =
+               (parse_schedule_time(&self.start), parse_schedule_time(&self.stop)) {
+            if stop_time <= start_time {
+                problems.push("The scheduled stop time is not after the scheduled start time".to_string());
+            }
+        }
+
+        problems
+    }
+}
+
+/// A time in a schedule, either a UTC time, or a time after some other time
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ScheduleTime {
+    At(SystemTime),
+    After(Duration),
+}
+
+impl ScheduleTime {
+    /// The time this gives, where a time after another is counted from the given time
+    pub fn from(&self, time: SystemTime) -> SystemTime {
+        match self {
+            ScheduleTime::At(at_time) => *at_time,
+
+            ScheduleTime::After(duration) => time + *duration,
+        }
+    }
+}
+
+/// Parse a time in a schedule, which is either a UTC time, such as '2020-01-01T12:00:00Z', or a
+/// time after another given with a '+' as seconds, such as '+90', or as hours, minutes and
+/// seconds, such as '+00:01:30'. An empty string is no time.
+pub fn parse_schedule_time(time_str: &str) -> Result<Option<ScheduleTime>, String> {
+    let time_str = time_str.trim();
+
+    if time_str.is_empty() {
+        return Ok(None);
+    }
+
+    if time_str.starts_with('+') {
+        let mut secs = 0.0;
+        for part in time_str[1..].split(':') {
+            let part_secs = part.trim().parse::<f64>()
+                                .map_err(|_| format!("'{}' is not a countdown, such as +90 or +00:01:30", time_str))?;
+            secs = secs * 60.0 + part_secs;
+        }
+
+        if secs < 0.0 {
+            return Err(format!("The countdown '{}' is negative", time_str));
+        }
+
+        return Ok(Some(ScheduleTime::After(Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32))));
+    }
+
+    let utc = parse_utc(time_str)?;
+    let unix_secs = utc.timestamp().max(0) as u64;
+
+    Ok(Some(ScheduleTime::At(UNIX_EPOCH + Duration::new(unix_secs, utc.timestamp_subsec_nanos()))))
+}
@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+use std::mem;
+
+use ccsds_router_core::types::*;
+use ccsds_router_core::router::*;
+
+
+/// The longest time that packet arrivals are kept for the timeline
+pub const MAX_TIMELINE_SECONDS: f32 = 120.0;
+
+/// The most packet arrivals kept for the timeline, limiting memory use at high packet rates
+pub const MAX_TIMELINE_ARRIVALS: usize = 100_000;
+
+/// The number of seconds of skipped bytes kept for the resync plot
+pub const MAX_RESYNC_HISTORY_SECONDS: usize = 120;
+
+/// A Session is a single route from an input to its outputs, with its own
+/// processing thread, statistics, and playback state. The GUI shows each
+/// session as a tab, and only the selected session's state is displayed.
+pub struct Session {
+    pub router: Router,
+
+    pub processing_stats: ProcessingStats,
+    pub processing: bool,
+    pub paused: bool,
+    pub output_index: usize,
+
+    pub packet_recv_diffs: VecDeque<SystemTime>,
+    pub packet_recv_count: usize,
+    pub packet_recv_bytes: usize,
+
+    /// The packets received of each APID since the rates were last updated
+    pub apid_recv_counts: HashMap<u16, usize>,
+
+    /// The time the rates were last updated
+    pub rates_time: SystemTime,
+
+    /// The rate alarms that are raised, by their index in the configuration, with a description of each
+    pub active_alarms: BTreeMap<usize, String>,
+
+    /// The receive time and APID of recent packets, oldest first
+    pub arrivals: VecDeque<(SystemTime, u16)>,
+
+    /// The bytes the input's parser skipped since the rates were last updated, and the bytes it
+    /// skipped in each second while processing, oldest first
+    pub skipped_recv_bytes: u64,
+    pub skipped_history: VecDeque<f32>,
+
+    /// The bytes read from the input since the rates were last updated
+    pub input_recv_bytes: u64,
+
+    pub playback_speed: f32,
+    pub seek_fraction: f32,
+    pub seek_dragging: bool,
+    pub input_position: u64,
+
+    /// The packet number and time, in seconds, entered for seeking with the input file's index
+    pub seek_packet: i32,
+    pub seek_time: String,
+
+    pub breakpoint: Option<(u16, String)>,
+    pub disabled_apids: Vec<u16>,
+
+    /// The APID and condition that started the current trigger based capture, if capturing
+    pub capture: Option<(u16, String)>,
+
+    /// The APID shown in the packet inspector, and the index into its history,
+    /// where 0 is the most recent packet.
+    pub inspect_apid: Option<u16>,
+    pub inspect_index: usize,
+
+    /// The index into the packets dropped for being too long that is being viewed,
+    /// where 0 is the most recent packet.
+    pub oversize_index: usize,
+
+    /// The outputs that have failed while processing, by index, with the error that degraded them
+    pub degraded_outputs: BTreeMap<usize, String>,
+
+    /// Every APID seen while processing, in this run or an earlier one, which is kept when the
+    /// statistics are cleared so APID filters can be chosen from them
+    pub observed_apids: BTreeSet<Apid>,
+
+    /// The stream processing is waiting to connect, if any
+    pub waiting_for: Option<String>,
+
+    /// Whether the outputs are in a simulated link outage
+    pub in_outage: bool,
+
+    /// The time processing is to start, if it is scheduled to start by its schedule or with a peer router
+    pub scheduled_start: Option<SystemTime>,
+
+    /// The time processing is to stop, if it is scheduled to stop
+    pub scheduled_stop: Option<SystemTime>,
+}
+
+impl Session {
+    /// Create a new session, spawning its processing thread
+    pub fn spawn() -> Session {
+        Session {
+            router: Router::new(Default::default()),
+
+            processing_stats: Default::default(),
+            processing: false,
+            paused: false,
+            output_index: 0,
+
+            packet_recv_diffs: VecDeque::new(),
+            packet_recv_count: 0,
+            packet_recv_bytes: 0,
+
+            apid_recv_counts: HashMap::new(),
+
+            rates_time: SystemTime::now(),
+
+            active_alarms: BTreeMap::new(),
+
+            arrivals: VecDeque::new(),
+
+            skipped_recv_bytes: 0,
+            skipped_history: VecDeque::new(),
+
+            input_recv_bytes: 0,
+
+            observed_apids: BTreeSet::new(),
+
+            playback_speed: 1.0,
+            seek_fraction: 0.0,
+            seek_dragging: false,
+            input_position: 0,
+
+            seek_packet: 0,
+            seek_time: "0".to_string(),
+
+            breakpoint: None,
+            disabled_apids: Vec::new(),
+
+            capture: None,
+
+            inspect_apid: None,
+            inspect_index: 0,
+
+            oversize_index: 0,
+
+            degraded_outputs: BTreeMap::new(),
+
+            waiting_for: None,
+            in_outage: false,
+
+            scheduled_start: None,
+            scheduled_stop: None,
+        }
+    }
+
+    /// Start processing with the given configuration
+    pub fn start(&mut self, session_config: &AppConfig) {
+        self.router.configure(session_config.clone());
+        self.router.start().unwrap();
+        self.processing = true;
+        self.paused = false;
+        self.breakpoint = None;
+        self.capture = None;
+        self.scheduled_start = None;
+        self.scheduled_stop = session_config.schedule.stop_time(SystemTime::now());
+    }
+
+    /// Whether the session's scheduled start time has been reached
+    pub fn start_due(&self) -> bool {
+        self.scheduled_start.map_or(false, |start_time| SystemTime::now() >= start_time)
+    }
+
+    /// Whether the session is processing and its scheduled stop time has been reached
+    pub fn stop_due(&self) -> bool {
+        (self.processing || self.paused) &&
+        self.scheduled_stop.map_or(false, |stop_time| SystemTime::now() >= stop_time)
+    }
+
+    /// Update the session with a message from its processing thread. Messages that
+    /// concern the whole application, such as Terminate and Error, are returned to
+    /// the caller to handle.
+    pub fn update(&mut self, msg: GuiMessage, history_length: usize) -> Option<GuiMessage> {
+        match msg {
+            GuiMessage::PacketUpdate(packet_update) => {
+                let apid = packet_update.apid;
+                self.observed_apids.insert(apid);
+                let packet_stats = self.processing_stats.packet_history.entry(apid).or_default();
+                let packet_count = packet_update.packet_count as usize;
+                let byte_count = packet_update.byte_count as usize;
+                packet_stats.update(packet_update, history_length);
+                self.packet_recv_diffs.push_back(packet_stats.recv_time);
+                self.packet_recv_count += packet_count;
+                self.packet_recv_bytes += byte_count;
+                *self.apid_recv_counts.entry(apid).or_insert(0) += packet_count;
+
+                self.arrivals.push_back((packet_stats.recv_time, apid));
+                if self.arrivals.len() > MAX_TIMELINE_ARRIVALS {
+                    self.arrivals.pop_front();
+                }
+            },
+
+            GuiMessage::FrameUpdate(frame_update) => {
+                let vc_stats = self.processing_stats.vc_history.entry(frame_update.virtual_channel).or_default();
+                vc_stats.update(frame_update);
+            },
+
+            GuiMessage::PacketDropped(_, drop_reason) => {
+                self.processing_stats.packets_dropped += 1;
+                *self.processing_stats.drop_reasons.entry(drop_reason).or_insert(0) += 1;
+            },
+
+            GuiMessage::PacketsFiltered(apid_counts) => {
+                for (apid, count) in apid_counts {
+                    self.processing_stats.packets_dropped += count;
+                    *self.processing_stats.drop_reasons.entry(DropReason::FilteredApid).or_insert(0) += count;
+                    *self.processing_stats.packets_filtered.entry(apid).or_insert(0) += count;
+                }
+            },
+
+            GuiMessage::OversizePacket(capture) => {
+                *self.processing_stats.oversize_counts.entry(capture.header.control.apid()).or_insert(0) += 1;
+
+                self.processing_stats.oversize_captures.push_back(capture);
+                if self.processing_stats.oversize_captures.len() > MAX_OVERSIZE_CAPTURES {
+                    self.processing_stats.oversize_captures.pop_front();
+                }
+            },
+
+            GuiMessage::BytesSkipped(resyncs, bytes_skipped) => {
+                self.processing_stats.resync_count += resyncs;
+                self.processing_stats.bytes_skipped += bytes_skipped;
+                self.skipped_recv_bytes += bytes_skipped;
+            },
+
+            GuiMessage::InputRead(input_read) => {
+                self.processing_stats.input_bytes_read += input_read.byte_count;
+                self.processing_stats.parser_buffered_bytes = input_read.buffered_bytes;
+                self.processing_stats.parser_max_bytes = input_read.max_buffered_bytes;
+                self.processing_stats.last_read_time = Some(input_read.read_time);
+                self.input_recv_bytes += input_read.byte_count;
+            },
+
+            GuiMessage::Finished => {
+                self.processing = false;
+                self.waiting_for = None;
+                self.in_outage = false;
+
+                // the outputs are closed when processing stops, and opened again when it starts
+                if !self.paused {
+                    self.degraded_outputs.clear();
+                    self.scheduled_stop = None;
+                }
+            },
+
+            GuiMessage::Paused => {
+                self.processing = false;
+                self.waiting_for = None;
+                self.in_outage = false;
+            },
+
+            GuiMessage::InputPosition(position) => {
+                self.input_position = position;
+            },
+
+            GuiMessage::Breakpoint(apid, description) => {
+                info!("Paused Processing on condition '{}' (APID {})", description, apid);
+                self.processing = false;
+                self.paused = true;
+                self.breakpoint = Some((apid, description));
+            },
+
+            GuiMessage::CaptureStatus(capture) => {
+                match capture {
+                    Some((apid, ref description)) => info!("Capture triggered on condition '{}' (APID {})", description, apid),
+
+                    None => info!("Capture ended, waiting for the next trigger"),
+                }
+                self.capture = capture;
+            },
+
+            GuiMessage::OutputDegraded(index, label, error_msg) => {
+                self.degraded_outputs.insert(index, error_msg.clone());
+                return Some(GuiMessage::Error(format!("{} failed: {}", label, error_msg)));
+            },
+
+            GuiMessage::WaitingForConnection(waiting_for) => {
+                if let Some(ref description) = waiting_for {
+                    info!("Waiting for {} to connect", description);
+                }
+                self.waiting_for = waiting_for;
+            },
+
+            GuiMessage::GapFill(gap_fill) => {
+                self.processing_stats.gap_fill.filled += gap_fill.filled;
+                self.processing_stats.gap_fill.duplicates += gap_fill.duplicates;
+            },
+
+            GuiMessage::Reorder(reorder) => {
+                self.processing_stats.reorder.reordered += reorder.reordered;
+                self.processing_stats.reorder.late += reorder.late;
+                self.processing_stats.reorder.late_dropped += reorder.late_dropped;
+            },
+
+            GuiMessage::Outage(in_outage) => {
+                if in_outage {
+                    info!("Simulated link outage started, dropping everything written to the outputs");
+                } else {
+                    info!("Simulated link outage ended");
+                }
+                self.in_outage = in_outage;
+            },
+
+            GuiMessage::StreamEnd(end_reason, packet_count, byte_count) => {
+                match end_reason {
+                    EndReason::EndOfFile => info!("End of input after {} packets ({} bytes)", packet_count, byte_count),
+
+                    EndReason::Error => info!("Input stopped by an error after {} packets ({} bytes)", packet_count, byte_count),
+                }
+            },
+
+            GuiMessage::OutputRecovered(index, label) => {
+                info!("{} reconnected", label);
+                self.degraded_outputs.remove(&index);
+            },
+
+            msg => return Some(msg),
+        }
+
+        None
+    }
+
+    /// Update the packet and byte rates once a second has passed, and check the rate alarms
+    /// against them, returning a description of each alarm that was raised. The rates fall to
+    /// zero when no packets are received for a second. Alarms are only checked while processing.
+    pub fn update_rates(&mut self, rate_alarms: &[RateAlarm]) -> Vec<String> {
+        let mut raised = Vec::new();
+
+        let window_start = self.packet_recv_diffs.front().cloned().unwrap_or(self.rates_time);
+        let window_ended = SystemTime::now().duration_since(window_start).map(|age| age > Duration::from_secs(1)).unwrap_or(false);
+        if window_ended {
+            self.processing_stats.packets_per_second = self.packet_recv_count;
+            self.processing_stats.bytes_per_second = self.packet_recv_bytes;
+            self.processing_stats.apid_packets_per_second = mem::replace(&mut self.apid_recv_counts, HashMap::new());
+            self.packet_recv_diffs.clear();
+            self.packet_recv_count = 0;
+            self.packet_recv_bytes = 0;
+            self.rates_time = SystemTime::now();
+
+            if self.processing {
+                self.skipped_history.push_back(self.skipped_recv_bytes as f32);
+                if self.skipped_history.len() > MAX_RESYNC_HISTORY_SECONDS {
+                    self.skipped_history.pop_front();
+                }
+            }
+            self.skipped_recv_bytes = 0;
+
+            self.processing_stats.input_bytes_per_second = self.input_recv_bytes;
+            self.input_recv_bytes = 0;
+
+            if self.processing {
+                // alarms removed from the configuration are no longer raised
+                self.active_alarms.split_off(&rate_alarms.len());
+
+                for (index, rate_alarm) in rate_alarms.iter().enumerate() {
+                    match rate_alarm.check(&self.processing_stats) {
+                        Some(description) => {
+                            if !self.active_alarms.contains_key(&index) {
+                                warn!("Rate alarm: {}", description);
+                                raised.push(description.clone());
+                            }
+                            self.active_alarms.insert(index, description);
+                        },
+
+                        None => {
+                            if let Some(description) = self.active_alarms.remove(&index) {
+                                info!("Rate alarm cleared: {}", description);
+                            }
+                        },
+                    }
+                }
+            } else {
+                self.active_alarms.clear();
+            }
+        }
+
+        let max_age = Duration::from_secs(MAX_TIMELINE_SECONDS as u64);
+        while self.arrivals.front().map_or(false, |&(recv_time, _)| recv_time.elapsed().map(|age| age > max_age).unwrap_or(false)) {
+            self.arrivals.pop_front();
+        }
+
+        raised
+    }
+
+    /// Clear the statistics and timeline of the session
+    pub fn clear_stats(&mut self) {
+        self.processing_stats = Default::default();
+        self.apid_recv_counts.clear();
+        self.arrivals.clear();
+        self.skipped_recv_bytes = 0;
+        self.skipped_history.clear();
+        self.input_recv_bytes = 0;
+    }
+
+    /// Terminate the session's processing thread and wait for it to exit,
+    /// logging any errors it reports while shutting down.
+    pub fn terminate(&mut self) {
+        self.router.shutdown();
+    }
+}
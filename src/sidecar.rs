@@ -0,0 +1,115 @@
+use std::fs::{File, remove_file};
+use std::io::{BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use floating_duration::TimeAsFloat;
+
+use serde_json;
+
+use ccsds_primary_header::primary_header::*;
+
+use types::*;
+
+
+/// The extension added to a packet file's name to get the name of its metadata sidecar
+pub const SIDECAR_EXTENSION: &str = "meta.jsonl";
+
+/// A SidecarRecord describes one packet written to a packet file. The sidecar file has a
+/// record per packet, each a line of JSON, in the order the packets were written to the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SidecarRecord {
+    /// The byte offset of the packet in the packet file
+    pub offset: u64,
+
+    pub apid: u16,
+
+    pub seq: u16,
+
+    /// The length of the packet, including its primary header
+    pub packet_length: usize,
+
+    /// The bytes written to the file for the packet. This differs from the packet's length
+    /// when the output's transforms frame or change the packet.
+    pub written_length: usize,
+
+    /// The time the packet was received, in seconds since the UNIX epoch
+    pub recv_time: f64,
+
+    /// The packet's timestamp in seconds, decoded using the timestamp definition
+    pub timestamp: f64,
+}
+
+/// A SidecarWriter writes the metadata sidecar of a packet file as packets are written to it,
+/// so that tools can find the packets in the file without parsing it.
+pub struct SidecarWriter {
+    sidecar_name: String,
+    writer: BufWriter<File>,
+    timestamp_def: TimestampDef,
+    little_endian_header: bool,
+}
+
+impl SidecarWriter {
+    /// The name of the metadata sidecar of a packet file
+    pub fn sidecar_file_name(file_name: &str) -> String {
+        format!("{}.{}", file_name, SIDECAR_EXTENSION)
+    }
+
+    pub fn create(file_name: &str, timestamp_def: &TimestampDef, little_endian_header: bool) -> Result<SidecarWriter, String> {
+        let sidecar_name = SidecarWriter::sidecar_file_name(file_name);
+
+        let sidecar_file = File::create(&sidecar_name)
+                                .map_err(|err| format!("Could not create metadata sidecar {}: {}", sidecar_name, err))?;
+
+        Ok(SidecarWriter {
+            sidecar_name: sidecar_name,
+            writer: BufWriter::new(sidecar_file),
+            timestamp_def: timestamp_def.clone(),
+            little_endian_header: little_endian_header,
+        })
+    }
+
+    /// Add the record of a packet written to the packet file at the given offset
+    pub fn write_record(&mut self, offset: u64, apid: u16, packet_bytes: &[u8], written_length: usize, recv_time: SystemTime) -> Result<(), String> {
+        // the sequence count is the second 16 bit word of the primary header
+        let seq = if packet_bytes.len() >= CCSDS_PRI_HEADER_SIZE_BYTES as usize {
+            let (high_byte, low_byte) = if self.little_endian_header {
+                (packet_bytes[3], packet_bytes[2])
+            } else {
+                (packet_bytes[2], packet_bytes[3])
+            };
+            (((high_byte as u16) << 8) | low_byte as u16) & CCSDS_SEQ_COUNT_MASK
+        } else {
+            0
+        };
+
+        let record = SidecarRecord {
+            offset: offset,
+            apid: apid,
+            seq: seq,
+            packet_length: packet_bytes.len(),
+            written_length: written_length,
+            recv_time: recv_time.duration_since(UNIX_EPOCH).map(|since_epoch| since_epoch.as_fractional_secs()).unwrap_or(0.0),
+            timestamp: self.timestamp_def.decode(packet_bytes).as_fractional_secs(),
+        };
+
+        serde_json::to_writer(&mut self.writer, &record)
+                   .map_err(|err| format!("Could not write metadata sidecar {}: {}", self.sidecar_name, err))?;
+        self.writer.write_all(b"\n")
+                   .map_err(|err| format!("Could not write metadata sidecar {}: {}", self.sidecar_name, err))
+    }
+
+    /// Write out the records, returning the name of the sidecar
+    pub fn finish(mut self) -> Result<String, String> {
+        self.writer.flush()
+            .map_err(|err| format!("Could not write metadata sidecar {}: {}", self.sidecar_name, err))?;
+
+        Ok(self.sidecar_name)
+    }
+
+    /// Discard the sidecar, such as when its packet file was never created
+    pub fn abandon(self) {
+        let sidecar_name = self.sidecar_name;
+        drop(self.writer);
+        let _ = remove_file(sidecar_name);
+    }
+}
@@ -1,15 +1,47 @@
 use std::fs::File;
-use std::io::{Read, BufReader};
+use std::io::{self, Read, BufReader, BufWriter, ErrorKind, Seek, SeekFrom};
 use std::io::prelude::*;
-use std::net::{TcpListener, TcpStream, UdpSocket, SocketAddrV4};
-use std::time::Duration;
-use std::borrow::BorrowMut;
+use std::net::{TcpListener, TcpStream, UdpSocket, SocketAddr, SocketAddrV4, Ipv4Addr, Shutdown};
+use std::time::{Duration, Instant, SystemTime};
+#[cfg(unix)]
+use std::time::UNIX_EPOCH;
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::{mem, ptr};
+use std::collections::{BTreeMap, HashMap};
+use std::cmp::{min, max};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::fmt;
+use std::path::Path;
 
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use bytes::BufMut;
 
+use chrono::{DateTime, Local};
+
 use ccsds_primary_header::primary_header::*;
 
+use mio;
+
+use index::QueryReader;
+use frame_output::*;
+
+#[cfg(unix)]
+use libc;
+
+
+/// The largest UDP datagram, and the most bytes a UDP input reads at once
+pub const MAX_DATAGRAM_BYTES: usize = 65536;
+
+/// How long a read of a network input waits for bytes before timing out, so the thread
+/// reading it can respond to control messages while no data is arriving
+pub const INPUT_READ_TIMEOUT_MS: u64 = 250;
+
+/// The file name of a file output written to stdout
+pub const STDOUT_FILE_NAME: &str = "-";
 
 /// The stream option is the input/output stream type
 #[derive(FromPrimitive, Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
@@ -22,6 +54,8 @@ pub enum StreamOption {
     TcpServer = 3,
     /// The stream is a UDP socket with a given port
     Udp       = 4,
+    /// The stream is provided by a registered plugin
+    Plugin    = 5,
 }
 
 impl Default for StreamOption {
@@ -30,8 +64,50 @@ impl Default for StreamOption {
     }
 }
 
+/// The time between checks for a connection to a TCP server stream
+const ACCEPT_POLL_MS: u64 = 50;
+
+/// Limits on waiting for a network stream to connect when it is opened
+#[derive(Debug, Clone, Default)]
+pub struct OpenWait {
+    /// The longest time to wait for a connection, or None to wait until cancelled
+    pub timeout: Option<Duration>,
+
+    /// Set from another thread to stop waiting for a TCP server stream's connection
+    pub cancel: Arc<AtomicBool>,
+}
+
+impl OpenWait {
+    pub fn new(timeout: Option<Duration>, cancel: Arc<AtomicBool>) -> OpenWait {
+        OpenWait { timeout: timeout, cancel: cancel }
+    }
+}
+
 impl StreamOption {
+    /// Whether opening the stream waits for a connection from, or to, another program
+    pub fn connects(&self) -> bool {
+        match self {
+            StreamOption::TcpClient | StreamOption::TcpServer => true,
+
+            _ => false,
+        }
+    }
+
+    /// Whether the stream can be both read and written, as a side of a bridge
+    pub fn duplex(&self) -> bool {
+        match self {
+            StreamOption::TcpClient | StreamOption::TcpServer | StreamOption::Plugin => true,
+
+            _ => false,
+        }
+    }
+
     pub fn open_input(&self, input_settings: &StreamSettings) -> Result<ReadStream, String> {
+        self.open_input_with(input_settings, &Default::default())
+    }
+
+    /// Open the stream as an input, waiting for a network stream to connect within the limits given
+    pub fn open_input_with(&self, input_settings: &StreamSettings, wait: &OpenWait) -> Result<ReadStream, String> {
         let result;
 
         match self {
@@ -42,56 +118,72 @@ impl StreamOption {
             },
 
             StreamOption::TcpClient => {
-                let addr = SocketAddrV4::new(input_settings.tcp_client.ip.parse().unwrap(),
-                                             input_settings.tcp_client.port);
-                result = TcpStream::connect(&addr)
-                         .map(|sock| ReadStream::Tcp(sock))
-                         .map_err(|err| format!("TCP Client Open Error: {}", err));
+                result = connect_tcp(&input_settings.tcp_client, wait).map(|sock| ReadStream::Tcp(sock));
             },
 
             StreamOption::TcpServer => {
-                let addr = SocketAddrV4::new(input_settings.tcp_server.ip.parse().unwrap(),
-                input_settings.tcp_server.port);
-                let listener = TcpListener::bind(&addr).unwrap();
-                let (sock, _) = listener.accept().map_err(|err| format!("TCP Server Open Error: {}", err))?;
-                result = Ok(ReadStream::Tcp(sock));
+                result = accept_tcp(&input_settings.tcp_server, wait).map(|sock| ReadStream::Tcp(sock));
             },
 
             StreamOption::Udp => {
-                let sock = UdpSocket::bind("0.0.0.0:0").map_err(|err| "couldn't bind to udp address/port")?;
-                result = Ok(ReadStream::Udp(sock));
+                let udp_settings = &input_settings.udp;
+                let sock = UdpSocket::bind((udp_settings.ip.as_str(), udp_settings.port))
+                                     .map_err(|err| format!("Could not bind to udp address {}:{}: {}", udp_settings.ip, udp_settings.port, err))?;
+
+                // packets are given the time they were received at when the kernel provides it
+                if let Err(err) = enable_receive_timestamps(&sock) {
+                    warn!("{}. Packets are timestamped when they are parsed", err);
+                }
+
+                result = Ok(ReadStream::Udp(sock, udp_settings.datagram_bytes()));
             },
+
+            StreamOption::Plugin => {
+                result = open_source(&input_settings.plugin).map(|source| ReadStream::Plugin(source));
+            },
+        }
+
+        let read_timeout = Some(Duration::from_millis(INPUT_READ_TIMEOUT_MS));
+        match result {
+            Ok(ReadStream::Tcp(ref sock)) => {
+                sock.set_read_timeout(read_timeout).map_err(|err| format!("Could not set the TCP read timeout: {}", err))?;
+            },
+
+            Ok(ReadStream::Udp(ref sock, _)) => {
+                sock.set_read_timeout(read_timeout).map_err(|err| format!("Could not set the UDP read timeout: {}", err))?;
+            },
+
+            _ => {},
         }
 
         result
     }
 
     pub fn open_output(&self, output_settings: &StreamSettings) -> Result<WriteStream, String> {
-        let result: Result<WriteStream, String>;
+        self.open_output_with(output_settings, &Default::default())
+    }
+
+    /// Open the stream as an output, waiting for a network stream to connect within the limits given
+    pub fn open_output_with(&self, output_settings: &StreamSettings, wait: &OpenWait) -> Result<WriteStream, String> {
+        let mut result: Result<WriteStream, String>;
 
         match self {
+            StreamOption::File if output_settings.file.file_name == STDOUT_FILE_NAME => {
+                result = Ok(WriteStream::File(FileOutput::stdout(&output_settings.file)));
+            },
+
             StreamOption::File => {
                 result = File::create(output_settings.file.file_name.clone())
-                         .map(|outfile| WriteStream::File(outfile))
+                         .map(|outfile| WriteStream::File(FileOutput::new(outfile, &output_settings.file)))
                          .map_err(|err| format!("File open error for writing: {}", err));
             },
 
             StreamOption::TcpClient => {
-                let addr = SocketAddrV4::new(output_settings.tcp_client.ip.parse().unwrap(),
-                output_settings.tcp_client.port);
-                result = TcpStream::connect(&addr)
-                         .map(|sock| WriteStream::Tcp(sock))
-                         .map_err(|err| format!("TCP Client Open Error: {}", err));
+                result = connect_tcp(&output_settings.tcp_client, wait).map(|sock| WriteStream::Tcp(sock));
             },
 
             StreamOption::TcpServer => {
-                let addr = SocketAddrV4::new(output_settings.tcp_server.ip.parse().unwrap(),
-                output_settings.tcp_server.port);
-                let listener = TcpListener::bind(&addr).unwrap();
-
-                result = listener.accept()
-                                 .map(|(sock, _)| WriteStream::Tcp(sock))
-                                 .map_err(|err| format!("TCP Server Open Error: {}", err));
+                result = accept_tcp(&output_settings.tcp_server, wait).map(|sock| WriteStream::Tcp(sock));
             },
 
             StreamOption::Udp => {
@@ -109,10 +201,51 @@ impl StreamOption {
                     },
                 }
             },
+
+            StreamOption::Plugin => {
+                result = open_sink(&output_settings.plugin).map(|sink| WriteStream::Plugin(sink));
+            },
+        }
+
+        // the sync marker is written before each record the stream is given, which are the
+        // frames of a framed stream
+        if output_settings.sync_marker.enabled {
+            result = result.map(|stream| WriteStream::Marked(Box::new(stream), output_settings.sync_marker.marker.clone()));
+        }
+
+        if output_settings.frame_output.enabled {
+            result = result.map(|stream| WriteStream::Framed(Box::new(stream), FrameBuilder::new(&output_settings.frame_output)));
         }
 
         result
     }
+
+    /// Open the stream to be both read and written, as a side of a bridge, giving its read and
+    /// write halves. A TCP stream is shared by both halves, and a plugin opens its source and
+    /// sink. Reads of the TCP stream time out like those of a network input.
+    pub fn open_duplex_with(&self, stream_settings: &StreamSettings, wait: &OpenWait) -> Result<(ReadStream, WriteStream), String> {
+        let sock = match self {
+            StreamOption::TcpClient => connect_tcp(&stream_settings.tcp_client, wait)?,
+
+            StreamOption::TcpServer => accept_tcp(&stream_settings.tcp_server, wait)?,
+
+            StreamOption::Plugin => {
+                let source = open_source(&stream_settings.plugin)?;
+                let sink = open_sink(&stream_settings.plugin)?;
+                return Ok((ReadStream::Plugin(source), WriteStream::Plugin(sink)));
+            },
+
+            _ => {
+                return Err(format!("A {:?} stream can not be both read and written", self));
+            },
+        };
+
+        sock.set_read_timeout(Some(Duration::from_millis(INPUT_READ_TIMEOUT_MS)))
+            .map_err(|err| format!("Could not set the TCP read timeout: {}", err))?;
+        let write_sock = sock.try_clone().map_err(|err| format!("Could not share the TCP stream for writing: {}", err))?;
+
+        Ok((ReadStream::Tcp(sock), WriteStream::Tcp(write_sock)))
+    }
 }
 
 /* Input Streams */
@@ -121,11 +254,322 @@ impl StreamOption {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FileSettings {
     pub file_name: String,
+
+    /// The bytes buffered before writing to an output file, or 0 to write each packet as it is sent
+    #[serde(default = "default_buffer_bytes")]
+    pub buffer_bytes: usize,
+
+    /// Write an output file's buffer to disk after this many packets, or 0 to not flush by packet count
+    #[serde(default)]
+    pub flush_packets: u32,
+
+    /// Write an output file's buffer to disk once this many milliseconds have passed since it
+    /// was last written, or 0 to not flush by time
+    #[serde(default = "default_flush_ms")]
+    pub flush_ms: u32,
+
+    /// Write a sidecar file next to an output file, with a line of JSON describing each packet
+    /// written to it
+    #[serde(default)]
+    pub metadata_sidecar: bool,
+
+    /// The form packets are written to an output file in
+    #[serde(default)]
+    pub format: FileFormat,
+
+    /// The payload bytes shown in hex on each line of a text output file
+    #[serde(default = "default_text_payload_bytes")]
+    pub text_payload_bytes: usize,
+
+    /// What is done when an output file already exists when processing starts
+    #[serde(default)]
+    pub if_exists: FileExistsPolicy,
+}
+
+/// What is done when an output file already exists when processing starts
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileExistsPolicy {
+    /// The existing file is overwritten
+    Overwrite,
+
+    /// The GUI asks whether to overwrite the file. Without the GUI, a new file is written
+    /// with a numeric suffix added to its name.
+    Ask,
+
+    /// A new file is written, with a numeric suffix added to its name
+    NewName,
+}
+
+impl Default for FileExistsPolicy {
+    fn default() -> Self {
+        FileExistsPolicy::Ask
+    }
+}
+
+/// Expand an output file name template, replacing '{time}' with the time processing started,
+/// such as 20200101_12_00_00, and '{session}' with the name of the session
+pub fn expand_file_name(file_name: &str, session_name: &str, start_time: &DateTime<Local>) -> String {
+    file_name.replace("{time}", &start_time.format("%Y%m%d_%H_%M_%S").to_string())
+             .replace("{session}", session_name)
+}
+
+/// The file name itself if no such file exists, and otherwise the first name that does not
+/// exist formed by adding a numeric suffix before its extension, such as data_1.bin for data.bin
+pub fn unique_file_name(file_name: &str) -> String {
+    let path = Path::new(file_name);
+    if !path.exists() {
+        return file_name.to_string();
+    }
+
+    let stem = path.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned());
+    let extension = path.extension().map_or(String::new(), |extension| format!(".{}", extension.to_string_lossy()));
+
+    let mut suffix = 1;
+    loop {
+        let candidate = path.with_file_name(format!("{}_{}{}", stem, suffix, extension));
+        if !candidate.exists() {
+            return candidate.to_string_lossy().into_owned();
+        }
+        suffix += 1;
+    }
+}
+
+/// The form packets are written to an output file in
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileFormat {
+    /// The packets' bytes
+    Binary,
+
+    /// A line of text describing each packet, for monitoring a stream or comparing runs
+    Text,
+}
+
+impl Default for FileFormat {
+    fn default() -> Self {
+        FileFormat::Binary
+    }
+}
+
+fn default_text_payload_bytes() -> usize {
+    16
+}
+
+fn default_buffer_bytes() -> usize {
+    65536
+}
+
+fn default_flush_ms() -> u32 {
+    1000
 }
 
 impl Default for FileSettings {
     fn default() -> Self {
-        FileSettings { file_name: "data.bin".to_string() }
+        FileSettings { file_name: "data.bin".to_string(),
+                       buffer_bytes: default_buffer_bytes(),
+                       flush_packets: 0,
+                       flush_ms: default_flush_ms(),
+                       metadata_sidecar: false,
+                       format: Default::default(),
+                       text_payload_bytes: default_text_payload_bytes(),
+                       if_exists: Default::default(),
+        }
+    }
+}
+
+/// The rate limit of an output, which queues packets so that they are written no faster than a
+/// slow link can carry them, while the other outputs are written at the rate packets are forwarded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimitSettings {
+    /// The most bytes written to the output per second, or 0 to write packets as they are forwarded
+    #[serde(default)]
+    pub bytes_per_sec: u64,
+
+    /// The most bytes queued for the output while waiting for its rate limit. Packets that do
+    /// not fit in the queue are dropped.
+    #[serde(default = "default_max_queued_bytes")]
+    pub max_queued_bytes: u64,
+}
+
+fn default_max_queued_bytes() -> u64 {
+    1_048_576
+}
+
+impl Default for RateLimitSettings {
+    fn default() -> Self {
+        RateLimitSettings {
+            bytes_per_sec: 0,
+            max_queued_bytes: default_max_queued_bytes(),
+        }
+    }
+}
+
+/// The attached sync marker of CCSDS frames, 0x1ACFFC1D
+pub const ATTACHED_SYNC_MARKER: [u8; 4] = [0x1A, 0xCF, 0xFC, 0x1D];
+
+/// The most bytes in a sync marker
+pub const MAX_SYNC_MARKER_BYTES: usize = 16;
+
+/// A sync marker before each record, such as the attached sync marker many test equipment
+/// streams put before each packet or frame. An input strips the marker from the start of each
+/// record, and an output writes it before each record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncMarkerSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The bytes of the marker, the attached sync marker by default
+    #[serde(default = "default_sync_marker")]
+    pub marker: Vec<u8>,
+}
+
+fn default_sync_marker() -> Vec<u8> {
+    ATTACHED_SYNC_MARKER.to_vec()
+}
+
+impl Default for SyncMarkerSettings {
+    fn default() -> Self {
+        SyncMarkerSettings {
+            enabled: false,
+            marker: default_sync_marker(),
+        }
+    }
+}
+
+impl SyncMarkerSettings {
+    /// The marker's bytes if it is enabled, and otherwise no bytes
+    pub fn bytes(&self) -> &[u8] {
+        if self.enabled {
+            &self.marker
+        } else {
+            &[]
+        }
+    }
+
+    /// A description of each problem with the sync marker
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if !self.enabled {
+            return problems;
+        }
+
+        if self.marker.is_empty() {
+            problems.push("The sync marker is enabled, but has no bytes".to_string());
+        } else if self.marker.len() > MAX_SYNC_MARKER_BYTES {
+            problems.push(format!("The sync marker is at most {} bytes, but is {} bytes", MAX_SYNC_MARKER_BYTES, self.marker.len()));
+        }
+
+        problems
+    }
+}
+
+/// Store and forward keeps the packets for an output while it is down in a backlog file, rather
+/// than dropping them, and writes the backlog once the output reconnects.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreForwardSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// The directory the backlog file is written in
+    #[serde(default = "default_backlog_directory")]
+    pub directory: String,
+
+    /// The largest the backlog file can grow to. Packets that do not fit are dropped.
+    #[serde(default = "default_max_backlog_bytes")]
+    pub max_backlog_bytes: u64,
+
+    /// The most bytes of the backlog written per second once the output reconnects, or 0 to
+    /// write it as fast as the output accepts it
+    #[serde(default)]
+    pub drain_bytes_per_sec: u64,
+}
+
+fn default_backlog_directory() -> String {
+    "backlog".to_string()
+}
+
+fn default_max_backlog_bytes() -> u64 {
+    104_857_600
+}
+
+impl Default for StoreForwardSettings {
+    fn default() -> Self {
+        StoreForwardSettings {
+            enabled: false,
+            directory: default_backlog_directory(),
+            max_backlog_bytes: default_max_backlog_bytes(),
+            drain_bytes_per_sec: 0,
+        }
+    }
+}
+
+/// When an output stream is opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutputOpenTiming {
+    /// The output is opened when processing starts, waiting for a network output to connect
+    Eager,
+    /// The output is opened when the first packet is forwarded to it, so a file is only created
+    /// if it is written to, and processing does not wait for a network output to connect
+    Lazy,
+}
+
+impl Default for OutputOpenTiming {
+    fn default() -> Self {
+        OutputOpenTiming::Eager
+    }
+}
+
+/// Connect to a TCP server, giving up after the wait's timeout
+fn connect_tcp(tcp_client: &TcpClientSettings, wait: &OpenWait) -> Result<TcpStream, String> {
+    let ip = tcp_client.ip.parse().map_err(|err| format!("Could not parse ip ({}): {}", tcp_client.ip, err))?;
+    let addr = SocketAddr::V4(SocketAddrV4::new(ip, tcp_client.port));
+
+    let result = match wait.timeout {
+        Some(timeout) => TcpStream::connect_timeout(&addr, timeout),
+
+        None => TcpStream::connect(&addr),
+    };
+
+    result.map_err(|err| format!("TCP Client Open Error: {}", err))
+}
+
+/// Wait for a TCP client to connect, giving up after the wait's timeout or when it is cancelled
+fn accept_tcp(tcp_server: &TcpServerSettings, wait: &OpenWait) -> Result<TcpStream, String> {
+    let ip = tcp_server.ip.parse().map_err(|err| format!("Could not parse ip ({}): {}", tcp_server.ip, err))?;
+    let addr = SocketAddrV4::new(ip, tcp_server.port);
+
+    let listener = TcpListener::bind(&addr).map_err(|err| format!("TCP Server Open Error: {}", err))?;
+
+    // the listener is polled so that waiting can be cancelled
+    listener.set_nonblocking(true).map_err(|err| format!("TCP Server Open Error: {}", err))?;
+
+    let start = Instant::now();
+    loop {
+        match listener.accept() {
+            Ok((sock, _)) => {
+                sock.set_nonblocking(false).map_err(|err| format!("TCP Server Open Error: {}", err))?;
+                return Ok(sock);
+            },
+
+            Err(ref err) if err.kind() == ErrorKind::WouldBlock => {
+                if wait.cancel.load(Ordering::SeqCst) {
+                    return Err(format!("Stopped waiting for a connection on {}", addr));
+                }
+
+                if let Some(timeout) = wait.timeout {
+                    if start.elapsed() >= timeout {
+                        let timeout_secs = timeout.as_secs() as f64 + timeout.subsec_nanos() as f64 / 1_000_000_000.0;
+                        return Err(format!("No connection on {} within {:.1} seconds", addr, timeout_secs));
+                    }
+                }
+
+                thread::sleep(Duration::from_millis(ACCEPT_POLL_MS));
+            },
+
+            Err(err) => {
+                return Err(format!("TCP Server Open Error: {}", err));
+            },
+        }
     }
 }
 
@@ -167,16 +611,43 @@ impl Default for TcpServerSettings {
 pub struct UdpSettings {
     pub port: u16,
     pub ip: String,
+
+    /// The largest datagram a UDP input reads. Larger datagrams are truncated.
+    #[serde(default = "default_max_datagram_bytes")]
+    pub max_datagram_bytes: usize,
+}
+
+fn default_max_datagram_bytes() -> usize {
+    MAX_DATAGRAM_BYTES
 }
 
 impl Default for UdpSettings {
     fn default() -> Self {
         UdpSettings { port: 8001,
-                      ip: "127.0.0.1".to_string()
+                      ip: "127.0.0.1".to_string(),
+                      max_datagram_bytes: default_max_datagram_bytes(),
         }
     }
 }
 
+impl UdpSettings {
+    /// The number of bytes read for each datagram, limited to the largest possible datagram
+    pub fn datagram_bytes(&self) -> usize {
+        min(max(self.max_datagram_bytes, 1), MAX_DATAGRAM_BYTES)
+    }
+}
+
+/// The plugin settings name a registered stream plugin, along with the settings passed to it
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PluginSettings {
+    /// The name the plugin was registered with
+    pub name: String,
+
+    /// The plugin's own settings, as names and values
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+}
+
 /// The stream settings are all the settings for all stream types
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StreamSettings {
@@ -191,15 +662,273 @@ pub struct StreamSettings {
 
     #[serde(default)]
     pub udp: UdpSettings,
+
+    #[serde(default)]
+    pub plugin: PluginSettings,
+
+    /// The rate limit of an output stream
+    #[serde(default)]
+    pub rate_limit: RateLimitSettings,
+
+    /// When an output stream is opened
+    #[serde(default)]
+    pub open_timing: OutputOpenTiming,
+
+    /// Whether an output stream keeps its packets in a backlog while it is down
+    #[serde(default)]
+    pub store_forward: StoreForwardSettings,
+
+    /// Whether an output stream packs its packets into transfer frames
+    #[serde(default)]
+    pub frame_output: FrameOutputSettings,
+
+    /// The sync marker an output stream writes before each packet, or each frame
+    #[serde(default)]
+    pub sync_marker: SyncMarkerSettings,
+}
+
+/// Parse a stream given on the command line, in the form 'file:<name>', 'udp:<ip>:<port>',
+/// 'tcp_client:<ip>:<port>', 'tcp_server:<ip>:<port>', or 'plugin:<name>[:<setting>=<value>,...]'.
+pub fn parse_stream_spec(spec: &str) -> Result<(StreamOption, StreamSettings), String> {
+    let mut stream_settings: StreamSettings = Default::default();
+
+    let (kind, location) = match spec.find(':') {
+        Some(index) => (&spec[..index], &spec[index + 1..]),
+
+        None => return Err(format!("Stream '{}' should be given as <type>:<location>, such as file:data.bin or udp:127.0.0.1:8001", spec)),
+    };
+
+    let stream_option = match kind {
+        "file" => {
+            stream_settings.file.file_name = location.to_string();
+            return Ok((StreamOption::File, stream_settings));
+        },
+
+        "plugin" => {
+            stream_settings.plugin = parse_plugin_spec(location)?;
+            return Ok((StreamOption::Plugin, stream_settings));
+        },
+
+        "udp" => StreamOption::Udp,
+
+        "tcp_client" => StreamOption::TcpClient,
+
+        "tcp_server" => StreamOption::TcpServer,
+
+        _ => return Err(format!("Unknown stream type '{}', expected file, udp, tcp_client, tcp_server, or plugin", kind)),
+    };
+
+    let (ip, port_str) = match location.rfind(':') {
+        Some(index) => (&location[..index], &location[index + 1..]),
+
+        None => return Err(format!("Stream '{}' should give an address as <ip>:<port>", spec)),
+    };
+    let port: u16 = port_str.parse().map_err(|_| format!("Invalid port '{}' in stream '{}'", port_str, spec))?;
+    let ip = ip.to_string();
+
+    match stream_option {
+        StreamOption::Udp => stream_settings.udp = UdpSettings { ip: ip, port: port, ..Default::default() },
+
+        StreamOption::TcpClient => stream_settings.tcp_client = TcpClientSettings { ip: ip, port: port },
+
+        StreamOption::TcpServer => stream_settings.tcp_server = TcpServerSettings { ip: ip, port: port },
+
+        StreamOption::File | StreamOption::Plugin => {},
+    }
+
+    Ok((stream_option, stream_settings))
+}
+
+/// Parse a plugin's name and settings, in the form '<name>[:<setting>=<value>,...]'
+pub fn parse_plugin_spec(spec: &str) -> Result<PluginSettings, String> {
+    let mut plugin_settings: PluginSettings = Default::default();
+
+    let parameters_str = match spec.find(':') {
+        Some(index) => {
+            plugin_settings.name = spec[..index].to_string();
+            &spec[index + 1..]
+        },
+
+        None => {
+            plugin_settings.name = spec.to_string();
+            ""
+        },
+    };
+
+    if plugin_settings.name.is_empty() {
+        return Err(format!("Plugin stream '{}' should give the plugin's name", spec));
+    }
+
+    for parameter_str in parameters_str.split(',').filter(|parameter_str| !parameter_str.trim().is_empty()) {
+        match parameter_str.find('=') {
+            Some(index) => {
+                plugin_settings.parameters.insert(parameter_str[..index].trim().to_string(),
+                                                  parameter_str[index + 1..].trim().to_string());
+            },
+
+            None => return Err(format!("Plugin setting '{}' should be given as <setting>=<value>", parameter_str)),
+        }
+    }
+
+    Ok(plugin_settings)
+}
+
+/// Describe a plugin's name and settings in the form parsed by parse_plugin_spec
+pub fn plugin_spec(plugin_settings: &PluginSettings) -> String {
+    let parameters: Vec<String> = plugin_settings.parameters.iter()
+                                                 .map(|(name, value)| format!("{}={}", name, value))
+                                                 .collect();
+
+    if parameters.is_empty() {
+        plugin_settings.name.clone()
+    } else {
+        format!("{}:{}", plugin_settings.name, parameters.join(","))
+    }
+}
+
+/// Describe a stream in the form used on the command line, such as 'file:data.bin' or 'udp:127.0.0.1:8001'
+pub fn stream_spec(stream_option: StreamOption, stream_settings: &StreamSettings) -> String {
+    match stream_option {
+        StreamOption::File => format!("file:{}", stream_settings.file.file_name),
+
+        StreamOption::TcpClient => format!("tcp_client:{}:{}", stream_settings.tcp_client.ip, stream_settings.tcp_client.port),
+
+        StreamOption::TcpServer => format!("tcp_server:{}:{}", stream_settings.tcp_server.ip, stream_settings.tcp_server.port),
+
+        StreamOption::Udp => format!("udp:{}:{}", stream_settings.udp.ip, stream_settings.udp.port),
+
+        StreamOption::Plugin => format!("plugin:{}", plugin_spec(&stream_settings.plugin)),
+    }
+}
+
+/* Stream Plugins */
+/// A PacketSource is an input stream provided by a plugin, for transports that are not built in,
+/// such as a recorder's own API. A read appends up to the given number of bytes to the buffer
+/// and returns the number of bytes read, in the same way as the built in streams. A read of
+/// no bytes ends the stream.
+pub trait PacketSource: Send {
+    fn read(&mut self, bytes: &mut BytesMut, num_bytes: usize) -> Result<usize, String>;
+
+    /// Move the read position to a byte offset. Plugins do not support seeking unless they provide this.
+    fn seek(&mut self, _offset: u64) -> Result<(), String> {
+        Err("Seeking is not supported by this plugin".to_string())
+    }
+}
+
+/// A PacketSink is an output stream provided by a plugin, which is sent each forwarded packet
+pub trait PacketSink: Send {
+    fn send(&mut self, packet: &[u8]) -> Result<(), String>;
+
+    /// Write out any packets the plugin buffers, when processing pauses or stops
+    fn flush(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PacketSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PacketSource")
+    }
+}
+
+impl fmt::Debug for PacketSink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PacketSink")
+    }
+}
+
+/// Creates a plugin's input stream from its settings
+pub type SourceFactory = fn(&PluginSettings) -> Result<Box<PacketSource>, String>;
+
+/// Creates a plugin's output stream from its settings
+pub type SinkFactory = fn(&PluginSettings) -> Result<Box<PacketSink>, String>;
+
+struct PluginRegistry {
+    sources: HashMap<String, SourceFactory>,
+    sinks: HashMap<String, SinkFactory>,
+}
+
+lazy_static! {
+    static ref PLUGINS: Mutex<PluginRegistry> = Mutex::new(PluginRegistry { sources: HashMap::new(), sinks: HashMap::new() });
+}
+
+/// Register a plugin's input stream under a name, which a stream's plugin settings use to select it.
+/// Plugins are registered before processing starts, and a later registration replaces an earlier one.
+pub fn register_source(name: &str, factory: SourceFactory) {
+    if let Ok(mut plugins) = PLUGINS.lock() {
+        plugins.sources.insert(name.to_string(), factory);
+    }
+}
+
+/// Register a plugin's output stream under a name
+pub fn register_sink(name: &str, factory: SinkFactory) {
+    if let Ok(mut plugins) = PLUGINS.lock() {
+        plugins.sinks.insert(name.to_string(), factory);
+    }
+}
+
+/// The names of the registered input and output plugins, sorted by name
+pub fn plugin_names() -> (Vec<String>, Vec<String>) {
+    match PLUGINS.lock() {
+        Ok(plugins) => {
+            let mut source_names: Vec<String> = plugins.sources.keys().cloned().collect();
+            let mut sink_names: Vec<String> = plugins.sinks.keys().cloned().collect();
+            source_names.sort();
+            sink_names.sort();
+            (source_names, sink_names)
+        },
+
+        Err(_) => (Vec::new(), Vec::new()),
+    }
+}
+
+fn open_source(plugin_settings: &PluginSettings) -> Result<Box<PacketSource>, String> {
+    // the factory is copied out so the registry is not locked while the plugin opens its stream
+    let factory = PLUGINS.lock()
+                         .map_err(|_| "The plugin registry is unavailable".to_string())?
+                         .sources.get(&plugin_settings.name).cloned();
+
+    match factory {
+        Some(factory) => factory(plugin_settings),
+
+        None => Err(format!("No input plugin named '{}' is registered", plugin_settings.name)),
+    }
+}
+
+fn open_sink(plugin_settings: &PluginSettings) -> Result<Box<PacketSink>, String> {
+    let factory = PLUGINS.lock()
+                         .map_err(|_| "The plugin registry is unavailable".to_string())?
+                         .sinks.get(&plugin_settings.name).cloned();
+
+    match factory {
+        Some(factory) => factory(plugin_settings),
+
+        None => Err(format!("No output plugin named '{}' is registered", plugin_settings.name)),
+    }
 }
 
 /* Input/Output Streams */
+/// The result of reading an input stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamRead {
+    /// The number of bytes read, and the time they were received at if the kernel records it.
+    /// No bytes are read at the end of a stream other than a datagram stream.
+    Bytes(usize, Option<SystemTime>),
+    /// No bytes arrived on a network stream before its read timed out
+    TimedOut,
+}
+
+
 /// A read stream a source of CCSDS packets
 #[derive(Debug)]
 pub enum ReadStream {
     File(BufReader<File>),
-    Udp(UdpSocket),
+    /// The parts of a file holding the packets selected by a replay query
+    Query(QueryReader),
+    /// A UDP socket, and the largest datagram read from it
+    Udp(UdpSocket, usize),
     Tcp(TcpStream),
+    Plugin(Box<PacketSource>),
     Null,
 }
 
@@ -207,24 +936,54 @@ impl ReadStream {
     pub fn stream_read(&mut self,
                        bytes: &mut BytesMut,
                        num_bytes: usize) -> Result<usize, String> {
+        match self.stream_read_timed(bytes, num_bytes)? {
+            StreamRead::Bytes(num_bytes_read, _) => Ok(num_bytes_read),
 
-        let result: Result<usize, String>;
+            StreamRead::TimedOut => Ok(0),
+        }
+    }
+
+    /// Read from the stream, along with the time the bytes were received at, for streams
+    /// where the kernel records it. Reads of network streams time out if no bytes arrive.
+    pub fn stream_read_timed(&mut self,
+                             bytes: &mut BytesMut,
+                             num_bytes: usize) -> Result<StreamRead, String> {
+
+        let result: Result<StreamRead, String>;
 
         match self {
             ReadStream::File(ref mut file) => {
                 result = read_bytes(file, bytes, num_bytes);
             },
 
-            ReadStream::Udp(udp_sock) => {
-                // for UDP we just read a message, which must contain a CCSDS packet
-                bytes.clear();
-                result = udp_sock.recv(bytes).map_err(|err| format!("Udp Socket Read Error: {}", err));
+            ReadStream::Query(ref mut query_reader) => {
+                result = read_bytes(query_reader, bytes, num_bytes);
+            },
+
+            ReadStream::Udp(udp_sock, max_datagram_bytes) => {
+                // for UDP we read one whole datagram, which is added to any bytes not yet parsed
+                let current_len = bytes.len();
+                bytes.resize(current_len + *max_datagram_bytes, 0);
+                let recv_result = udp_sock.recv_timestamped(&mut bytes[current_len..]);
+                bytes.truncate(current_len + recv_result.as_ref().map(|&(num_bytes_read, _)| num_bytes_read).unwrap_or(0));
+
+                result = match recv_result {
+                    Ok((num_bytes_read, recv_time)) => Ok(StreamRead::Bytes(num_bytes_read, recv_time)),
+
+                    Err(ref err) if is_timeout(err) => Ok(StreamRead::TimedOut),
+
+                    Err(err) => Err(format!("Udp Socket Read Error: {}", err)),
+                };
             },
 
             ReadStream::Tcp(tcp_stream) => {
                 result = read_bytes(tcp_stream, bytes, num_bytes);
             },
 
+            ReadStream::Plugin(source) => {
+                result = source.read(bytes, num_bytes).map(|num_bytes_read| StreamRead::Bytes(num_bytes_read, None));
+            },
+
             ReadStream::Null => {
                 result = Err("Reading a Null Stream! This should not happen!".to_string());
             },
@@ -232,23 +991,207 @@ impl ReadStream {
 
         result
     }
+
+    /// Whether the stream reads whole datagrams. Every other stream has ended when a read
+    /// returns no bytes, while a datagram stream can receive an empty datagram.
+    pub fn is_datagram(&self) -> bool {
+        match self {
+            ReadStream::Udp(_, _) => true,
+
+            _ => false,
+        }
+    }
+
+    /// A way to unblock a read of this stream from another thread, for network streams whose
+    /// reads can wait indefinitely. Reads of other streams return on their own.
+    pub fn read_interrupt(&self) -> Option<ReadInterrupt> {
+        match self {
+            ReadStream::Tcp(tcp_stream) => {
+                tcp_stream.try_clone().ok().map(ReadInterrupt::Tcp)
+            },
+
+            ReadStream::Udp(udp_sock, _) => {
+                // a socket bound to every interface is reached on the loopback interface
+                let local_addr = udp_sock.local_addr().ok()?;
+                let addr = if local_addr.ip().is_unspecified() {
+                    SocketAddr::from((Ipv4Addr::new(127, 0, 0, 1), local_addr.port()))
+                } else {
+                    local_addr
+                };
+                udp_sock.try_clone().ok().map(|sock| ReadInterrupt::Udp(sock, addr))
+            },
+
+            _ => None,
+        }
+    }
+
+    /// Move the read position of the stream to the given byte offset. Only file streams
+    /// support seeking.
+    pub fn stream_seek(&mut self, offset: u64) -> Result<(), String> {
+        match self {
+            ReadStream::File(ref mut file) => {
+                file.seek(SeekFrom::Start(offset))
+                    .map(|_| ())
+                    .map_err(|err| format!("Stream Seek Error: {}", err))
+            },
+
+            ReadStream::Plugin(source) => {
+                source.seek(offset)
+            },
+
+            _ => {
+                Err("Seeking is only supported for file inputs".to_string())
+            },
+        }
+    }
 }
 
 
+/// A file output, buffered so that each packet is not a separate write to disk. The buffer
+/// is flushed by packet count or time, when processing pauses, and when the file is closed.
+#[derive(Debug)]
+pub struct FileOutput {
+    writer: BufWriter<FileTarget>,
+    flush_packets: u32,
+    flush_interval: Option<Duration>,
+    packets_since_flush: u32,
+    last_flush: Instant,
+}
+
+impl FileOutput {
+    pub fn new(file: File, file_settings: &FileSettings) -> FileOutput {
+        FileOutput::with_target(FileTarget::Disk(file), file_settings)
+    }
+
+    /// A file output written to stdout, with the buffering of the given file settings
+    pub fn stdout(file_settings: &FileSettings) -> FileOutput {
+        FileOutput::with_target(FileTarget::Stdout(io::stdout()), file_settings)
+    }
+
+    fn with_target(target: FileTarget, file_settings: &FileSettings) -> FileOutput {
+        let flush_interval = if file_settings.flush_ms > 0 {
+            Some(Duration::from_millis(file_settings.flush_ms as u64))
+        } else {
+            None
+        };
+
+        FileOutput {
+            writer: BufWriter::with_capacity(file_settings.buffer_bytes, target),
+            flush_packets: file_settings.flush_packets,
+            flush_interval: flush_interval,
+            packets_since_flush: 0,
+            last_flush: Instant::now(),
+        }
+    }
+
+    fn write_packet(&mut self, packet: &[u8]) -> Result<(), String> {
+        self.writer.write_all(packet).map_err(|err| format!("IO error {}", err))?;
+        self.packets_since_flush += 1;
+
+        if self.flush_packets > 0 && self.packets_since_flush >= self.flush_packets {
+            self.flush()?;
+        }
+
+        self.flush_if_due()
+    }
+
+    /// Flush the buffer if the flush interval has passed since it was last flushed
+    fn flush_if_due(&mut self) -> Result<(), String> {
+        match self.flush_interval {
+            Some(flush_interval) if self.packets_since_flush > 0 && self.last_flush.elapsed() >= flush_interval => {
+                self.flush()
+            },
+
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        self.packets_since_flush = 0;
+        self.last_flush = Instant::now();
+        self.writer.flush().map_err(|err| format!("IO error {}", err))
+    }
+
+    /// Flush the buffer and wait for the file's data to reach the disk
+    fn close(&mut self) -> Result<(), String> {
+        self.flush()?;
+
+        match self.writer.get_ref() {
+            FileTarget::Disk(file) => file.sync_all().map_err(|err| format!("Could not sync file to disk: {}", err)),
+
+            FileTarget::Stdout(_) => Ok(()),
+        }
+    }
+}
+
+/// Where a file output is written- a file on disk, or stdout for the file name '-'
+#[derive(Debug)]
+enum FileTarget {
+    Disk(File),
+    Stdout(io::Stdout),
+}
+
+impl Write for FileTarget {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            FileTarget::Disk(file) => file.write(buf),
+
+            FileTarget::Stdout(stdout) => stdout.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            FileTarget::Disk(file) => file.flush(),
+
+            FileTarget::Stdout(stdout) => stdout.flush(),
+        }
+    }
+}
+
+/// Unblocks a thread waiting to read from a network stream, so that it can check whether
+/// it should stop.
+#[derive(Debug)]
+pub enum ReadInterrupt {
+    /// A TCP stream is shut down, ending the stream for the reader
+    Tcp(TcpStream),
+    /// A UDP socket is sent an empty datagram
+    Udp(UdpSocket, SocketAddr),
+}
+
+impl ReadInterrupt {
+    pub fn interrupt(&self) {
+        match self {
+            ReadInterrupt::Tcp(tcp_stream) => {
+                let _ = tcp_stream.shutdown(Shutdown::Both);
+            },
+
+            ReadInterrupt::Udp(udp_sock, addr) => {
+                let _ = udp_sock.send_to(&[], addr);
+            },
+        }
+    }
+}
+
 /// A read stream a sink of CCSDS packets
 #[derive(Debug)]
 pub enum WriteStream {
-    File(File),
+    File(FileOutput),
     Udp((UdpSocket, SocketAddrV4)),
     Tcp(TcpStream),
+    Plugin(Box<PacketSink>),
+    /// A stream written in transfer frames, packing the packets sent to it
+    Framed(Box<WriteStream>, FrameBuilder),
+    /// A stream with a sync marker written before each record sent to it
+    Marked(Box<WriteStream>, Vec<u8>),
     Null,
 }
 
 impl WriteStream {
-    pub fn stream_send(&mut self, packet: &Vec<u8>) -> Result<(), String> {
+    pub fn stream_send(&mut self, packet: &[u8]) -> Result<(), String> {
         match self {
-            WriteStream::File(file) => {
-                file.write_all(&packet).map_err(|err| format!("IO error {}", err))
+            WriteStream::File(file_output) => {
+                file_output.write_packet(packet)
             },
 
             WriteStream::Udp((udp_sock, addr)) => {
@@ -261,31 +1204,287 @@ impl WriteStream {
                 tcp_stream.write_all(&packet).map_err(|err| format!("IO error {}", err))
             },
 
+            WriteStream::Plugin(sink) => {
+                sink.send(&packet)
+            },
+
+            WriteStream::Framed(stream, frame_builder) => {
+                let mut frames = Vec::new();
+                frame_builder.push(packet, Instant::now(), &mut frames);
+                send_frames(stream, &frames)
+            },
+
+            // the marker and record are sent together, so a datagram holds both
+            WriteStream::Marked(stream, marker) => {
+                let mut record = Vec::with_capacity(marker.len() + packet.len());
+                record.extend_from_slice(marker);
+                record.extend_from_slice(packet);
+                stream.stream_send(&record)
+            },
+
             WriteStream::Null => {
                 Ok(())
             },
         }
     }
+
+    /// Write out buffered packets whose flush interval has passed
+    pub fn stream_flush_due(&mut self) -> Result<(), String> {
+        match self {
+            WriteStream::File(file_output) => file_output.flush_if_due(),
+
+            WriteStream::Framed(stream, frame_builder) => {
+                let mut frames = Vec::new();
+                frame_builder.due(Instant::now(), &mut frames);
+                send_frames(stream, &frames)?;
+                stream.stream_flush_due()
+            },
+
+            WriteStream::Marked(stream, _) => stream.stream_flush_due(),
+
+            _ => Ok(()),
+        }
+    }
+
+    /// Write out any buffered packets, such as when processing pauses
+    pub fn stream_flush(&mut self) -> Result<(), String> {
+        match self {
+            WriteStream::File(file_output) => file_output.flush(),
+
+            WriteStream::Plugin(sink) => sink.flush(),
+
+            WriteStream::Framed(stream, frame_builder) => {
+                let mut frames = Vec::new();
+                frame_builder.flush(Instant::now(), &mut frames);
+                send_frames(stream, &frames)?;
+                stream.stream_flush()
+            },
+
+            WriteStream::Marked(stream, _) => stream.stream_flush(),
+
+            _ => Ok(()),
+        }
+    }
+
+    /// Write out any buffered packets when processing stops. Files are synced to disk, so
+    /// they are complete once processing has stopped.
+    pub fn stream_close(&mut self) -> Result<(), String> {
+        match self {
+            WriteStream::File(file_output) => file_output.close(),
+
+            WriteStream::Plugin(sink) => sink.flush(),
+
+            WriteStream::Framed(stream, frame_builder) => {
+                let mut frames = Vec::new();
+                frame_builder.flush(Instant::now(), &mut frames);
+                send_frames(stream, &frames)?;
+                stream.stream_close()
+            },
+
+            WriteStream::Marked(stream, _) => stream.stream_close(),
+
+            _ => Ok(()),
+        }
+    }
 }
 
+/// Write the frames completed by a framed stream to the stream beneath it
+fn send_frames(stream: &mut WriteStream, frames: &[Vec<u8>]) -> Result<(), String> {
+    for frame in frames.iter() {
+        stream.stream_send(frame)?;
+    }
+
+    Ok(())
+}
 
-/// The packet structure contains the data for a packet, as well as the primary header
+
+/// The packet structure contains the data for a packet, as well as the primary header.
+/// The bytes are reference counted, so a packet is passed to the outputs, the GUI, and
+/// any buffers without copying it.
 #[derive(Debug, Clone)]
 pub struct Packet {
     pub header: CcsdsPrimaryHeader,
-    pub bytes:  Vec<u8>,
+    pub bytes:  Bytes,
+}
+
+
+/// A UDP socket that a datagram can be received from along with the time the kernel received
+/// it at. Where the platform does not provide receive timestamps, the time is None.
+pub trait RecvTimestamped {
+    fn recv_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, Option<SystemTime>)>;
+}
+
+#[cfg(unix)]
+impl RecvTimestamped for UdpSocket {
+    fn recv_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, Option<SystemTime>)> {
+        recv_timestamped_fd(self.as_raw_fd(), buf)
+    }
+}
+
+#[cfg(unix)]
+impl RecvTimestamped for mio::net::UdpSocket {
+    fn recv_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, Option<SystemTime>)> {
+        recv_timestamped_fd(self.as_raw_fd(), buf)
+    }
+}
+
+#[cfg(not(unix))]
+impl RecvTimestamped for UdpSocket {
+    fn recv_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, Option<SystemTime>)> {
+        self.recv(buf).map(|num_bytes| (num_bytes, None))
+    }
+}
+
+#[cfg(not(unix))]
+impl RecvTimestamped for mio::net::UdpSocket {
+    fn recv_timestamped(&self, buf: &mut [u8]) -> io::Result<(usize, Option<SystemTime>)> {
+        self.recv(buf).map(|num_bytes| (num_bytes, None))
+    }
+}
+
+// Linux records receive times with nanosecond resolution, while other platforms use microseconds
+#[cfg(target_os = "linux")]
+const TIMESTAMP_OPTION: libc::c_int = libc::SO_TIMESTAMPNS;
+#[cfg(target_os = "linux")]
+const TIMESTAMP_MESSAGE: libc::c_int = libc::SCM_TIMESTAMPNS;
+
+#[cfg(all(unix, not(target_os = "linux")))]
+const TIMESTAMP_OPTION: libc::c_int = libc::SO_TIMESTAMP;
+#[cfg(all(unix, not(target_os = "linux")))]
+const TIMESTAMP_MESSAGE: libc::c_int = libc::SCM_TIMESTAMP;
+
+/// Have the kernel record the time each datagram is received at on a UDP socket, which
+/// is then returned by recv_timestamped.
+#[cfg(unix)]
+pub fn enable_receive_timestamps(sock: &UdpSocket) -> Result<(), String> {
+    let enable: libc::c_int = 1;
+
+    let result = unsafe {
+        libc::setsockopt(sock.as_raw_fd(),
+                         libc::SOL_SOCKET,
+                         TIMESTAMP_OPTION,
+                         &enable as *const libc::c_int as *const libc::c_void,
+                         mem::size_of::<libc::c_int>() as libc::socklen_t)
+    };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(format!("Could not enable UDP receive timestamps: {}", io::Error::last_os_error()))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn enable_receive_timestamps(_sock: &UdpSocket) -> Result<(), String> {
+    Err("UDP receive timestamps are not supported on this platform".to_string())
+}
+
+#[cfg(unix)]
+fn recv_timestamped_fd(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, Option<SystemTime>)> {
+    // room for the timestamp's control message, aligned for its header
+    let mut control = [0u64; 8];
+
+    let mut iov = libc::iovec { iov_base: buf.as_mut_ptr() as *mut libc::c_void, iov_len: buf.len() };
+
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let num_bytes = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+    if num_bytes < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut recv_time = None;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == TIMESTAMP_MESSAGE {
+                recv_time = Some(cmsg_time(libc::CMSG_DATA(cmsg)));
+            }
+
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+
+    Ok((num_bytes as usize, recv_time))
 }
 
+#[cfg(target_os = "linux")]
+unsafe fn cmsg_time(data: *const libc::c_uchar) -> SystemTime {
+    let time: libc::timespec = ptr::read_unaligned(data as *const libc::timespec);
 
-fn read_bytes<R: Read>(reader: &mut R, bytes: &mut BytesMut, num_bytes: usize) -> Result<usize, String> {
+    UNIX_EPOCH + Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+unsafe fn cmsg_time(data: *const libc::c_uchar) -> SystemTime {
+    let time: libc::timeval = ptr::read_unaligned(data as *const libc::timeval);
+
+    UNIX_EPOCH + Duration::new(time.tv_sec as u64, time.tv_usec as u32 * 1000)
+}
+
+/// Read up to num_bytes from a reader, appending them to the given bytes. The read returns
+/// as soon as any bytes are available, so a large read does not wait on a slow stream.
+/// Reading no bytes means the end of the stream was reached.
+fn read_bytes<R: Read>(reader: &mut R, bytes: &mut BytesMut, num_bytes: usize) -> Result<StreamRead, String> {
     let current_len = bytes.len();
 
-    bytes.reserve(num_bytes);
+    bytes.resize(current_len + num_bytes, 0);
+
+    let result = loop {
+        match reader.read(&mut bytes[current_len..]) {
+            Err(ref err) if err.kind() == ErrorKind::Interrupted => continue,
+
+            result => break result,
+        }
+    };
 
-    let mut_bytes: &mut [u8] = bytes.borrow_mut();
-    reader.read_exact(&mut mut_bytes[current_len..(current_len + num_bytes)])
-          .map_err(|err| format!("Stream Read Error: {}", err))?;
+    let num_bytes_read = *result.as_ref().unwrap_or(&0);
+    bytes.truncate(current_len + num_bytes_read);
 
-    Ok(num_bytes)
+    match result {
+        Ok(num_bytes_read) => Ok(StreamRead::Bytes(num_bytes_read, None)),
+
+        Err(ref err) if is_timeout(err) => Ok(StreamRead::TimedOut),
+
+        Err(err) => Err(format!("Stream Read Error: {}", err)),
+    }
 }
 
+/// Whether an error is a read timing out, which is reported as WouldBlock on Unix and
+/// TimedOut on Windows
+fn is_timeout(err: &io::Error) -> bool {
+    err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn udp_datagrams_are_appended_and_truncated() {
+        let udp_settings = UdpSettings { max_datagram_bytes: 8, ..Default::default() };
+
+        let sock = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let addr = sock.local_addr().unwrap();
+        let mut read_stream = ReadStream::Udp(sock, udp_settings.datagram_bytes());
+
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.send_to(&[1, 2, 3], addr).unwrap();
+        sender.send_to(&[4, 5, 6, 7, 8, 9, 10, 11, 12, 13], addr).unwrap();
+
+        let mut bytes = BytesMut::new();
+
+        assert_eq!(read_stream.stream_read(&mut bytes, 0), Ok(3));
+        assert_eq!(&bytes[..], &[1, 2, 3]);
+
+        // the second datagram follows the first, cut to the configured size
+        assert_eq!(read_stream.stream_read(&mut bytes, 0), Ok(8));
+        assert_eq!(&bytes[..], &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11]);
+    }
+}
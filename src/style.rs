@@ -1,5 +1,129 @@
 use imgui::*;
 
+use ccsds_router_core::types::ThemeColors;
+
+
+/// The sizes within an ImGuiStyle that are multiplied by the UI scale factor.
+/// The default sizes are recorded before any theme is applied so that changing the
+/// scale does not compound with a previous scale.
+#[derive(Debug, Clone, Copy)]
+pub struct StyleSizes {
+    window_padding: ImVec2,
+    window_rounding: f32,
+    child_rounding: f32,
+    frame_padding: ImVec2,
+    frame_rounding: f32,
+    item_spacing: ImVec2,
+    item_inner_spacing: ImVec2,
+    indent_spacing: f32,
+    scrollbar_size: f32,
+    scrollbar_rounding: f32,
+    grab_min_size: f32,
+    grab_rounding: f32,
+}
+
+impl StyleSizes {
+    pub fn from_style(style: &ImGuiStyle) -> StyleSizes {
+        StyleSizes {
+            window_padding: style.window_padding,
+            window_rounding: style.window_rounding,
+            child_rounding: style.child_rounding,
+            frame_padding: style.frame_padding,
+            frame_rounding: style.frame_rounding,
+            item_spacing: style.item_spacing,
+            item_inner_spacing: style.item_inner_spacing,
+            indent_spacing: style.indent_spacing,
+            scrollbar_size: style.scrollbar_size,
+            scrollbar_rounding: style.scrollbar_rounding,
+            grab_min_size: style.grab_min_size,
+            grab_rounding: style.grab_rounding,
+        }
+    }
+
+    pub fn restore(&self, style: &mut ImGuiStyle) {
+        style.window_padding = self.window_padding;
+        style.window_rounding = self.window_rounding;
+        style.child_rounding = self.child_rounding;
+        style.frame_padding = self.frame_padding;
+        style.frame_rounding = self.frame_rounding;
+        style.item_spacing = self.item_spacing;
+        style.item_inner_spacing = self.item_inner_spacing;
+        style.indent_spacing = self.indent_spacing;
+        style.scrollbar_size = self.scrollbar_size;
+        style.scrollbar_rounding = self.scrollbar_rounding;
+        style.grab_min_size = self.grab_min_size;
+        style.grab_rounding = self.grab_rounding;
+    }
+}
+
+fn scale_vec(vec: ImVec2, scale: f32) -> ImVec2 {
+    ImVec2::new(vec.x * scale, vec.y * scale)
+}
+
+/// Multiply the sizes of a style by a scale factor, such as for high DPI displays.
+pub fn scale_style(style: &mut ImGuiStyle, scale: f32) {
+    style.window_padding     = scale_vec(style.window_padding, scale);
+    style.window_rounding    *= scale;
+    style.child_rounding     *= scale;
+    style.frame_padding      = scale_vec(style.frame_padding, scale);
+    style.frame_rounding     *= scale;
+    style.item_spacing       = scale_vec(style.item_spacing, scale);
+    style.item_inner_spacing = scale_vec(style.item_inner_spacing, scale);
+    style.indent_spacing     *= scale;
+    style.scrollbar_size     *= scale;
+    style.scrollbar_rounding *= scale;
+    style.grab_min_size      *= scale;
+    style.grab_rounding      *= scale;
+}
+
+fn color_array(color: ImVec4) -> [f32; 4] {
+    [color.x, color.y, color.z, color.w]
+}
+
+fn color_vec(color: [f32; 4]) -> ImVec4 {
+    ImVec4::new(color[0], color[1], color[2], color[3])
+}
+
+/// The key colors of a style, as edited in the theme editor
+pub fn theme_colors(style: &ImGuiStyle) -> ThemeColors {
+    ThemeColors {
+        text:             color_array(style.colors[ImGuiCol::Text as usize]),
+        text_disabled:    color_array(style.colors[ImGuiCol::TextDisabled as usize]),
+        window_bg:        color_array(style.colors[ImGuiCol::WindowBg as usize]),
+        popup_bg:         color_array(style.colors[ImGuiCol::PopupBg as usize]),
+        border:           color_array(style.colors[ImGuiCol::Border as usize]),
+        frame_bg:         color_array(style.colors[ImGuiCol::FrameBg as usize]),
+        frame_bg_hovered: color_array(style.colors[ImGuiCol::FrameBgHovered as usize]),
+        button:           color_array(style.colors[ImGuiCol::Button as usize]),
+        button_hovered:   color_array(style.colors[ImGuiCol::ButtonHovered as usize]),
+        button_active:    color_array(style.colors[ImGuiCol::ButtonActive as usize]),
+        header:           color_array(style.colors[ImGuiCol::Header as usize]),
+        check_mark:       color_array(style.colors[ImGuiCol::CheckMark as usize]),
+        slider_grab:      color_array(style.colors[ImGuiCol::SliderGrab as usize]),
+        plot_histogram:   color_array(style.colors[ImGuiCol::PlotHistogram as usize]),
+        text_selected_bg: color_array(style.colors[ImGuiCol::TextSelectedBg as usize]),
+    }
+}
+
+/// Replace the key colors of a style with the colors of a custom theme
+pub fn apply_theme_colors(style: &mut ImGuiStyle, colors: &ThemeColors) {
+    style.colors[ImGuiCol::Text as usize]           = color_vec(colors.text);
+    style.colors[ImGuiCol::TextDisabled as usize]   = color_vec(colors.text_disabled);
+    style.colors[ImGuiCol::WindowBg as usize]       = color_vec(colors.window_bg);
+    style.colors[ImGuiCol::PopupBg as usize]        = color_vec(colors.popup_bg);
+    style.colors[ImGuiCol::Border as usize]         = color_vec(colors.border);
+    style.colors[ImGuiCol::FrameBg as usize]        = color_vec(colors.frame_bg);
+    style.colors[ImGuiCol::FrameBgHovered as usize] = color_vec(colors.frame_bg_hovered);
+    style.colors[ImGuiCol::Button as usize]         = color_vec(colors.button);
+    style.colors[ImGuiCol::ButtonHovered as usize]  = color_vec(colors.button_hovered);
+    style.colors[ImGuiCol::ButtonActive as usize]   = color_vec(colors.button_active);
+    style.colors[ImGuiCol::Header as usize]         = color_vec(colors.header);
+    style.colors[ImGuiCol::CheckMark as usize]      = color_vec(colors.check_mark);
+    style.colors[ImGuiCol::SliderGrab as usize]     = color_vec(colors.slider_grab);
+    style.colors[ImGuiCol::PlotHistogram as usize]  = color_vec(colors.plot_histogram);
+    style.colors[ImGuiCol::TextSelectedBg as usize] = color_vec(colors.text_selected_bg);
+}
+
 // dark theme from codz01 (https://github.com/ocornut/imgui/issues/707)
 pub fn set_style_dark(style: &mut ImGuiStyle) {
     style.frame_border_size = 1.0;
@@ -0,0 +1,142 @@
+use std::time::SystemTime;
+
+use types::*;
+
+
+/// The length of a TM transfer frame primary header
+pub const TM_FRAME_HEADER_BYTES: usize = 6;
+
+/// A virtual channel is identified by its spacecraft ID and VCID
+pub type VirtualChannel = (u16, u8);
+
+/// The fields of a TM transfer frame primary header used for virtual channel statistics, along
+/// with the Reed-Solomon corrections the ground station reported for the frame, if it did
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct FrameHeader {
+    pub spacecraft_id: u16,
+    pub vcid: u8,
+    pub vc_frame_count: u8,
+    pub fec_corrections: Option<u8>,
+}
+
+impl FrameHeader {
+    /// Decode the transfer frame header within a packet's frame prefix, or None if the frame
+    /// settings do not locate one, or the prefix is too short to hold it
+    pub fn from_prefix(prefix: &[u8], frame_settings: &FrameSettings) -> Option<FrameHeader> {
+        let offset = frame_settings.transfer_frame_offset?;
+        if offset + TM_FRAME_HEADER_BYTES > prefix.len() {
+            return None;
+        }
+        let header = &prefix[offset..offset + TM_FRAME_HEADER_BYTES];
+
+        // the first word is the version (2 bits), spacecraft ID (10 bits), VCID (3 bits), and the
+        // operational control field flag. The master and virtual channel frame counts follow.
+        let id_word = ((header[0] as u16) << 8) | header[1] as u16;
+
+        Some(FrameHeader {
+            spacecraft_id: (id_word >> 4) & 0x3FF,
+            vcid: ((id_word >> 1) & 0x7) as u8,
+            vc_frame_count: header[3],
+            fec_corrections: frame_settings.fec_corrections_offset.and_then(|offset| prefix.get(offset).cloned()),
+        })
+    }
+
+    pub fn virtual_channel(&self) -> VirtualChannel {
+        (self.spacecraft_id, self.vcid)
+    }
+}
+
+/// A FrameUpdate is sent from the processing thread for the frames of a virtual channel,
+/// in the same way as a PacketUpdate is for the packets of an APID
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct FrameUpdate {
+    pub virtual_channel: VirtualChannel,
+
+    /// The virtual channel frame count of the last frame
+    pub vc_frame_count: u8,
+
+    /// The system time at which the last frame was received
+    pub recv_time: SystemTime,
+
+    /// The number of frames this update stands for, the breaks in their frame count, and the
+    /// frames missing in those breaks
+    pub frame_count: u64,
+    pub gap_count: u64,
+    pub frames_missed: u64,
+
+    /// The frames with Reed-Solomon corrections, and the symbols corrected in them
+    pub corrected_frames: u64,
+    pub corrected_symbols: u64,
+}
+
+impl FrameUpdate {
+    /// The update for a frame, given the frame count of the last frame of its virtual channel
+    pub fn new(frame: &FrameHeader, last_frame_count: Option<u8>, recv_time: SystemTime) -> FrameUpdate {
+        // the frame count is 8 bits, and wraps
+        let frames_missed = last_frame_count.map_or(0, |last_frame_count| {
+            frame.vc_frame_count.wrapping_sub(last_frame_count.wrapping_add(1)) as u64
+        });
+        let corrections = frame.fec_corrections.unwrap_or(0) as u64;
+
+        FrameUpdate {
+            virtual_channel: frame.virtual_channel(),
+            vc_frame_count: frame.vc_frame_count,
+            recv_time: recv_time,
+            frame_count: 1,
+            gap_count: if frames_missed > 0 { 1 } else { 0 },
+            frames_missed: frames_missed,
+            corrected_frames: if corrections > 0 { 1 } else { 0 },
+            corrected_symbols: corrections,
+        }
+    }
+
+    /// Add a later frame of the same virtual channel to this update
+    pub fn combine(&mut self, frame_update: FrameUpdate) {
+        self.frame_count += frame_update.frame_count;
+        self.gap_count += frame_update.gap_count;
+        self.frames_missed += frame_update.frames_missed;
+        self.corrected_frames += frame_update.corrected_frames;
+        self.corrected_symbols += frame_update.corrected_symbols;
+
+        self.vc_frame_count = frame_update.vc_frame_count;
+        self.recv_time = frame_update.recv_time;
+    }
+}
+
+/// The statistics of the frames received on a virtual channel
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct VcStats {
+    pub frame_count: u64,
+    pub last_frame_count: u8,
+    pub gap_count: u64,
+    pub frames_missed: u64,
+    pub corrected_frames: u64,
+    pub corrected_symbols: u64,
+    pub recv_time: SystemTime,
+}
+
+impl Default for VcStats {
+    fn default() -> Self {
+        VcStats {
+            frame_count: 0,
+            last_frame_count: 0,
+            gap_count: 0,
+            frames_missed: 0,
+            corrected_frames: 0,
+            corrected_symbols: 0,
+            recv_time: SystemTime::now(),
+        }
+    }
+}
+
+impl VcStats {
+    pub fn update(&mut self, frame_update: FrameUpdate) {
+        self.frame_count += frame_update.frame_count;
+        self.gap_count += frame_update.gap_count;
+        self.frames_missed += frame_update.frames_missed;
+        self.corrected_frames += frame_update.corrected_frames;
+        self.corrected_symbols += frame_update.corrected_symbols;
+        self.last_frame_count = frame_update.vc_frame_count;
+        self.recv_time = frame_update.recv_time;
+    }
+}
@@ -0,0 +1,230 @@
+use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ccsds_primary_header::primary_header::*;
+
+use cfs::*;
+
+
+/// A Transform is one step of the pipeline an output applies to each packet before writing
+/// it. An output's transforms are applied in the order they are configured, so that outputs
+/// given the same packet can each write it in their own form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Transform {
+    /// Only pass packets with one of the given APIDs
+    KeepApids(Vec<u16>),
+
+    /// Drop packets with any of the given APIDs
+    DropApids(Vec<u16>),
+
+    /// Overwrite the bytes at an offset from the start of the packet, for packets with the
+    /// given APID, or every packet if no APID is given. Bytes past the end of a packet are
+    /// not written.
+    Patch {
+        #[serde(default)]
+        apid: Option<u16>,
+        offset: usize,
+        bytes: Vec<u8>,
+    },
+
+    /// Surround each packet with a frame of fixed header and footer bytes
+    Frame {
+        #[serde(default)]
+        header: Vec<u8>,
+        #[serde(default)]
+        footer: Vec<u8>,
+    },
+
+    /// Swap the byte order of each 16 bit word of the primary header, converting between
+    /// big and little endian headers
+    SwapHeaderEndianness,
+
+    /// Prefix each packet with a preamble built from a template of fields, such as the
+    /// preamble a ground system's telemetry link expects before each packet
+    Preamble(Vec<PreambleField>),
+
+    /// Only pass cFS commands with one of the given function codes, for commands with the
+    /// given APID, or every command if no APID is given. Telemetry is passed unchanged.
+    KeepCommandCodes {
+        #[serde(default)]
+        apid: Option<u16>,
+        codes: Vec<u8>,
+    },
+
+    /// Drop cFS commands with any of the given function codes, for commands with the given
+    /// APID, or every command if no APID is given
+    DropCommandCodes {
+        #[serde(default)]
+        apid: Option<u16>,
+        codes: Vec<u8>,
+    },
+
+    /// Set the checksum of each cFS command, such as after patching its bytes
+    SetCfsChecksum,
+}
+
+impl Transform {
+    /// Whether the transform writes packets with a different length than they were forwarded with
+    pub fn changes_length(&self) -> bool {
+        match self {
+            Transform::Frame { header, footer } => !header.is_empty() || !footer.is_empty(),
+
+            Transform::Preamble(fields) => !fields.is_empty(),
+
+            _ => false,
+        }
+    }
+}
+
+/// A field of a preamble template. The fields are written in order, and multi-byte
+/// values are big endian unless the field is little endian.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PreambleField {
+    /// Fixed bytes, such as a sync marker or reserved bytes
+    Bytes(Vec<u8>),
+
+    /// The length of the packet in bytes, plus an adjustment, such as -1 for a CCSDS style
+    /// length or the size of the preamble for a length covering the whole frame
+    Length {
+        num_bytes: usize,
+        #[serde(default)]
+        adjust: i64,
+        #[serde(default)]
+        little_endian: bool,
+    },
+
+    /// The time the packet is written, as seconds since the UNIX epoch, followed by the
+    /// fraction of a second as a binary fraction of the given number of bytes
+    Time {
+        seconds_bytes: usize,
+        #[serde(default)]
+        subsecond_bytes: usize,
+        #[serde(default)]
+        little_endian: bool,
+    },
+}
+
+/// Build the preamble of a packet of the given length from a template
+pub fn build_preamble(fields: &[PreambleField], packet_length: usize) -> Vec<u8> {
+    let mut preamble = Vec::new();
+
+    for field in fields.iter() {
+        match field {
+            PreambleField::Bytes(bytes) => {
+                preamble.extend_from_slice(bytes);
+            },
+
+            PreambleField::Length { num_bytes, adjust, little_endian } => {
+                let length = (packet_length as i64 + adjust).max(0) as u64;
+                push_value(&mut preamble, length, *num_bytes, *little_endian);
+            },
+
+            PreambleField::Time { seconds_bytes, subsecond_bytes, little_endian } => {
+                let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                push_value(&mut preamble, since_epoch.as_secs(), *seconds_bytes, *little_endian);
+
+                let subsecond_bytes = (*subsecond_bytes).min(8);
+                let fraction = (since_epoch.subsec_nanos() as u128 * (1u128 << 64) / 1_000_000_000) as u64;
+                push_value(&mut preamble, fraction >> (64 - 8 * subsecond_bytes as u32).min(63), subsecond_bytes, *little_endian);
+            },
+        }
+    }
+
+    preamble
+}
+
+/// Add the low bytes of a value, truncated to the given number of bytes
+fn push_value(bytes: &mut Vec<u8>, value: u64, num_bytes: usize, little_endian: bool) {
+    let num_bytes = num_bytes.min(8);
+    let start = bytes.len();
+
+    for index in (0..num_bytes).rev() {
+        bytes.push((value >> (8 * index)) as u8);
+    }
+
+    if little_endian {
+        bytes[start..].reverse();
+    }
+}
+
+/// Apply a chain of transforms to a packet, returning the bytes to write, or None if one of
+/// the transforms drops the packet. The packet's bytes are only copied if a transform
+/// changes them.
+pub fn apply_transforms<'a>(transforms: &[Transform], apid: u16, bytes: &'a [u8], little_endian_header: bool) -> Option<Cow<'a, [u8]>> {
+    let mut packet: Cow<'a, [u8]> = Cow::Borrowed(bytes);
+
+    for transform in transforms.iter() {
+        match transform {
+            Transform::KeepApids(apids) => {
+                if !apids.contains(&apid) {
+                    return None;
+                }
+            },
+
+            Transform::DropApids(apids) => {
+                if apids.contains(&apid) {
+                    return None;
+                }
+            },
+
+            Transform::Patch { apid: patch_apid, offset, bytes: patch_bytes } => {
+                if patch_apid.map_or(true, |patch_apid| patch_apid == apid) && *offset < packet.len() {
+                    let packet_bytes = packet.to_mut();
+                    let end = packet_bytes.len().min(*offset + patch_bytes.len());
+                    packet_bytes[*offset..end].copy_from_slice(&patch_bytes[..end - *offset]);
+                }
+            },
+
+            Transform::Frame { header, footer } => {
+                let mut framed = Vec::with_capacity(header.len() + packet.len() + footer.len());
+                framed.extend_from_slice(header);
+                framed.extend_from_slice(&packet);
+                framed.extend_from_slice(footer);
+                packet = Cow::Owned(framed);
+            },
+
+            Transform::KeepCommandCodes { apid: code_apid, codes } => {
+                if code_apid.map_or(true, |code_apid| code_apid == apid) {
+                    if let Some(function_code) = cfs_function_code(&packet, little_endian_header) {
+                        if !codes.contains(&function_code) {
+                            return None;
+                        }
+                    }
+                }
+            },
+
+            Transform::DropCommandCodes { apid: code_apid, codes } => {
+                if code_apid.map_or(true, |code_apid| code_apid == apid) {
+                    if let Some(function_code) = cfs_function_code(&packet, little_endian_header) {
+                        if codes.contains(&function_code) {
+                            return None;
+                        }
+                    }
+                }
+            },
+
+            Transform::SetCfsChecksum => {
+                if is_cfs_command(&packet, little_endian_header) && !cfs_checksum_valid(&packet) {
+                    set_cfs_checksum(packet.to_mut());
+                }
+            },
+
+            Transform::Preamble(fields) => {
+                let mut framed = build_preamble(fields, packet.len());
+                framed.extend_from_slice(&packet);
+                packet = Cow::Owned(framed);
+            },
+
+            Transform::SwapHeaderEndianness => {
+                if packet.len() >= CCSDS_PRI_HEADER_SIZE_BYTES as usize {
+                    let packet_bytes = packet.to_mut();
+                    for word in packet_bytes[..CCSDS_PRI_HEADER_SIZE_BYTES as usize].chunks_mut(2) {
+                        word.swap(0, 1);
+                    }
+                }
+            },
+        }
+    }
+
+    Some(packet)
+}
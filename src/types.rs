@@ -1,22 +1,66 @@
 use std::time::{Duration, SystemTime};
 use std::default::Default;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::io::Cursor;
+use std::cmp::{min, max};
 
-use imgui::*;
+use bytes::{Buf, Bytes};
 
 use ccsds_primary_header::primary_header::*;
 
 use stream::*;
+use transform::*;
+use container::*;
+use decom::*;
+use parquet_archive::*;
+use epoch::*;
+use transfer_frame::*;
+use schedule::*;
+use outage::*;
+use priority::*;
+use bridge::*;
+use gap_fill::*;
+use reorder::*;
+use index::*;
 
 
 /// Apid from CCSDS standard
 type Apid = u16;
 
+/// The CCSDS sequence count is 14 bits, and wraps around to 0
+pub const CCSDS_SEQ_COUNT_MASK: u16 = 0x3FFF;
+
+/// The sequence count that follows the given one, wrapping around to 0
+pub fn next_seq(seq_count: u16) -> u16 {
+    seq_count.wrapping_add(1) & CCSDS_SEQ_COUNT_MASK
+}
+
+/// The number of sequence counts from the last packet of an APID to the next, accounting for
+/// the count wrapping around. Consecutive packets are 1 apart, and a repeated count is 0 apart.
+pub fn seq_delta(last_seq: u16, seq_count: u16) -> u16 {
+    seq_count.wrapping_sub(last_seq) & CCSDS_SEQ_COUNT_MASK
+}
+
+/// Whether a packet's sequence count does not follow the last sequence count of its APID
+pub fn is_seq_gap(last_seq: u16, seq_count: u16) -> bool {
+    seq_delta(last_seq, seq_count) != 1
+}
+
+/// The number of packets missing between the last sequence count of an APID and the next
+pub fn seq_missing(last_seq: u16, seq_count: u16) -> u16 {
+    seq_delta(last_seq, seq_count).saturating_sub(1)
+}
+
+/// The directory that log files and processing reports are written to
+pub const LOG_DIRECTORY: &str = "logs";
+
 /// The GuiTheme to use with ImGui
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+#[derive(FromPrimitive, Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub enum GuiTheme {
-    Dark,
-    Light,
+    Dark   = 1,
+    Light  = 2,
+    /// A user defined theme, named by the configuration's custom_theme field
+    Custom = 3,
 }
 
 impl Default for GuiTheme {
@@ -25,6 +69,57 @@ impl Default for GuiTheme {
     }
 }
 
+/// The key colors of a theme that can be changed in the theme editor.
+/// Colors are given as RGBA with each component from 0 to 1.
+#[derive(Debug, PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub struct ThemeColors {
+    pub text: [f32; 4],
+    pub text_disabled: [f32; 4],
+    pub window_bg: [f32; 4],
+    pub popup_bg: [f32; 4],
+    pub border: [f32; 4],
+    pub frame_bg: [f32; 4],
+    pub frame_bg_hovered: [f32; 4],
+    pub button: [f32; 4],
+    pub button_hovered: [f32; 4],
+    pub button_active: [f32; 4],
+    pub header: [f32; 4],
+    pub check_mark: [f32; 4],
+    pub slider_grab: [f32; 4],
+    pub plot_histogram: [f32; 4],
+    pub text_selected_bg: [f32; 4],
+}
+
+impl ThemeColors {
+    /// The colors along with the name displayed for each in the theme editor
+    pub fn named_colors_mut(&mut self) -> Vec<(&'static str, &mut [f32; 4])> {
+        vec!(("Text",              &mut self.text),
+             ("Text Disabled",     &mut self.text_disabled),
+             ("Window Background", &mut self.window_bg),
+             ("Popup Background",  &mut self.popup_bg),
+             ("Border",            &mut self.border),
+             ("Frame",             &mut self.frame_bg),
+             ("Frame Hovered",     &mut self.frame_bg_hovered),
+             ("Button",            &mut self.button),
+             ("Button Hovered",    &mut self.button_hovered),
+             ("Button Active",     &mut self.button_active),
+             ("Header",            &mut self.header),
+             ("Check Mark",        &mut self.check_mark),
+             ("Slider Grab",       &mut self.slider_grab),
+             ("Plot Histogram",    &mut self.plot_histogram),
+             ("Text Selected",     &mut self.text_selected_bg))
+    }
+}
+
+/// A named theme created in the theme editor. The theme starts from one of the
+/// built in themes and replaces its key colors.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct CustomTheme {
+    pub name: String,
+    pub base: GuiTheme,
+    pub colors: ThemeColors,
+}
+
 /* Application Configuration */
 /// Application configuration contains all configuration required to processing
 /// inputs and outputs. This struct is loaded from a configuration file at startup,
@@ -48,10 +143,37 @@ pub struct AppConfig {
     /// to its output.
     pub allowed_output_apids: Vec<Option<Vec<u16>>>,
 
+    /// The transforms each output applies to a packet before writing it, in order, after the
+    /// output's allowed APIDs are checked. An output without an entry writes packets unchanged.
+    #[serde(default)]
+    pub output_transforms: Vec<Vec<Transform>>,
+
+    /// Whether each output is enabled. A disabled output keeps its settings, but is not opened
+    /// or sent packets. An output without an entry is enabled.
+    #[serde(default)]
+    pub output_enabled: Vec<bool>,
+
+    /// The name of each output, used to identify it in the GUI, statistics, and errors. An
+    /// output without a name is identified by its number alone.
+    #[serde(default)]
+    pub output_names: Vec<String>,
+
     /// A vector of APIDs that can be received from an input stream.
     /// If None, allow all APIDs.
     pub allowed_input_apids: Option<Vec<u16>>,
 
+    /// Replay only the packets of an input file selected by APID and time, using its index
+    #[serde(default)]
+    pub replay_query: ReplayQuery,
+
+    /// A backup input receiving the same downlink, whose packets fill the gaps in the input
+    #[serde(default)]
+    pub gap_fill: GapFillSettings,
+
+    /// A buffer releasing the input's packets in timestamp or sequence order
+    #[serde(default)]
+    pub reorder: ReorderSettings,
+
     /// GUI theme for IMGUI
     pub theme: GuiTheme,
 
@@ -65,10 +187,19 @@ pub struct AppConfig {
     /// The frame settings describe any fixed headers before or after the CCSDS headers.
     pub frame_settings: FrameSettings,
 
+    /// The container format of the input, whose records are unwrapped before the packets
+    /// within them are parsed.
+    #[serde(default)]
+    pub container_format: ContainerFormat,
+
     /// The maximum number of bytes in a packet. This is used to filter out malformed packets
     /// when the maximum length is known beforehand.
     pub max_length_bytes: i32,
 
+    /// Drop packets whose CRC-16, in their last two bytes, is not valid
+    #[serde(default)]
+    pub drop_crc_failures: bool,
+
     /// The timestamp settings describe how to throttle/delay/replay packets.
     pub timestamp_setting: TimestampSetting,
 
@@ -81,6 +212,216 @@ pub struct AppConfig {
     /// start button.
     #[serde(default)]
     pub auto_start: bool,
+
+    /// Conditions that pause processing when a packet matches them. This is used
+    /// to stop at a packet of interest, such as when hunting for a bad packet in a replay.
+    #[serde(default)]
+    pub pause_conditions: Vec<PauseCondition>,
+
+    /// Limits on the packet rate of APIDs, or of all packets, which raise an alarm when crossed
+    #[serde(default)]
+    pub rate_alarms: Vec<RateAlarm>,
+
+    /// The number of seconds without a packet after which an APID is shown as stale
+    /// in the statistics table. A value of 0 disables stale highlighting.
+    #[serde(default = "default_stale_timeout")]
+    pub stale_timeout: f32,
+
+    /// The scale factor applied to the GUI's text and widgets, such as for high DPI displays
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+
+    /// Themes created in the theme editor
+    #[serde(default)]
+    pub custom_themes: Vec<CustomTheme>,
+
+    /// The name of the custom theme used when the theme is GuiTheme::Custom
+    #[serde(default)]
+    pub custom_theme: String,
+
+    /// Trigger based capture settings
+    #[serde(default)]
+    pub trigger_settings: TriggerSettings,
+
+    /// The number of recent packets kept for each APID, which can be browsed in the packet inspector
+    #[serde(default = "default_packet_history_length")]
+    pub packet_history_length: usize,
+
+    /// Create an index alongside file inputs and outputs, giving the offset, APID, and timestamp
+    /// of each packet. The index of an input file is used to seek to a packet number or time.
+    #[serde(default)]
+    pub index_files: bool,
+
+    /// Settings for archiving the header information of forwarded packets into a database
+    #[serde(default)]
+    pub archive_settings: ArchiveSettings,
+
+    /// Settings for writing forwarded packets into Parquet tables
+    #[serde(default)]
+    pub parquet_settings: ParquetSettings,
+
+    /// The localhost port of the control socket, which accepts line based commands such as
+    /// 'start', 'pause', and 'stats'. The control socket is disabled if no port is given.
+    #[serde(default)]
+    pub control_port: Option<u16>,
+
+    /// The address the control socket listens on. This is localhost unless a peer router
+    /// on another machine sends commands to this router.
+    #[serde(default = "default_control_address")]
+    pub control_address: String,
+
+    /// The token a control connection must give with 'auth <token>' before any other command,
+    /// or empty to accept commands from any connection
+    #[serde(default)]
+    pub control_token: String,
+
+    /// The hosts, as IP addresses or host names, allowed to connect to the control socket.
+    /// Connections from this machine are only allowed if it is listed, such as by 127.0.0.1.
+    /// If empty, any host that can reach the control socket may connect.
+    #[serde(default)]
+    pub control_allowed_hosts: Vec<String>,
+
+    /// Settings for coordinating processing with a peer router
+    #[serde(default)]
+    pub peer_settings: PeerSettings,
+
+    /// The times processing starts and stops without an operator
+    #[serde(default)]
+    pub schedule: ScheduleSettings,
+
+    /// The simulated link outages, during which nothing is written to the outputs
+    #[serde(default)]
+    pub outage_settings: OutageSettings,
+
+    /// The priorities of APIDs on outputs with a rate limit or a store and forward backlog
+    #[serde(default)]
+    pub apid_priorities: PrioritySettings,
+
+    /// The bridge between two streams, forwarding packets in both directions alongside the sessions
+    #[serde(default)]
+    pub bridge: BridgeSettings,
+
+    /// Settings for logging a line per packet to a CSV file
+    #[serde(default)]
+    pub csv_log_settings: CsvLogSettings,
+
+    /// Settings for writing the values of selected parameters to a CSV file
+    #[serde(default)]
+    pub decom_settings: DecomSettings,
+
+    /// Settings for writing the bytes read from the input, before parsing, to a file
+    #[serde(default)]
+    pub raw_capture_settings: RawCaptureSettings,
+
+    /// Settings for the application log
+    #[serde(default)]
+    pub log_settings: LogSettings,
+
+    /// The most packet updates sent to the GUI for each APID per second, or 0 to send an update
+    /// for every packet. Packet counts are accurate either way, while the packet shown for each
+    /// APID, and its history, are sampled.
+    #[serde(default = "default_gui_updates_per_sec")]
+    pub gui_updates_per_sec: u32,
+
+    /// Settings for the size of reads from the input stream
+    #[serde(default)]
+    pub read_settings: ReadSettings,
+
+    /// How network inputs are read: with a thread for each input, or by a single thread
+    /// polling every network input.
+    #[serde(default)]
+    pub io_engine: IoEngine,
+
+    /// The seconds between attempts to reconnect an output that failed to send a packet,
+    /// or 0 to leave failed outputs disconnected. Packets are forwarded to the other outputs
+    /// while an output is disconnected. Only network and plugin outputs are reconnected, as
+    /// reopening a file output would overwrite it.
+    #[serde(default = "default_output_retry_secs")]
+    pub output_retry_secs: f32,
+
+    /// Whether processing starts when some of its outputs can not be opened
+    #[serde(default)]
+    pub output_open_policy: OutputOpenPolicy,
+
+    /// Stop reading the input while a TCP output is disconnected, and continue once it
+    /// reconnects, so that the output does not miss the packets read in between
+    #[serde(default)]
+    pub pause_on_disconnect: bool,
+
+    /// The seconds to wait for a TCP stream to connect when processing starts, or 0 to wait
+    /// until processing is cancelled.
+    #[serde(default)]
+    pub connect_timeout_secs: f32,
+
+    /// Settings for writing heartbeat packets to outputs that have been quiet
+    #[serde(default)]
+    pub heartbeat_settings: HeartbeatSettings,
+
+    /// Settings for writing time packets to the outputs, for downstream time correlation
+    #[serde(default)]
+    pub time_packet_settings: TimePacketSettings,
+
+    /// Exit the application once every session has finished processing, such as when their
+    /// input files have been read, rather than waiting to process again.
+    #[serde(default)]
+    pub exit_on_finish: bool,
+
+    /// Exit with an error when a rate alarm is raised while running without the GUI
+    #[serde(default)]
+    pub exit_on_alarm: bool,
+
+    /// Run the input, parsing, and filtering of processing without writing to any output,
+    /// reporting the packets that would have been forwarded to each output. The archive,
+    /// CSV log, and index files are not written during a dry run.
+    #[serde(default)]
+    pub dry_run: bool,
+
+    /// The number of seconds between statistics reports in the log when running without the
+    /// GUI. A value of 0 disables the reports.
+    #[serde(default = "default_stats_interval")]
+    pub stats_interval: f32,
+
+    /// The name of the session using this configuration's route, shown on its tab in the GUI
+    #[serde(default = "default_session_name")]
+    pub session_name: String,
+
+    /// Additional routing sessions, each with its own input, outputs, and processing thread.
+    /// Only the route settings of these configurations are used- GUI settings such as the
+    /// theme, and any sessions within them, are ignored.
+    #[serde(default)]
+    pub sessions: Vec<AppConfig>,
+}
+
+fn default_stale_timeout() -> f32 {
+    5.0
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+fn default_packet_history_length() -> usize {
+    10
+}
+
+fn default_gui_updates_per_sec() -> u32 {
+    30
+}
+
+fn default_output_retry_secs() -> f32 {
+    5.0
+}
+
+fn default_stats_interval() -> f32 {
+    10.0
+}
+
+fn default_session_name() -> String {
+    "Main".to_string()
+}
+
+fn default_control_address() -> String {
+    "127.0.0.1".to_string()
 }
 
 impl Default for AppConfig {
@@ -91,15 +432,131 @@ impl Default for AppConfig {
             output_settings: vec!(Default::default()),
             output_selection: vec!(Default::default()),
             allowed_output_apids: vec!(Default::default()),
+            output_transforms: Vec::new(),
+            output_enabled: Vec::new(),
+            output_names: Vec::new(),
             allowed_input_apids: None,
+            replay_query: Default::default(),
+            gap_fill: Default::default(),
+            reorder: Default::default(),
             theme: Default::default(),
             packet_size: Default::default(),
             little_endian_ccsds: false,
             frame_settings: Default::default(),
+            container_format: Default::default(),
             max_length_bytes: CCSDS_MAX_LENGTH as i32,
+            drop_crc_failures: false,
             timestamp_setting: Default::default(),
             timestamp_def: Default::default(),
             auto_start: false,
+            pause_conditions: Vec::new(),
+            rate_alarms: Vec::new(),
+            stale_timeout: default_stale_timeout(),
+            ui_scale: default_ui_scale(),
+            custom_themes: Vec::new(),
+            custom_theme: "".to_string(),
+            trigger_settings: Default::default(),
+            packet_history_length: default_packet_history_length(),
+            index_files: false,
+            archive_settings: Default::default(),
+            parquet_settings: Default::default(),
+            csv_log_settings: Default::default(),
+            decom_settings: Default::default(),
+            raw_capture_settings: Default::default(),
+            control_port: None,
+            control_address: default_control_address(),
+            control_token: "".to_string(),
+            control_allowed_hosts: Vec::new(),
+            peer_settings: Default::default(),
+            schedule: Default::default(),
+            outage_settings: Default::default(),
+            apid_priorities: Default::default(),
+            bridge: Default::default(),
+            log_settings: Default::default(),
+            gui_updates_per_sec: default_gui_updates_per_sec(),
+            read_settings: Default::default(),
+            io_engine: Default::default(),
+            output_retry_secs: default_output_retry_secs(),
+            output_open_policy: Default::default(),
+            pause_on_disconnect: false,
+            connect_timeout_secs: 0.0,
+            heartbeat_settings: Default::default(),
+            time_packet_settings: Default::default(),
+            exit_on_finish: false,
+            exit_on_alarm: false,
+            dry_run: false,
+            stats_interval: default_stats_interval(),
+            session_name: default_session_name(),
+            sessions: Vec::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// The custom theme in use, if the theme is set to a custom theme that exists
+    pub fn selected_custom_theme(&self) -> Option<&CustomTheme> {
+        if self.theme != GuiTheme::Custom {
+            return None;
+        }
+
+        self.custom_themes.iter().find(|theme| theme.name == self.custom_theme)
+    }
+
+    /// Whether an output is enabled, which it is unless it has been turned off
+    pub fn is_output_enabled(&self, index: usize) -> bool {
+        self.output_enabled.get(index).cloned().unwrap_or(true)
+    }
+
+    /// Turn an output on or off, keeping its settings
+    pub fn set_output_enabled(&mut self, index: usize, enabled: bool) {
+        if self.output_enabled.len() <= index {
+            self.output_enabled.resize(index + 1, true);
+        }
+        self.output_enabled[index] = enabled;
+    }
+
+    /// The name given to an output, if it has one
+    pub fn output_name(&self, index: usize) -> Option<&str> {
+        self.output_names.get(index).map(|name| name.trim()).filter(|name| !name.is_empty())
+    }
+
+    /// Name an output, or remove its name with an empty name
+    pub fn set_output_name(&mut self, index: usize, name: String) {
+        if self.output_names.len() <= index {
+            self.output_names.resize(index + 1, String::new());
+        }
+        self.output_names[index] = name;
+    }
+
+    /// How an output is identified to the user, by its number and its name if it has one
+    pub fn output_label(&self, index: usize) -> String {
+        match self.output_name(index) {
+            Some(name) => format!("Output {} ({})", index, name),
+
+            None => format!("Output {}", index),
+        }
+    }
+
+    /// The number of routing sessions, including this configuration's own route
+    pub fn session_count(&self) -> usize {
+        self.sessions.len() + 1
+    }
+
+    /// The configuration of a routing session. Session 0 is this configuration's own route.
+    pub fn session_config(&self, index: usize) -> &AppConfig {
+        if index == 0 {
+            self
+        } else {
+            &self.sessions[index - 1]
+        }
+    }
+
+    /// The configuration of a routing session, to be modified
+    pub fn session_config_mut(&mut self, index: usize) -> &mut AppConfig {
+        if index == 0 {
+            self
+        } else {
+            &mut self.sessions[index - 1]
         }
     }
 }
@@ -113,6 +570,484 @@ pub struct FrameSettings {
     pub keep_prefix: bool,
     pub postfix_bytes: i32,
     pub keep_postfix: bool,
+
+    /// The offset within the prefix of a TM transfer frame primary header, such as 4 to skip
+    /// an attached sync marker. When set, statistics are kept for each virtual channel.
+    #[serde(default)]
+    pub transfer_frame_offset: Option<usize>,
+
+    /// The offset within the prefix of a byte giving the number of symbols the ground station's
+    /// Reed-Solomon decoder corrected in the frame, if the station reports it
+    #[serde(default)]
+    pub fec_corrections_offset: Option<usize>,
+
+    /// A sync marker stripped from the start of each frame, before the prefix. The parser
+    /// searches for the marker to find each frame.
+    #[serde(default)]
+    pub sync_marker: SyncMarkerSettings,
+}
+
+/// A PauseCondition is a rule checked against each forwarded packet. When a packet
+/// matches the rule, processing is paused after the packet is forwarded.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub enum PauseCondition {
+    /// Pause when a packet with the given APID is seen
+    ApidSeen(u16),
+
+    /// Pause when the sequence count of an APID skips one or more packets
+    SequenceGap,
+
+    /// Pause when the packet contains a pattern of bytes, either at a given byte
+    /// offset into the packet, or anywhere in the packet if no offset is given.
+    PatternMatch {
+        offset: Option<u32>,
+        pattern: Vec<u8>,
+    },
+
+    /// Pause when the CRC-16 in the last two bytes of the packet is not valid
+    CrcFailure,
+
+    /// Pause once the given number of packets have been forwarded since processing started
+    PacketCount(u64),
+}
+
+/// A RateAlarm is raised when the packet rate of an APID, or of all packets, is outside of
+/// its limits, such as when an instrument stops sending packets partway through a test.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct RateAlarm {
+    /// The APID whose packet rate is checked, or None to check the rate of all packets
+    #[serde(default)]
+    pub apid: Option<u16>,
+
+    /// The fewest packets per second allowed
+    #[serde(default)]
+    pub min_rate: Option<usize>,
+
+    /// The most packets per second allowed
+    #[serde(default)]
+    pub max_rate: Option<usize>,
+}
+
+impl RateAlarm {
+    /// Describe the alarm's limits, as shown in the GUI
+    pub fn description(&self) -> String {
+        let name = match self.apid {
+            Some(apid) => format!("APID {}", apid),
+
+            None => "Total".to_string(),
+        };
+
+        match (self.min_rate, self.max_rate) {
+            (Some(min_rate), Some(max_rate)) => format!("{} rate {} to {} packets/s", name, min_rate, max_rate),
+
+            (Some(min_rate), None) => format!("{} rate at least {} packets/s", name, min_rate),
+
+            (None, Some(max_rate)) => format!("{} rate at most {} packets/s", name, max_rate),
+
+            (None, None) => format!("{} rate unlimited", name),
+        }
+    }
+
+    /// Check the packet rates against this alarm's limits, returning a description of the
+    /// alarm if they are crossed. The minimum rate is only checked once a packet has been
+    /// received, of the alarm's APID if it has one, so the alarm is not raised before data starts.
+    pub fn check(&self, processing_stats: &ProcessingStats) -> Option<String> {
+        let (name, rate, started) = match self.apid {
+            Some(apid) => (format!("APID {}", apid),
+                           processing_stats.apid_packets_per_second.get(&apid).cloned().unwrap_or(0),
+                           processing_stats.packet_history.contains_key(&apid)),
+
+            None => ("Total".to_string(),
+                     processing_stats.packets_per_second,
+                     processing_stats.packet_history.len() > 0),
+        };
+
+        if let Some(min_rate) = self.min_rate {
+            if started && rate < min_rate {
+                return Some(format!("{} rate of {} packets/s is below the minimum of {}", name, rate, min_rate));
+            }
+        }
+
+        if let Some(max_rate) = self.max_rate {
+            if rate > max_rate {
+                return Some(format!("{} rate of {} packets/s is above the maximum of {}", name, rate, max_rate));
+            }
+        }
+
+        None
+    }
+}
+
+impl PauseCondition {
+    pub fn description(&self) -> String {
+        match self {
+            PauseCondition::ApidSeen(apid) => format!("APID {} Seen", apid),
+
+            PauseCondition::SequenceGap => "Sequence Gap".to_string(),
+
+            PauseCondition::PatternMatch { offset, pattern } => {
+                let pattern_str = pattern.iter().map(|byte| format!("{:02X}", byte)).collect::<String>();
+                match offset {
+                    Some(offset) => format!("Pattern {} at byte {}", pattern_str, offset),
+                    None => format!("Pattern {}", pattern_str),
+                }
+            },
+
+            PauseCondition::CrcFailure => "CRC Failure".to_string(),
+
+            PauseCondition::PacketCount(count) => format!("{} Packets Forwarded", count),
+        }
+    }
+}
+
+/// Trigger based capture only writes packets to the outputs once a trigger condition
+/// is met, along with the packets received shortly before the trigger. The trigger
+/// conditions are the same as the conditions used to pause processing.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerSettings {
+    /// When disabled, every packet is written to the outputs
+    pub enabled: bool,
+
+    /// A packet matching any of these conditions starts a capture
+    pub conditions: Vec<PauseCondition>,
+
+    /// Seconds of packets kept from before the trigger, which are written when it fires
+    pub pre_trigger_secs: f32,
+
+    /// Seconds to capture after the trigger, after which the capture waits for the next
+    /// trigger. A value of 0 captures until processing stops.
+    pub post_trigger_secs: f32,
+}
+
+impl Default for TriggerSettings {
+    fn default() -> Self {
+        TriggerSettings {
+            enabled: false,
+            conditions: Vec::new(),
+            pre_trigger_secs: 5.0,
+            post_trigger_secs: 0.0,
+        }
+    }
+}
+
+/// Heartbeat packets are written to each output that has not been sent a packet for a time,
+/// so that systems downstream which time out a quiet link stay connected.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatSettings {
+    pub enabled: bool,
+
+    /// The APID of the heartbeat packet. The idle APID, 0x7FF, is used by default.
+    pub apid: u16,
+
+    /// The heartbeat packet's data, after the primary header
+    pub data: Vec<u8>,
+
+    /// The seconds an output can go without a packet before a heartbeat is written to it,
+    /// which is also the time between heartbeats while it stays quiet
+    pub interval_secs: f32,
+}
+
+impl Default for HeartbeatSettings {
+    fn default() -> Self {
+        HeartbeatSettings {
+            enabled: false,
+            apid: 0x7FF,
+            data: vec!(0),
+            interval_secs: 1.0,
+        }
+    }
+}
+
+/// Time packets are written to every output at a fixed interval, holding the current ground
+/// time, so that simulators downstream can keep their time correlated with the ground's while
+/// stored data is replayed.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct TimePacketSettings {
+    pub enabled: bool,
+
+    /// The APID of the time packets
+    pub apid: u16,
+
+    /// The seconds between time packets
+    pub interval_secs: f32,
+
+    /// Set the secondary header flag of the time packets
+    #[serde(default)]
+    pub secondary_header: bool,
+
+    /// The layout of the time within the packets, and its epoch. With a relative epoch, the
+    /// time is the seconds since processing started.
+    pub time_def: TimestampDef,
+}
+
+impl Default for TimePacketSettings {
+    fn default() -> Self {
+        TimePacketSettings {
+            enabled: false,
+            apid: 0x7FE,
+            interval_secs: 1.0,
+            secondary_header: false,
+            time_def: TimestampDef {
+                offset: 0,
+                num_bytes_seconds: TimeSize::FourBytes,
+                num_bytes_subseconds: TimeSize::TwoBytes,
+                subsecond_resolution: 1.0 / 65536.0,
+                is_little_endian: false,
+                epoch: TimeEpoch::Unix,
+            },
+        }
+    }
+}
+
+/// A peer router follows this router through its control socket, starting, pausing,
+/// continuing, and cancelling when this router does. Both routers start at the same time,
+/// so a replay sent from one site can be received at another for end to end testing.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct PeerSettings {
+    /// The address of the peer router's control socket, such as '10.0.0.2:8200', or empty
+    /// to run without a peer
+    pub address: String,
+
+    /// The seconds from pressing start until both routers start, leaving time for the peer
+    /// to be told the start time
+    pub start_delay_secs: f32,
+
+    /// The token the peer router's control socket requires, or empty if it does not require one
+    #[serde(default)]
+    pub token: String,
+}
+
+impl Default for PeerSettings {
+    fn default() -> Self {
+        PeerSettings {
+            address: "".to_string(),
+            start_delay_secs: 2.0,
+            token: "".to_string(),
+        }
+    }
+}
+
+/// The packet archive records one row per forwarded packet into an SQLite database
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveSettings {
+    pub enabled: bool,
+
+    /// The database file, which is created if it does not exist. Rows are added
+    /// to any existing archive.
+    pub file_name: String,
+
+    /// Check the CRC-16 in the last two bytes of each packet, recording whether it is valid
+    pub check_crc: bool,
+}
+
+/// The CSV packet log writes one line per packet, separate from the application log
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct CsvLogSettings {
+    pub enabled: bool,
+
+    /// The CSV file to write. If empty, a file named by the time processing
+    /// started is written to the logs directory.
+    pub file_name: String,
+}
+
+/// The raw capture writes the bytes read from the input, before they are parsed, to a file
+#[derive(Default, PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct RawCaptureSettings {
+    pub enabled: bool,
+
+    /// The file to write. If empty, a file named by the time processing
+    /// started is written to the logs directory.
+    pub file_name: String,
+}
+
+/// The input stream is read in chunks, which are passed to the CCSDS parser. A read returns
+/// once some bytes are available, up to the chunk size, so a larger chunk does not delay
+/// packets from a slow stream.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct ReadSettings {
+    /// The number of bytes requested in each read of the input stream
+    pub chunk_bytes: usize,
+
+    /// Grow the chunk size while reads fill the whole chunk, such as for a high rate TCP
+    /// stream, and shrink it back towards chunk_bytes when reads return less than a quarter of it.
+    pub adaptive: bool,
+
+    /// The largest chunk size reached by adaptive growth
+    pub max_chunk_bytes: usize,
+}
+
+impl Default for ReadSettings {
+    fn default() -> Self {
+        ReadSettings {
+            chunk_bytes: 4096,
+            adaptive: true,
+            max_chunk_bytes: 1024 * 1024,
+        }
+    }
+}
+
+impl ReadSettings {
+    /// The size of the next read, given the size of the last read and the number of bytes
+    /// it returned.
+    pub fn next_chunk_bytes(&self, chunk_bytes: usize, num_bytes_read: usize) -> usize {
+        let min_chunk_bytes = max(self.chunk_bytes, 1);
+        let max_chunk_bytes = max(self.max_chunk_bytes, min_chunk_bytes);
+
+        if !self.adaptive {
+            min_chunk_bytes
+        } else if num_bytes_read >= chunk_bytes {
+            min(chunk_bytes.saturating_mul(2), max_chunk_bytes)
+        } else if num_bytes_read < chunk_bytes / 4 {
+            max(chunk_bytes / 2, min_chunk_bytes)
+        } else {
+            chunk_bytes
+        }
+    }
+}
+
+impl Default for ArchiveSettings {
+    fn default() -> Self {
+        ArchiveSettings {
+            enabled: false,
+            file_name: "ccsds_router_archive.sqlite".to_string(),
+            check_crc: false,
+        }
+    }
+}
+
+/// Settings for the application log, which is written to the terminal, the logs directory,
+/// the GUI's log panel, and optionally syslog.
+#[derive(PartialEq, Debug, Clone, Serialize, Deserialize)]
+pub struct LogSettings {
+    /// The most verbose level logged: off, error, warn, info, debug, or trace
+    pub level: String,
+
+    /// Levels for particular modules, such as "ccsds_router::processing", used in place of
+    /// the level. A module's level also applies to the modules within it.
+    pub module_levels: BTreeMap<String, String>,
+
+    /// Write each log message as a line of JSON, rather then as text
+    pub json: bool,
+
+    /// Send log messages to syslog as well, which journald also collects. Only supported on
+    /// POSIX systems.
+    pub syslog: bool,
+
+    /// Start a new log file once the current one reaches this size in bytes. A value of 0
+    /// does not limit the size of a log file.
+    pub max_file_bytes: u64,
+
+    /// The most log files kept in the logs directory, removing the oldest. A value of 0
+    /// keeps every log file.
+    pub max_files: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        LogSettings {
+            level: "info".to_string(),
+            module_levels: BTreeMap::new(),
+            json: false,
+            syslog: false,
+            max_file_bytes: 10_000_000,
+            max_files: 20,
+        }
+    }
+}
+
+/// The sequence flags of a CCSDS packet, indicating whether it is part of a
+/// segmented series of packets.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum SequenceFlags {
+    Continuation = 0,
+    First        = 1,
+    Last         = 2,
+    Unsegmented  = 3,
+}
+
+impl Default for SequenceFlags {
+    fn default() -> Self {
+        SequenceFlags::Unsegmented
+    }
+}
+
+impl SequenceFlags {
+    pub fn from_index(index: i32) -> Self {
+        match index {
+            0 => SequenceFlags::Continuation,
+            1 => SequenceFlags::First,
+            2 => SequenceFlags::Last,
+            _ => SequenceFlags::Unsegmented,
+        }
+    }
+}
+
+/// A PacketTemplate describes a CCSDS packet to construct, such as a packet
+/// hand-crafted in the GUI.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct PacketTemplate {
+    pub apid: u16,
+
+    /// Is the packet a command (rather then telemetry)
+    pub is_command: bool,
+
+    pub secondary_header: bool,
+
+    pub sequence_flags: SequenceFlags,
+
+    pub sequence_count: u16,
+
+    /// The packet's data, after the primary header
+    pub data: Vec<u8>,
+}
+
+impl Default for PacketTemplate {
+    fn default() -> Self {
+        PacketTemplate {
+            apid: 0,
+            is_command: false,
+            secondary_header: false,
+            sequence_flags: Default::default(),
+            sequence_count: 0,
+            data: vec!(0),
+        }
+    }
+}
+
+impl PacketTemplate {
+    /// Construct the bytes of the packet, including the primary header. CCSDS packets
+    /// must have at least one byte of data. A little endian header has each 16 bit
+    /// word of the header byte swapped.
+    pub fn to_bytes(&self, little_endian: bool) -> Result<Vec<u8>, String> {
+        if self.data.len() == 0 {
+            return Err("Packet data must contain at least one byte".to_string());
+        }
+
+        if self.data.len() > 0x10000 {
+            return Err(format!("Packet data is too long ({} bytes)", self.data.len()));
+        }
+
+        let control: u16 = ((self.is_command as u16) << 12) |
+                           ((self.secondary_header as u16) << 11) |
+                           (self.apid & 0x07FF);
+        let sequence: u16 = ((self.sequence_flags as u16) << 14) |
+                            (self.sequence_count & CCSDS_SEQ_COUNT_MASK);
+        let length: u16 = (self.data.len() - 1) as u16;
+
+        let mut bytes = Vec::with_capacity(CCSDS_PRI_HEADER_SIZE_BYTES as usize + self.data.len());
+        for word in [control, sequence, length].iter() {
+            if little_endian {
+                bytes.push(*word as u8);
+                bytes.push((*word >> 8) as u8);
+            } else {
+                bytes.push((*word >> 8) as u8);
+                bytes.push(*word as u8);
+            }
+        }
+        bytes.extend(self.data.iter());
+
+        Ok(bytes)
+    }
 }
 
 /* Packet Data */
@@ -121,9 +1056,67 @@ pub struct FrameSettings {
 #[derive(Default, PartialEq, Debug, Clone)]
 pub struct ProcessingStats {
     pub packet_history: HashMap<Apid, PacketStats>,
+    /// The statistics of each virtual channel, when transfer frame headers are read
+    pub vc_history: BTreeMap<VirtualChannel, VcStats>,
     pub packets_per_second: usize,
     pub bytes_per_second: usize,
-    pub packets_dropped: usize,
+    /// The packets per second of each APID
+    pub apid_packets_per_second: HashMap<Apid, usize>,
+    pub packets_dropped: u64,
+    /// The packets dropped for each reason
+    pub drop_reasons: BTreeMap<DropReason, u64>,
+    /// The packets of each APID removed by the input APID filter
+    pub packets_filtered: BTreeMap<Apid, u64>,
+    /// The packets of each APID dropped for being longer than the maximum packet size
+    pub oversize_counts: BTreeMap<Apid, u64>,
+    /// The most recent packets dropped for being too long, oldest first
+    pub oversize_captures: VecDeque<OversizeCapture>,
+    /// The times the input's parser lost and found a valid header again, and the bytes it
+    /// skipped over while searching
+    pub resync_count: u64,
+    pub bytes_skipped: u64,
+    /// The bytes read from the input stream, whether or not they parsed into packets, and the
+    /// bytes read in the last second
+    pub input_bytes_read: u64,
+    pub input_bytes_per_second: u64,
+    /// The bytes held by the input's parser after the last read, and the most it holds while
+    /// searching for a packet
+    pub parser_buffered_bytes: usize,
+    pub parser_max_bytes: usize,
+    /// The time of the last read from the input stream
+    pub last_read_time: Option<SystemTime>,
+    /// The packets of the backup input that filled gaps in the input, and the packets of
+    /// either input removed as duplicates
+    pub gap_fill: GapFillStats,
+    /// The packets the reorder buffer released ahead of a packet that arrived before them, and
+    /// the packets that arrived too late to release in order
+    pub reorder: ReorderStats,
+}
+
+/// An InputReadUpdate describes the reads from the input stream since the last update, as
+/// opposed to the packets parsed from them
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct InputReadUpdate {
+    /// The bytes read since the last update
+    pub byte_count: u64,
+
+    /// The bytes held by the parser after the last read, and the most it holds while
+    /// searching for a packet
+    pub buffered_bytes: usize,
+    pub max_buffered_bytes: usize,
+
+    /// The time of the last read
+    pub read_time: SystemTime,
+}
+
+impl InputReadUpdate {
+    /// Add a later read to this update
+    pub fn combine(&mut self, input_read: InputReadUpdate) {
+        self.byte_count += input_read.byte_count;
+        self.buffered_bytes = input_read.buffered_bytes;
+        self.max_buffered_bytes = input_read.max_buffered_bytes;
+        self.read_time = input_read.read_time;
+    }
 }
 
 #[derive(PartialEq, Clone, Eq, Debug)]
@@ -145,11 +1138,28 @@ pub struct PacketStats {
     /// The last packet length read for this APID
     pub last_len: u16,
 
+    /// The number of sequence count gaps detected for this APID
+    pub gap_count: u64,
+
     /// The system time at which the packet was received
     pub recv_time: SystemTime,
 
     /// The packet itself
-    pub bytes: Vec<u8>,
+    pub bytes: Bytes,
+
+    /// The most recent packets received for this APID, oldest first
+    pub history: VecDeque<PacketRecord>,
+
+    /// The function codes of the cFS commands received for this APID
+    pub command_codes: BTreeSet<u8>,
+}
+
+/// A packet kept in an APID's history
+#[derive(PartialEq, Clone, Eq, Debug)]
+pub struct PacketRecord {
+    pub seq_count: u16,
+    pub recv_time: SystemTime,
+    pub bytes: Bytes,
 }
 
 impl Default for PacketStats {
@@ -160,8 +1170,11 @@ impl Default for PacketStats {
             byte_count: 0,
             last_seq: 0,
             last_len: 0,
+            gap_count: 0,
             recv_time: SystemTime::now(),
-            bytes: Vec::new(),
+            bytes: Bytes::new(),
+            history: VecDeque::new(),
+            command_codes: BTreeSet::new(),
         }
     }
 }
@@ -182,20 +1195,64 @@ pub struct PacketUpdate {
     /// The system time at which the packet was received
     pub recv_time: SystemTime,
 
-    /// The packet itself
-    pub bytes: Vec<u8>,
+    /// The packet itself, shared with the processing thread rather than copied
+    pub bytes: Bytes,
+
+    /// The number of packets this update stands for. When updates are limited, the packets
+    /// not sent to the GUI are counted in the next update, along with their bytes and gaps.
+    pub packet_count: u64,
+    pub byte_count: u64,
+    pub gap_count: u64,
+
+    /// The cFS command function codes of the packets this update stands for
+    pub command_codes: BTreeSet<u8>,
+}
+
+impl PacketUpdate {
+    /// Add a later packet of the same APID to this update, which then shows the later packet
+    pub fn combine(&mut self, packet_update: PacketUpdate) {
+        self.packet_count += packet_update.packet_count;
+        self.byte_count += packet_update.byte_count;
+        self.gap_count += packet_update.gap_count;
+        self.command_codes.extend(packet_update.command_codes);
+
+        self.packet_length = packet_update.packet_length;
+        self.seq_count = packet_update.seq_count;
+        self.recv_time = packet_update.recv_time;
+        self.bytes = packet_update.bytes;
+    }
 }
 
 impl PacketStats {
-    pub fn update(&mut self, packet_update: PacketUpdate) {
+    /// Update the statistics with a new packet, keeping up to history_length
+    /// of the most recent packets.
+    pub fn update(&mut self, packet_update: PacketUpdate, history_length: usize) {
+        // gaps are found by the processing thread, which sees every packet
+        self.gap_count += packet_update.gap_count;
+        self.command_codes.extend(packet_update.command_codes.iter().cloned());
+
         self.apid = packet_update.apid;
-        self.packet_count += 1;
-        self.byte_count += packet_update.packet_length as u64;
+        self.packet_count += packet_update.packet_count;
+        self.byte_count += packet_update.byte_count;
         self.last_seq = packet_update.seq_count;
         self.last_len = packet_update.packet_length;
         self.recv_time = packet_update.recv_time;
-        self.bytes.clear();
-        self.bytes.extend(packet_update.bytes);
+        self.bytes = packet_update.bytes.clone();
+
+        if history_length > 0 {
+            self.history.push_back(PacketRecord { seq_count: packet_update.seq_count,
+                                                  recv_time: packet_update.recv_time,
+                                                  bytes: packet_update.bytes,
+            });
+        }
+        while self.history.len() > history_length {
+            self.history.pop_front();
+        }
+    }
+
+    /// An APID is stale if no packet has been received within the timeout
+    pub fn is_stale(&self, timeout: Duration) -> bool {
+        self.recv_time.elapsed().map(|elapsed| elapsed > timeout).unwrap_or(false)
     }
 }
 
@@ -265,6 +1322,101 @@ pub struct TimestampDef {
     /// The endianness of the seconds and subseconds field.
     /// Using a bool makes the GUI code simplier.
     pub is_little_endian: bool,
+
+    /// The epoch of the timestamp, used to show it as a UTC time
+    #[serde(default)]
+    pub epoch: TimeEpoch,
+}
+
+impl TimestampDef {
+    /// Decode the raw seconds and subseconds fields of a packet's timestamp.
+    /// If the packet is too short to contain the timestamp, None is returned.
+    pub fn decode_fields(&self, bytes: &[u8]) -> Option<(u64, u64)> {
+        let time_start_byte = CCSDS_PRI_HEADER_SIZE_BYTES as usize + self.offset as usize;
+
+        let time_length_bytes = self.num_bytes_seconds.to_num_bytes() +
+                                self.num_bytes_subseconds.to_num_bytes();
+
+        let last_byte_offset = time_start_byte + time_length_bytes;
+
+        // make sure there is space in the packet for the timestamp
+        if last_byte_offset > bytes.len() {
+            return None;
+        }
+
+        let mut cursor = Cursor::new(&bytes[time_start_byte..last_byte_offset]);
+
+        let num_secs = self.decode_field(&mut cursor, &self.num_bytes_seconds);
+        let num_subsecs = self.decode_field(&mut cursor, &self.num_bytes_subseconds);
+
+        Some((num_secs, num_subsecs))
+    }
+
+    /// Decode a timestamp from a packet into a Duration.
+    /// If the packet does not contain the timestamp, a duration of 0 is returned.
+    pub fn decode(&self, bytes: &[u8]) -> Duration {
+        match self.decode_fields(bytes) {
+            Some((num_secs, num_subsecs)) => {
+                let subseconds = num_subsecs as f32 * self.subsecond_resolution;
+                Duration::from_secs(num_secs) +
+                Duration::from_nanos((1_000_000_000.0 * subseconds.fract()) as u64)
+            },
+
+            None => Duration::from_millis(0),
+        }
+    }
+
+    /// Encode a time in seconds as the seconds and subseconds fields of a timestamp. The
+    /// seconds are truncated to the size of their field, so they wrap as a mission clock would.
+    pub fn encode_fields(&self, timestamp_secs: f64) -> Vec<u8> {
+        let timestamp_secs = timestamp_secs.max(0.0);
+
+        let num_secs = timestamp_secs.trunc() as u64;
+        let num_subsecs = if self.subsecond_resolution > 0.0 {
+            (timestamp_secs.fract() / self.subsecond_resolution as f64) as u64
+        } else {
+            0
+        };
+
+        let mut bytes = Vec::new();
+        self.encode_field(&mut bytes, &self.num_bytes_seconds, num_secs);
+        self.encode_field(&mut bytes, &self.num_bytes_subseconds, num_subsecs);
+
+        bytes
+    }
+
+    fn encode_field(&self, bytes: &mut Vec<u8>, time_size: &TimeSize, value: u64) {
+        let num_bytes = time_size.to_num_bytes();
+
+        for index in 0..num_bytes {
+            let shift = if self.is_little_endian { index * 8 } else { (num_bytes - 1 - index) * 8 };
+            bytes.push((value >> shift) as u8);
+        }
+    }
+
+    fn decode_field(&self, cursor: &mut Cursor<&[u8]>, time_size: &TimeSize) -> u64 {
+        match time_size {
+            TimeSize::ZeroBytes => 0,
+
+            TimeSize::OneByte => cursor.get_u8() as u64,
+
+            TimeSize::TwoBytes => {
+                if self.is_little_endian {
+                    cursor.get_u16_le() as u64
+                } else {
+                    cursor.get_u16_be() as u64
+                }
+            },
+
+            TimeSize::FourBytes => {
+                if self.is_little_endian {
+                    cursor.get_u32_le() as u64
+                } else {
+                    cursor.get_u32_be() as u64
+                }
+            },
+        }
+    }
 }
 
 /// The TimestampSetting are the options for how to use time when 
@@ -298,6 +1450,52 @@ impl Default for TimestampSetting {
     }
 }
 
+impl TimestampSetting {
+    /// Parse a timestamp setting given on the command line, as 'asap', 'replay',
+    /// 'delay:<seconds>', or 'throttle:<seconds>'.
+    pub fn from_spec(spec: &str) -> Result<TimestampSetting, String> {
+        let (kind, secs_str) = match spec.find(':') {
+            Some(index) => (&spec[..index], Some(&spec[index + 1..])),
+
+            None => (spec, None),
+        };
+
+        let secs = match secs_str {
+            Some(secs_str) => {
+                let secs: f64 = secs_str.parse().map_err(|_| format!("Invalid number of seconds '{}' in timing '{}'", secs_str, spec))?;
+                if secs < 0.0 {
+                    return Err(format!("The number of seconds in timing '{}' can not be negative", spec));
+                }
+                Some(Duration::new(secs as u64, (secs.fract() * 1_000_000_000.0) as u32))
+            },
+
+            None => None,
+        };
+
+        match (kind, secs) {
+            ("asap", None) => Ok(TimestampSetting::Asap),
+
+            ("replay", None) => Ok(TimestampSetting::Replay),
+
+            ("delay", Some(duration)) => Ok(TimestampSetting::Delay(duration)),
+
+            ("throttle", Some(duration)) => Ok(TimestampSetting::Throttle(duration)),
+
+            _ => Err(format!("Unknown timing '{}', expected asap, replay, delay:<seconds>, or throttle:<seconds>", spec)),
+        }
+    }
+
+    /// The index of the setting in the GUI's time settings selection
+    pub fn selection_index(&self) -> i32 {
+        match self {
+            TimestampSetting::Asap => 1,
+            TimestampSetting::Replay => 2,
+            TimestampSetting::Delay(_) => 3,
+            TimestampSetting::Throttle(_) => 4,
+        }
+    }
+}
+
 /* Messages Generated During Packet Processing */
 /// A GuiMessage is a message generated by the processing thread and received
 /// by the GUI thread to indicate a change in state or the result of 
@@ -305,10 +1503,163 @@ impl Default for TimestampSetting {
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum GuiMessage {
     PacketUpdate(PacketUpdate),
-    PacketDropped(CcsdsPrimaryHeader),
+    FrameUpdate(FrameUpdate),
+    /// A packet was dropped rather than forwarded, for the given reason
+    PacketDropped(CcsdsPrimaryHeader, DropReason),
+    /// The packets of each APID removed by the input APID filter since the last count
+    PacketsFiltered(BTreeMap<Apid, u64>),
+    /// A packet longer than the maximum packet size was dropped
+    OversizePacket(OversizeCapture),
+    /// The input's parser resynchronized the given number of times since the last count,
+    /// skipping the given number of bytes while searching for a valid header
+    BytesSkipped(u64, u64),
+    /// The input stream was read, with the bytes read since the last update
+    InputRead(InputReadUpdate),
     Finished,
+    /// Processing paused, by a pause request, a step, or a pause condition, rather than finishing
+    Paused,
     Terminate,
     Error(String),
+    /// An error that stopped processing, such as a stream that could not be opened
+    Failed(FailureKind, String),
+    /// The byte offset the input stream has read up to
+    InputPosition(u64),
+    /// A pause condition was triggered by a packet with the given APID
+    Breakpoint(Apid, String),
+    /// A trigger based capture started, with the APID and condition that triggered it,
+    /// or ended and is waiting for the next trigger.
+    CaptureStatus(Option<(Apid, String)>),
+    /// The output with the given index and label failed, with the error given, and is not sent
+    /// packets until it is reconnected
+    OutputDegraded(usize, String, String),
+    /// The output with the given index and label was reconnected after failing
+    OutputRecovered(usize, String),
+    /// Processing is waiting for the described stream to connect, or None once it is no longer waiting
+    WaitingForConnection(Option<String>),
+    /// A simulated link outage started, dropping everything written to the outputs, or ended
+    Outage(bool),
+    /// The packets of the backup input that filled gaps, and the duplicates removed, since the last update
+    GapFill(GapFillStats),
+    /// The packets the reorder buffer released out of the order they arrived in, and the late
+    /// packets, since the last update
+    Reorder(ReorderStats),
+    /// The input stream ended, with the number of packets and bytes read from it
+    StreamEnd(EndReason, u64, u64),
+}
+
+/// The reason a packet was dropped rather than forwarded
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize)]
+pub enum DropReason {
+    /// The packet's APID is not accepted from the input
+    FilteredApid,
+    /// The packet is longer than the maximum packet size
+    TooLong,
+    /// The CRC-16 in the last two bytes of the packet is not valid
+    CrcFailure,
+    /// The packet could not be parsed completely, such as when the input ends partway through it
+    ParseError,
+    /// An output's rate limit queue was full
+    QueueOverflow,
+    /// The packet was dropped on purpose, to simulate a lossy link
+    SimulatedDrop,
+}
+
+impl DropReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DropReason::FilteredApid => "Filtered APID",
+            DropReason::TooLong => "Too Long",
+            DropReason::CrcFailure => "CRC Failure",
+            DropReason::ParseError => "Parse Error",
+            DropReason::QueueOverflow => "Queue Overflow",
+            DropReason::SimulatedDrop => "Simulated Drop",
+        }
+    }
+}
+
+/// The most packets dropped for being too long that are kept for viewing
+pub const MAX_OVERSIZE_CAPTURES: usize = 32;
+
+/// The number of bytes of a packet dropped for being too long that are kept, following its frame prefix
+pub const OVERSIZE_CAPTURE_BYTES: usize = 64;
+
+/// An OversizeCapture records a packet dropped for being longer than the maximum packet size,
+/// keeping the start of its frame. A stream that has lost its alignment shows headers that do
+/// not look like the mission's packets, while a genuinely oversize packet has a sensible header.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct OversizeCapture {
+    pub header: CcsdsPrimaryHeader,
+
+    /// The length of the packet, not including any frame bytes
+    pub length: usize,
+
+    /// The byte offset of the packet's frame in the input stream
+    pub offset: u64,
+
+    pub recv_time: SystemTime,
+
+    /// The frame prefix, if any, followed by the first bytes of the packet
+    pub bytes: Vec<u8>,
+
+    /// The offset of the primary header within the captured bytes
+    pub header_offset: usize,
+}
+
+impl OversizeCapture {
+    /// Capture a packet, given the prefix split from its frame, and whether the packet's bytes
+    /// begin with that prefix
+    pub fn new(packet: &Packet,
+               frame_prefix: Option<&[u8]>,
+               prefix_kept: bool,
+               length: usize,
+               offset: u64,
+               recv_time: SystemTime) -> OversizeCapture {
+        let header_offset = frame_prefix.map_or(0, |frame_prefix| frame_prefix.len());
+
+        let mut bytes = Vec::with_capacity(header_offset + OVERSIZE_CAPTURE_BYTES);
+        if !prefix_kept {
+            if let Some(frame_prefix) = frame_prefix {
+                bytes.extend_from_slice(frame_prefix);
+            }
+        }
+        let packet_bytes = min(packet.bytes.len(), header_offset + OVERSIZE_CAPTURE_BYTES);
+        bytes.extend_from_slice(&packet.bytes[..packet_bytes]);
+        bytes.truncate(header_offset + OVERSIZE_CAPTURE_BYTES);
+
+        OversizeCapture {
+            header: packet.header,
+            length: length,
+            offset: offset,
+            recv_time: recv_time,
+            bytes: bytes,
+            header_offset: header_offset,
+        }
+    }
+
+    /// The bytes of the primary header, as they were received
+    pub fn header_bytes(&self) -> &[u8] {
+        let start = min(self.header_offset, self.bytes.len());
+        let end = min(start + CCSDS_PRI_HEADER_SIZE_BYTES as usize, self.bytes.len());
+        &self.bytes[start..end]
+    }
+}
+
+/// The reason an input stream ended
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EndReason {
+    /// The end of the input file was reached, or the stream was closed by its other end
+    EndOfFile,
+    /// The input stream could not be opened, read, or parsed
+    Error,
+}
+
+/// The kind of error that stops processing
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FailureKind {
+    /// A stream, or another file used while processing, could not be opened
+    Open,
+    /// The input stream could not be parsed into packets
+    Parse,
 }
 
 /// a ProcessingMsg is a message from the GUI thread to the processing thread
@@ -320,6 +1671,24 @@ pub enum ProcessingMsg {
     Continue,
     Cancel,
     Terminate,
+    /// Move the input stream to a byte offset. Only file inputs can seek.
+    Seek(u64),
+    /// Move the input stream to a packet number, starting from 0, using the input file's index
+    SeekPacket(u64),
+    /// Move the input stream to the first packet at or after a time in seconds, using the input file's index
+    SeekTime(f64),
+    /// Forward exactly one packet while paused, then pause again.
+    Step,
+    /// Set the speed multiplier applied to Replay/Delay/Throttle timing.
+    SetSpeed(f32),
+    /// Change how packets are timed while processing, without restarting it
+    UpdateTiming(TimestampSetting),
+    /// Set the APIDs that are not forwarded to any output
+    UpdateFilter(Vec<u16>),
+    /// Send a packet with the given APID to the outputs
+    SendPacket(u16, Vec<u8>),
+    /// Attempt to reopen the failed output with the given index now
+    RetryOutput(usize),
 }
 
 impl ProcessingMsg {
@@ -330,55 +1699,105 @@ impl ProcessingMsg {
             ProcessingMsg::Continue => "Continue",
             ProcessingMsg::Cancel => "Cancel",
             ProcessingMsg::Terminate => "Terminate",
+            ProcessingMsg::Seek(_) => "Seek",
+            ProcessingMsg::SeekPacket(_) => "SeekPacket",
+            ProcessingMsg::SeekTime(_) => "SeekTime",
+            ProcessingMsg::Step => "Step",
+            ProcessingMsg::SetSpeed(_) => "SetSpeed",
+            ProcessingMsg::UpdateTiming(_) => "UpdateTiming",
+            ProcessingMsg::UpdateFilter(_) => "UpdateFilter",
+            ProcessingMsg::SendPacket(_, _) => "SendPacket",
+            ProcessingMsg::RetryOutput(_) => "RetryOutput",
         }
     }
 }
 
-/// The state of the app, with information stored
-/// or shared across GUI components
-#[derive(Debug, PartialEq, Clone)]
-pub struct AppState {
-    pub imgui_str: ImString,
-    pub config_file_name: String,
+/// The GUI layout is saved when the GUI exits and restored when it starts, so the GUI
+/// reopens the way the operator left it. This is kept separate from the AppConfig so that
+/// changing the layout does not modify the configuration.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuiLayout {
+    pub window_width: u32,
+    pub window_height: u32,
     pub config_settings_shown: bool,
     pub input_settings_shown: bool,
     pub output_settings_shown: bool,
     pub ccsds_settings_shown: bool,
-    pub timestamp_selection: i32,
+    pub log_shown: bool,
+    pub packet_builder_shown: bool,
+    pub theme_editor_shown: bool,
+    pub timeline_shown: bool,
+    pub timeline_seconds: f32,
+    pub vc_stats_shown: bool,
+    pub resync_shown: bool,
+    pub bridge_shown: bool,
+    pub output_index: usize,
 }
 
-impl AppState {
-    pub fn new() -> AppState {
-        AppState {
-            imgui_str: ImString::with_capacity(256),
-            config_file_name: "".to_string(),
+impl Default for GuiLayout {
+    fn default() -> Self {
+        GuiLayout {
+            window_width: 680,
+            window_height: 740,
             config_settings_shown: true,
             input_settings_shown: true,
             output_settings_shown: true,
             ccsds_settings_shown: true,
-            timestamp_selection: 1,
+            log_shown: false,
+            packet_builder_shown: false,
+            theme_editor_shown: false,
+            timeline_shown: false,
+            timeline_seconds: 30.0,
+            vc_stats_shown: false,
+            resync_shown: false,
+            bridge_shown: false,
+            output_index: 0,
         }
     }
+}
 
-    pub fn all_shown(&self) -> bool {
-        self.input_settings_shown  &&
-        self.output_settings_shown &&
-        self.ccsds_settings_shown  &&
-        self.config_settings_shown
-    }
+/// The values entered in the GUI for a new pause or trigger condition
+#[derive(Debug, PartialEq, Clone)]
+pub struct ConditionEditor {
+    /// The index of the kind of condition selected
+    pub condition: i32,
+    pub apid: i32,
+    /// Byte offset of a pattern, or -1 to match anywhere in the packet
+    pub offset: i32,
+    /// Pattern given as hex
+    pub pattern: String,
+    /// The number of packets forwarded before the condition is met
+    pub packet_count: i32,
+}
 
-    pub fn show_all(&mut self) {
-        self.input_settings_shown  = true;
-        self.output_settings_shown = true;
-        self.ccsds_settings_shown  = true;
-        self.config_settings_shown = true;
+impl Default for ConditionEditor {
+    fn default() -> Self {
+        ConditionEditor {
+            condition: 0,
+            apid: 0,
+            offset: -1,
+            pattern: "".to_string(),
+            packet_count: 1,
+        }
     }
+}
+
+/// The values entered in the GUI for a new rate alarm. A value of -1 means the APID or limit is not used.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RateAlarmEditor {
+    pub apid: i32,
+    pub min_rate: i32,
+    pub max_rate: i32,
+}
 
-    pub fn hide_all(&mut self) {
-        self.input_settings_shown  = false;
-        self.output_settings_shown = false;
-        self.ccsds_settings_shown  = false;
-        self.config_settings_shown = false;
+impl Default for RateAlarmEditor {
+    fn default() -> Self {
+        RateAlarmEditor {
+            apid: -1,
+            min_rate: -1,
+            max_rate: -1,
+        }
     }
 }
 
@@ -397,6 +1816,39 @@ pub enum ProcessingState {
     Terminating,
 }
 
+/// The engine used to read network inputs. File and plugin inputs are always read on a
+/// thread of their own.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum IoEngine {
+    /// Each input is read by a thread that blocks on the input stream
+    Threaded,
+    /// Every UDP and TCP input, across all sessions, is read by a single thread polling the
+    /// sockets, for deployments with many simultaneous inputs.
+    Polled,
+}
+
+impl Default for IoEngine {
+    fn default() -> Self {
+        IoEngine::Threaded
+    }
+}
+
+/// What processing does when an output can not be opened as it starts
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum OutputOpenPolicy {
+    /// Processing only starts if every output opens
+    AllOrNothing,
+    /// Processing starts with the outputs that opened. The others are marked as failed, and
+    /// are reconnected in the same way as an output that fails while processing.
+    Continue,
+}
+
+impl Default for OutputOpenPolicy {
+    fn default() -> Self {
+        OutputOpenPolicy::AllOrNothing
+    }
+}
+
 /// The packet size is used when reading CCSDS- a variable length packet uses the packet length in
 /// the CCSDS header, while a fixed size packet assumes we know the packet length beforehand and we
 /// do not want to use the packet length.
@@ -437,3 +1889,39 @@ impl Default for Endianness {
     }
 }
 
+
+/// Parse a string of hex digits into bytes. Whitespace, commas, and an optional '0x'
+/// prefix on each group of digits are allowed.
+pub fn parse_hex_bytes(hex_str: &str) -> Result<Vec<u8>, String> {
+    let mut digits = String::new();
+    for group in hex_str.split(|c: char| c.is_whitespace() || c == ',') {
+        let group = group.trim_start_matches("0x").trim_start_matches("0X");
+        digits.push_str(group);
+    }
+
+    if !digits.is_ascii() {
+        return Err(format!("Hex string contains invalid characters: {}", hex_str));
+    }
+
+    if digits.len() % 2 != 0 {
+        return Err(format!("Hex string has an odd number of digits: {}", hex_str));
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() / 2);
+    for index in (0..digits.len()).step_by(2) {
+        let byte = u8::from_str_radix(&digits[index..index + 2], 16)
+                     .map_err(|err| format!("Invalid hex '{}': {}", &digits[index..index + 2], err))?;
+        bytes.push(byte);
+    }
+
+    Ok(bytes)
+}
+
+/// Format bytes as hex text, with 16 space separated bytes per line. The text can be
+/// read back with parse_hex_bytes.
+pub fn format_hex_bytes(bytes: &[u8]) -> String {
+    bytes.chunks(16)
+         .map(|line| line.iter().map(|byte| format!("{:02X}", byte)).collect::<Vec<String>>().join(" "))
+         .collect::<Vec<String>>()
+         .join("\n")
+}